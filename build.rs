@@ -89,6 +89,8 @@ struct PollingImplementation {
 
 fn main() -> anyhow::Result<()> {
     println!("cargo:rerun-if-changed=integrations");
+    // Embedded by `sqlx::migrate!`
+    println!("cargo:rerun-if-changed=migrations");
 
     let out_dir = env::var_os("OUT_DIR").unwrap();
     let dest_path = Path::new(&out_dir).join("integrations.rs");
@@ -156,7 +158,7 @@ fn main() -> anyhow::Result<()> {
                         let body_logic = if let Some(body) = body_template {
                             quote! {
                                 let body_template = #body;
-                                let body_rendered = env.render_str(body_template, &data)
+                                let body_rendered = env.render_str(body_template, &context)
                                     .map_err(|e| anyhow::anyhow!("Failed to render body template: {}", e))?;
                                 let body_json: serde_json::Value = serde_json::from_str(&body_rendered)
                                     .map_err(|e| anyhow::anyhow!("Failed to parse rendered body as JSON: {}", e))?;
@@ -199,6 +201,7 @@ fn main() -> anyhow::Result<()> {
                         node_structs.push(quote! {
                             pub struct #struct_name {
                                 client: reqwest::Client,
+                                credential: serde_json::Value,
                             }
 
                             impl Default for #struct_name {
@@ -211,8 +214,14 @@ fn main() -> anyhow::Result<()> {
                                 pub fn new() -> Self {
                                     Self {
                                         client: reqwest::Client::new(),
+                                        credential: serde_json::Value::Null,
                                     }
                                 }
+
+                                /// Fields of the node's credential, as `{{ credential.<field> }}`
+                                pub fn with_credential(credential: serde_json::Value) -> Self {
+                                    Self { credential, ..Self::new() }
+                                }
                             }
 
                             #[async_trait::async_trait]
@@ -228,8 +237,9 @@ fn main() -> anyhow::Result<()> {
                                     if let Some(rx) = _inputs.get_mut(0) {
                                         // We don't just take first output anymore, we use indices
                                         while let Some(data) = rx.recv().await {
+                                            let context = super::template_context(&data, &self.credential);
                                             let url_template = #url;
-                                            let url = env.render_str(url_template, &data)
+                                            let url = env.render_str(url_template, &context)
                                                 .map_err(|e| anyhow::anyhow!("Failed to render URL template: {}", e))?;
 
                                             let mut headers: std::collections::HashMap<String, String> = std::collections::HashMap::new();
@@ -253,7 +263,7 @@ fn main() -> anyhow::Result<()> {
                                                 };
 
                                                 for (k, v) in &headers {
-                                                    let v_rendered = env.render_str(v, &data)
+                                                    let v_rendered = env.render_str(v, &context)
                                                         .map_err(|e| anyhow::anyhow!("Failed to render header {}: {}", k, e))?;
                                                     req_builder = req_builder.header(k, v_rendered);
                                                 }
@@ -342,8 +352,7 @@ fn main() -> anyhow::Result<()> {
                         let body_logic = if let Some(body) = body_template {
                             quote! {
                                 let body_template = #body;
-                                let data = serde_json::Value::Null; 
-                                let body_rendered = env.render_str(body_template, &data)
+                                let body_rendered = env.render_str(body_template, &context)
                                     .map_err(|e| anyhow::anyhow!("Failed to render body template: {}", e))?;
                                 let body_json: serde_json::Value = serde_json::from_str(&body_rendered)
                                     .map_err(|e| anyhow::anyhow!("Failed to parse rendered body as JSON: {}", e))?;
@@ -402,6 +411,7 @@ fn main() -> anyhow::Result<()> {
                         node_structs.push(quote! {
                             pub struct #struct_name {
                                 client: reqwest::Client,
+                                credential: serde_json::Value,
                             }
 
                             impl Default for #struct_name {
@@ -414,8 +424,14 @@ fn main() -> anyhow::Result<()> {
                                 pub fn new() -> Self {
                                     Self {
                                         client: reqwest::Client::new(),
+                                        credential: serde_json::Value::Null,
                                     }
                                 }
+
+                                /// Fields of the node's credential, as `{{ credential.<field> }}`
+                                pub fn with_credential(credential: serde_json::Value) -> Self {
+                                    Self { credential, ..Self::new() }
+                                }
                             }
 
                             #[async_trait::async_trait]
@@ -436,9 +452,10 @@ fn main() -> anyhow::Result<()> {
                                             interval.tick().await;
 
                                             let data: serde_json::Value = serde_json::Value::Null;
+                                            let context = super::template_context(&data, &self.credential);
 
                                             let url_template = #url;
-                                            let url = env.render_str(url_template, &data)
+                                            let url = env.render_str(url_template, &context)
                                                 .map_err(|e| anyhow::anyhow!("Failed to render URL template: {}", e))?;
 
                                             let mut headers: std::collections::HashMap<String, String> = std::collections::HashMap::new();
@@ -451,7 +468,7 @@ fn main() -> anyhow::Result<()> {
                                             };
 
                                             for (k, v) in headers {
-                                                let v_rendered = env.render_str(&v, &data)
+                                                let v_rendered = env.render_str(&v, &context)
                                                     .map_err(|e| anyhow::anyhow!("Failed to render header {}: {}", k, e))?;
                                                 req_builder = req_builder.header(k, v_rendered);
                                             }
@@ -502,7 +519,7 @@ fn main() -> anyhow::Result<()> {
 
                 let node_id = format!("{}_{}", integration_name.to_snake_case(), node.name.to_snake_case());
                 id_match_arms.push(quote! {
-                    #node_id => Some(Box::new(#module_name::#struct_name::new())),
+                    #node_id => Some(Box::new(#module_name::#struct_name::with_credential(credential))),
                 });
                 let label = format!("{}: {}", integration_name, node.name);
                 let category = "Integration";
@@ -592,9 +609,11 @@ fn main() -> anyhow::Result<()> {
                 });
             }
 
+            let integration_id = integration_name.to_snake_case();
             let integration_description = format!("{} integration", integration_name);
             integration_definitions.push(quote! {
                 crate::integration_registry::IntegrationDefinition {
+                    id: #integration_id.to_string(),
                     name: #integration_name.to_string(),
                     description: #integration_description.to_string(),
                     credentials: vec![#(#credential_props_code),*],
@@ -613,7 +632,7 @@ fn main() -> anyhow::Result<()> {
             }
         }
 
-        pub fn create_node_by_id(id: &str) -> Option<Box<dyn crate::stream_engine::StreamNode>> {
+        pub fn create_node_by_id(id: &str, credential: serde_json::Value) -> Option<Box<dyn crate::stream_engine::StreamNode>> {
             match id {
                 #(#id_match_arms)*
                 _ => None,
@@ -625,6 +644,12 @@ fn main() -> anyhow::Result<()> {
                 #(#node_definitions),*
             ]
         }
+
+        pub fn get_integration_definitions() -> Vec<crate::integration_registry::IntegrationDefinition> {
+            vec![
+                #(#integration_definitions),*
+            ]
+        }
     };
 
     fs::write(&dest_path, output.to_string())?;
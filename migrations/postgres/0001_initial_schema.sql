@@ -0,0 +1,60 @@
+-- The schema as created by `PostgresStorage::init` before migrations. IF NOT EXISTS lets
+-- databases created back then adopt it as their first migration.
+CREATE TABLE IF NOT EXISTS users (
+    id UUID PRIMARY KEY,
+    username TEXT NOT NULL UNIQUE,
+    password_hash TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL
+);
+CREATE TABLE IF NOT EXISTS teams (
+    id UUID PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL
+);
+CREATE TABLE IF NOT EXISTS team_members (
+    user_id UUID NOT NULL,
+    team_id UUID NOT NULL,
+    role TEXT NOT NULL,
+    joined_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (user_id, team_id),
+    FOREIGN KEY(user_id) REFERENCES users(id),
+    FOREIGN KEY(team_id) REFERENCES teams(id)
+);
+CREATE TABLE IF NOT EXISTS workflows (
+    id UUID PRIMARY KEY,
+    account_id UUID NOT NULL,
+    name TEXT NOT NULL,
+    definition JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    FOREIGN KEY(account_id) REFERENCES teams(id)
+);
+CREATE TABLE IF NOT EXISTS key_value (
+    key TEXT PRIMARY KEY,
+    value JSONB NOT NULL
+);
+CREATE TABLE IF NOT EXISTS credentials (
+    id UUID PRIMARY KEY,
+    account_id UUID NOT NULL,
+    name TEXT NOT NULL,
+    type TEXT NOT NULL,
+    data TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    FOREIGN KEY(account_id) REFERENCES teams(id)
+);
+CREATE TABLE IF NOT EXISTS executions (
+    id UUID PRIMARY KEY,
+    workflow_id UUID,
+    status TEXT NOT NULL,
+    started_at TIMESTAMPTZ NOT NULL,
+    finished_at TIMESTAMPTZ,
+    error TEXT,
+    FOREIGN KEY(workflow_id) REFERENCES workflows(id)
+);
+CREATE TABLE IF NOT EXISTS execution_events (
+    id UUID PRIMARY KEY,
+    execution_id UUID NOT NULL,
+    event_type TEXT NOT NULL,
+    data JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    FOREIGN KEY(execution_id) REFERENCES executions(id)
+);
@@ -0,0 +1,11 @@
+-- Events published by emit_event nodes, delivered to subscribed workflows by the event bus
+CREATE TABLE events (
+    id UUID PRIMARY KEY,
+    team_id UUID NOT NULL,
+    name TEXT NOT NULL,
+    payload JSONB NOT NULL,
+    status TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    delivered_at TIMESTAMPTZ,
+    FOREIGN KEY(team_id) REFERENCES teams(id)
+);
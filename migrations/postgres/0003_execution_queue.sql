@@ -0,0 +1,8 @@
+-- The durable execution queue: what queued executions run, and which worker runs them
+ALTER TABLE executions
+    ADD COLUMN team_id UUID,
+    ADD COLUMN priority INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN definition JSONB, -- Set for queued executions
+    ADD COLUMN inputs JSONB,
+    ADD COLUMN worker_id TEXT,
+    ADD COLUMN heartbeat_at TIMESTAMPTZ;
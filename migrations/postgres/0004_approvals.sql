@@ -0,0 +1,11 @@
+-- Records held by approval nodes until they are signalled or expire
+CREATE TABLE approvals (
+    token TEXT PRIMARY KEY,
+    execution_id UUID NOT NULL,
+    record JSONB NOT NULL,
+    status TEXT NOT NULL,
+    decision JSONB,
+    expires_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL,
+    decided_at TIMESTAMPTZ
+);
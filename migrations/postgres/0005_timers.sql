@@ -0,0 +1,11 @@
+-- Records held by durable delays, and the node suspended executions resume from
+CREATE TABLE timers (
+    id UUID PRIMARY KEY,
+    execution_id UUID NOT NULL,
+    node_id TEXT NOT NULL,
+    record JSONB NOT NULL,
+    wake_at TIMESTAMPTZ NOT NULL,
+    status TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL
+);
+ALTER TABLE executions ADD COLUMN resume_from TEXT;
@@ -0,0 +1,2 @@
+-- Orders events logged within the same microsecond
+ALTER TABLE execution_events ADD COLUMN seq BIGSERIAL;
@@ -0,0 +1,12 @@
+-- Who changed what in a team
+CREATE TABLE audit_log (
+    id UUID PRIMARY KEY,
+    team_id UUID NOT NULL,
+    actor_id UUID,
+    action TEXT NOT NULL,
+    target TEXT NOT NULL,
+    diff JSONB,
+    created_at TIMESTAMPTZ NOT NULL,
+    seq BIGSERIAL
+);
+CREATE INDEX audit_log_team ON audit_log (team_id, created_at);
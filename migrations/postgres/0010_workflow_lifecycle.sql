@@ -0,0 +1,3 @@
+-- Disabled workflows cannot be run; deleted ones are hidden but keep their versions and executions
+ALTER TABLE workflows ADD COLUMN active BOOLEAN NOT NULL DEFAULT TRUE;
+ALTER TABLE workflows ADD COLUMN deleted_at TIMESTAMPTZ;
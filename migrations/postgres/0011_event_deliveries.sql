@@ -0,0 +1,7 @@
+-- Subscribed workflows a pending event was delivered to, so a retry only starts the others
+CREATE TABLE event_deliveries (
+    event_id UUID NOT NULL,
+    workflow_id UUID NOT NULL,
+    delivered_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (event_id, workflow_id)
+);
@@ -0,0 +1,60 @@
+-- The schema as created by `SqliteStorage::init` before migrations. IF NOT EXISTS lets
+-- databases created back then adopt it as their first migration.
+CREATE TABLE IF NOT EXISTS users (
+    id TEXT PRIMARY KEY,
+    username TEXT NOT NULL UNIQUE,
+    password_hash TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS teams (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    created_at TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS team_members (
+    user_id TEXT NOT NULL,
+    team_id TEXT NOT NULL,
+    role TEXT NOT NULL,
+    joined_at TEXT NOT NULL,
+    PRIMARY KEY (user_id, team_id),
+    FOREIGN KEY(user_id) REFERENCES users(id),
+    FOREIGN KEY(team_id) REFERENCES teams(id)
+);
+CREATE TABLE IF NOT EXISTS workflows (
+    id TEXT PRIMARY KEY,
+    account_id TEXT NOT NULL, -- This is team_id
+    name TEXT NOT NULL,
+    definition TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY(account_id) REFERENCES teams(id)
+);
+CREATE TABLE IF NOT EXISTS key_value (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS credentials (
+    id TEXT PRIMARY KEY,
+    account_id TEXT NOT NULL, -- This is team_id
+    name TEXT NOT NULL,
+    type TEXT NOT NULL,
+    data TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY(account_id) REFERENCES teams(id)
+);
+CREATE TABLE IF NOT EXISTS executions (
+    id TEXT PRIMARY KEY,
+    workflow_id TEXT, -- Nullable
+    status TEXT NOT NULL,
+    started_at TEXT NOT NULL,
+    finished_at TEXT,
+    error TEXT,
+    FOREIGN KEY(workflow_id) REFERENCES workflows(id)
+);
+CREATE TABLE IF NOT EXISTS execution_events (
+    id TEXT PRIMARY KEY,
+    execution_id TEXT NOT NULL,
+    event_type TEXT NOT NULL,
+    data TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY(execution_id) REFERENCES executions(id)
+);
@@ -0,0 +1,11 @@
+-- Events published by emit_event nodes, delivered to subscribed workflows by the event bus
+CREATE TABLE events (
+    id TEXT PRIMARY KEY,
+    team_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    status TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    delivered_at TEXT,
+    FOREIGN KEY(team_id) REFERENCES teams(id)
+);
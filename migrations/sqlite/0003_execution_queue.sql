@@ -0,0 +1,7 @@
+-- The durable execution queue: what queued executions run, and which worker runs them
+ALTER TABLE executions ADD COLUMN team_id TEXT;
+ALTER TABLE executions ADD COLUMN priority INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE executions ADD COLUMN definition TEXT; -- Set for queued executions
+ALTER TABLE executions ADD COLUMN inputs TEXT;
+ALTER TABLE executions ADD COLUMN worker_id TEXT;
+ALTER TABLE executions ADD COLUMN heartbeat_at TEXT;
@@ -0,0 +1,11 @@
+-- Records held by approval nodes until they are signalled or expire
+CREATE TABLE approvals (
+    token TEXT PRIMARY KEY,
+    execution_id TEXT NOT NULL,
+    record TEXT NOT NULL,
+    status TEXT NOT NULL,
+    decision TEXT,
+    expires_at TEXT,
+    created_at TEXT NOT NULL,
+    decided_at TEXT
+);
@@ -0,0 +1,11 @@
+-- Records held by durable delays, and the node suspended executions resume from
+CREATE TABLE timers (
+    id TEXT PRIMARY KEY,
+    execution_id TEXT NOT NULL,
+    node_id TEXT NOT NULL,
+    record TEXT NOT NULL,
+    wake_at TEXT NOT NULL,
+    status TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+ALTER TABLE executions ADD COLUMN resume_from TEXT;
@@ -0,0 +1 @@
+-- Events logged within the same timestamp are ordered by rowid; nothing to add
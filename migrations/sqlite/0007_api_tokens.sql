@@ -0,0 +1,11 @@
+-- Login sessions and personal API tokens, stored hashed
+CREATE TABLE api_tokens (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    created_at TEXT NOT NULL,
+    expires_at TEXT,
+    FOREIGN KEY(user_id) REFERENCES users(id)
+);
@@ -0,0 +1,11 @@
+-- Who changed what in a team
+CREATE TABLE audit_log (
+    id TEXT PRIMARY KEY,
+    team_id TEXT NOT NULL,
+    actor_id TEXT,
+    action TEXT NOT NULL,
+    target TEXT NOT NULL,
+    diff TEXT,
+    created_at TEXT NOT NULL
+);
+CREATE INDEX audit_log_team ON audit_log (team_id, created_at);
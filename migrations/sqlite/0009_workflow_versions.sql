@@ -0,0 +1,19 @@
+-- Immutable workflow versions, and the version each execution ran
+ALTER TABLE workflows ADD COLUMN version INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE executions ADD COLUMN workflow_version INTEGER;
+CREATE TABLE workflow_versions (
+    workflow_id TEXT NOT NULL,
+    version INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    definition TEXT NOT NULL,
+    author_id TEXT,
+    message TEXT,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (workflow_id, version),
+    FOREIGN KEY(workflow_id) REFERENCES workflows(id)
+);
+
+-- What existing workflows hold now becomes their version 1
+INSERT INTO workflow_versions (workflow_id, version, name, definition, created_at)
+    SELECT id, 1, name, definition, created_at FROM workflows;
+UPDATE workflows SET version = 1;
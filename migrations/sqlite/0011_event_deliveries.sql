@@ -0,0 +1,7 @@
+-- Subscribed workflows a pending event was delivered to, so a retry only starts the others
+CREATE TABLE event_deliveries (
+    event_id TEXT NOT NULL,
+    workflow_id TEXT NOT NULL,
+    delivered_at TEXT NOT NULL,
+    PRIMARY KEY (event_id, workflow_id)
+);
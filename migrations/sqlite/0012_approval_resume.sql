@@ -0,0 +1,3 @@
+-- Approvals of suspended executions: the node they resume from, and when the decision was handed on
+ALTER TABLE approvals ADD COLUMN node_id TEXT;
+ALTER TABLE approvals ADD COLUMN delivered_at TEXT;
@@ -0,0 +1,2 @@
+-- The user who started a queued execution, recorded as the actor of its credential reads
+ALTER TABLE executions ADD COLUMN started_by TEXT;
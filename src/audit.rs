@@ -0,0 +1,75 @@
+use anyhow::Result;
+use chrono::Utc;
+use serde_json::{json, Map, Value};
+use uuid::Uuid;
+use crate::credentials::Secrets;
+use crate::schema::WorkflowDefinition;
+use crate::storage::{AuditEntry, Storage};
+
+/// Actions of runs clients execute themselves, e.g. `run` against a server, which they report
+/// through `POST /api/teams/{team_id}/audit`. The server records every other action when it
+/// performs the operation.
+pub const CLIENT_ACTIONS: &[&str] = &["execution.start", "credential.read"];
+
+/// Appends an entry to the team's audit log.
+pub async fn record(storage: &dyn Storage, actor_id: Option<Uuid>, team_id: Uuid, action: &str, target: String, diff: Option<Value>) -> Result<()> {
+    let entry = AuditEntry {
+        id: Uuid::new_v4(),
+        team_id,
+        actor_id,
+        action: action.to_string(),
+        target,
+        diff,
+        created_at: Utc::now(),
+    };
+    storage.log_audit(&entry).await
+}
+
+/// The differences between two JSON values, keyed by JSON pointer:
+/// `{ "/name": { "before": "a", "after": "b" } }`. Objects and arrays are compared member by
+/// member; missing members are `null`. A `null` before stands for a resource that did not
+/// exist yet, so each member of `after` is listed.
+pub fn diff(before: &Value, after: &Value) -> Value {
+    let mut changes = Map::new();
+    let created = Value::Object(Map::new());
+    let before = if before.is_null() && after.is_object() { &created } else { before };
+    diff_into(&mut changes, String::new(), before, after);
+    Value::Object(changes)
+}
+
+fn diff_into(changes: &mut Map<String, Value>, path: String, before: &Value, after: &Value) {
+    let child = |key: &str| format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1"));
+    match (before, after) {
+        (Value::Object(a), Value::Object(b)) => {
+            for (key, value) in a {
+                diff_into(changes, child(key), value, b.get(key).unwrap_or(&Value::Null));
+            }
+            for (key, value) in b.iter().filter(|(key, _)| !a.contains_key(*key)) {
+                diff_into(changes, child(key), &Value::Null, value);
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            for i in 0..a.len().max(b.len()) {
+                diff_into(changes, child(&i.to_string()), a.get(i).unwrap_or(&Value::Null), b.get(i).unwrap_or(&Value::Null));
+            }
+        }
+        _ if before != after => {
+            changes.insert(path, json!({ "before": before, "after": after }));
+        }
+        _ => {}
+    }
+}
+
+/// Decrypts the team credentials `definition` references for a run that starts (see
+/// `crate::storage::referenced_secrets`), recording a `credential.read` entry for each of them.
+/// Entries that cannot be written are logged; they do not keep the run from starting.
+pub async fn read_secrets(storage: &dyn Storage, actor_id: Option<Uuid>, team_id: Uuid, definition: &WorkflowDefinition, execution_id: Option<Uuid>) -> Result<Secrets> {
+    let secrets = crate::storage::referenced_secrets(storage, team_id, definition).await?;
+    for id in secrets.keys() {
+        let details = json!({ "execution_id": execution_id });
+        if let Err(e) = record(storage, actor_id, team_id, "credential.read", format!("credential/{}", id), Some(details)).await {
+            tracing::warn!("Failed to audit the read of credential {}: {:#}", id, e);
+        }
+    }
+    Ok(secrets)
+}
@@ -0,0 +1,89 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+use crate::storage::{ApiToken, Role, Storage, User};
+
+/// How long a login session lasts.
+pub const SESSION_TTL_DAYS: i64 = 30;
+/// Longest lifetime of a personal token, about ten years.
+pub const MAX_TOKEN_DAYS: i64 = 3650;
+const TOKEN_PREFIX: &str = "rf_";
+
+/// Hash under which a token is stored. Tokens are random, so a plain SHA-256 suffices.
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Creates a random token for `user_id` and stores its hash. The returned secret cannot be
+/// recovered later.
+pub async fn issue_token(storage: &dyn Storage, user_id: Uuid, name: &str, kind: &str, expires_at: Option<DateTime<Utc>>) -> Result<(String, ApiToken)> {
+    let mut bytes = [0u8; 32];
+    rand::rng().fill_bytes(&mut bytes);
+    let secret = format!("{}{}", TOKEN_PREFIX, hex::encode(bytes));
+
+    let token = ApiToken {
+        id: Uuid::new_v4(),
+        user_id,
+        name: name.to_string(),
+        kind: kind.to_string(),
+        token_hash: hash_token(&secret),
+        created_at: Utc::now(),
+        expires_at,
+    };
+    storage.create_api_token(&token).await?;
+    Ok((secret, token))
+}
+
+/// Verifies the password and starts a session. `None` for unknown users and wrong passwords alike.
+pub async fn login(storage: &dyn Storage, username: &str, password: &str) -> Result<Option<(User, String, ApiToken)>> {
+    let Some(user) = storage.get_user_by_username(username).await? else {
+        return Ok(None);
+    };
+    if !user.verify_password(password) {
+        return Ok(None);
+    }
+
+    let expires_at = Utc::now() + Duration::days(SESSION_TTL_DAYS);
+    let (secret, token) = issue_token(storage, user.id, "login", "session", Some(expires_at)).await?;
+    Ok(Some((user, secret, token)))
+}
+
+/// The user a bearer token belongs to, unless it is unknown or expired.
+pub async fn authenticate(storage: &dyn Storage, secret: &str) -> Result<Option<(User, ApiToken)>> {
+    let Some(token) = storage.get_api_token_by_hash(&hash_token(secret)).await? else {
+        return Ok(None);
+    };
+    if token.expires_at.is_some_and(|at| at <= Utc::now()) {
+        return Ok(None);
+    }
+    Ok(storage.get_user(token.user_id).await?.map(|user| (user, token)))
+}
+
+/// The user's role in a team, `None` if they are not a member.
+pub async fn team_role(storage: &dyn Storage, user_id: Uuid, team_id: Uuid) -> Result<Option<Role>> {
+    let members = storage.get_team_members(team_id).await?;
+    Ok(members.into_iter().find(|m| m.user_id == user_id).map(|m| m.role))
+}
+
+/// Whether the user holds `required` or a higher role in the team.
+pub async fn has_role(storage: &dyn Storage, user_id: Uuid, team_id: Uuid, required: Role) -> Result<bool> {
+    Ok(team_role(storage, user_id, team_id).await?.is_some_and(|role| role.includes(&required)))
+}
+
+/// The team an execution belongs to: the team it was queued for, else the team of its stored
+/// workflow. `None` for unknown executions; `Some(None)` for ad hoc runs without a team.
+pub async fn execution_team(storage: &dyn Storage, execution_id: Uuid) -> Result<Option<Option<Uuid>>> {
+    let Some(execution) = storage.get_execution(execution_id).await? else {
+        return Ok(None);
+    };
+    if execution.team_id.is_some() {
+        return Ok(Some(execution.team_id));
+    }
+    let workflow = match execution.workflow_id {
+        Some(id) => storage.get_workflow(id).await?,
+        None => None,
+    };
+    Ok(Some(workflow.map(|wf| wf.account_id)))
+}
@@ -405,6 +405,7 @@ impl BuilderState {
         let workflow = WorkflowDefinition {
             nodes: self.nodes.clone(),
             edges: self.edges.clone(),
+            ..Default::default()
         };
         let yaml = serde_yaml::to_string(&workflow)?;
         
@@ -528,6 +529,7 @@ impl BuilderState {
         let workflow = WorkflowDefinition {
             nodes: temp_nodes,
             edges: temp_edges,
+            ..Default::default()
         };
 
         // Run it
@@ -584,6 +586,7 @@ impl BuilderState {
         let workflow = WorkflowDefinition {
             nodes: self.nodes.clone(),
             edges: self.edges.clone(),
+            ..Default::default()
         };
 
         let yaml = serde_yaml::to_string(&workflow)?;
@@ -633,6 +636,7 @@ impl BuilderState {
         let workflow = WorkflowDefinition {
             nodes: self.nodes.clone(),
             edges: self.edges.clone(),
+            ..Default::default()
         };
 
         // Run it
@@ -1,5 +1,6 @@
 use clap::{Parser, Subcommand};
 use uuid::Uuid;
+use chrono::{DateTime, Utc};
 use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
@@ -21,6 +22,14 @@ pub enum Commands {
         #[arg(long, default_value = "sqlite:rustflow.db")]
         db_url: String,
     },
+    /// Show or apply the database's schema migrations
+    Db {
+        #[command(subcommand)]
+        cmd: DbCommands,
+        /// Database URL
+        #[arg(long, default_value = "sqlite:rustflow.db")]
+        db_url: String,
+    },
     
     // --- Authentication ---
     /// Register a new user
@@ -43,8 +52,16 @@ pub enum Commands {
         #[arg(long, default_value = "sqlite:rustflow.db")]
         db_url: String,
     },
-    /// Logout (clear local session)
+    /// Logout (ends the server session and clears the local one)
     Logout,
+    /// Manage personal API tokens for automation
+    Token {
+        #[command(subcommand)]
+        cmd: TokenCommands,
+        /// Database URL
+        #[arg(long, default_value = "sqlite:rustflow.db")]
+        db_url: String,
+    },
     /// Show current logged in user and active team
     Whoami,
 
@@ -59,11 +76,17 @@ pub enum Commands {
     },
 
     // --- Workflow & Resources ---
-    /// Run a workflow from a file
+    /// Run a workflow from a file or from the database
     Run {
         /// Path to workflow file (YAML/JSON)
+        #[arg(short, long, required_unless_present = "workflow_id", conflicts_with = "workflow_id")]
+        file: Option<String>,
+        /// ID of a saved workflow (runs with its team's credentials)
         #[arg(short, long)]
-        file: String,
+        workflow_id: Option<Uuid>,
+        /// JSON record injected into the workflow's manual triggers
+        #[arg(short, long)]
+        input: Option<String>,
         /// Team ID (optional, defaults to active team)
         #[arg(short, long)]
         team_id: Option<Uuid>,
@@ -88,6 +111,12 @@ pub enum Commands {
         /// Workflow name
         #[arg(short, long)]
         name: String,
+        /// Save as the next version of this workflow instead of creating a new one
+        #[arg(long)]
+        id: Option<Uuid>,
+        /// Describes the change in the workflow's history
+        #[arg(short, long)]
+        message: Option<String>,
         /// Database URL
         #[arg(long, default_value = "sqlite:rustflow.db")]
         db_url: String,
@@ -101,17 +130,25 @@ pub enum Commands {
         #[arg(long, default_value = "sqlite:rustflow.db")]
         db_url: String,
     },
+    /// Manage a saved workflow and its versions
+    Workflow {
+        #[command(subcommand)]
+        cmd: WorkflowCommands,
+        /// Database URL
+        #[arg(long, default_value = "sqlite:rustflow.db")]
+        db_url: String,
+    },
     /// Create a new credential
     CreateCredential {
         /// Credential name
         #[arg(short, long)]
         name: String,
-        /// Credential type (e.g., openai_api)
+        /// Credential type (e.g., openai_api; see `credentials types`)
         #[arg(short = 't', long)]
         credential_type: String,
-        /// Secret data
+        /// The fields as a JSON object, or the secret of single-field types; prompted for if omitted
         #[arg(short, long)]
-        data: String,
+        data: Option<String>,
         /// Team ID (optional, defaults to active team)
         #[arg(short, long)]
         team_id: Option<Uuid>,
@@ -128,6 +165,53 @@ pub enum Commands {
         #[arg(long, default_value = "sqlite:rustflow.db")]
         db_url: String,
     },
+    /// Manage the credentials' encryption
+    Credentials {
+        #[command(subcommand)]
+        cmd: CredentialCommands,
+        /// Database URL
+        #[arg(long, default_value = "sqlite:rustflow.db")]
+        db_url: String,
+    },
+    /// Inspect past executions
+    Executions {
+        #[command(subcommand)]
+        cmd: ExecutionCommands,
+        /// Database URL
+        #[arg(long, default_value = "sqlite:rustflow.db")]
+        db_url: String,
+    },
+    /// Delete old executions and payloads (defaults to the server's RUSTFLOW_RETENTION_* settings)
+    Prune {
+        /// Delete executions finished more than this many days ago
+        #[arg(long)]
+        max_age_days: Option<i64>,
+        /// Keep only this many executions per workflow
+        #[arg(long)]
+        max_per_workflow: Option<i64>,
+        /// Keep failed executions this many days instead of applying the limits above
+        #[arg(long)]
+        failed_max_age_days: Option<i64>,
+        /// Keep only this many records per edge of completed executions (0 keeps no payloads)
+        #[arg(long)]
+        payload_sample: Option<i64>,
+        /// Database URL
+        #[arg(long, default_value = "sqlite:rustflow.db")]
+        db_url: String,
+    },
+    /// Show who did what in a team (newest first)
+    Audit {
+        /// Team ID (optional, defaults to active team)
+        #[arg(short, long)]
+        team_id: Option<Uuid>,
+        #[arg(short, long, default_value_t = 50)]
+        limit: i64,
+        #[arg(long, default_value_t = 0)]
+        offset: i64,
+        /// Database URL
+        #[arg(long, default_value = "sqlite:rustflow.db")]
+        db_url: String,
+    },
     #[command(about = "List available nodes")]
     ListNodes,
     #[command(about = "Start interactive workflow builder")]
@@ -141,6 +225,115 @@ pub enum Commands {
     },
 }
 
+#[derive(Subcommand, Debug)]
+pub enum DbCommands {
+    /// Apply pending migrations
+    Migrate,
+    /// List applied and pending migrations
+    Status,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum WorkflowCommands {
+    /// Save a new definition and/or name as the next version
+    Update {
+        id: Uuid,
+        /// Path to the new workflow YAML file
+        #[arg(short, long)]
+        file: Option<PathBuf>,
+        /// New name
+        #[arg(short, long)]
+        name: Option<String>,
+        /// Describes the change in the workflow's history
+        #[arg(short, long)]
+        message: Option<String>,
+    },
+    /// Rename a workflow (saved as the next version)
+    Rename {
+        id: Uuid,
+        name: String,
+    },
+    /// Delete a workflow; its versions and executions are kept (Admin only)
+    Delete {
+        id: Uuid,
+    },
+    /// Allow the workflow to run again
+    Enable {
+        id: Uuid,
+    },
+    /// Keep the workflow from being run, by hand, events or failure handlers
+    Disable {
+        id: Uuid,
+    },
+    /// List the versions of a workflow, newest first
+    History {
+        id: Uuid,
+    },
+    /// Show what changed between two versions
+    Diff {
+        id: Uuid,
+        from: i32,
+        /// Defaults to the latest version
+        to: Option<i32>,
+    },
+    /// Save an earlier version as the latest one
+    Rollback {
+        id: Uuid,
+        version: i32,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CredentialCommands {
+    /// List the credential types and their fields
+    Types,
+    /// Rename a credential and/or replace its fields (prompted for unless --name or --data)
+    Update {
+        id: Uuid,
+        #[arg(short, long)]
+        name: Option<String>,
+        /// All fields as a JSON object, or the secret of single-field types
+        #[arg(short, long)]
+        data: Option<String>,
+    },
+    /// Delete a credential (Admin only)
+    Delete {
+        id: Uuid,
+        /// Delete it even if stored workflows reference it
+        #[arg(long)]
+        force: bool,
+    },
+    /// Show a credential's fields with secrets masked (Admin only, audited)
+    Reveal {
+        id: Uuid,
+    },
+    /// List the nodes of stored workflows that reference a credential
+    Usage {
+        id: Uuid,
+    },
+    /// Re-encrypt every credential under the current master key; keep the old key in
+    /// RUSTFLOW_PREVIOUS_MASTER_KEYS until this is done
+    RotateKey,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TokenCommands {
+    /// Create a token; use it as RUSTFLOW_TOKEN or `Authorization: Bearer <token>`
+    Create {
+        #[arg(short, long)]
+        name: String,
+        /// Days until the token expires, at most 3650 (never if unset)
+        #[arg(long, value_parser = clap::value_parser!(i64).range(1..=rust_flow::auth::MAX_TOKEN_DAYS))]
+        expires_in_days: Option<i64>,
+    },
+    /// List your tokens and sessions
+    List,
+    /// Revoke a token
+    Revoke {
+        id: Uuid,
+    },
+}
+
 #[derive(Subcommand, Debug)]
 pub enum TeamCommands {
     /// Create a new team
@@ -165,3 +358,56 @@ pub enum TeamCommands {
         team_id: Uuid,
     },
 }
+
+#[derive(Subcommand, Debug)]
+pub enum ExecutionCommands {
+    /// List executions, newest first
+    List {
+        /// Only executions of this saved workflow
+        #[arg(short, long)]
+        workflow_id: Option<Uuid>,
+        /// Only executions of this team
+        #[arg(long)]
+        team_id: Option<Uuid>,
+        /// Only executions with this status (e.g. failed)
+        #[arg(short, long)]
+        status: Option<String>,
+        /// Started at or after (RFC 3339, e.g. 2024-05-01T00:00:00Z)
+        #[arg(long)]
+        since: Option<DateTime<Utc>>,
+        /// Started before (RFC 3339)
+        #[arg(long)]
+        until: Option<DateTime<Utc>>,
+        #[arg(short, long, default_value_t = 20)]
+        limit: i64,
+        #[arg(long, default_value_t = 0)]
+        offset: i64,
+    },
+    /// Show an execution
+    Show {
+        id: Uuid,
+    },
+    /// Print the events logged by an execution
+    Logs {
+        id: Uuid,
+    },
+    /// Re-run an execution's nodes on the records they received the first time
+    Replay {
+        id: Uuid,
+        /// Replay only this node
+        #[arg(short, long)]
+        node: Option<String>,
+        /// Reuse the recorded outputs of nodes with side effects (HTTP, SQL, files, ...) instead of running them
+        #[arg(long)]
+        stub_side_effects: bool,
+        /// Reuse the recorded outputs of this node instead of running it (repeatable)
+        #[arg(long = "stub")]
+        stub_nodes: Vec<String>,
+        /// Workflow file to replay with, e.g. a local fix (defaults to the definition the execution ran)
+        #[arg(short, long)]
+        file: Option<String>,
+        /// Seconds each node may run
+        #[arg(long, default_value_t = 30)]
+        timeout: u64,
+    },
+}
@@ -10,6 +10,8 @@ pub struct Config {
     pub user_id: Option<Uuid>,
     pub username: Option<String>,
     pub active_team_id: Option<Uuid>,
+    /// Session token from logging in to a server.
+    pub token: Option<String>,
 }
 
 impl Config {
@@ -34,7 +36,11 @@ impl Config {
         Ok(())
     }
 
+    /// `RUSTFLOW_CONFIG` if set, else `config.toml` in the user's config directory.
     fn get_path() -> Result<PathBuf> {
+        if let Ok(path) = std::env::var("RUSTFLOW_CONFIG") {
+            return Ok(PathBuf::from(path));
+        }
         let proj_dirs = ProjectDirs::from("com", "rustflow", "cli")
             .ok_or_else(|| anyhow!("Could not determine config directory"))?;
         Ok(proj_dirs.config_dir().join("config.toml"))
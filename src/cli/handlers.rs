@@ -2,43 +2,156 @@ use anyhow::{Result, Context, anyhow};
 use uuid::Uuid;
 use chrono::Utc;
 use std::fs;
-use argon2::PasswordVerifier;
-use rust_flow::schema::WorkflowLoader;
-use rust_flow::storage::{Storage, SqliteStorage, RemoteStorage, PostgresStorage, WorkflowEntity, Role};
+use std::sync::Arc;
+use rust_flow::schema::{WorkflowDefinition, WorkflowLoader};
+use rust_flow::replay::ReplayOptions;
+use rust_flow::audit;
+use rust_flow::credentials;
+use dialoguer::{theme::ColorfulTheme, Input, Password};
+use rust_flow::storage::encryption;
+use rust_flow::storage::{Storage, SqliteStorage, RemoteStorage, PostgresStorage, FileStorage, WorkflowEntity, Role, ExecutionFilter, RetentionPolicy};
 use crate::cli::config::Config;
-use crate::cli::commands::{Args, Commands, TeamCommands};
+use crate::cli::commands::{Args, Commands, TeamCommands, ExecutionCommands, TokenCommands, DbCommands, WorkflowCommands, CredentialCommands};
 use crate::cli::builder;
 
+fn remote_storage(url: &str) -> Result<RemoteStorage> {
+    // An explicit token (e.g. for automation) wins over the login session
+    let token = std::env::var("RUSTFLOW_TOKEN").ok().or(Config::load()?.token);
+    Ok(RemoteStorage::new(url).with_token(token))
+}
+
 async fn get_storage(server: Option<String>, db_url: String) -> Result<Box<dyn Storage>> {
     if let Some(url) = server {
-        let storage = RemoteStorage::new(&url);
+        let storage = remote_storage(&url)?;
         storage.init().await?;
         Ok(Box::new(storage))
     } else if db_url.starts_with("postgres://") {
         let storage = PostgresStorage::new(&db_url).await?;
         storage.init().await?;
         Ok(Box::new(storage))
+    } else if db_url.starts_with("file://") {
+        let storage = FileStorage::new(&db_url).await?;
+        storage.init().await?;
+        Ok(Box::new(storage))
     } else {
         let storage = SqliteStorage::new(&db_url).await?;
         Ok(Box::new(storage))
     }
 }
 
+async fn credential(storage: &dyn Storage, id: Uuid) -> Result<rust_flow::storage::Credential> {
+    storage.get_credential(id).await?.ok_or_else(|| anyhow!("Credential {} not found", id))
+}
+
+/// `--data`: a JSON object of fields, or else the bare secret of a single-field type.
+fn credential_data(data: String) -> serde_json::Value {
+    serde_json::from_str::<serde_json::Value>(&data).ok()
+        .filter(|v| v.is_object())
+        .unwrap_or(serde_json::Value::String(data))
+}
+
+/// Asks for each field of a credential type, hiding passwords.
+fn prompt_credential_fields(credential_type: &credentials::CredentialType) -> Result<serde_json::Value> {
+    let theme = ColorfulTheme::default();
+    let mut fields = serde_json::Map::new();
+    for property in &credential_type.properties {
+        let mut prompt = property.label.clone();
+        if let Some(description) = &property.description {
+            prompt = format!("{} ({})", prompt, description);
+        }
+        let value: String = if property.property_type == "password" {
+            Password::with_theme(&theme).with_prompt(prompt).allow_empty_password(!property.required).interact()?
+        } else {
+            Input::with_theme(&theme).with_prompt(prompt).allow_empty(!property.required).interact_text()?
+        };
+        if value.is_empty() {
+            continue;
+        }
+        let value = match property.property_type.as_str() {
+            "number" | "boolean" | "json" => serde_json::from_str(&value)
+                .with_context(|| format!("{} must be {}", property.label, property.property_type))?,
+            _ => serde_json::Value::String(value),
+        };
+        fields.insert(property.name.clone(), value);
+    }
+    Ok(serde_json::Value::Object(fields))
+}
+
+/// Creates the file of a `sqlite:<path>[?<options>]` database if missing.
+fn create_sqlite_file(db_url: &str) -> Result<()> {
+    if db_url.starts_with("sqlite:") {
+        let path = db_url.trim_start_matches("sqlite:").split('?').next().unwrap_or_default();
+        if !std::path::Path::new(path).exists() {
+            fs::File::create(path)?;
+        }
+    }
+    Ok(())
+}
+
 pub async fn handle_command(args: Args) -> Result<()> {
     let command = args.command.ok_or_else(|| anyhow!("No command specified"))?;
     match command {
         Commands::InitDb { db_url } => {
-            if db_url.starts_with("sqlite:") {
-                let path = db_url.trim_start_matches("sqlite:");
-                if !std::path::Path::new(path).exists() {
-                    fs::File::create(path)?;
-                }
-            }
-            let storage = SqliteStorage::new(&db_url).await?;
+            let storage: Box<dyn Storage> = if db_url.starts_with("file://") {
+                Box::new(FileStorage::new(&db_url).await?)
+            } else {
+                create_sqlite_file(&db_url)?;
+                Box::new(SqliteStorage::new(&db_url).await?)
+            };
             storage.init().await?;
             println!("Database initialized at {}", db_url);
         }
+        Commands::Db { cmd, db_url } => {
+            if args.server.is_some() {
+                return Err(anyhow!("Database commands run against the database itself, not --server"));
+            }
+            // Not get_storage: that would migrate Postgres databases already
+            let storage: Box<dyn Storage> = if db_url.starts_with("postgres://") {
+                Box::new(PostgresStorage::new(&db_url).await?)
+            } else if db_url.starts_with("file://") {
+                Box::new(FileStorage::new(&db_url).await?)
+            } else {
+                if matches!(cmd, DbCommands::Migrate) {
+                    create_sqlite_file(&db_url)?;
+                }
+                Box::new(SqliteStorage::new(&db_url).await?)
+            };
+            let status = storage.schema_status().await?;
+
+            match cmd {
+                DbCommands::Migrate => {
+                    storage.init().await?;
+                    let applied = status.pending();
+                    for migration in &applied {
+                        println!("Applied {:04} {}", migration.version, migration.description);
+                    }
+                    let version = storage.schema_status().await?.version();
+                    if applied.is_empty() {
+                        println!("Schema is up to date at version {}.", version.unwrap_or_default());
+                    } else {
+                        println!("Schema migrated to version {}.", version.unwrap_or_default());
+                    }
+                }
+                DbCommands::Status => {
+                    match status.version() {
+                        Some(version) => println!("Schema version: {}", version),
+                        None => println!("Schema version: none (empty database)"),
+                    }
+                    for migration in &status.migrations {
+                        let state = if migration.applied { "applied" } else { "pending" };
+                        println!("{:04} {:<8} {}", migration.version, state, migration.description);
+                    }
+                    for version in &status.unknown {
+                        println!("{:04} {:<8} (unknown to this RustFlow)", version, "applied");
+                    }
+                    if let Err(e) = status.check() {
+                        println!("Warning: {}", e);
+                    }
+                }
+            }
+        }
         Commands::Register { username, password, db_url } => {
+            let server = args.server.clone();
             let storage = get_storage(args.server, db_url).await?;
             let user = storage.create_user(&username, &password).await?;
             println!("User registered: {} (ID: {})", user.username, user.id);
@@ -47,37 +160,85 @@ pub async fn handle_command(args: Args) -> Result<()> {
             let mut config = Config::load()?;
             config.user_id = Some(user.id);
             config.username = Some(user.username);
+            config.token = None;
+            if let Some(url) = server {
+                config.token = Some(RemoteStorage::new(&url).login(&username, &password).await?.token);
+            }
             config.save()?;
             println!("Logged in as {}", username);
         }
         Commands::Login { username, password, db_url } => {
-            let storage = get_storage(args.server, db_url).await?;
-            let user = storage.get_user_by_username(&username).await?
-                .ok_or_else(|| anyhow!("User not found"))?;
-            
-            // Verify password
-            let parsed_hash = argon2::PasswordHash::new(&user.password_hash)
-                .map_err(|e| anyhow!("Invalid password hash: {}", e))?;
-            
-            if argon2::Argon2::default().verify_password(password.as_bytes(), &parsed_hash).is_ok() {
-                let mut config = Config::load()?;
-                config.user_id = Some(user.id);
-                config.username = Some(user.username);
-                config.active_team_id = None; 
-                config.save()?;
-                println!("Logged in as {}", username);
+            // The server verifies the password and hands out a session token
+            let (user, token) = if let Some(url) = args.server {
+                let issued = RemoteStorage::new(&url).login(&username, &password).await?;
+                (issued.user, Some(issued.token))
             } else {
-                return Err(anyhow!("Invalid password"));
-            }
+                let storage = get_storage(None, db_url).await?;
+                let user = storage.get_user_by_username(&username).await?
+                    .ok_or_else(|| anyhow!("User not found"))?;
+                if !user.verify_password(&password) {
+                    return Err(anyhow!("Invalid password"));
+                }
+                (user, None)
+            };
+
+            let mut config = Config::load()?;
+            config.user_id = Some(user.id);
+            config.username = Some(user.username);
+            config.active_team_id = None; 
+            config.token = token;
+            config.save()?;
+            println!("Logged in as {}", username);
         }
         Commands::Logout => {
             let mut config = Config::load()?;
+            if let (Some(url), Some(token)) = (args.server, config.token.take()) {
+                if let Err(e) = RemoteStorage::new(&url).with_token(Some(token)).logout().await {
+                    eprintln!("Could not end the server session: {}", e);
+                }
+            }
             config.user_id = None;
             config.username = None;
             config.active_team_id = None;
+            config.token = None;
             config.save()?;
             println!("Logged out.");
         }
+        Commands::Token { cmd, db_url } => {
+            let config = Config::load()?;
+            let user_id = config.user_id.ok_or_else(|| anyhow!("Not logged in"))?;
+
+            match cmd {
+                TokenCommands::Create { name, expires_in_days } => {
+                    let (token, info) = if let Some(url) = args.server {
+                        let remote = RemoteStorage::new(&url).with_token(config.token);
+                        let issued = remote.create_token(&name, expires_in_days).await?;
+                        (issued.token, issued.info)
+                    } else {
+                        let storage = get_storage(None, db_url).await?;
+                        let expires_at = expires_in_days.map(|days| Utc::now() + chrono::Duration::days(days));
+                        rust_flow::auth::issue_token(storage.as_ref(), user_id, &name, "personal", expires_at).await?
+                    };
+                    println!("Token {} created (ID: {}). It is shown only once:", info.name, info.id);
+                    println!("{}", token);
+                }
+                TokenCommands::List => {
+                    let storage = get_storage(args.server, db_url).await?;
+                    for token in storage.list_api_tokens(user_id).await? {
+                        let expires = token.expires_at.map(|t| t.to_rfc3339()).unwrap_or_else(|| "never".to_string());
+                        println!("{} {:<8} {} (expires: {})", token.id, token.kind, token.name, expires);
+                    }
+                }
+                TokenCommands::Revoke { id } => {
+                    let storage = get_storage(args.server, db_url).await?;
+                    if storage.delete_api_token(user_id, id).await? {
+                        println!("Token {} revoked.", id);
+                    } else {
+                        return Err(anyhow!("Token {} not found", id));
+                    }
+                }
+            }
+        }
         Commands::Whoami => {
             let config = Config::load()?;
             if let Some(username) = config.username {
@@ -99,7 +260,14 @@ pub async fn handle_command(args: Args) -> Result<()> {
             match cmd {
                 TeamCommands::Create { name } => {
                     let team = storage.create_team(&name).await?;
-                    storage.add_team_member(team.id, user_id, Role::Admin).await?;
+                    // The server makes the creator an admin itself
+                    if !rust_flow::auth::has_role(storage.as_ref(), user_id, team.id, Role::Admin).await? {
+                        storage.add_team_member(team.id, user_id, Role::Admin).await?;
+                    }
+                    let diff = audit::diff(&serde_json::Value::Null, &serde_json::json!({ "name": team.name }));
+                    audit::record(storage.as_ref(), Some(user_id), team.id, "team.create", format!("team/{}", team.id), Some(diff)).await?;
+                    let diff = audit::diff(&serde_json::Value::Null, &serde_json::json!({ "role": Role::Admin.as_str() }));
+                    audit::record(storage.as_ref(), Some(user_id), team.id, "member.add", format!("user/{}", user_id), Some(diff)).await?;
                     println!("Team created: {} (ID: {})", team.name, team.id);
                     
                     config.active_team_id = Some(team.id);
@@ -128,7 +296,9 @@ pub async fn handle_command(args: Args) -> Result<()> {
                     
                     let role_enum = Role::from_str(&role).ok_or_else(|| anyhow!("Invalid role. Use 'admin' or 'member'"))?;
                     
-                    storage.add_team_member(team_id, new_user.id, role_enum).await?;
+                    storage.add_team_member(team_id, new_user.id, role_enum.clone()).await?;
+                    let diff = audit::diff(&serde_json::Value::Null, &serde_json::json!({ "role": role_enum.as_str() }));
+                    audit::record(storage.as_ref(), Some(user_id), team_id, "member.add", format!("user/{}", new_user.id), Some(diff)).await?;
                     println!("Added {} to team.", username);
                 }
                 TeamCommands::Switch { team_id } => {
@@ -143,33 +313,52 @@ pub async fn handle_command(args: Args) -> Result<()> {
                 }
             }
         }
-        Commands::Run { file, team_id, db_url, debug, limit } => {
+        Commands::Run { file, workflow_id, input, team_id, db_url, debug, limit } => {
             let config = Config::load()?;
-            let effective_team_id = team_id.or(config.active_team_id);
+            let mut effective_team_id = team_id.or(config.active_team_id);
+
+            // Storage is required to load saved workflows and team credentials; otherwise it is
+            // only used for node state (e.g. poll watermarks), so a missing database is not fatal.
+            let storage: Option<Arc<dyn Storage>> = if effective_team_id.is_some() || workflow_id.is_some() {
+                Some(Arc::from(get_storage(args.server, db_url).await?))
+            } else {
+                get_storage(args.server, db_url).await.ok().map(Arc::from)
+            };
+
+            let mut workflow_version = None;
+            let workflow_def = if let Some(wf_id) = workflow_id {
+                let storage = storage.as_ref().ok_or_else(|| anyhow!("Storage required to load workflow"))?;
+                let workflow = storage.get_workflow(wf_id).await?
+                    .ok_or_else(|| anyhow!("Workflow {} not found", wf_id))?;
+                workflow.ensure_runnable()?;
+                println!("Loading workflow: {} (ID: {}, version {})", workflow.name, workflow.id, workflow.version);
+                workflow_version = Some(workflow.version);
+                // A saved workflow runs with the credentials of the team it belongs to
+                effective_team_id = Some(workflow.account_id);
+                serde_json::from_value::<rust_flow::schema::WorkflowDefinition>(workflow.definition)?
+            } else {
+                let file = file.ok_or_else(|| anyhow!("Either --file or --workflow-id is required"))?;
+                let content = std::fs::read_to_string(&file)
+                    .with_context(|| format!("Failed to read file: {}", file))?;
+
+                println!("Loading workflow from: {:?}", file);
+                let loader = WorkflowLoader::new();
+                loader.load(&content)?
+            };
 
-            let content = std::fs::read_to_string(&file)
-                .with_context(|| format!("Failed to read file: {}", file))?;
-            
-            println!("Loading workflow from: {:?}", file);
-            let loader = WorkflowLoader::new();
-            let workflow_def = loader.load(&content)?;
-            
             println!("Workflow parsed successfully. Building graph...");
 
+            let input: serde_json::Value = match input {
+                Some(json) => serde_json::from_str(&json).context("--input must be valid JSON")?,
+                None => serde_json::Value::Null,
+            };
+
+            // Recorded so the run shows up in the execution history
+            let execution_id = Uuid::new_v4();
+
             let mut secrets = std::collections::HashMap::new();
-            if let Some(tid) = effective_team_id {
-                let storage = get_storage(args.server, db_url).await?;
-                let creds = storage.list_credentials(tid).await?;
-                for cred in creds {
-                    match rust_flow::storage::encryption::decrypt(&cred.data) {
-                        Ok(decrypted) => {
-                            secrets.insert(cred.id.to_string(), decrypted);
-                        }
-                        Err(e) => {
-                            eprintln!("Warning: Failed to decrypt credential {}: {}", cred.id, e);
-                        }
-                    }
-                }
+            if let (Some(tid), Some(storage)) = (effective_team_id, &storage) {
+                secrets = audit::read_secrets(storage.as_ref(), config.user_id, tid, &workflow_def, Some(execution_id)).await?;
                 println!("Loaded {} credentials for Team ID {}.", secrets.len(), tid);
             } else {
                 println!("No Team ID provided or active. Running without stored credentials.");
@@ -184,7 +373,42 @@ pub async fn handle_command(args: Args) -> Result<()> {
                 println!("Record limit set to: {}", l);
             }
 
-            let mut executor = workflow_def.to_executor(&secrets, debug_config)?;
+            // Approvals are addressed to the recorded execution
+            let mut recorded = false;
+            if let Some(storage) = &storage {
+                match storage.create_execution(execution_id, workflow_id, workflow_version, effective_team_id, "running").await {
+                    Ok(_) => recorded = true,
+                    Err(e) => eprintln!("Warning: Failed to record execution: {}", e),
+                }
+                if let Some(tid) = effective_team_id {
+                    let details = serde_json::json!({ "workflow_id": workflow_id });
+                    if let Err(e) = audit::record(storage.as_ref(), config.user_id, tid, "execution.start", format!("execution/{}", execution_id), Some(details)).await {
+                        eprintln!("Warning: Failed to audit the start of execution {}: {:#}", execution_id, e);
+                    }
+                }
+            }
+
+            let context = rust_flow::stream_engine::NodeContext {
+                storage: storage.clone(),
+                team_id: effective_team_id,
+                event_signal: None, // Events are picked up by the server's event bus
+                execution_id: recorded.then_some(execution_id),
+                workflow_id: None, // Schedules of local runs fire regardless of server leadership
+                resumable: false,
+                node_id: None,
+            };
+            let mut executor = match workflow_def.to_executor_with_context(&secrets, debug_config, context) {
+                Ok(executor) => executor,
+                Err(e) => {
+                    if let Some(storage) = storage.as_ref().filter(|_| recorded) {
+                        let _ = storage.update_execution(execution_id, "failed", Some(Utc::now()), Some(e.to_string())).await;
+                    }
+                    return Err(e);
+                }
+            };
+            for (node_id, value) in rust_flow::job_manager::JobSpec::manual_inputs(&workflow_def, &input) {
+                executor.inject_input(&node_id, value);
+            }
             
             let (tx, mut rx) = tokio::sync::broadcast::channel(100);
             executor.set_event_sender(tx);
@@ -216,28 +440,146 @@ pub async fn handle_command(args: Args) -> Result<()> {
             });
             
             println!("Starting execution...");
-            executor.run().await?;
-            println!("Execution finished.");
+            let result = executor.run().await;
+            if let Some(storage) = &storage {
+                let (status, error) = match &result {
+                    Ok(_) => ("completed", None),
+                    Err(e) => ("failed", Some(e.to_string())),
+                };
+                let _ = storage.update_execution(execution_id, status, Some(Utc::now()), error).await;
+            }
+            result?;
+            println!("Execution finished (ID: {}).", execution_id);
         }
-        Commands::SaveWorkflow { file, team_id, name, db_url } => {
+        Commands::SaveWorkflow { file, team_id, name, id, message, db_url } => {
             let config = Config::load()?;
-            let effective_team_id = team_id.or(config.active_team_id)
-                .ok_or_else(|| anyhow!("Team ID required. Use --team-id or switch to a team."))?;
-
             let storage = get_storage(args.server, db_url).await?;
             let content = fs::read_to_string(&file)?;
             let definition: serde_json::Value = serde_yaml::from_str(&content)?; 
-            
+
+            // A new version keeps the workflow's team
+            let before = match id {
+                Some(id) => Some(storage.get_workflow(id).await?.ok_or_else(|| anyhow!("Workflow {} not found", id))?),
+                None => None,
+            };
+            let effective_team_id = match &before {
+                Some(before) => before.account_id,
+                None => team_id.or(config.active_team_id)
+                    .ok_or_else(|| anyhow!("Team ID required. Use --team-id or switch to a team."))?,
+            };
+
             let workflow = WorkflowEntity {
-                id: Uuid::new_v4(),
-                account_id: effective_team_id,
-                name,
-                definition,
-                created_at: Utc::now(),
+                id: id.unwrap_or_else(Uuid::new_v4),
+                created_at: before.as_ref().map(|b| b.created_at).unwrap_or_else(Utc::now),
+                ..WorkflowEntity::new(effective_team_id, name, definition)
             };
             
-            storage.save_workflow(&workflow).await?;
-            println!("Workflow saved: {} (ID: {})", workflow.name, workflow.id);
+            let version = storage.save_workflow(&workflow, config.user_id, message.as_deref()).await?;
+            let audited = |wf: &WorkflowEntity| serde_json::json!({ "name": wf.name, "team_id": wf.account_id, "definition": wf.definition });
+            let diff = audit::diff(&before.as_ref().map(audited).unwrap_or_default(), &audited(&workflow));
+            audit::record(storage.as_ref(), config.user_id, effective_team_id, "workflow.save", format!("workflow/{}", workflow.id), Some(diff)).await?;
+            println!("Workflow saved: {} (ID: {}, version {})", workflow.name, workflow.id, version);
+        }
+        Commands::Workflow { cmd, db_url } => {
+            let storage = get_storage(args.server.clone(), db_url).await?;
+            let config = Config::load()?;
+            let workflow = |id: Uuid| {
+                let storage = &storage;
+                async move {
+                    storage.get_workflow(id).await?
+                        .filter(|w| w.deleted_at.is_none())
+                        .ok_or_else(|| anyhow!("Workflow {} not found", id))
+                }
+            };
+            match cmd {
+                WorkflowCommands::Update { id, file, name, message } => {
+                    let before = workflow(id).await?;
+                    let definition = match file {
+                        Some(file) => Some(serde_yaml::from_str::<serde_json::Value>(&fs::read_to_string(&file)?)?),
+                        None => None,
+                    };
+                    if definition.is_none() && name.is_none() {
+                        return Err(anyhow!("Nothing to update; pass --file and/or --name"));
+                    }
+                    let version = rust_flow::storage::update_workflow(storage.as_ref(), id, name, definition, config.user_id, message.as_deref()).await?;
+                    let after = workflow(id).await?;
+                    let audited = |wf: &WorkflowEntity| serde_json::json!({ "name": wf.name, "definition": wf.definition });
+                    let diff = audit::diff(&audited(&before), &audited(&after));
+                    audit::record(storage.as_ref(), config.user_id, before.account_id, "workflow.save", format!("workflow/{}", id), Some(diff)).await?;
+                    println!("Workflow saved: {} (ID: {}, version {})", after.name, id, version);
+                }
+                WorkflowCommands::Rename { id, name } => {
+                    let before = workflow(id).await?;
+                    let message = format!("Rename to {}", name);
+                    let version = rust_flow::storage::update_workflow(storage.as_ref(), id, Some(name.clone()), None, config.user_id, Some(&message)).await?;
+                    let diff = serde_json::json!({ "/name": { "before": before.name, "after": name } });
+                    audit::record(storage.as_ref(), config.user_id, before.account_id, "workflow.save", format!("workflow/{}", id), Some(diff)).await?;
+                    println!("Workflow {} renamed to {} (version {}).", id, name, version);
+                }
+                WorkflowCommands::Delete { id } => {
+                    let before = workflow(id).await?;
+                    if !storage.delete_workflow(id).await? {
+                        return Err(anyhow!("Workflow {} not found", id));
+                    }
+                    audit::record(storage.as_ref(), config.user_id, before.account_id, "workflow.delete", format!("workflow/{}", id), None).await?;
+                    println!("Workflow {} deleted. Its executions are kept.", id);
+                }
+                WorkflowCommands::Enable { id } | WorkflowCommands::Disable { id } => {
+                    let active = matches!(cmd, WorkflowCommands::Enable { .. });
+                    let before = workflow(id).await?;
+                    if !storage.set_workflow_active(id, active).await? {
+                        return Err(anyhow!("Workflow {} not found", id));
+                    }
+                    let action = if active { "workflow.enable" } else { "workflow.disable" };
+                    let diff = serde_json::json!({ "/active": { "before": before.active, "after": active } });
+                    audit::record(storage.as_ref(), config.user_id, before.account_id, action, format!("workflow/{}", id), Some(diff)).await?;
+                    println!("Workflow {} {}.", id, if active { "enabled" } else { "disabled" });
+                }
+                WorkflowCommands::History { id } => {
+                    let workflow = storage.get_workflow(id).await?
+                        .ok_or_else(|| anyhow!("Workflow {} not found", id))?;
+                    println!("Versions of {} (ID: {}):", workflow.name, workflow.id);
+                    for version in storage.list_workflow_versions(id).await? {
+                        let author = match version.author_id {
+                            Some(author_id) => storage.get_user(author_id).await?
+                                .map(|u| u.username)
+                                .unwrap_or_else(|| author_id.to_string()),
+                            None => "-".to_string(),
+                        };
+                        let current = if version.version == workflow.version { " (current)" } else { "" };
+                        println!("v{} {} by {}{}: {}", version.version, version.created_at.to_rfc3339(), author, current, version.message.as_deref().unwrap_or(""));
+                    }
+                }
+                WorkflowCommands::Diff { id, from, to } => {
+                    let to = match to {
+                        Some(to) => to,
+                        None => storage.get_workflow(id).await?.ok_or_else(|| anyhow!("Workflow {} not found", id))?.version,
+                    };
+                    let version = |v: i32| {
+                        let storage = &storage;
+                        async move {
+                            storage.get_workflow_version(id, v).await?
+                                .ok_or_else(|| anyhow!("Workflow {} has no version {}", id, v))
+                        }
+                    };
+                    let diff = version(from).await?.diff(&version(to).await?);
+                    println!("{}", serde_json::to_string_pretty(&diff)?);
+                }
+                WorkflowCommands::Rollback { id, version } => {
+                    let before = storage.get_workflow(id).await?
+                        .ok_or_else(|| anyhow!("Workflow {} not found", id))?;
+                    // Servers audit the rollback themselves
+                    let new_version = if let Some(url) = &args.server {
+                        remote_storage(url)?.rollback_workflow(id, version).await?
+                    } else {
+                        let new_version = rust_flow::storage::rollback_workflow(storage.as_ref(), id, version, config.user_id).await?;
+                        let details = serde_json::json!({ "version": { "before": before.version, "after": new_version, "restored": version } });
+                        audit::record(storage.as_ref(), config.user_id, before.account_id, "workflow.rollback", format!("workflow/{}", id), Some(details)).await?;
+                        new_version
+                    };
+                    println!("Workflow {} rolled back to version {} (saved as version {}).", id, version, new_version);
+                }
+            }
         }
         Commands::ListWorkflows { team_id, db_url } => {
             let config = Config::load()?;
@@ -248,7 +590,8 @@ pub async fn handle_command(args: Args) -> Result<()> {
             let workflows = storage.list_workflows(effective_team_id).await?;
             println!("Workflows for Team {}:", effective_team_id);
             for wf in workflows {
-                println!("- {} (ID: {})", wf.name, wf.id);
+                let disabled = if wf.active { "" } else { ", disabled" };
+                println!("- {} (ID: {}, version {}{})", wf.name, wf.id, wf.version, disabled);
             }
         }
         Commands::CreateCredential { name, credential_type, data, team_id, db_url } => {
@@ -256,8 +599,17 @@ pub async fn handle_command(args: Args) -> Result<()> {
             let effective_team_id = team_id.or(config.active_team_id)
                 .ok_or_else(|| anyhow!("Team ID required. Use --team-id or switch to a team."))?;
 
+            let credential_type = credentials::get_credential_type(&credential_type)?;
+            let data = match data {
+                Some(data) => credential_data(data),
+                None => prompt_credential_fields(&credential_type)?,
+            };
+            credential_type.validate(&data)?;
+
             let storage = get_storage(args.server, db_url).await?;
-            let cred = storage.create_credential(&name, &credential_type, &data, effective_team_id).await?;
+            let cred = storage.create_credential(&name, &credential_type.id, &data, effective_team_id).await?;
+            let diff = audit::diff(&serde_json::Value::Null, &serde_json::json!({ "name": cred.name, "credential_type": cred.credential_type }));
+            audit::record(storage.as_ref(), config.user_id, effective_team_id, "credential.create", format!("credential/{}", cred.id), Some(diff)).await?;
             println!("Credential created: {} (ID: {})", cred.name, cred.id);
         }
         Commands::ListCredentials { team_id, db_url } => {
@@ -267,11 +619,249 @@ pub async fn handle_command(args: Args) -> Result<()> {
 
             let storage = get_storage(args.server, db_url).await?;
             let creds = storage.list_credentials(effective_team_id).await?;
+            audit::record(storage.as_ref(), config.user_id, effective_team_id, "credential.list", format!("team/{}", effective_team_id), None).await?;
             println!("Credentials for Team {}:", effective_team_id);
             for cred in creds {
                 println!("- {} (Type: {}, ID: {})", cred.name, cred.credential_type, cred.id);
             }
         }
+        Commands::Executions { cmd, db_url } => {
+            let remote = args.server.is_some();
+            let storage = get_storage(args.server, db_url).await?;
+            match cmd {
+                ExecutionCommands::List { workflow_id, mut team_id, status, since, until, limit, offset } => {
+                    // Servers list the executions of a team or workflow
+                    if remote && team_id.is_none() && workflow_id.is_none() {
+                        team_id = Config::load()?.active_team_id;
+                    }
+                    let filter = ExecutionFilter { workflow_id, team_id, status, since, until, limit, offset };
+                    let executions = storage.list_executions(&filter).await?;
+                    if executions.is_empty() {
+                        println!("No executions found.");
+                    }
+                    for exec in executions {
+                        let workflow = exec.workflow_id.map(|id| id.to_string()).unwrap_or_else(|| "-".to_string());
+                        println!("{} {:<10} {} (Workflow: {})", exec.id, exec.status, exec.started_at.to_rfc3339(), workflow);
+                    }
+                }
+                ExecutionCommands::Show { id } => {
+                    let exec = storage.get_execution(id).await?
+                        .ok_or_else(|| anyhow!("Execution {} not found", id))?;
+                    println!("Execution: {}", exec.id);
+                    if let Some(workflow_id) = exec.workflow_id {
+                        match exec.workflow_version {
+                            Some(version) => println!("Workflow:  {} (version {})", workflow_id, version),
+                            None => println!("Workflow:  {}", workflow_id),
+                        }
+                    }
+                    println!("Status:    {}", exec.status);
+                    println!("Started:   {}", exec.started_at.to_rfc3339());
+                    if let Some(finished_at) = exec.finished_at {
+                        println!("Finished:  {} ({}s)", finished_at.to_rfc3339(), (finished_at - exec.started_at).num_seconds());
+                    }
+                    if let Some(error) = exec.error {
+                        println!("Error:     {}", error);
+                    }
+                }
+                ExecutionCommands::Logs { id } => {
+                    for event in storage.get_execution_logs(id).await? {
+                        println!("{}", serde_json::to_string(&event)?);
+                    }
+                }
+                ExecutionCommands::Replay { id, node, stub_side_effects, stub_nodes, file, timeout } => {
+                    let exec = storage.get_execution(id).await?
+                        .ok_or_else(|| anyhow!("Execution {} not found", id))?;
+                    let workflow = match exec.workflow_id {
+                        Some(workflow_id) => storage.get_workflow(workflow_id).await?,
+                        None => None,
+                    };
+
+                    let ran = match (exec.workflow_id, exec.workflow_version) {
+                        (Some(workflow_id), Some(version)) => storage.get_workflow_version(workflow_id, version).await?,
+                        _ => None,
+                    };
+
+                    // A local file first, then what the execution ran, then the workflow as saved now
+                    let definition: WorkflowDefinition = if let Some(path) = file {
+                        WorkflowLoader::new().load(&fs::read_to_string(&path).context("Failed to read workflow file")?)?
+                    } else if let Some(definition) = storage.get_execution_definition(id).await? {
+                        serde_json::from_value(definition)?
+                    } else if let Some(ran) = ran {
+                        serde_json::from_value(ran.definition)?
+                    } else if let Some(workflow) = &workflow {
+                        println!("Execution has no stored definition, replaying the current version of workflow {}.", workflow.id);
+                        serde_json::from_value(workflow.definition.clone())?
+                    } else {
+                        return Err(anyhow!("No definition found for execution {}; pass one with --file", id));
+                    };
+
+                    let secrets = match &workflow {
+                        Some(workflow) => audit::read_secrets(storage.as_ref(), Config::load()?.user_id, workflow.account_id, &definition, Some(id)).await?,
+                        None => std::collections::HashMap::new(),
+                    };
+
+                    let events = storage.get_execution_logs(id).await?;
+                    let options = ReplayOptions { node, stub_side_effects, stub_nodes, timeout: std::time::Duration::from_secs(timeout) };
+                    let replays = rust_flow::replay::replay(&definition, &events, &secrets, &options).await?;
+
+                    let mut differing = 0;
+                    for replay in &replays {
+                        let count = |ports: &Vec<Vec<serde_json::Value>>| ports.iter().map(Vec::len).sum::<usize>();
+                        let verdict = if replay.stubbed {
+                            "stubbed"
+                        } else if replay.matches() {
+                            "matches"
+                        } else {
+                            differing += 1;
+                            "DIFFERS"
+                        };
+                        println!("{:<8} {} ({}): {} in, {} out", verdict, replay.node_id, replay.node_type, count(&replay.inputs), count(&replay.outputs));
+                        if let Some(error) = &replay.error {
+                            println!("         Error: {}", error);
+                        }
+                        if !replay.stubbed && !replay.matches() {
+                            println!("         Replayed: {}", serde_json::to_string(&replay.outputs)?);
+                            println!("         Recorded: {}", serde_json::to_string(&replay.recorded_outputs)?);
+                        }
+                    }
+                    println!("{} nodes replayed, {} differ.", replays.len(), differing);
+                }
+            }
+        }
+        Commands::Credentials { cmd, db_url } => {
+            let config = Config::load()?;
+            match cmd {
+                CredentialCommands::Types => {
+                    for credential_type in credentials::get_credential_types() {
+                        println!("{} ({})", credential_type.id, credential_type.label);
+                        for property in &credential_type.properties {
+                            let required = if property.required { ", required" } else { "" };
+                            println!("    {:<12} {} [{}{}]", property.name, property.label, property.property_type, required);
+                        }
+                    }
+                }
+                CredentialCommands::Update { id, name, data } => {
+                    let storage = get_storage(args.server, db_url).await?;
+                    let before = credential(storage.as_ref(), id).await?;
+                    let credential_type = credentials::get_credential_type(&before.credential_type)?;
+                    let data = match data {
+                        Some(data) => Some(credential_data(data)),
+                        None if name.is_none() => Some(prompt_credential_fields(&credential_type)?),
+                        None => None,
+                    };
+                    if let Some(data) = &data {
+                        credential_type.validate(data)?;
+                    }
+                    if !storage.update_credential(id, name.as_deref(), data.as_ref()).await? {
+                        return Err(anyhow!("Credential {} not found", id));
+                    }
+                    let diff = rust_flow::server::credential_update_diff(&before, &rust_flow::server::UpdateCredentialRequest { name, data });
+                    audit::record(storage.as_ref(), config.user_id, before.account_id, "credential.update", format!("credential/{}", id), Some(diff)).await?;
+                    println!("Credential {} updated.", id);
+                }
+                CredentialCommands::Delete { id, force } => {
+                    let storage = get_storage(args.server, db_url).await?;
+                    let before = credential(storage.as_ref(), id).await?;
+                    let usage = rust_flow::storage::credential_usage(storage.as_ref(), &before).await?;
+                    if !usage.is_empty() && !force {
+                        for used in &usage {
+                            println!("- {} (ID: {}), node {}", used.workflow_name, used.workflow_id, used.node_id);
+                        }
+                        return Err(anyhow!("Credential {} is used by {} nodes of stored workflows; pass --force to delete it anyway", id, usage.len()));
+                    }
+                    if !storage.delete_credential(id, force).await? {
+                        return Err(anyhow!("Credential {} not found", id));
+                    }
+                    let diff = audit::diff(&serde_json::json!({ "name": before.name, "credential_type": before.credential_type }), &serde_json::Value::Null);
+                    audit::record(storage.as_ref(), config.user_id, before.account_id, "credential.delete", format!("credential/{}", id), Some(diff)).await?;
+                    println!("Credential {} deleted.", id);
+                }
+                CredentialCommands::Reveal { id } => {
+                    // Only the server can decrypt its credentials
+                    let fields = if let Some(url) = &args.server {
+                        remote_storage(url)?.reveal_credential(id).await?
+                            .ok_or_else(|| anyhow!("Credential {} not found", id))?
+                    } else {
+                        let storage = get_storage(None, db_url).await?;
+                        let cred = credential(storage.as_ref(), id).await?;
+                        let fields = rust_flow::storage::reveal_credential(&cred)?;
+                        audit::record(storage.as_ref(), config.user_id, cred.account_id, "credential.reveal", format!("credential/{}", id), None).await?;
+                        fields
+                    };
+                    for (name, value) in fields.as_object().into_iter().flatten() {
+                        println!("{}: {}", name, value.as_str().map(String::from).unwrap_or_else(|| value.to_string()));
+                    }
+                }
+                CredentialCommands::Usage { id } => {
+                    let storage = get_storage(args.server, db_url).await?;
+                    let cred = credential(storage.as_ref(), id).await?;
+                    let usage = rust_flow::storage::credential_usage(storage.as_ref(), &cred).await?;
+                    if usage.is_empty() {
+                        println!("Credential {} is not used by stored workflows.", id);
+                    }
+                    for used in usage {
+                        println!("- {} (ID: {}), node {}", used.workflow_name, used.workflow_id, used.node_id);
+                    }
+                }
+                CredentialCommands::RotateKey => {
+                    if args.server.is_some() {
+                        return Err(anyhow!("Key rotation runs against the database itself with the server's master keys, not --server"));
+                    }
+                    let keyring = encryption::keyring()?;
+                    let storage = get_storage(None, db_url).await?;
+                    let rotation = rust_flow::storage::rotate_credentials(storage.as_ref()).await?;
+                    println!(
+                        "Re-encrypted {} credentials under master key {} ({} already were).",
+                        rotation.rotated, keyring.current.id, rotation.current
+                    );
+                    for (id, error) in &rotation.failed {
+                        println!("Failed to decrypt credential {}: {}", id, error);
+                    }
+                    if !rotation.failed.is_empty() {
+                        return Err(anyhow!("{} credentials could not be re-encrypted; add their key to RUSTFLOW_PREVIOUS_MASTER_KEYS", rotation.failed.len()));
+                    }
+                }
+            }
+        }
+        Commands::Prune { max_age_days, max_per_workflow, failed_max_age_days, payload_sample, db_url } => {
+            if args.server.is_some() {
+                return Err(anyhow!("Pruning is not supported with --server: servers prune on their own (RUSTFLOW_RETENTION_*), or run it against the database itself"));
+            }
+            let mut policy = RetentionPolicy {
+                max_age_days,
+                max_per_workflow,
+                failed_max_age_days,
+                success_payload_sample: payload_sample,
+            };
+            if policy.is_empty() {
+                policy = rust_flow::retention::policy_from_env()?;
+            }
+            if policy.is_empty() {
+                return Err(anyhow!("Nothing to prune: pass a limit such as --max-age-days or set RUSTFLOW_RETENTION_* variables"));
+            }
+
+            let storage = get_storage(None, db_url).await?;
+            let stats = storage.prune_executions(&policy, Utc::now()).await?;
+            println!("Deleted {} executions and {} events.", stats.executions, stats.events);
+        }
+        Commands::Audit { team_id, limit, offset, db_url } => {
+            let config = Config::load()?;
+            let effective_team_id = team_id.or(config.active_team_id)
+                .ok_or_else(|| anyhow!("Team ID required. Use --team-id or switch to a team."))?;
+
+            let storage = get_storage(args.server, db_url).await?;
+            let entries = storage.list_audit(effective_team_id, limit, offset).await?;
+            if entries.is_empty() {
+                println!("No audit entries found.");
+            }
+            for entry in entries {
+                let actor = entry.actor_id.map(|id| id.to_string()).unwrap_or_else(|| "system".to_string());
+                println!("{} {:<18} {} by {}", entry.created_at.to_rfc3339(), entry.action, entry.target, actor);
+                if let Some(diff) = entry.diff.filter(|d| d.as_object().is_none_or(|o| !o.is_empty())) {
+                    println!("    {}", serde_json::to_string(&diff)?);
+                }
+            }
+        }
         Commands::ListNodes => {
             let registry = rust_flow::node_registry::get_node_registry();
             println!("Available Nodes:");
@@ -195,18 +195,20 @@ async fn credential_menu() -> Result<()> {
                 let name: String = Input::with_theme(&ColorfulTheme::default())
                     .with_prompt("Credential Name")
                     .interact_text()?;
-                let ctype: String = Input::with_theme(&ColorfulTheme::default())
-                    .with_prompt("Type (e.g. openai_api)")
-                    .interact_text()?;
-                let data: String = Input::with_theme(&ColorfulTheme::default())
-                    .with_prompt("Secret Data")
-                    .interact_text()?;
+                let types = rust_flow::credentials::get_credential_types();
+                let labels: Vec<String> = types.iter().map(|t| format!("{} ({})", t.label, t.id)).collect();
+                let type_index = Select::with_theme(&ColorfulTheme::default())
+                    .with_prompt("Type")
+                    .items(&labels)
+                    .default(0)
+                    .interact()?;
 
+                // The fields of the type are prompted for
                 handle_command(crate::cli::commands::Args {
                     command: Some(Commands::CreateCredential { 
                         name, 
-                        credential_type: ctype, 
-                        data, 
+                        credential_type: types[type_index].id.clone(), 
+                        data: None, 
                         team_id: None, 
                         db_url 
                     }),
@@ -0,0 +1,190 @@
+use anyhow::{Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use crate::integration_registry::CredentialProperty;
+
+/// Decrypted credentials of a team, keyed by credential id, each an object of its fields.
+pub type Secrets = HashMap<String, Value>;
+
+/// The fields a credential of a type holds, e.g. `slack` credentials hold a `token`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CredentialType {
+    pub id: String,
+    pub label: String,
+    pub properties: Vec<CredentialProperty>,
+}
+
+fn property(name: &str, label: &str, property_type: &str, description: Option<&str>) -> CredentialProperty {
+    CredentialProperty {
+        name: name.to_string(),
+        label: label.to_string(),
+        property_type: property_type.to_string(),
+        required: true,
+        description: description.map(|d| d.to_string()),
+    }
+}
+
+fn api_key_type(id: &str, label: &str) -> CredentialType {
+    CredentialType {
+        id: id.to_string(),
+        label: label.to_string(),
+        properties: vec![property("api_key", "API Key", "password", None)],
+    }
+}
+
+/// Credential types of the built-in nodes, then those declared by integrations.
+pub fn get_credential_types() -> Vec<CredentialType> {
+    let mut types = vec![
+        // The agent node's providers
+        api_key_type("openai_api", "OpenAI API"),
+        api_key_type("gemini_api", "Gemini API"),
+        api_key_type("api_key", "API Key"),
+        CredentialType {
+            id: "http_basic".to_string(),
+            label: "HTTP Basic Auth".to_string(),
+            properties: vec![
+                property("username", "Username", "text", None),
+                property("password", "Password", "password", None),
+            ],
+        },
+    ];
+    for integration in crate::integrations::get_integration_definitions() {
+        if !integration.credentials.is_empty() {
+            types.push(CredentialType {
+                id: integration.id,
+                label: integration.name,
+                properties: integration.credentials,
+            });
+        }
+    }
+    types
+}
+
+pub fn get_credential_type(id: &str) -> Result<CredentialType> {
+    let types = get_credential_types();
+    let known = types.iter().map(|t| t.id.clone()).collect::<Vec<_>>();
+    types.into_iter().find(|t| t.id == id)
+        .ok_or_else(|| anyhow!("Unknown credential type '{}' (known: {})", id, known.join(", ")))
+}
+
+impl CredentialType {
+    /// Checks `data` against the type's fields and returns them as an object. A string stands
+    /// for the only field of single-field types, e.g. an `openai_api` key.
+    pub fn validate(&self, data: &Value) -> Result<Value> {
+        let fields = match data {
+            Value::Object(fields) => fields.clone(),
+            Value::String(s) if self.properties.len() == 1 => {
+                Map::from_iter([(self.properties[0].name.clone(), Value::String(s.clone()))])
+            }
+            _ => bail!("Credentials of type '{}' are an object of fields: {}", self.id, self.field_names()),
+        };
+        if let Some(unknown) = fields.keys().find(|name| !self.properties.iter().any(|p| &p.name == *name)) {
+            bail!("Credentials of type '{}' have no field '{}' (fields: {})", self.id, unknown, self.field_names());
+        }
+        for property in &self.properties {
+            let value = fields.get(&property.name).filter(|v| !v.is_null() && v.as_str() != Some(""));
+            let Some(value) = value else {
+                if property.required {
+                    bail!("Field '{}' of '{}' credentials is required", property.name, self.id);
+                }
+                continue;
+            };
+            let valid = match property.property_type.as_str() {
+                "number" => value.is_number(),
+                "boolean" => value.is_boolean(),
+                "json" => true,
+                _ => value.is_string(),
+            };
+            if !valid {
+                bail!("Field '{}' of '{}' credentials must be {}", property.name, self.id, property.property_type);
+            }
+        }
+        Ok(Value::Object(fields))
+    }
+
+    fn field_names(&self) -> String {
+        self.properties.iter().map(|p| p.name.as_str()).collect::<Vec<_>>().join(", ")
+    }
+}
+
+/// The fields of a decrypted credential. Credentials stored before they had fields hold the bare
+/// secret, which becomes the first field of their type (`value` for unknown types).
+pub fn fields_from_plaintext(credential_type: &str, plaintext: &str) -> Value {
+    match serde_json::from_str::<Value>(plaintext) {
+        Ok(fields @ Value::Object(_)) => fields,
+        _ => {
+            let name = get_credential_type(credential_type).ok()
+                .and_then(|t| t.properties.first().map(|p| p.name.clone()))
+                .unwrap_or_else(|| "value".to_string());
+            Value::Object(Map::from_iter([(name, Value::String(plaintext.to_string()))]))
+        }
+    }
+}
+
+/// The fields with the secret ones (`password` fields, and any of unknown types) masked but for
+/// their last 4 characters, e.g. to tell which key a credential holds.
+pub fn mask_fields(credential_type: &str, fields: &Value) -> Value {
+    let credential_type = get_credential_type(credential_type).ok();
+    let Some(fields) = fields.as_object() else {
+        return Value::Null;
+    };
+    let masked = fields.iter().map(|(name, value)| {
+        let secret = credential_type.as_ref()
+            .and_then(|t| t.properties.iter().find(|p| &p.name == name))
+            .is_none_or(|p| p.property_type == "password");
+        let value = match value {
+            _ if !secret => value.clone(),
+            Value::String(s) if s.chars().count() >= 12 => {
+                let last: String = s.chars().skip(s.chars().count() - 4).collect();
+                Value::String(format!("********{}", last))
+            }
+            _ => Value::String("********".to_string()),
+        };
+        (name.clone(), value)
+    });
+    Value::Object(masked.collect())
+}
+
+/// The fields of the credential a node config references with `credential_id`, if the run has
+/// it; runs only get the credentials of their team.
+pub fn node_credential(config: &Value, secrets: &Secrets) -> Option<Value> {
+    config.get("credential_id").and_then(|v| v.as_str()).and_then(|id| secrets.get(id)).cloned()
+}
+
+/// Replaces `{{ credential.<field> }}` in the config's strings with the fields of the node's
+/// credential. Other templates are left for the node to render.
+pub fn resolve_config(config: Value, credential: Option<&Value>) -> Result<Value> {
+    Ok(match config {
+        Value::String(s) => Value::String(resolve_str(&s, credential)?),
+        Value::Array(items) => Value::Array(items.into_iter().map(|v| resolve_config(v, credential)).collect::<Result<_>>()?),
+        Value::Object(fields) => Value::Object(
+            fields.into_iter().map(|(k, v)| Ok((k, resolve_config(v, credential)?))).collect::<Result<_>>()?,
+        ),
+        other => other,
+    })
+}
+
+fn resolve_str(s: &str, credential: Option<&Value>) -> Result<String> {
+    let mut resolved = String::new();
+    let mut rest = s;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start..].find("}}") else { break };
+        let expression = rest[start + 2..start + len].trim();
+        resolved.push_str(&rest[..start]);
+        match expression.strip_prefix("credential.") {
+            Some(field) if !field.is_empty() && field.chars().all(|c| c.is_alphanumeric() || c == '_') => {
+                let credential = credential.ok_or_else(|| anyhow!("'{{{{ credential.{} }}}}' needs a credential_id of the run's team", field))?;
+                match credential.get(field) {
+                    Some(Value::String(value)) => resolved.push_str(value),
+                    Some(value) if !value.is_null() => resolved.push_str(&value.to_string()),
+                    _ => bail!("The credential has no field '{}'", field),
+                }
+            }
+            _ => resolved.push_str(&rest[start..start + len + 2]),
+        }
+        rest = &rest[start + len + 2..];
+    }
+    resolved.push_str(rest);
+    Ok(resolved)
+}
@@ -0,0 +1,140 @@
+use std::sync::Arc;
+use std::time::Duration;
+use serde_json::{json, Value};
+use anyhow::Result;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use crate::job_manager::{JobManager, JobSpec};
+use crate::schema::WorkflowDefinition;
+use crate::storage::{Storage, EventRecord};
+
+const DISPATCH_BATCH: i64 = 100;
+const LEADERSHIP: &str = "rustflow:event_bus";
+
+/// Delivers events published by `emit_event` nodes to subscribed workflows.
+///
+/// Events are persisted by the publisher (the `events` table acts as an outbox), so delivery
+/// survives restarts: pending events are picked up on the next dispatch. For each event, every
+/// stored workflow of the event's team with an `event_trigger` whose `event` matches is started
+/// with the event injected into that trigger. Delivery is at-least-once: an event stays pending
+/// until every subscriber started, and retries only start the subscribers that failed.
+pub struct EventBus {
+    storage: Arc<dyn Storage>,
+    job_manager: Arc<JobManager>,
+    signal: Arc<Notify>,
+}
+
+impl EventBus {
+    pub fn new(storage: Arc<dyn Storage>, job_manager: Arc<JobManager>) -> Self {
+        let signal = job_manager.event_signal();
+        Self { storage, job_manager, signal }
+    }
+
+    /// Delivers all pending events. Returns the ids of the jobs that were started.
+    /// Events some subscriber failed to start for stay pending until the next dispatch.
+    pub async fn dispatch_pending(&self) -> Result<Vec<String>> {
+        let mut started = Vec::new();
+        // Events published meanwhile, e.g. by the workflows started here, wait for the next dispatch
+        let dispatched_at = chrono::Utc::now();
+        // Undelivered events stay pending, so the next page starts after them
+        let mut offset = 0;
+        loop {
+            let events = self.storage.list_pending_events(DISPATCH_BATCH, offset).await?;
+            let last_page = (events.len() as i64) < DISPATCH_BATCH;
+            for event in &events {
+                if event.created_at > dispatched_at {
+                    return Ok(started);
+                }
+                if self.deliver(event, &mut started).await? {
+                    self.storage.mark_event_delivered(event.id).await?;
+                } else {
+                    offset += 1;
+                }
+            }
+            if last_page {
+                break;
+            }
+        }
+        Ok(started)
+    }
+
+    /// Starts the subscribers the event has not started yet. Returns false if one failed to start.
+    async fn deliver(&self, event: &EventRecord, started: &mut Vec<String>) -> Result<bool> {
+        let record = json!({
+            "event": event.name,
+            "event_id": event.id,
+            "team_id": event.team_id,
+            "payload": event.payload,
+            "emitted_at": event.created_at,
+        });
+
+        let delivered = self.storage.list_event_deliveries(event.id).await?;
+        let mut complete = true;
+        for workflow in self.storage.list_workflows(event.team_id).await? {
+            if !workflow.active || delivered.contains(&workflow.id) {
+                continue;
+            }
+            let definition: WorkflowDefinition = match serde_json::from_value(workflow.definition.clone()) {
+                Ok(def) => def,
+                Err(_) => continue, // Not a runnable definition; cannot subscribe
+            };
+
+            let triggers = subscribed_triggers(&definition, &event.name);
+            if triggers.is_empty() {
+                continue;
+            }
+
+            let spec = JobSpec {
+                workflow_id: Some(workflow.id),
+                workflow_version: Some(workflow.version),
+                team_id: Some(workflow.account_id),
+                inputs: triggers.into_iter().map(|id| (id, record.clone())).collect(),
+                ..Default::default()
+            };
+            match self.job_manager.start_workflow(&definition, spec).await {
+                Ok(job_id) => {
+                    tracing::info!("Event '{}' ({}) started workflow {} as job {}", event.name, event.id, workflow.id, job_id);
+                    self.storage.record_event_delivery(event.id, workflow.id).await?;
+                    started.push(job_id);
+                }
+                Err(e) => {
+                    tracing::error!("Event '{}' ({}): failed to start workflow {}, will retry: {}", event.name, event.id, workflow.id, e);
+                    complete = false;
+                }
+            }
+        }
+        Ok(complete)
+    }
+
+    /// Runs the dispatcher in the background. It wakes when a local `emit_event` fires,
+    /// and otherwise polls every `poll_interval` (events may come from other processes, e.g. the CLI).
+    /// When several servers share the storage, only the elected leader dispatches.
+    pub fn start(self, poll_interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                match self.storage.try_acquire_leadership(LEADERSHIP).await {
+                    Ok(true) => {
+                        if let Err(e) = self.dispatch_pending().await {
+                            tracing::error!("Event dispatch failed: {}", e);
+                        }
+                    }
+                    Ok(false) => {}
+                    Err(e) => tracing::error!("Event bus leader election failed: {}", e),
+                }
+                tokio::select! {
+                    _ = self.signal.notified() => {},
+                    _ = tokio::time::sleep(poll_interval) => {},
+                }
+            }
+        })
+    }
+}
+
+/// Ids of the `event_trigger` nodes in `definition` subscribed to `event_name`.
+fn subscribed_triggers(definition: &WorkflowDefinition, event_name: &str) -> Vec<String> {
+    definition.nodes.iter()
+        .filter(|n| n.node_type == "event_trigger")
+        .filter(|n| n.config.get("event").and_then(Value::as_str) == Some(event_name))
+        .map(|n| n.id.clone())
+        .collect()
+}
@@ -12,6 +12,8 @@ pub struct CredentialProperty {
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct IntegrationDefinition {
+    /// Also the id of the integration's credential type, e.g. `slack`
+    pub id: String,
     pub name: String,
     pub description: String,
     pub credentials: Vec<CredentialProperty>,
@@ -3,3 +3,12 @@
 #![allow(unused_variables)]
 include!(concat!(env!("OUT_DIR"), "/integrations.rs"));
 
+/// What an integration's templates see: the input record's fields, plus the record as `input`
+/// and the fields of the node's credential as `credential`.
+pub fn template_context(data: &serde_json::Value, credential: &serde_json::Value) -> serde_json::Value {
+    let mut context = data.as_object().cloned().unwrap_or_default();
+    context.insert("input".to_string(), data.clone());
+    context.insert("credential".to_string(), credential.clone());
+    serde_json::Value::Object(context)
+}
+
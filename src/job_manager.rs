@@ -1,11 +1,14 @@
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
 use uuid::Uuid;
 use serde::{Serialize, Deserialize};
-use tokio::sync::{mpsc, broadcast};
-use crate::stream_engine::StreamExecutor;
-use crate::schema::ExecutionEvent;
-use crate::storage::Storage;
+use serde_json::Value;
+use tokio::sync::{mpsc, broadcast, Notify};
+use anyhow::Result;
+use crate::stream_engine::{StreamExecutor, DebugConfig, NodeContext, NodeFailure};
+use crate::schema::{ConcurrencyPolicy, ExecutionEvent, WorkflowDefinition, WorkflowLoader};
+use crate::storage::{Storage, QueuedExecution, ExecutionFilter, ExecutionRecord};
 use chrono::Utc;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -14,6 +17,13 @@ pub enum JobStatus {
     Running,
     Completed,
     Failed(String),
+    /// Waiting for running executions of the same workflow (`concurrency: queue`).
+    Queued,
+    /// Not run because of the workflow's concurrency policy.
+    Skipped(String),
+    Cancelled,
+    /// Suspended until its durable timers are due (long `delay`s); resumed by `Timers`.
+    Waiting,
 }
 
 #[derive(Debug, Serialize)]
@@ -25,11 +35,100 @@ pub struct Job {
     pub log_sender: Option<mpsc::Sender<String>>,
     #[serde(skip)]
     pub event_sender: broadcast::Sender<ExecutionEvent>,
+    #[serde(skip)]
+    pub failure_handler: Option<FailureHandler>,
+    #[serde(skip)]
+    pub workflow_id: Option<Uuid>,
+    #[serde(skip)]
+    cancel: Arc<Notify>,
+}
+
+/// Workflow to start when a job fails, taken from `on_failure` of its definition.
+#[derive(Debug, Clone)]
+pub struct FailureHandler {
+    /// Stored workflow id, or a path to a workflow file.
+    pub target: String,
+    pub workflow_id: Option<Uuid>,
+    pub team_id: Option<Uuid>,
+}
+
+/// What to run and on whose behalf; see `JobManager::start_workflow`.
+#[derive(Debug, Clone, Default)]
+pub struct JobSpec {
+    /// Stored workflow being executed, recorded on the execution.
+    pub workflow_id: Option<Uuid>,
+    /// Version of the stored workflow being executed, recorded on the execution.
+    pub workflow_version: Option<i32>,
+    pub team_id: Option<Uuid>,
+    /// Records injected into trigger nodes, as (node id, value).
+    pub inputs: Vec<(String, Value)>,
+    /// Higher runs first when executions wait in the durable queue.
+    pub priority: i32,
+    /// User the run was started by, recorded as the actor of its credential reads.
+    pub actor_id: Option<Uuid>,
+}
+
+impl JobSpec {
+    /// Inputs handing `input` to every `manual_trigger` of `definition`, e.g. for runs started
+    /// through the API or CLI. A null input injects nothing (the triggers fire once with null).
+    pub fn manual_inputs(definition: &WorkflowDefinition, input: &Value) -> Vec<(String, Value)> {
+        if input.is_null() {
+            return Vec::new();
+        }
+        definition.nodes.iter()
+            .filter(|n| n.node_type == "manual_trigger")
+            .map(|n| (n.id.clone(), input.clone()))
+            .collect()
+    }
+}
+
+/// Running and queued jobs of one stored workflow, for its concurrency policy.
+#[derive(Default)]
+struct WorkflowRuns {
+    running: Vec<String>,
+    queued: VecDeque<QueuedExecution>,
+}
+
+enum Admission {
+    Start(QueuedExecution),
+    Queued,
+    Skip(&'static str),
+    Replace(Vec<String>, QueuedExecution),
+}
+
+/// How often idle workers look for executions enqueued by other processes.
+const WORKER_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Worker pool settings for the durable execution queue; see `JobManager::start_workers`.
+#[derive(Debug, Clone)]
+pub struct WorkerConfig {
+    pub workers: usize,
+    /// Identifies this process as the owner of the executions it runs.
+    pub worker_id: String,
+    pub heartbeat_interval: Duration,
+    /// Unfinished executions without a heartbeat for this long are taken over (their process died).
+    pub heartbeat_timeout: Duration,
+}
+
+impl Default for WorkerConfig {
+    fn default() -> Self {
+        Self {
+            workers: 4,
+            worker_id: Uuid::new_v4().to_string(),
+            heartbeat_interval: Duration::from_secs(10),
+            heartbeat_timeout: Duration::from_secs(60),
+        }
+    }
 }
 
 pub struct JobManager {
     jobs: Arc<Mutex<HashMap<String, Job>>>,
     storage: Arc<dyn Storage>,
+    event_signal: Arc<Notify>,
+    runs: Mutex<HashMap<Uuid, WorkflowRuns>>,
+    /// Set by `start_workers`: runs go through the execution queue in storage.
+    worker_config: OnceLock<WorkerConfig>,
+    work_signal: Notify,
 }
 
 // Cannot implement Default because Storage is required
@@ -40,23 +139,58 @@ impl JobManager {
         Self {
             jobs: Arc::new(Mutex::new(HashMap::new())),
             storage,
+            event_signal: Arc::new(Notify::new()),
+            runs: Mutex::new(HashMap::new()),
+            worker_config: OnceLock::new(),
+            work_signal: Notify::new(),
         }
     }
 
+    /// Signal fired by `emit_event` nodes of jobs started here; the event bus waits on it.
+    pub fn event_signal(&self) -> Arc<Notify> {
+        self.event_signal.clone()
+    }
+
     pub fn create_job(&self) -> String {
         let id_uuid = Uuid::new_v4();
-        let id = id_uuid.to_string();
         
         // Persist initial Pending state
         // We spawn this because create_job is sync currently.
         // Ideally create_job should be async. But to minimize refactor, we spawn.
         let storage = self.storage.clone();
         tokio::spawn(async move {
-            let _ = storage.create_execution(id_uuid, None, "pending").await;
+            let _ = storage.create_execution(id_uuid, None, None, None, "pending").await;
         });
 
+        self.register_job(id_uuid)
+    }
+
+    /// Like `create_job`, but with a given id, and the pending execution is persisted
+    /// (with its workflow, version and team) before returning.
+    pub async fn create_job_for_workflow(&self, id_uuid: Uuid, workflow_id: Option<Uuid>, workflow_version: Option<i32>, team_id: Option<Uuid>) -> String {
+        if let Err(e) = self.storage.create_execution(id_uuid, workflow_id, workflow_version, team_id, "pending").await {
+            eprintln!("Failed to persist execution {}: {}", id_uuid, e);
+        }
+        let id = self.register_job(id_uuid);
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(&id) {
+            job.workflow_id = workflow_id;
+        }
+        id
+    }
+
+    fn register_job(&self, id_uuid: Uuid) -> String {
+        let id = id_uuid.to_string();
+
         let (tx, mut rx) = mpsc::channel(100);
-        let (event_tx, _) = broadcast::channel(100);
+        let (event_tx, mut event_rx) = broadcast::channel(100);
+
+        // Persist events of every run of the job (it runs again when resumed)
+        let storage = self.storage.clone();
+        tokio::spawn(async move {
+            while let Ok(event) = event_rx.recv().await {
+                let _ = storage.log_execution_event(id_uuid, &event).await;
+            }
+        });
         
         let job = Job {
             id: id.clone(),
@@ -64,6 +198,9 @@ impl JobManager {
             logs: Vec::new(),
             log_sender: Some(tx),
             event_sender: event_tx,
+            failure_handler: None,
+            workflow_id: None,
+            cancel: Arc::new(Notify::new()),
         };
 
         let jobs = self.jobs.clone();
@@ -101,47 +238,586 @@ impl JobManager {
         self.jobs.lock().unwrap().get(id).map(|j| j.event_sender.subscribe())
     }
 
+    /// Cancels a running or queued job. Returns false if the job is unknown or already finished.
+    pub async fn cancel_job(&self, id: &str) -> bool {
+        let local = self.jobs.lock().unwrap().get(id).map(|job| (job.status.clone(), job.cancel.clone(), job.workflow_id));
+        let Some((status, cancel, workflow_id)) = local else {
+            // Queued in storage, or run by another process
+            return self.is_durable() && self.cancel_stored(id).await;
+        };
+
+        match status {
+            // Picked up by run_single_job, also if the job has not started yet
+            JobStatus::Pending | JobStatus::Running => {
+                cancel.notify_one();
+                true
+            }
+            JobStatus::Queued => {
+                let dequeued = workflow_id.is_some_and(|wf| {
+                    let mut runs = self.runs.lock().unwrap();
+                    let Some(runs) = runs.get_mut(&wf) else { return false };
+                    let before = runs.queued.len();
+                    runs.queued.retain(|q| q.id.to_string() != id);
+                    runs.queued.len() < before
+                });
+                if !dequeued {
+                    // Already handed its slot; cancel it like a running job
+                    cancel.notify_one();
+                    return true;
+                }
+                self.update_status(id, JobStatus::Cancelled);
+                let id_uuid = Uuid::parse_str(id).unwrap_or_default();
+                let _ = self.storage.update_execution(id_uuid, "cancelled", Some(Utc::now()), None).await;
+                true
+            }
+            JobStatus::Waiting => {
+                // Its timers are dropped when they are due
+                self.update_status(id, JobStatus::Cancelled);
+                let id_uuid = Uuid::parse_str(id).unwrap_or_default();
+                let _ = self.storage.update_execution(id_uuid, "cancelled", Some(Utc::now()), None).await;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Cancels an execution of the durable queue in storage: queued and waiting ones are not
+    /// claimed anymore, and running ones are cancelled by their worker on its next heartbeat.
+    async fn cancel_stored(&self, id: &str) -> bool {
+        let Ok(id_uuid) = Uuid::parse_str(id) else { return false };
+        match self.storage.get_execution(id_uuid).await {
+            Ok(Some(execution)) if matches!(execution.status.as_str(), "pending" | "queued" | "running" | "waiting") => {
+                self.storage.update_execution(id_uuid, "cancelled", Some(Utc::now()), None).await.is_ok()
+            }
+            _ => false,
+        }
+    }
+
+    /// Continues a suspended (`waiting`) execution from `node_id`, with `records` injected into it.
+    /// It goes through the execution queue, so workers must be running. Returns false if the
+    /// execution is not waiting.
+    pub async fn resume_execution(&self, id: Uuid, node_id: &str, records: Vec<Value>) -> Result<bool> {
+        let inputs: Vec<(String, Value)> = records.into_iter().map(|r| (node_id.to_string(), r)).collect();
+        if !self.storage.resume_execution(id, node_id, &serde_json::to_value(&inputs)?).await? {
+            return Ok(false);
+        }
+        self.work_signal.notify_one();
+        Ok(true)
+    }
+
+    /// Runs `definition` in the background with storage-backed node services and `spec.inputs`
+    /// injected, subject to the workflow's concurrency policy. Once `start_workers` was called, the
+    /// run goes through the durable execution queue instead. Nodes are built when the run starts.
+    /// Returns the job (execution) id, also when the job was queued or skipped.
+    pub async fn start_workflow(self: &Arc<Self>, definition: &WorkflowDefinition, spec: JobSpec) -> Result<String> {
+        definition.validate()?;
+        let id = Uuid::new_v4();
+        let job_id = id.to_string();
+        let run = QueuedExecution {
+            id,
+            workflow_id: spec.workflow_id,
+            team_id: spec.team_id,
+            definition: serde_json::to_value(definition)?,
+            inputs: serde_json::to_value(&spec.inputs)?,
+            priority: spec.priority,
+            resume_from: None,
+            started_by: spec.actor_id,
+        };
+
+        if self.is_durable() {
+            // The worker claiming the run keeps the job while running it
+            self.storage.create_execution(id, spec.workflow_id, spec.workflow_version, spec.team_id, "pending").await?;
+            // The rest of the policy is applied when a worker claims the run
+            match self.skip_reason(&definition.concurrency, &run).await {
+                Ok(Some(reason)) => self.skip_job(&job_id, reason).await,
+                Ok(None) => self.enqueue(run).await,
+                Err(e) => self.fail_job(&job_id, &e).await,
+            }
+            return Ok(job_id);
+        }
+
+        self.create_job_for_workflow(id, spec.workflow_id, spec.workflow_version, spec.team_id).await;
+        self.set_failure_handler(&job_id, definition, &spec);
+        if let Some(run) = self.admit(&definition.concurrency, run).await {
+            let manager = self.clone();
+            tokio::spawn(async move {
+                let executor = manager.executor_for(&run).await;
+                manager.run_jobs(VecDeque::from([(run.id.to_string(), executor)])).await;
+            });
+        }
+
+        Ok(job_id)
+    }
+
+    /// Switches to the durable execution queue: runs are persisted as `queued` executions and
+    /// executed by `config.workers` background workers, highest priority first. Concurrency
+    /// policies then hold across every process sharing the queue. Several processes
+    /// may share one queue (Postgres); each heartbeats the executions it runs, and executions of
+    /// dead processes are taken over once their heartbeat expired, also those of a crashed
+    /// previous run of this process.
+    pub async fn start_workers(self: &Arc<Self>, config: WorkerConfig) -> Result<()> {
+        if self.worker_config.set(config.clone()).is_err() {
+            return Err(anyhow::anyhow!("Workers already started"));
+        }
+
+        self.recover_expired(&config).await?;
+
+        let workers = config.workers.max(1);
+        let manager = self.clone();
+        tokio::spawn(async move {
+            manager.heartbeat(config).await;
+        });
+        for _ in 0..workers {
+            let manager = self.clone();
+            tokio::spawn(async move {
+                manager.work().await;
+            });
+        }
+        Ok(())
+    }
+
+    /// Whether runs go through the durable execution queue (see `start_workers`). Their jobs are
+    /// then only known to the process running them, and storage has the status of the rest.
+    pub fn is_durable(&self) -> bool {
+        self.worker_config.get().is_some()
+    }
+
+    async fn heartbeat(&self, config: WorkerConfig) {
+        let mut ticker = tokio::time::interval(config.heartbeat_interval);
+        loop {
+            ticker.tick().await;
+
+            // Everything this process still has to finish, including runs waiting for a concurrency slot
+            let alive: Vec<Uuid> = self.jobs.lock().unwrap().values()
+                .filter(|j| matches!(j.status, JobStatus::Pending | JobStatus::Running | JobStatus::Queued))
+                .filter_map(|j| Uuid::parse_str(&j.id).ok())
+                .collect();
+            if let Err(e) = self.storage.heartbeat_executions(&config.worker_id, &alive).await {
+                tracing::error!("Failed to send execution heartbeats: {}", e);
+            }
+
+            // Cancelled through another process, or replaced by a run claimed there
+            for id in alive {
+                if let Ok(Some(execution)) = self.storage.get_execution(id).await {
+                    if execution.status == "cancelled" {
+                        self.cancel_job(&id.to_string()).await;
+                    }
+                }
+            }
+
+            if let Err(e) = self.recover_expired(&config).await {
+                tracing::error!("Failed to recover expired executions: {}", e);
+            }
+        }
+    }
+
+    async fn recover_expired(&self, config: &WorkerConfig) -> Result<()> {
+        let cutoff = Utc::now() - chrono::Duration::from_std(config.heartbeat_timeout)?;
+        let recovered = self.storage.recover_expired_executions(cutoff).await?;
+        if recovered > 0 {
+            tracing::info!("Requeued {} interrupted executions", recovered);
+            self.work_signal.notify_waiters();
+        }
+        Ok(())
+    }
+
+    async fn work(&self) {
+        let Some(worker_id) = self.worker_config.get().map(|c| c.worker_id.clone()) else { return };
+        loop {
+            match self.storage.claim_execution(&worker_id).await {
+                Ok(Some(run)) => self.run_claimed(run).await,
+                Ok(None) => {
+                    tokio::select! {
+                        _ = self.work_signal.notified() => {},
+                        _ = tokio::time::sleep(WORKER_POLL_INTERVAL) => {},
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Failed to claim execution: {}", e);
+                    tokio::time::sleep(WORKER_POLL_INTERVAL).await;
+                }
+            }
+        }
+    }
+
+    async fn run_claimed(&self, run: QueuedExecution) {
+        let id = run.id.to_string();
+        // Resumed executions went through the policy when they first started
+        if run.resume_from.is_none() {
+            match self.admit_claimed(&run).await {
+                Ok(true) => {}
+                Ok(false) => return,
+                Err(e) => return self.fail_job(&id, &e).await,
+            }
+        }
+
+        // Evicted again by run_jobs once the run finished
+        self.register_job(run.id);
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(&id) {
+            job.workflow_id = run.workflow_id;
+        }
+        if let Ok(definition) = serde_json::from_value::<WorkflowDefinition>(run.definition.clone()) {
+            let spec = JobSpec { workflow_id: run.workflow_id, team_id: run.team_id, ..Default::default() };
+            self.set_failure_handler(&id, &definition, &spec);
+        }
+
+        let executor = self.executor_for(&run).await;
+        self.run_jobs(VecDeque::from([(id, executor)])).await;
+    }
+
+    /// Persists a run in the execution queue and wakes a worker.
+    async fn enqueue(&self, run: QueuedExecution) {
+        let id = run.id.to_string();
+        match self.storage.enqueue_execution(&run).await {
+            Ok(_) => {
+                self.update_status(&id, JobStatus::Queued);
+                self.work_signal.notify_one();
+            }
+            Err(e) => self.fail_job(&id, &e.context("Failed to enqueue execution")).await,
+        }
+    }
+
+    /// Applies the concurrency policy of a stored workflow to a run claimed from the durable
+    /// queue, against the executions of the workflow running in any process. Returns whether the
+    /// run goes ahead. Runs claimed at the same time are decided by when they were started.
+    async fn admit_claimed(&self, run: &QueuedExecution) -> Result<bool> {
+        let Some(workflow_id) = run.workflow_id else { return Ok(true) };
+        let policy = serde_json::from_value::<WorkflowDefinition>(run.definition.clone())?.concurrency;
+        if policy.is_parallel() {
+            return Ok(true);
+        }
+
+        let order = |e: &ExecutionRecord| (e.started_at, e.id);
+        let this = self.storage.get_execution(run.id).await?
+            .ok_or_else(|| anyhow::anyhow!("Execution {} not found", run.id))?;
+        let filter = ExecutionFilter { workflow_id: Some(workflow_id), status: Some("running".to_string()), limit: i64::MAX, ..Default::default() };
+        let (earlier, later): (Vec<_>, Vec<_>) = self.storage.list_executions(&filter).await?.into_iter()
+            .filter(|e| e.id != run.id)
+            .partition(|e| order(e) < order(&this));
+
+        let id = run.id.to_string();
+        match policy {
+            ConcurrencyPolicy::Parallel => Ok(true),
+            _ if earlier.is_empty() && later.is_empty() => Ok(true),
+            ConcurrencyPolicy::Skip if earlier.is_empty() => Ok(true),
+            ConcurrencyPolicy::Skip => {
+                self.skip_job(&id, "Skipped: workflow is already running").await;
+                Ok(false)
+            }
+            // Claimed along with an earlier run: back in line
+            ConcurrencyPolicy::Queue { .. } if earlier.is_empty() => Ok(true),
+            ConcurrencyPolicy::Queue { .. } => {
+                self.storage.enqueue_execution(run).await?;
+                self.update_status(&id, JobStatus::Queued);
+                Ok(false)
+            }
+            ConcurrencyPolicy::Replace if !later.is_empty() => {
+                self.update_status(&id, JobStatus::Cancelled);
+                self.storage.update_execution(run.id, "cancelled", Some(Utc::now()), None).await?;
+                Ok(false)
+            }
+            ConcurrencyPolicy::Replace => {
+                for old in earlier {
+                    self.cancel_job(&old.id.to_string()).await;
+                }
+                Ok(true)
+            }
+        }
+    }
+
+    /// Why a new durable run of a stored workflow is skipped right away, if it is: `skip`
+    /// workflows with a run queued or running, and `queue` workflows whose queue is full.
+    async fn skip_reason(&self, policy: &ConcurrencyPolicy, run: &QueuedExecution) -> Result<Option<&'static str>> {
+        let Some(workflow_id) = run.workflow_id else { return Ok(None) };
+        let (max_waiting, reason) = match policy {
+            ConcurrencyPolicy::Skip => (0, "Skipped: workflow is already running"),
+            ConcurrencyPolicy::Queue { max_depth } => (*max_depth, "Skipped: execution queue is full"),
+            ConcurrencyPolicy::Parallel | ConcurrencyPolicy::Replace => return Ok(None),
+        };
+
+        // One of the queued runs may be about to start rather than waiting. Running ones are
+        // counted first, so a run claimed in between is not counted twice.
+        let mut active = 0;
+        for status in ["running", "queued"] {
+            let filter = ExecutionFilter { workflow_id: Some(workflow_id), status: Some(status.to_string()), limit: max_waiting as i64 + 1, ..Default::default() };
+            active += self.storage.list_executions(&filter).await?.len();
+        }
+        Ok((active > max_waiting).then_some(reason))
+    }
+
+    async fn skip_job(&self, id: &str, reason: &str) {
+        self.update_status(id, JobStatus::Skipped(reason.to_string()));
+        let id_uuid = Uuid::parse_str(id).unwrap_or_default();
+        let _ = self.storage.update_execution(id_uuid, "skipped", Some(Utc::now()), Some(reason.to_string())).await;
+    }
+
+    async fn fail_job(&self, id: &str, error: &anyhow::Error) {
+        self.update_status(id, JobStatus::Failed(error.to_string()));
+        let id_uuid = Uuid::parse_str(id).unwrap_or_default();
+        let _ = self.storage.update_execution(id_uuid, "failed", Some(Utc::now()), Some(error.to_string())).await;
+    }
+
+    /// Applies the concurrency policy of a stored workflow to a new run started in this process
+    /// (without workers). Returns the run if it may start now. Ad-hoc runs (no workflow id) always start.
+    async fn admit(&self, policy: &ConcurrencyPolicy, run: QueuedExecution) -> Option<QueuedExecution> {
+        let Some(workflow_id) = run.workflow_id else {
+            return Some(run);
+        };
+        let id = &run.id.to_string();
+
+        let admission = {
+            let mut runs = self.runs.lock().unwrap();
+            let runs = runs.entry(workflow_id).or_default();
+            if runs.running.is_empty() || policy.is_parallel() {
+                runs.running.push(id.to_string());
+                Admission::Start(run)
+            } else {
+                match policy {
+                    ConcurrencyPolicy::Parallel => unreachable!(),
+                    ConcurrencyPolicy::Skip => Admission::Skip("Skipped: workflow is already running"),
+                    ConcurrencyPolicy::Queue { max_depth } if runs.queued.len() >= *max_depth => {
+                        Admission::Skip("Skipped: execution queue is full")
+                    }
+                    ConcurrencyPolicy::Queue { .. } => {
+                        runs.queued.push_back(run);
+                        Admission::Queued
+                    }
+                    ConcurrencyPolicy::Replace => {
+                        let replaced = std::mem::replace(&mut runs.running, vec![id.to_string()]);
+                        Admission::Replace(replaced, run)
+                    }
+                }
+            }
+        };
+
+        let id_uuid = Uuid::parse_str(id).unwrap_or_default();
+        match admission {
+            Admission::Start(run) => Some(run),
+            Admission::Queued => {
+                self.update_status(id, JobStatus::Queued);
+                let _ = self.storage.update_execution(id_uuid, "queued", None, None).await;
+                None
+            }
+            Admission::Skip(reason) => {
+                self.skip_job(id, reason).await;
+                None
+            }
+            Admission::Replace(replaced, run) => {
+                for old in replaced {
+                    self.cancel_job(&old).await;
+                }
+                Some(run)
+            }
+        }
+    }
+
+    /// Frees the concurrency slot of a finished job and hands it to the next queued run, if any.
+    fn release_slot(&self, id: &str) -> Option<QueuedExecution> {
+        let workflow_id = self.jobs.lock().unwrap().get(id).and_then(|j| j.workflow_id)?;
+        let mut runs = self.runs.lock().unwrap();
+        let runs = runs.get_mut(&workflow_id)?;
+
+        let before = runs.running.len();
+        runs.running.retain(|r| r != id);
+        if runs.running.len() == before {
+            return None; // Not admitted through start_workflow, or replaced
+        }
+
+        let next = runs.queued.pop_front()?;
+        runs.running.push(next.id.to_string());
+        Some(next)
+    }
+
+    /// Builds the nodes of a run; `resumed` runs read their credentials without auditing them again.
+    async fn build_executor(&self, definition: &WorkflowDefinition, spec: &JobSpec, execution_id: Uuid, resumed: bool) -> Result<StreamExecutor> {
+        let context = NodeContext {
+            storage: Some(self.storage.clone()),
+            team_id: spec.team_id,
+            event_signal: Some(self.event_signal.clone()),
+            execution_id: Some(execution_id),
+            workflow_id: spec.workflow_id,
+            resumable: self.is_durable(),
+            node_id: None,
+        };
+
+        // Nodes reference team credentials by id (e.g. `credential_id`)
+        let secrets = match spec.team_id {
+            Some(team_id) if resumed => crate::storage::referenced_secrets(self.storage.as_ref(), team_id, definition).await?,
+            Some(team_id) => crate::audit::read_secrets(self.storage.as_ref(), spec.actor_id, team_id, definition, Some(execution_id)).await?,
+            None => HashMap::new(),
+        };
+        let mut executor = definition.to_executor_with_context(&secrets, DebugConfig::default(), context)?;
+        for (node_id, value) in &spec.inputs {
+            executor.inject_input(node_id, value.clone());
+        }
+        Ok(executor)
+    }
+
+    async fn executor_for(&self, run: &QueuedExecution) -> Result<StreamExecutor> {
+        let mut definition: WorkflowDefinition = serde_json::from_value(run.definition.clone())?;
+        if let Some(node_id) = &run.resume_from {
+            definition = definition.downstream_of(node_id)?;
+            // The resumed delay hands its records on right away; a resumed approval hands on decisions
+            if let Some(node) = definition.nodes.iter_mut().find(|n| &n.id == node_id) {
+                node.config = match node.node_type.as_str() {
+                    "approval" => serde_json::json!({ "resume": true }),
+                    _ => serde_json::json!({ "duration_ms": 0 }),
+                };
+            }
+        }
+        let spec = JobSpec {
+            workflow_id: run.workflow_id,
+            workflow_version: None, // Recorded when the execution was created
+            team_id: run.team_id,
+            inputs: if run.inputs.is_null() { Vec::new() } else { serde_json::from_value(run.inputs.clone())? },
+            priority: run.priority,
+            actor_id: run.started_by,
+        };
+        self.build_executor(&definition, &spec, run.id, run.resume_from.is_some()).await
+    }
+
+    fn set_failure_handler(&self, job_id: &str, definition: &WorkflowDefinition, spec: &JobSpec) {
+        if let Some(target) = &definition.on_failure {
+            if let Some(job) = self.jobs.lock().unwrap().get_mut(job_id) {
+                job.failure_handler = Some(FailureHandler {
+                    target: target.clone(),
+                    workflow_id: spec.workflow_id,
+                    team_id: spec.team_id,
+                });
+            }
+        }
+    }
+
     pub async fn run_job(&self, id: String, executor: StreamExecutor) {
+        self.run_jobs(VecDeque::from([(id, Ok(executor))])).await;
+    }
+
+    /// Runs jobs whose executors were built, failing those that could not be built.
+    async fn run_jobs(&self, mut pending: VecDeque<(String, Result<StreamExecutor>)>) {
+        // A finished job may hand over to its failure handler and to the next queued
+        // job of the same workflow, which run in this same task
+        while let Some((id, executor)) = pending.pop_front() {
+            match executor {
+                Ok(executor) => {
+                    if let Some((handler_id, handler)) = self.run_single_job(id.clone(), executor).await {
+                        pending.push_back((handler_id, Ok(handler)));
+                    }
+                }
+                Err(e) => self.fail_job(&id, &e).await,
+            }
+
+            if self.is_durable() {
+                // Its execution is in storage; runs of the same workflow may have waited for it
+                self.jobs.lock().unwrap().remove(&id);
+                self.work_signal.notify_waiters();
+            } else if let Some(next) = self.release_slot(&id) {
+                let executor = self.executor_for(&next).await;
+                pending.push_back((next.id.to_string(), executor));
+            }
+        }
+    }
+
+    async fn run_single_job(&self, id: String, executor: StreamExecutor) -> Option<(String, StreamExecutor)> {
         let id_uuid = Uuid::parse_str(&id).unwrap_or_default(); // Should be valid as we generated it
 
         self.update_status(&id, JobStatus::Running);
         let _ = self.storage.update_execution(id_uuid, "running", None, None).await;
 
-        // Subscribe to events for persistence
-        if let Some(mut rx) = self.subscribe_to_events(&id) {
-            let storage = self.storage.clone();
-            let log_id_uuid = id_uuid;
-            tokio::spawn(async move {
-                while let Ok(event) = rx.recv().await {
-                    let _ = storage.log_execution_event(log_id_uuid, &event).await;
-                }
-            });
-        }
-        
         // Inject event sender
-        let event_sender = {
+        let job_handles = {
             let jobs = self.jobs.lock().unwrap();
-            jobs.get(&id).map(|j| j.event_sender.clone())
+            jobs.get(&id).map(|j| (j.event_sender.clone(), j.cancel.clone()))
         };
 
-        if let Some(sender) = event_sender {
+        if let Some((sender, cancel)) = job_handles {
             let mut executor = executor;
             executor.set_event_sender(sender);
             
-            let result = executor.run().await;
+            // Dropping the run on cancellation aborts all node tasks
+            let result = tokio::select! {
+                result = executor.run() => result,
+                _ = cancel.notified() => {
+                    self.update_status(&id, JobStatus::Cancelled);
+                    let _ = self.storage.update_execution(id_uuid, "cancelled", Some(Utc::now()), None).await;
+                    return None;
+                }
+            };
             
             match result {
                 Ok(_) => {
-                    self.update_status(&id, JobStatus::Completed);
-                    let _ = self.storage.update_execution(id_uuid, "completed", Some(Utc::now()), None).await;
+                    // Records held by durable delays or undecided approvals keep the execution open
+                    if self.storage.count_waiting_timers(id_uuid).await.unwrap_or(0) > 0
+                        || self.storage.count_open_approvals(id_uuid).await.unwrap_or(0) > 0 {
+                        self.update_status(&id, JobStatus::Waiting);
+                        let _ = self.storage.update_execution(id_uuid, "waiting", None, None).await;
+                    } else {
+                        self.update_status(&id, JobStatus::Completed);
+                        let _ = self.storage.update_execution(id_uuid, "completed", Some(Utc::now()), None).await;
+                    }
                 },
                 Err(e) => {
                     self.update_status(&id, JobStatus::Failed(e.to_string()));
                     let _ = self.storage.update_execution(id_uuid, "failed", Some(Utc::now()), Some(e.to_string())).await;
+                    return self.prepare_failure_handler(&id, &e).await;
                 },
             }
         } else {
              self.update_status(&id, JobStatus::Failed("Job not found during run".to_string()));
         }
+        None
+    }
+
+    /// Builds the `on_failure` workflow of a failed job, with the `error_trigger` record injected.
+    async fn prepare_failure_handler(&self, id: &str, error: &anyhow::Error) -> Option<(String, StreamExecutor)> {
+        let handler = self.jobs.lock().unwrap().get(id).and_then(|j| j.failure_handler.clone())?;
+
+        let prepared = async {
+            let (mut definition, workflow_id, workflow_version, team_id) = match Uuid::parse_str(&handler.target) {
+                Ok(wf_id) => {
+                    let workflow = self.storage.get_workflow(wf_id).await?
+                        .ok_or_else(|| anyhow::anyhow!("Workflow {} not found", wf_id))?;
+                    // Handlers run with their team's credentials
+                    if Some(workflow.account_id) != handler.team_id {
+                        anyhow::bail!("Workflow {} does not belong to the failed execution's team", wf_id);
+                    }
+                    workflow.ensure_runnable()?;
+                    let definition: WorkflowDefinition = serde_json::from_value(workflow.definition)?;
+                    (definition, Some(workflow.id), Some(workflow.version), Some(workflow.account_id))
+                }
+                Err(_) => {
+                    let content = std::fs::read_to_string(&handler.target)
+                        .map_err(|e| anyhow::anyhow!("Failed to read workflow file '{}': {}", handler.target, e))?;
+                    (WorkflowLoader::new().load(&content)?, None, None, handler.team_id)
+                }
+            };
+            definition.on_failure = None; // Handlers do not chain
+
+            let failure = error.downcast_ref::<NodeFailure>();
+            let record = serde_json::json!({
+                "execution_id": id,
+                "workflow_id": handler.workflow_id,
+                "failed_node": failure.map(|f| f.node_id.clone()),
+                "error": error.to_string(),
+                "last_input": failure.and_then(|f| f.last_input.clone()),
+                "failed_at": Utc::now(),
+            });
+            let inputs = definition.nodes.iter()
+                .filter(|n| n.node_type == "error_trigger")
+                .map(|n| (n.id.clone(), record.clone()))
+                .collect();
+
+            let handler_id = Uuid::new_v4();
+            let executor = self.build_executor(&definition, &JobSpec { workflow_id, workflow_version, team_id, inputs, ..Default::default() }, handler_id, false).await?;
+            let handler_id = self.create_job_for_workflow(handler_id, workflow_id, workflow_version, team_id).await;
+            Ok::<_, anyhow::Error>((handler_id, executor))
+        };
+
+        match prepared.await {
+            Ok(job) => Some(job),
+            Err(e) => {
+                eprintln!("Failed to start failure handler '{}' for job {}: {}", handler.target, id, e);
+                None
+            }
+        }
     }
 }
@@ -3,10 +3,18 @@ pub mod schema;
 pub mod storage;
 pub mod node_registry;
 pub mod integration_registry;
+pub mod credentials;
 pub mod integrations;
 pub mod job_manager;
+pub mod event_bus;
+pub mod timers;
+pub mod replay;
+pub mod retention;
+pub mod auth;
 
 // Re-export the main components from stream_engine
 pub use stream_engine::{StreamExecutor as Executor, StreamNode as Node};
 // We can also re-export nodes for convenience
 
+pub mod audit;
+pub mod server;
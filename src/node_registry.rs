@@ -107,6 +107,153 @@ fn get_trigger_nodes() -> Vec<NodeType> {
             ],
             outputs: vec![],
         },
+        NodeType {
+            id: "sql_poll_trigger".to_string(),
+            label: "SQL Change Poll".to_string(),
+            category: "Trigger".to_string(),
+            description: Some("Poll a SQLite/Postgres table and emit rows newer than the stored watermark".to_string()),
+            documentation: Some("Runs the query every interval with the last seen watermark bound to `$1` and emits each new row. The watermark is persisted, so restarts resume where they left off. Postgres timestamps should be cast (e.g. `updated_at::text > $1`) because the generic driver only decodes scalar types.".to_string()),
+            properties: vec![
+                NodeProperty {
+                    name: "connection_string".to_string(),
+                    label: "Connection String".to_string(),
+                    property_type: "text".to_string(),
+                    options: None,
+                    default: None,
+                    required: true,
+                    json_schema: None,
+                },
+                NodeProperty {
+                    name: "table".to_string(),
+                    label: "Table".to_string(),
+                    property_type: "text".to_string(),
+                    options: None,
+                    default: None,
+                    required: false,
+                    json_schema: None,
+                },
+                NodeProperty {
+                    name: "watermark_column".to_string(),
+                    label: "Watermark Column".to_string(),
+                    property_type: "text".to_string(),
+                    options: None,
+                    default: Some("id".to_string()),
+                    required: true,
+                    json_schema: None,
+                },
+                NodeProperty {
+                    name: "query".to_string(),
+                    label: "Custom Query (use $1 for the watermark)".to_string(),
+                    property_type: "code".to_string(),
+                    options: None,
+                    default: None,
+                    required: false,
+                    json_schema: None,
+                },
+                NodeProperty {
+                    name: "initial_watermark".to_string(),
+                    label: "Initial Watermark".to_string(),
+                    property_type: "json".to_string(),
+                    options: None,
+                    default: Some("0".to_string()),
+                    required: false,
+                    json_schema: None,
+                },
+                NodeProperty {
+                    name: "interval_seconds".to_string(),
+                    label: "Interval (seconds)".to_string(),
+                    property_type: "number".to_string(),
+                    options: None,
+                    default: Some("60".to_string()),
+                    required: false,
+                    json_schema: None,
+                },
+                NodeProperty {
+                    name: "batch_size".to_string(),
+                    label: "Batch Size".to_string(),
+                    property_type: "number".to_string(),
+                    options: None,
+                    default: Some("100".to_string()),
+                    required: false,
+                    json_schema: None,
+                },
+                NodeProperty {
+                    name: "state_key".to_string(),
+                    label: "State Key".to_string(),
+                    property_type: "text".to_string(),
+                    options: None,
+                    default: None,
+                    required: false,
+                    json_schema: None,
+                },
+            ],
+            outputs: vec![],
+        },
+        NodeType {
+            id: "pg_notify_trigger".to_string(),
+            label: "Postgres NOTIFY".to_string(),
+            category: "Trigger".to_string(),
+            description: Some("LISTEN on Postgres channels and emit each NOTIFY payload".to_string()),
+            documentation: Some("Emits `{ channel, payload, process_id }` for every notification; the payload is parsed as JSON when possible. The connection is re-established automatically if it drops.".to_string()),
+            properties: vec![
+                NodeProperty {
+                    name: "connection_string".to_string(),
+                    label: "Connection String".to_string(),
+                    property_type: "text".to_string(),
+                    options: None,
+                    default: None,
+                    required: true,
+                    json_schema: None,
+                },
+                NodeProperty {
+                    name: "channels".to_string(),
+                    label: "Channels (comma separated)".to_string(),
+                    property_type: "text".to_string(),
+                    options: None,
+                    default: None,
+                    required: true,
+                    json_schema: None,
+                },
+                NodeProperty {
+                    name: "reconnect_delay_ms".to_string(),
+                    label: "Reconnect Delay (ms)".to_string(),
+                    property_type: "number".to_string(),
+                    options: None,
+                    default: Some("1000".to_string()),
+                    required: false,
+                    json_schema: None,
+                },
+            ],
+            outputs: vec![],
+        },
+        NodeType {
+            id: "error_trigger".to_string(),
+            label: "Error Trigger".to_string(),
+            category: "Trigger".to_string(),
+            description: Some("Start when a workflow that names this one in `on_failure` fails".to_string()),
+            documentation: Some("Emits `{ execution_id, workflow_id, failed_node, error, last_input, failed_at }` for the failed execution.".to_string()),
+            properties: vec![],
+            outputs: vec![],
+        },
+        NodeType {
+            id: "event_trigger".to_string(),
+            label: "Event Trigger".to_string(),
+            category: "Trigger".to_string(),
+            description: Some("Start when another workflow emits a named event".to_string()),
+            documentation: Some("Stored workflows of the same team are started by the server's event bus for every matching `emit_event`. Emits `{ event, event_id, team_id, payload, emitted_at }`.".to_string()),
+            properties: vec![
+                NodeProperty {
+                    name: "event".to_string(),
+                    label: "Event Name".to_string(),
+                    property_type: "text".to_string(),
+                    options: None,
+                    default: None,
+                    required: true,
+                    json_schema: None,
+                },
+            ],
+            outputs: vec![],
+        },
     ]
 }
 
@@ -188,6 +335,34 @@ fn get_logic_nodes() -> Vec<NodeType> {
             // Dynamic outputs depending on cases + 1 default
             outputs: vec![], 
         },
+        NodeType {
+            id: "approval".to_string(),
+            label: "Human Approval".to_string(),
+            category: "Logic".to_string(),
+            description: Some("Holds each record until it is approved or rejected via the signal API".to_string()),
+            documentation: None,
+            properties: vec![
+                NodeProperty {
+                    name: "message".to_string(),
+                    label: "Message (Template)".to_string(),
+                    property_type: "text".to_string(),
+                    options: None,
+                    default: None,
+                    required: false,
+                    json_schema: None,
+                },
+                NodeProperty {
+                    name: "timeout_seconds".to_string(),
+                    label: "Expires after (seconds)".to_string(),
+                    property_type: "number".to_string(),
+                    options: None,
+                    default: None,
+                    required: false,
+                    json_schema: None,
+                },
+            ],
+            outputs: vec!["approved".to_string(), "rejected".to_string(), "expired".to_string(), "requested".to_string()],
+        },
     ]
 }
 
@@ -837,6 +1012,43 @@ fn get_database_nodes() -> Vec<NodeType> { vec![
             ],
             outputs: vec![],
         },
+        NodeType {
+            id: "pg_notify".to_string(),
+            category: "Action".to_string(),
+            label: "Postgres NOTIFY".to_string(),
+            description: Some("Send a NOTIFY on a Postgres channel for each record".to_string()),
+            documentation: None,
+            properties: vec![
+                NodeProperty {
+                    name: "connection_string".to_string(),
+                    label: "Connection String".to_string(),
+                    property_type: "text".to_string(),
+                    options: None,
+                    default: None,
+                    required: true,
+                    json_schema: None,
+                },
+                NodeProperty {
+                    name: "channel".to_string(),
+                    label: "Channel".to_string(),
+                    property_type: "text".to_string(),
+                    options: None,
+                    default: None,
+                    required: true,
+                    json_schema: None,
+                },
+                NodeProperty {
+                    name: "payload".to_string(),
+                    label: "Payload Template (defaults to the record as JSON)".to_string(),
+                    property_type: "code".to_string(),
+                    options: None,
+                    default: None,
+                    required: false,
+                    json_schema: None,
+                },
+            ],
+            outputs: vec![],
+        },
 ] }
 
 fn get_filesystem_nodes() -> Vec<NodeType> { vec![
@@ -995,12 +1207,40 @@ fn get_utility_nodes() -> Vec<NodeType> { vec![
 ] }
 
 fn get_control_nodes() -> Vec<NodeType> { vec![
+        NodeType {
+            id: "emit_event".to_string(),
+            label: "Emit Event".to_string(),
+            category: "Action".to_string(),
+            description: Some("Publish a named event to subscribed workflows".to_string()),
+            documentation: Some("Every record publishes one event (persisted, delivered at least once) to the team's workflows with a matching `event_trigger`. The payload template defaults to the whole record. Records pass through.".to_string()),
+            properties: vec![
+                NodeProperty {
+                    name: "event".to_string(),
+                    label: "Event Name".to_string(),
+                    property_type: "text".to_string(),
+                    options: None,
+                    default: None,
+                    required: true,
+                    json_schema: None,
+                },
+                NodeProperty {
+                    name: "payload".to_string(),
+                    label: "Payload (Template)".to_string(),
+                    property_type: "text".to_string(),
+                    options: None,
+                    default: None,
+                    required: false,
+                    json_schema: None,
+                },
+            ],
+            outputs: vec![],
+        },
         NodeType {
             id: "delay".to_string(),
             label: "Delay".to_string(),
             category: "Action".to_string(),
             description: Some("Pause execution for a duration".to_string()),
-            documentation: None,
+            documentation: Some("With `durable`, delays of at least `durable_threshold_ms` (default 60000) are stored and the execution is suspended until they are due, surviving restarts.".to_string()),
             properties: vec![
                 NodeProperty {
                     name: "duration_ms".to_string(),
@@ -1011,6 +1251,15 @@ fn get_control_nodes() -> Vec<NodeType> { vec![
                     required: true,
                     json_schema: None,
                 },
+                NodeProperty {
+                    name: "durable".to_string(),
+                    label: "Durable".to_string(),
+                    property_type: "boolean".to_string(),
+                    options: None,
+                    default: Some("false".to_string()),
+                    required: false,
+                    json_schema: None,
+                },
             ],
             outputs: vec![],
         },
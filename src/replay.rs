@@ -0,0 +1,200 @@
+use std::time::Duration;
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::mpsc;
+use crate::credentials::Secrets;
+use crate::integrations;
+use crate::schema::{ExecutionEvent, WorkflowDefinition};
+use crate::stream_engine::NodeContext;
+
+/// Node types stubbed by `stub_side_effects`: they reach outside the workflow (network,
+/// files, databases, other workflows) or wait on it. Integration nodes are stubbed as well.
+pub const SIDE_EFFECT_NODE_TYPES: &[&str] = &[
+    "http_request", "sql_query", "pg_notify", "emit_event", "file_write", "ftp_op", "ssh_command",
+    "agent", "execute_workflow", "loop", "approval", "delay", "wait",
+];
+
+#[derive(Debug, Clone)]
+pub struct ReplayOptions {
+    /// Replays only this node; otherwise every node that has incoming edges.
+    pub node: Option<String>,
+    /// Skips the node types in `SIDE_EFFECT_NODE_TYPES` and integrations, reusing their recorded outputs.
+    pub stub_side_effects: bool,
+    /// Further nodes (by id) to skip.
+    pub stub_nodes: Vec<String>,
+    /// Limit per node run.
+    pub timeout: Duration,
+}
+
+impl Default for ReplayOptions {
+    fn default() -> Self {
+        Self { node: None, stub_side_effects: false, stub_nodes: Vec::new(), timeout: Duration::from_secs(30) }
+    }
+}
+
+/// Outcome of replaying one node. Records are grouped by port.
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeReplay {
+    pub node_id: String,
+    pub node_type: String,
+    /// Not run; `outputs` are the recorded ones.
+    pub stubbed: bool,
+    pub inputs: Vec<Vec<Value>>,
+    pub outputs: Vec<Vec<Value>>,
+    /// `None` for ports without edges, whose records were never recorded.
+    pub recorded_outputs: Vec<Option<Vec<Value>>>,
+    pub error: Option<String>,
+}
+
+impl NodeReplay {
+    /// Whether the node ran without error and emitted on its connected ports what it emitted
+    /// the first time.
+    pub fn matches(&self) -> bool {
+        self.error.is_none()
+            && self.outputs.iter().zip(&self.recorded_outputs)
+                .all(|(output, recorded)| recorded.as_ref().is_none_or(|r| r == output))
+    }
+}
+
+/// Re-runs the nodes of a past execution from its recorded `EdgeData` events.
+///
+/// Every node runs in isolation and receives exactly the records that crossed its incoming
+/// edges the first time, so a node behaves the same no matter what its replayed upstream nodes
+/// emit now. Nodes without incoming edges (triggers) are not replayed. Only records that reached
+/// a downstream node are recorded, so outputs are compared on connected ports only, and runs
+/// with `limit_records` may have dropped records. Nodes run without storage or execution id.
+pub async fn replay(
+    definition: &WorkflowDefinition,
+    events: &[ExecutionEvent],
+    secrets: &Secrets,
+    options: &ReplayOptions,
+) -> Result<Vec<NodeReplay>> {
+    let edges = definition.resolved_edges()?;
+
+    let nodes: Vec<_> = match &options.node {
+        Some(id) => {
+            let node = definition.nodes.iter().find(|n| &n.id == id)
+                .ok_or_else(|| anyhow!("Node {} is not part of the workflow", id))?;
+            if !edges.iter().any(|(_, _, to, _)| to == id) {
+                return Err(anyhow!("Node {} has no incoming edges, nothing to replay", id));
+            }
+            vec![node]
+        }
+        None => definition.nodes.iter()
+            .filter(|n| edges.iter().any(|(_, _, to, _)| to == &n.id))
+            .collect(),
+    };
+
+    let mut replays = Vec::new();
+    for node_def in nodes {
+        let id = &node_def.id;
+
+        // Inputs per port, in arrival order
+        let input_ports = edges.iter().filter(|(_, _, to, _)| to == id).map(|(_, _, _, port)| port + 1).max().unwrap_or(0);
+        let mut inputs = vec![Vec::new(); input_ports];
+        for event in events {
+            if let ExecutionEvent::EdgeData { from, to, value } = event {
+                if to != id {
+                    continue;
+                }
+                if let Some((_, _, _, port)) = edges.iter().find(|(f, _, t, _)| f == from && t == to) {
+                    inputs[*port].push(value.clone());
+                }
+            }
+        }
+
+        // Outputs per port, as recorded on the first edge leaving each port
+        let output_ports = edges.iter().filter(|(from, _, _, _)| from == id).map(|(_, port, _, _)| port + 1).max().unwrap_or(1);
+        let recorded_outputs: Vec<Option<Vec<Value>>> = (0..output_ports)
+            .map(|port| {
+                let (_, _, target, _) = edges.iter().find(|(from, p, _, _)| from == id && *p == port)?;
+                Some(events.iter()
+                    .filter_map(|e| match e {
+                        ExecutionEvent::EdgeData { from, to, value } if from == id && to == target => Some(value.clone()),
+                        _ => None,
+                    })
+                    .collect())
+            })
+            .collect();
+
+        let stubbed = options.stub_nodes.contains(id)
+            || (options.stub_side_effects && is_side_effecting(&node_def.node_type));
+        let (outputs, error) = if stubbed {
+            (recorded_outputs.iter().map(|r| r.clone().unwrap_or_default()).collect(), None)
+        } else {
+            match run_node(node_def, secrets, &inputs, output_ports, options.timeout).await {
+                Ok((outputs, error)) => (outputs, error),
+                Err(e) => (vec![Vec::new(); output_ports], Some(e.to_string())),
+            }
+        };
+
+        replays.push(NodeReplay {
+            node_id: id.clone(),
+            node_type: node_def.node_type.clone(),
+            stubbed,
+            inputs,
+            outputs,
+            recorded_outputs,
+            error,
+        });
+    }
+    Ok(replays)
+}
+
+fn is_side_effecting(node_type: &str) -> bool {
+    SIDE_EFFECT_NODE_TYPES.contains(&node_type) || integrations::create_node_by_id(node_type, Value::Null).is_some()
+}
+
+/// Runs a single node on `inputs`, returning what it emitted (also when it failed) and its error.
+async fn run_node(
+    node_def: &crate::schema::NodeDefinition,
+    secrets: &Secrets,
+    inputs: &[Vec<Value>],
+    output_ports: usize,
+    limit: Duration,
+) -> Result<(Vec<Vec<Value>>, Option<String>)> {
+    let node = node_def.create(secrets, &NodeContext::default())?;
+
+    let mut receivers = Vec::new();
+    for records in inputs {
+        let (tx, rx) = mpsc::channel(100);
+        let records = records.clone();
+        tokio::spawn(async move {
+            for record in records {
+                if tx.send(record).await.is_err() {
+                    break;
+                }
+            }
+        });
+        receivers.push(rx);
+    }
+
+    let mut senders = Vec::new();
+    let mut collectors = Vec::new();
+    for _ in 0..output_ports {
+        let (tx, mut rx) = mpsc::channel::<Value>(100);
+        senders.push(tx);
+        collectors.push(tokio::spawn(async move {
+            let mut records = Vec::new();
+            while let Some(record) = rx.recv().await {
+                records.push(record);
+            }
+            records
+        }));
+    }
+
+    // The node owns the senders, so the collectors finish once it returns (or is dropped)
+    let error = match tokio::time::timeout(limit, node.run(receivers, senders)).await {
+        Ok(Ok(())) => None,
+        Ok(Err(e)) => Some(e.to_string()),
+        Err(_) => Some(format!("Timed out after {:?}", limit)),
+    };
+
+    // Tasks spawned by the node may still hold a sender
+    let mut outputs = Vec::new();
+    for collector in collectors {
+        outputs.push(tokio::time::timeout(limit, collector).await.unwrap_or(Ok(Vec::new()))?);
+    }
+    Ok((outputs, error))
+}
@@ -0,0 +1,60 @@
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use tokio::task::JoinHandle;
+use crate::storage::{PruneStats, RetentionPolicy, Storage};
+
+const LEADERSHIP: &str = "rustflow:retention";
+
+/// Reads the server's retention policy from `RUSTFLOW_RETENTION_MAX_AGE_DAYS`,
+/// `RUSTFLOW_RETENTION_MAX_PER_WORKFLOW`, `RUSTFLOW_RETENTION_FAILED_MAX_AGE_DAYS` and
+/// `RUSTFLOW_RETENTION_PAYLOAD_SAMPLE`. Unset variables keep everything.
+pub fn policy_from_env() -> Result<RetentionPolicy> {
+    let var = |name: &str| -> Result<Option<i64>> {
+        match std::env::var(name) {
+            Ok(value) => value.parse().map(Some).map_err(|_| anyhow!("{} must be a number, got '{}'", name, value)),
+            Err(_) => Ok(None),
+        }
+    };
+    Ok(RetentionPolicy {
+        max_age_days: var("RUSTFLOW_RETENTION_MAX_AGE_DAYS")?,
+        max_per_workflow: var("RUSTFLOW_RETENTION_MAX_PER_WORKFLOW")?,
+        failed_max_age_days: var("RUSTFLOW_RETENTION_FAILED_MAX_AGE_DAYS")?,
+        success_payload_sample: var("RUSTFLOW_RETENTION_PAYLOAD_SAMPLE")?,
+    })
+}
+
+/// Deletes old executions and payloads according to a `RetentionPolicy`.
+pub struct Pruner {
+    storage: Arc<dyn Storage>,
+    policy: RetentionPolicy,
+}
+
+impl Pruner {
+    pub fn new(storage: Arc<dyn Storage>, policy: RetentionPolicy) -> Self {
+        Self { storage, policy }
+    }
+
+    pub async fn prune(&self) -> Result<PruneStats> {
+        self.storage.prune_executions(&self.policy, Utc::now()).await
+    }
+
+    /// Prunes every `interval` in the background.
+    /// When several servers share the storage, only the elected leader prunes.
+    pub fn start(self, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                match self.storage.try_acquire_leadership(LEADERSHIP).await {
+                    Ok(true) => match self.prune().await {
+                        Ok(stats) => tracing::info!("Pruned {} executions and {} events", stats.executions, stats.events),
+                        Err(e) => tracing::error!("Failed to prune executions: {}", e),
+                    },
+                    Ok(false) => {}
+                    Err(e) => tracing::error!("Retention leader election failed: {}", e),
+                }
+                tokio::time::sleep(interval).await;
+            }
+        })
+    }
+}
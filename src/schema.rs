@@ -2,10 +2,43 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use anyhow::Result;
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub struct WorkflowDefinition {
     pub nodes: Vec<NodeDefinition>,
     pub edges: Vec<EdgeDefinition>,
+    /// Workflow (stored id or file path) started with an `error_trigger` record when an execution fails.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_failure: Option<String>,
+    /// What to do when the stored workflow is started while an execution of it is still running.
+    #[serde(default, skip_serializing_if = "ConcurrencyPolicy::is_parallel")]
+    pub concurrency: ConcurrencyPolicy,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum ConcurrencyPolicy {
+    /// Every start runs immediately.
+    #[default]
+    Parallel,
+    /// Starts are recorded as `skipped` while an execution is running.
+    Skip,
+    /// Starts wait (FIFO, recorded as `queued`) until running executions finish; beyond `max_depth` they are skipped.
+    Queue {
+        #[serde(default = "default_queue_depth")]
+        max_depth: usize,
+    },
+    /// Running executions are cancelled in favour of the new one.
+    Replace,
+}
+
+fn default_queue_depth() -> usize {
+    10
+}
+
+impl ConcurrencyPolicy {
+    pub fn is_parallel(&self) -> bool {
+        *self == ConcurrencyPolicy::Parallel
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -18,6 +51,18 @@ pub struct NodeDefinition {
     pub on_error: Option<ErrorPolicy>,
 }
 
+impl NodeDefinition {
+    /// Builds the node through the `NodeFactory`, with its own id in the context.
+    pub fn create(&self, secrets: &crate::credentials::Secrets, context: &NodeContext) -> Result<Box<dyn crate::stream_engine::StreamNode>> {
+        // Nodes that persist their progress (e.g. durable delays) need to know their id
+        let factory = crate::stream_engine::factory::NodeFactory::with_context(NodeContext {
+            node_id: Some(self.id.clone()),
+            ..context.clone()
+        });
+        factory.create(&self.node_type, self.config.clone(), secrets)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(tag = "action", rename_all = "snake_case")]
 pub enum ErrorPolicy {
@@ -44,23 +89,54 @@ pub enum ExecutionEvent {
     WorkflowFinish { workflow_id: Option<String> },
 }
 
-use crate::stream_engine::{StreamExecutor, DebugConfig};
+use crate::stream_engine::{StreamExecutor, DebugConfig, NodeContext};
 
 impl WorkflowDefinition {
-    pub fn to_executor(&self, secrets: &std::collections::HashMap<String, String>, debug_config: DebugConfig) -> Result<StreamExecutor> {
+    pub fn to_executor(&self, secrets: &crate::credentials::Secrets, debug_config: DebugConfig) -> Result<StreamExecutor> {
+        self.to_executor_with_context(secrets, debug_config, NodeContext::default())
+    }
+
+    pub fn to_executor_with_context(&self, secrets: &crate::credentials::Secrets, debug_config: DebugConfig, context: NodeContext) -> Result<StreamExecutor> {
         let mut executor = StreamExecutor::new(debug_config);
-        let factory = crate::stream_engine::factory::NodeFactory::new();
 
         for node_def in &self.nodes {
-            let node = factory.create(&node_def.node_type, node_def.config.clone(), secrets)?;
+            let node = node_def.create(secrets, &context)?;
             executor.add_node(node_def.id.clone(), node, node_def.on_error.clone());
         }
 
+        for (from, from_port, to, to_port) in self.resolved_edges()? {
+            executor.add_connection(from, from_port, to, to_port);
+        }
+
+        Ok(executor)
+    }
+
+    /// Checks what can be checked without building the nodes (and decrypting credentials for
+    /// them): every node type exists, and edges connect existing nodes through existing ports.
+    pub fn validate(&self) -> Result<()> {
+        let factory = crate::stream_engine::factory::NodeFactory::new();
+        if let Some(node) = self.nodes.iter().find(|n| !factory.supports(&n.node_type)) {
+            return Err(anyhow::anyhow!("Unknown node type: {}", node.node_type));
+        }
+        for edge in &self.edges {
+            for end in [&edge.from, &edge.to] {
+                if !self.nodes.iter().any(|n| &n.id == end) {
+                    return Err(anyhow::anyhow!("Unknown node: {}", end));
+                }
+            }
+        }
+        self.resolved_edges().map(|_| ())
+    }
+
+    /// Edges as `(from, from_port, to, to_port)`, with named output ports (e.g. `approved`)
+    /// resolved to their index through the node registry.
+    pub fn resolved_edges(&self) -> Result<Vec<(String, usize, String, usize)>> {
         let registry = crate::node_registry::get_node_registry();
         let node_type_map: std::collections::HashMap<String, String> = self.nodes.iter()
             .map(|n| (n.id.clone(), n.node_type.clone()))
             .collect();
 
+        let mut edges = Vec::new();
         for edge_def in &self.edges {
             let from_port = if let Some(p_str) = &edge_def.from_port {
                 if let Ok(idx) = p_str.parse::<usize>() {
@@ -76,10 +152,33 @@ impl WorkflowDefinition {
             };
 
             let to_port = edge_def.to_port.as_deref().and_then(|p| p.parse::<usize>().ok()).unwrap_or(0);
-            executor.add_connection(edge_def.from.clone(), from_port, edge_def.to.clone(), to_port);
+            edges.push((edge_def.from.clone(), from_port, edge_def.to.clone(), to_port));
         }
+        Ok(edges)
+    }
 
-        Ok(executor)
+    /// The part of the workflow that `node_id` feeds: the node itself and every node reachable
+    /// from it, with the edges between them. Used to resume a suspended execution at that node.
+    pub fn downstream_of(&self, node_id: &str) -> Result<WorkflowDefinition> {
+        if !self.nodes.iter().any(|n| n.id == node_id) {
+            return Err(anyhow::anyhow!("Unknown node: {}", node_id));
+        }
+
+        let mut reached = std::collections::HashSet::from([node_id.to_string()]);
+        let mut frontier = vec![node_id.to_string()];
+        while let Some(current) = frontier.pop() {
+            for edge in self.edges.iter().filter(|e| e.from == current) {
+                if reached.insert(edge.to.clone()) {
+                    frontier.push(edge.to.clone());
+                }
+            }
+        }
+
+        Ok(WorkflowDefinition {
+            nodes: self.nodes.iter().filter(|n| reached.contains(&n.id)).cloned().collect(),
+            edges: self.edges.iter().filter(|e| reached.contains(&e.from)).cloned().collect(),
+            ..self.clone()
+        })
     }
 }
 
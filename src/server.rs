@@ -0,0 +1,1278 @@
+use axum::{
+    routing::{post, get, delete},
+    Router,
+    Json,
+    Extension,
+    response::{IntoResponse, Response},
+    http::{HeaderMap, StatusCode, header::AUTHORIZATION},
+    extract::{State, Path, Query, Request, ws::{WebSocketUpgrade, WebSocket, Message}},
+    middleware::{self, Next},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+use uuid::Uuid;
+use crate::schema::{ExecutionEvent, WorkflowDefinition, WorkflowLoader};
+use crate::job_manager::{JobManager, JobSpec, JobStatus};
+use crate::storage::{self, Storage, Role, WorkflowEntity, WorkflowVersion, ExecutionFilter, User, ApiToken, ApprovalRecord, TimerRecord};
+use crate::{audit, auth, credentials};
+
+pub struct AppState {
+    pub job_manager: Arc<JobManager>,
+    pub storage: Arc<dyn Storage>,
+    pub settings: ServerSettings,
+}
+
+/// Access settings of the API. The defaults are the restrictive ones.
+#[derive(Debug, Clone, Default)]
+pub struct ServerSettings {
+    /// Anyone may register an account. Otherwise only the first account registers itself,
+    /// later ones are registered by team admins.
+    pub open_registration: bool,
+    /// Every signed-in user may run workflows without a team and see those runs (and node
+    /// state kept outside teams). Otherwise runs need a team.
+    pub allow_unscoped_runs: bool,
+}
+
+impl ServerSettings {
+    /// Reads `RUSTFLOW_OPEN_REGISTRATION` and `RUSTFLOW_ALLOW_UNSCOPED_RUNS` (`true` to enable).
+    pub fn from_env() -> Self {
+        let flag = |name: &str| std::env::var(name).is_ok_and(|v| v.eq_ignore_ascii_case("true") || v == "1");
+        Self {
+            open_registration: flag("RUSTFLOW_OPEN_REGISTRATION"),
+            allow_unscoped_runs: flag("RUSTFLOW_ALLOW_UNSCOPED_RUNS"),
+        }
+    }
+}
+
+/// The API routes. Everything but login and `/health` needs a bearer token; registration
+/// needs one unless `ServerSettings::open_registration` is set or no account exists yet.
+pub fn router(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/api/run", post(run_workflow))
+        .route("/api/jobs/{id}", get(get_job_status))
+        .route("/api/jobs/{id}/cancel", post(cancel_job))
+        .route("/api/executions", get(list_executions).post(create_execution))
+        .route("/api/executions/{id}", get(get_execution).patch(update_execution))
+        .route("/api/executions/{id}/logs", get(get_execution_logs).post(log_execution_event))
+        .route("/api/executions/{id}/definition", get(get_execution_definition))
+        .route("/api/executions/{id}/signal/{token}", post(signal_execution))
+        .route("/api/approvals", post(create_approval))
+        .route("/api/approvals/{token}", get(get_approval))
+        .route("/api/approvals/{token}/resolve", post(resolve_approval))
+        .route("/api/kv/{key}", get(get_kv).put(set_kv))
+        .route("/api/ws/{id}", get(ws_handler))
+        .route("/api/node-types", get(get_node_types))
+        .route("/api/credential-types", get(get_credential_types))
+        // Auth
+        .route("/api/auth/lookup", get(lookup_user))
+        .route("/api/auth/me", get(current_user))
+        .route("/api/auth/logout", post(logout))
+        .route("/api/auth/tokens", post(create_token).get(list_tokens))
+        .route("/api/auth/tokens/{id}", delete(delete_token))
+        .route("/api/users/{user_id}", get(get_user))
+        // Teams
+        .route("/api/teams", post(create_team).get(find_team))
+        .route("/api/teams/{team_id}", get(get_team))
+        .route("/api/users/{user_id}/teams", get(list_teams))
+        .route("/api/teams/{team_id}/members", post(add_team_member).get(get_team_members))
+        .route("/api/teams/{team_id}/audit", get(list_audit).post(report_audit))
+        .route("/api/teams/{team_id}/events", post(publish_event))
+        // Workflows
+        .route("/api/workflows", post(save_workflow))
+        .route("/api/workflows/{id}", get(get_workflow).put(update_workflow).delete(delete_workflow))
+        .route("/api/workflows/{id}/enable", post(enable_workflow))
+        .route("/api/workflows/{id}/disable", post(disable_workflow))
+        .route("/api/workflows/{id}/versions", get(list_workflow_versions))
+        .route("/api/workflows/{id}/versions/{version}", get(get_workflow_version))
+        .route("/api/workflows/{id}/diff", get(diff_workflow_versions))
+        .route("/api/workflows/{id}/rollback", post(rollback_workflow))
+        .route("/api/teams/{team_id}/workflows", get(list_workflows))
+        .route("/api/workflows/{id}/run", post(run_stored_workflow))
+        // Credentials
+        .route("/api/credentials", post(create_credential))
+        .route("/api/credentials/{id}", get(get_credential).put(update_credential).delete(delete_credential))
+        .route("/api/credentials/{id}/reveal", get(reveal_credential))
+        .route("/api/credentials/{id}/usage", get(credential_usage))
+        .route("/api/teams/{team_id}/credentials", get(list_credentials))
+        // Everything above needs a bearer token
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_auth))
+        .route("/api/auth/register", post(register_user))
+        .route("/api/auth/login", post(login))
+        .route("/health", get(|| async { "OK" }))
+        .with_state(state)
+}
+
+// --- Errors ---
+
+/// Body of every error response.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ErrorBody {
+    pub error: String,
+}
+
+/// An error response: the status code with an `ErrorBody`.
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub message: String,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self { status, message: message.into() }
+    }
+
+    fn not_found(what: impl std::fmt::Display) -> Self {
+        Self::new(StatusCode::NOT_FOUND, format!("{} not found", what))
+    }
+
+    fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, message)
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        // Details stay in the server log; they may name tables, paths or hosts
+        tracing::error!("Request failed: {:#}", e);
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, "Internal server error")
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(ErrorBody { error: self.message })).into_response()
+    }
+}
+
+type ApiResult<T> = Result<Json<T>, ApiError>;
+
+// --- Auth ---
+
+/// The authenticated caller, set by `require_auth`.
+#[derive(Clone)]
+struct CurrentUser {
+    user: User,
+    token_id: Uuid,
+}
+
+/// Rejects requests without a valid `Authorization: Bearer <token>` header. WebSocket clients,
+/// which cannot set headers in browsers, may pass `?access_token=<token>` instead.
+async fn require_auth(
+    State(state): State<Arc<AppState>>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    // Browsers cannot set headers on WebSockets; elsewhere tokens stay out of URLs and logs
+    let query = req.uri().query()
+        .filter(|_| req.uri().path().starts_with("/api/ws/"))
+        .and_then(|q| q.split('&').find_map(|p| p.strip_prefix("access_token=")));
+    let Some(secret) = bearer_token(req.headers()).or(query).map(str::to_string) else {
+        return ApiError::new(StatusCode::UNAUTHORIZED, "Missing bearer token").into_response();
+    };
+
+    match auth::authenticate(state.storage.as_ref(), &secret).await {
+        Ok(Some((user, token))) => {
+            req.extensions_mut().insert(CurrentUser { user, token_id: token.id });
+            next.run(req).await
+        }
+        Ok(None) => ApiError::new(StatusCode::UNAUTHORIZED, "Invalid or expired token").into_response(),
+        Err(e) => ApiError::from(e).into_response(),
+    }
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers.get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+}
+
+/// 403 unless the caller holds `required` or a higher role in the team. Resources without a
+/// team, such as ad hoc runs, are refused unless `ServerSettings::allow_unscoped_runs` is set.
+async fn authorize(state: &AppState, current: &CurrentUser, team_id: Option<Uuid>, required: Role) -> Result<(), ApiError> {
+    let Some(team_id) = team_id else {
+        if state.settings.allow_unscoped_runs {
+            return Ok(());
+        }
+        return Err(ApiError::new(StatusCode::FORBIDDEN, "Runs without a team are disabled on this server; pass a team_id"));
+    };
+    if auth::has_role(state.storage.as_ref(), current.user.id, team_id, required.clone()).await? {
+        Ok(())
+    } else {
+        Err(ApiError::new(StatusCode::FORBIDDEN, format!("Requires the {} role in team {}", required.as_str(), team_id)))
+    }
+}
+
+/// Like `authorize`, for the team an execution belongs to. 404 for unknown executions.
+async fn authorize_execution(state: &AppState, current: &CurrentUser, id: Uuid, required: Role) -> Result<(), ApiError> {
+    match auth::execution_team(state.storage.as_ref(), id).await? {
+        Some(team_id) => authorize(state, current, team_id, required).await,
+        None => Err(ApiError::not_found(format!("Execution {}", id))),
+    }
+}
+
+/// Like `authorize`, for the team owning a stored workflow. 404 for unknown and deleted workflows.
+async fn authorize_workflow(state: &AppState, current: &CurrentUser, id: Uuid, required: Role) -> Result<WorkflowEntity, ApiError> {
+    let workflow = state.storage.get_workflow(id).await?
+        .filter(|w| w.deleted_at.is_none())
+        .ok_or_else(|| ApiError::not_found(format!("Workflow {}", id)))?;
+    authorize(state, current, Some(workflow.account_id), required).await?;
+    Ok(workflow)
+}
+
+/// Job ids are execution ids.
+fn job_execution_id(id: &str) -> Result<Uuid, ApiError> {
+    Uuid::parse_str(id).map_err(|_| ApiError::not_found(format!("Job {}", id)))
+}
+
+/// Records an audit entry for the caller. The operation already happened, so failures are
+/// only logged.
+async fn record_audit(state: &AppState, current: &CurrentUser, team_id: Uuid, action: &str, target: String, diff: Option<Value>) {
+    if let Err(e) = audit::record(state.storage.as_ref(), Some(current.user.id), team_id, action, target, diff).await {
+        tracing::error!("Failed to record audit entry {}: {}", action, e);
+    }
+}
+
+#[derive(Deserialize)]
+struct RegisterRequest {
+    username: String,
+    password: String,
+}
+
+async fn register_user(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<RegisterRequest>
+) -> ApiResult<User> {
+    if !state.settings.open_registration && state.storage.count_users().await? > 0 {
+        let caller = match bearer_token(&headers) {
+            Some(secret) => auth::authenticate(state.storage.as_ref(), secret).await?,
+            None => None,
+        };
+        let Some((caller, _)) = caller else {
+            return Err(ApiError::new(StatusCode::UNAUTHORIZED, "Registration is closed; a team admin has to register new users"));
+        };
+        let teams = state.storage.list_teams_for_user(caller.id).await?;
+        if !teams.iter().any(|(_, role)| *role == Role::Admin) {
+            return Err(ApiError::new(StatusCode::FORBIDDEN, "Only team admins can register users"));
+        }
+    }
+    if state.storage.get_user_by_username(&payload.username).await?.is_some() {
+        return Err(ApiError::new(StatusCode::CONFLICT, format!("User {} already exists", payload.username)));
+    }
+    Ok(Json(state.storage.create_user(&payload.username, &payload.password).await?))
+}
+
+#[derive(Deserialize)]
+struct LoginRequest {
+    username: String,
+    password: String,
+}
+
+#[derive(Serialize)]
+struct TokenResponse {
+    token: String, // Only returned once
+    info: ApiToken,
+    user: User,
+}
+
+async fn login(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<LoginRequest>
+) -> ApiResult<TokenResponse> {
+    match auth::login(state.storage.as_ref(), &payload.username, &payload.password).await? {
+        Some((user, token, info)) => Ok(Json(TokenResponse { token, info, user })),
+        None => Err(ApiError::new(StatusCode::UNAUTHORIZED, "Invalid username or password")),
+    }
+}
+
+async fn current_user(Extension(current): Extension<CurrentUser>) -> Json<User> {
+    Json(current.user)
+}
+
+async fn logout(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>
+) -> Result<StatusCode, ApiError> {
+    state.storage.delete_api_token(current.user.id, current.token_id).await?;
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+struct CreateTokenRequest {
+    name: String,
+    expires_in_days: Option<i64>, // Never expires if unset
+}
+
+async fn create_token(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Json(payload): Json<CreateTokenRequest>
+) -> ApiResult<TokenResponse> {
+    let expires_at = match payload.expires_in_days {
+        None => None,
+        Some(days) => {
+            let expires_at = (1..=auth::MAX_TOKEN_DAYS).contains(&days)
+                .then(|| Utc::now().checked_add_signed(chrono::Duration::days(days)))
+                .flatten()
+                .ok_or_else(|| ApiError::bad_request(format!("expires_in_days must be between 1 and {}", auth::MAX_TOKEN_DAYS)))?;
+            Some(expires_at)
+        }
+    };
+    let (token, info) = auth::issue_token(state.storage.as_ref(), current.user.id, &payload.name, "personal", expires_at).await?;
+    Ok(Json(TokenResponse { token, info, user: current.user }))
+}
+
+async fn list_tokens(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>
+) -> ApiResult<Vec<ApiToken>> {
+    Ok(Json(state.storage.list_api_tokens(current.user.id).await?))
+}
+
+async fn delete_token(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path(id): Path<Uuid>
+) -> Result<StatusCode, ApiError> {
+    if state.storage.delete_api_token(current.user.id, id).await? {
+        Ok(StatusCode::OK)
+    } else {
+        Err(ApiError::not_found(format!("Token {}", id)))
+    }
+}
+
+// --- Users and teams ---
+
+#[derive(Deserialize)]
+struct LookupUserQuery {
+    username: String,
+}
+
+async fn lookup_user(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<LookupUserQuery>
+) -> ApiResult<User> {
+    let user = state.storage.get_user_by_username(&query.username).await?
+        .ok_or_else(|| ApiError::not_found(format!("User {}", query.username)))?;
+    Ok(Json(user))
+}
+
+async fn get_user(
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<Uuid>
+) -> ApiResult<User> {
+    let user = state.storage.get_user(user_id).await?
+        .ok_or_else(|| ApiError::not_found(format!("User {}", user_id)))?;
+    Ok(Json(user))
+}
+
+#[derive(Deserialize)]
+struct CreateTeamReq {
+    name: String,
+}
+
+async fn create_team(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Json(payload): Json<CreateTeamReq>
+) -> ApiResult<crate::storage::Team> {
+    if state.storage.get_team_by_name(&payload.name).await?.is_some() {
+        return Err(ApiError::new(StatusCode::CONFLICT, format!("Team {} already exists", payload.name)));
+    }
+    let team = state.storage.create_team(&payload.name).await?;
+    // The creator administers the new team
+    state.storage.add_team_member(team.id, current.user.id, Role::Admin).await?;
+
+    let name = audit::diff(&Value::Null, &serde_json::json!({ "name": team.name }));
+    record_audit(&state, &current, team.id, "team.create", format!("team/{}", team.id), Some(name)).await;
+    let role = audit::diff(&Value::Null, &serde_json::json!({ "role": Role::Admin.as_str() }));
+    record_audit(&state, &current, team.id, "member.add", format!("user/{}", current.user.id), Some(role)).await;
+    Ok(Json(team))
+}
+
+#[derive(Deserialize)]
+struct FindTeamQuery {
+    name: String,
+}
+
+/// Teams by name; teams the caller cannot see are reported missing.
+async fn find_team(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Query(query): Query<FindTeamQuery>
+) -> ApiResult<crate::storage::Team> {
+    let not_found = || ApiError::not_found(format!("Team {}", query.name));
+    let team = state.storage.get_team_by_name(&query.name).await?.ok_or_else(not_found)?;
+    authorize(&state, &current, Some(team.id), Role::Viewer).await.map_err(|_| not_found())?;
+    Ok(Json(team))
+}
+
+async fn get_team(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path(team_id): Path<Uuid>
+) -> ApiResult<crate::storage::Team> {
+    authorize(&state, &current, Some(team_id), Role::Viewer).await?;
+    let team = state.storage.get_team(team_id).await?
+        .ok_or_else(|| ApiError::not_found(format!("Team {}", team_id)))?;
+    Ok(Json(team))
+}
+
+async fn list_teams(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path(user_id): Path<Uuid>
+) -> ApiResult<Vec<(crate::storage::Team, Role)>> {
+    if user_id != current.user.id {
+        return Err(ApiError::new(StatusCode::FORBIDDEN, "Only your own teams can be listed"));
+    }
+    Ok(Json(state.storage.list_teams_for_user(user_id).await?))
+}
+
+#[derive(Deserialize)]
+struct AddMemberReq {
+    user_id: Uuid,
+    role: String,
+}
+
+async fn add_team_member(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path(team_id): Path<Uuid>,
+    Json(payload): Json<AddMemberReq>
+) -> Result<StatusCode, ApiError> {
+    authorize(&state, &current, Some(team_id), Role::Admin).await?;
+    let role = Role::from_str(&payload.role)
+        .ok_or_else(|| ApiError::bad_request(format!("Invalid role '{}', use admin, member or viewer", payload.role)))?;
+
+    state.storage.add_team_member(team_id, payload.user_id, role.clone()).await?;
+    let diff = audit::diff(&Value::Null, &serde_json::json!({ "role": role.as_str() }));
+    record_audit(&state, &current, team_id, "member.add", format!("user/{}", payload.user_id), Some(diff)).await;
+    Ok(StatusCode::OK)
+}
+
+async fn get_team_members(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path(team_id): Path<Uuid>
+) -> ApiResult<Vec<crate::storage::TeamMember>> {
+    authorize(&state, &current, Some(team_id), Role::Viewer).await?;
+    Ok(Json(state.storage.get_team_members(team_id).await?))
+}
+
+#[derive(Deserialize)]
+struct AuditQuery {
+    #[serde(default = "default_audit_limit")]
+    limit: i64,
+    #[serde(default)]
+    offset: i64,
+}
+
+fn default_audit_limit() -> i64 {
+    100
+}
+
+async fn list_audit(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path(team_id): Path<Uuid>,
+    Query(query): Query<AuditQuery>
+) -> ApiResult<Vec<crate::storage::AuditEntry>> {
+    authorize(&state, &current, Some(team_id), Role::Admin).await?;
+    Ok(Json(state.storage.list_audit(team_id, query.limit, query.offset).await?))
+}
+
+#[derive(Deserialize)]
+struct ReportAuditRequest {
+    action: String,
+    target: String,
+    #[serde(default)]
+    diff: Option<Value>,
+}
+
+/// Records an entry of a run the client executed itself (see `audit::CLIENT_ACTIONS`), with the
+/// caller as actor.
+async fn report_audit(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path(team_id): Path<Uuid>,
+    Json(payload): Json<ReportAuditRequest>
+) -> Result<StatusCode, ApiError> {
+    authorize(&state, &current, Some(team_id), Role::Member).await?;
+    if !audit::CLIENT_ACTIONS.contains(&payload.action.as_str()) {
+        return Err(ApiError::bad_request(format!("'{}' is recorded by the server itself", payload.action)));
+    }
+    // Only the team's own executions and credentials
+    let target_team = match payload.target.split_once('/') {
+        Some(("execution", id)) => match Uuid::parse_str(id) {
+            Ok(id) => auth::execution_team(state.storage.as_ref(), id).await?.flatten(),
+            Err(_) => None,
+        },
+        Some(("credential", id)) => match Uuid::parse_str(id) {
+            Ok(id) => state.storage.get_credential(id).await?.map(|c| c.account_id),
+            Err(_) => None,
+        },
+        _ => return Err(ApiError::bad_request(format!("Invalid audit target '{}'", payload.target))),
+    };
+    if target_team != Some(team_id) {
+        return Err(ApiError::not_found(format!("{} in team {}", payload.target, team_id)));
+    }
+    audit::record(state.storage.as_ref(), Some(current.user.id), team_id, &payload.action, payload.target, payload.diff).await?;
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+struct PublishEventRequest {
+    name: String,
+    #[serde(default)]
+    payload: Value,
+}
+
+async fn publish_event(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path(team_id): Path<Uuid>,
+    Json(payload): Json<PublishEventRequest>
+) -> ApiResult<crate::storage::EventRecord> {
+    authorize(&state, &current, Some(team_id), Role::Member).await?;
+    Ok(Json(state.storage.publish_event(team_id, &payload.name, &payload.payload).await?))
+}
+
+// --- Workflows ---
+
+/// Body of `POST /api/workflows`: the workflow to save as its next version.
+#[derive(Serialize, Deserialize)]
+pub struct SaveWorkflowRequest {
+    #[serde(flatten)]
+    pub workflow: WorkflowEntity,
+    /// Describes the change in the workflow's history.
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SaveWorkflowResponse {
+    pub version: i32,
+}
+
+async fn save_workflow(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Json(SaveWorkflowRequest { workflow, message }): Json<SaveWorkflowRequest>
+) -> ApiResult<SaveWorkflowResponse> {
+    authorize(&state, &current, Some(workflow.account_id), Role::Member).await?;
+    // Overwriting requires editing rights in the team that owns the workflow now
+    let before = match authorize_workflow(&state, &current, workflow.id, Role::Member).await {
+        Ok(before) => Some(before),
+        Err(e) if e.status == StatusCode::NOT_FOUND => None,
+        Err(e) => return Err(e),
+    };
+    if before.is_none() && state.storage.get_workflow(workflow.id).await?.is_some() {
+        return Err(ApiError::new(StatusCode::CONFLICT, format!("Workflow {} was deleted", workflow.id)));
+    }
+
+    let version = state.storage.save_workflow(&workflow, Some(current.user.id), message.as_deref()).await?;
+    let audited = |wf: &WorkflowEntity| serde_json::json!({ "name": wf.name, "team_id": wf.account_id, "definition": wf.definition });
+    let diff = audit::diff(&before.as_ref().map(audited).unwrap_or_default(), &audited(&workflow));
+    record_audit(&state, &current, workflow.account_id, "workflow.save", format!("workflow/{}", workflow.id), Some(diff)).await;
+    Ok(Json(SaveWorkflowResponse { version }))
+}
+
+async fn get_workflow(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path(id): Path<Uuid>
+) -> ApiResult<WorkflowEntity> {
+    Ok(Json(authorize_workflow(&state, &current, id, Role::Viewer).await?))
+}
+
+/// Body of `PUT /api/workflows/{id}`; what is left out stays as it is.
+#[derive(Serialize, Deserialize, Default)]
+pub struct UpdateWorkflowRequest {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub definition: Option<Value>,
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+async fn update_workflow(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<UpdateWorkflowRequest>
+) -> ApiResult<SaveWorkflowResponse> {
+    let before = authorize_workflow(&state, &current, id, Role::Member).await?;
+
+    let version = storage::update_workflow(
+        state.storage.as_ref(),
+        id,
+        payload.name.clone(),
+        payload.definition.clone(),
+        Some(current.user.id),
+        payload.message.as_deref(),
+    ).await?;
+    let audited = |name: &str, definition: &Value| serde_json::json!({ "name": name, "definition": definition });
+    let after = audited(payload.name.as_deref().unwrap_or(&before.name), payload.definition.as_ref().unwrap_or(&before.definition));
+    let diff = audit::diff(&audited(&before.name, &before.definition), &after);
+    record_audit(&state, &current, before.account_id, "workflow.save", format!("workflow/{}", id), Some(diff)).await;
+    Ok(Json(SaveWorkflowResponse { version }))
+}
+
+async fn delete_workflow(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path(id): Path<Uuid>
+) -> Result<StatusCode, ApiError> {
+    let workflow = authorize_workflow(&state, &current, id, Role::Admin).await?;
+    if !state.storage.delete_workflow(id).await? {
+        return Err(ApiError::not_found(format!("Workflow {}", id)));
+    }
+    record_audit(&state, &current, workflow.account_id, "workflow.delete", format!("workflow/{}", id), None).await;
+    Ok(StatusCode::OK)
+}
+
+async fn enable_workflow(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path(id): Path<Uuid>
+) -> Result<StatusCode, ApiError> {
+    set_workflow_active(&state, &current, id, true).await
+}
+
+async fn disable_workflow(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path(id): Path<Uuid>
+) -> Result<StatusCode, ApiError> {
+    set_workflow_active(&state, &current, id, false).await
+}
+
+async fn set_workflow_active(state: &AppState, current: &CurrentUser, id: Uuid, active: bool) -> Result<StatusCode, ApiError> {
+    let workflow = authorize_workflow(state, current, id, Role::Member).await?;
+    if !state.storage.set_workflow_active(id, active).await? {
+        return Err(ApiError::not_found(format!("Workflow {}", id)));
+    }
+    let action = if active { "workflow.enable" } else { "workflow.disable" };
+    let diff = serde_json::json!({ "/active": { "before": workflow.active, "after": active } });
+    record_audit(state, current, workflow.account_id, action, format!("workflow/{}", id), Some(diff)).await;
+    Ok(StatusCode::OK)
+}
+
+async fn list_workflow_versions(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path(id): Path<Uuid>
+) -> ApiResult<Vec<WorkflowVersion>> {
+    authorize_workflow(&state, &current, id, Role::Viewer).await?;
+    Ok(Json(state.storage.list_workflow_versions(id).await?))
+}
+
+/// A version of the workflow, or 404.
+async fn workflow_version(state: &AppState, id: Uuid, version: i32) -> Result<WorkflowVersion, ApiError> {
+    state.storage.get_workflow_version(id, version).await?
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, format!("Workflow {} has no version {}", id, version)))
+}
+
+async fn get_workflow_version(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path((id, version)): Path<(Uuid, i32)>
+) -> ApiResult<WorkflowVersion> {
+    authorize_workflow(&state, &current, id, Role::Viewer).await?;
+    Ok(Json(workflow_version(&state, id, version).await?))
+}
+
+#[derive(Deserialize)]
+struct DiffQuery {
+    from: i32,
+    to: Option<i32>, // The latest version if unset
+}
+
+async fn diff_workflow_versions(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<DiffQuery>
+) -> ApiResult<Value> {
+    let workflow = authorize_workflow(&state, &current, id, Role::Viewer).await?;
+    let from = workflow_version(&state, id, query.from).await?;
+    let to = workflow_version(&state, id, query.to.unwrap_or(workflow.version)).await?;
+    Ok(Json(from.diff(&to)))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RollbackRequest {
+    pub version: i32,
+}
+
+async fn rollback_workflow(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<RollbackRequest>
+) -> ApiResult<SaveWorkflowResponse> {
+    let workflow = authorize_workflow(&state, &current, id, Role::Member).await?;
+    let target = workflow_version(&state, id, payload.version).await?;
+
+    let version = storage::rollback_workflow(state.storage.as_ref(), id, payload.version, Some(current.user.id)).await?;
+    let audited = |name: &str, definition: &Value| serde_json::json!({ "name": name, "definition": definition });
+    let mut diff = audit::diff(&audited(&workflow.name, &workflow.definition), &audited(&target.name, &target.definition));
+    diff["version"] = serde_json::json!({ "before": workflow.version, "after": version, "restored": payload.version });
+    record_audit(&state, &current, workflow.account_id, "workflow.rollback", format!("workflow/{}", id), Some(diff)).await;
+    Ok(Json(SaveWorkflowResponse { version }))
+}
+
+async fn list_workflows(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path(team_id): Path<Uuid>
+) -> ApiResult<Vec<WorkflowEntity>> {
+    authorize(&state, &current, Some(team_id), Role::Viewer).await?;
+    Ok(Json(state.storage.list_workflows(team_id).await?))
+}
+
+// --- Credentials ---
+
+#[derive(Deserialize)]
+struct CreateCredReq {
+    name: String,
+    credential_type: String,
+    /// The credential's fields, or a string for types with a single field
+    data: Value,
+    team_id: Uuid,
+}
+
+async fn create_credential(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Json(payload): Json<CreateCredReq>
+) -> ApiResult<crate::storage::Credential> {
+    authorize(&state, &current, Some(payload.team_id), Role::Admin).await?;
+    credentials::get_credential_type(&payload.credential_type)
+        .and_then(|t| t.validate(&payload.data))
+        .map_err(|e| ApiError::bad_request(e.to_string()))?;
+    let cred = state.storage.create_credential(&payload.name, &payload.credential_type, &payload.data, payload.team_id).await?;
+    // Never the secret itself
+    let diff = audit::diff(&Value::Null, &serde_json::json!({ "name": cred.name, "credential_type": cred.credential_type }));
+    record_audit(&state, &current, payload.team_id, "credential.create", format!("credential/{}", cred.id), Some(diff)).await;
+    Ok(Json(cred))
+}
+
+/// The credential, if the caller is an admin of its team.
+async fn authorize_credential(state: &AppState, current: &CurrentUser, id: Uuid) -> Result<crate::storage::Credential, ApiError> {
+    let cred = state.storage.get_credential(id).await?
+        .ok_or_else(|| ApiError::not_found(format!("Credential {}", id)))?;
+    authorize(state, current, Some(cred.account_id), Role::Admin).await?;
+    Ok(cred)
+}
+
+async fn get_credential(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path(id): Path<Uuid>
+) -> ApiResult<crate::storage::Credential> {
+    let cred = authorize_credential(&state, &current, id).await?;
+    record_audit(&state, &current, cred.account_id, "credential.read", format!("credential/{}", cred.id), None).await;
+    Ok(Json(cred))
+}
+
+/// Body of `PUT /api/credentials/{id}`; what is left out stays as it is.
+#[derive(Serialize, Deserialize, Default)]
+pub struct UpdateCredentialRequest {
+    #[serde(default)]
+    pub name: Option<String>,
+    /// All fields of the credential, replacing the current ones
+    #[serde(default)]
+    pub data: Option<Value>,
+}
+
+async fn update_credential(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<UpdateCredentialRequest>
+) -> Result<StatusCode, ApiError> {
+    let cred = authorize_credential(&state, &current, id).await?;
+    if let Some(data) = &payload.data {
+        credentials::get_credential_type(&cred.credential_type)
+            .and_then(|t| t.validate(data))
+            .map_err(|e| ApiError::bad_request(e.to_string()))?;
+    }
+    if !state.storage.update_credential(id, payload.name.as_deref(), payload.data.as_ref()).await? {
+        return Err(ApiError::not_found(format!("Credential {}", id)));
+    }
+    record_audit(&state, &current, cred.account_id, "credential.update", format!("credential/{}", id), Some(credential_update_diff(&cred, &payload))).await;
+    Ok(StatusCode::OK)
+}
+
+/// The audit diff of a credential update. Replaced fields show as `/data`, never their values.
+pub fn credential_update_diff(cred: &crate::storage::Credential, update: &UpdateCredentialRequest) -> Value {
+    let name = update.name.as_ref().unwrap_or(&cred.name);
+    let mut diff = audit::diff(&serde_json::json!({ "name": cred.name }), &serde_json::json!({ "name": name }));
+    if update.data.is_some() {
+        diff["/data"] = serde_json::json!({ "before": "(secret)", "after": "(secret)" });
+    }
+    diff
+}
+
+#[derive(Deserialize)]
+struct DeleteCredentialQuery {
+    /// Also delete a credential stored workflows reference
+    #[serde(default)]
+    force: bool,
+}
+
+async fn delete_credential(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<DeleteCredentialQuery>
+) -> Result<StatusCode, ApiError> {
+    let cred = authorize_credential(&state, &current, id).await?;
+    let usage = storage::credential_usage(state.storage.as_ref(), &cred).await?;
+    if !usage.is_empty() && !query.force {
+        let names = usage.iter().map(|u| format!("{} ({})", u.workflow_name, u.node_id)).collect::<Vec<_>>();
+        return Err(ApiError::new(StatusCode::CONFLICT, format!("Credential {} is used by {}; delete with force=true anyway", id, names.join(", "))));
+    }
+    // Checked above, to answer with the usage
+    if !state.storage.delete_credential(id, true).await? {
+        return Err(ApiError::not_found(format!("Credential {}", id)));
+    }
+    let diff = audit::diff(&serde_json::json!({ "name": cred.name, "credential_type": cred.credential_type }), &Value::Null);
+    record_audit(&state, &current, cred.account_id, "credential.delete", format!("credential/{}", id), Some(diff)).await;
+    Ok(StatusCode::OK)
+}
+
+async fn reveal_credential(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path(id): Path<Uuid>
+) -> ApiResult<Value> {
+    let cred = authorize_credential(&state, &current, id).await?;
+    let fields = storage::reveal_credential(&cred)?;
+    record_audit(&state, &current, cred.account_id, "credential.reveal", format!("credential/{}", id), None).await;
+    Ok(Json(fields))
+}
+
+async fn credential_usage(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path(id): Path<Uuid>
+) -> ApiResult<Vec<storage::CredentialUsage>> {
+    let cred = authorize_credential(&state, &current, id).await?;
+    Ok(Json(storage::credential_usage(state.storage.as_ref(), &cred).await?))
+}
+
+async fn list_credentials(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path(team_id): Path<Uuid>
+) -> ApiResult<Vec<crate::storage::Credential>> {
+    authorize(&state, &current, Some(team_id), Role::Admin).await?;
+    let creds = state.storage.list_credentials(team_id).await?;
+    record_audit(&state, &current, team_id, "credential.list", format!("team/{}", team_id), None).await;
+    Ok(Json(creds))
+}
+
+// --- Runs ---
+
+#[derive(Deserialize)]
+struct RunRequest {
+    workflow: String, // YAML or JSON string
+    team_id: Option<Uuid>, // Scopes events emitted by the workflow
+    #[serde(default)]
+    priority: i32, // Higher runs first when executions are queued
+}
+
+#[derive(Serialize)]
+struct RunResponse {
+    job_id: String,
+    status: String,
+}
+
+async fn run_workflow(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Json(payload): Json<RunRequest>
+) -> ApiResult<RunResponse> {
+    tracing::info!("Received workflow execution request");
+    authorize(&state, &current, payload.team_id, Role::Member).await?;
+
+    let workflow_def = WorkflowLoader::new().load(&payload.workflow)
+        .map_err(|e| ApiError::bad_request(format!("Failed to parse workflow: {}", e)))?;
+
+    let spec = JobSpec {
+        team_id: payload.team_id,
+        priority: payload.priority,
+        actor_id: Some(current.user.id),
+        ..Default::default()
+    };
+
+    // Create job and spawn execution
+    let job_id = state.job_manager.start_workflow(&workflow_def, spec).await
+        .map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to start workflow: {}", e)))?;
+    if let Some(team_id) = payload.team_id {
+        record_audit(&state, &current, team_id, "execution.start", format!("execution/{}", job_id), None).await;
+    }
+
+    Ok(Json(RunResponse { job_id, status: "pending".to_string() }))
+}
+
+#[derive(Deserialize)]
+struct RunStoredRequest {
+    #[serde(default)]
+    input: Value, // Injected into the workflow's manual triggers
+    #[serde(default)]
+    priority: i32,
+}
+
+async fn run_stored_workflow(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<RunStoredRequest>
+) -> ApiResult<RunResponse> {
+    let workflow = authorize_workflow(&state, &current, id, Role::Member).await?;
+    workflow.ensure_runnable().map_err(|e| ApiError::new(StatusCode::CONFLICT, e.to_string()))?;
+    let workflow_def: WorkflowDefinition = serde_json::from_value(workflow.definition)
+        .map_err(|e| ApiError::bad_request(format!("Failed to parse workflow: {}", e)))?;
+
+    let spec = JobSpec {
+        workflow_id: Some(workflow.id),
+        workflow_version: Some(workflow.version),
+        team_id: Some(workflow.account_id),
+        inputs: JobSpec::manual_inputs(&workflow_def, &payload.input),
+        priority: payload.priority,
+        actor_id: Some(current.user.id),
+    };
+
+    let job_id = state.job_manager.start_workflow(&workflow_def, spec).await
+        .map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to start workflow: {}", e)))?;
+    let details = serde_json::json!({ "workflow_id": workflow.id });
+    record_audit(&state, &current, workflow.account_id, "execution.start", format!("execution/{}", job_id), Some(details)).await;
+
+    Ok(Json(RunResponse { job_id, status: "pending".to_string() }))
+}
+
+#[derive(Serialize)]
+struct JobStatusResponse {
+    id: String,
+    status: String,
+    logs: Vec<String>,
+}
+
+async fn get_job_status(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path(id): Path<String>
+) -> ApiResult<JobStatusResponse> {
+    let execution_id = job_execution_id(&id)?;
+    authorize_execution(&state, &current, execution_id, Role::Viewer).await?;
+
+    // Queued executions, and those run by other servers, are only in storage
+    let status = if state.job_manager.is_durable() {
+        state.storage.get_execution(execution_id).await?.map(|e| e.status)
+    } else {
+        state.job_manager.get_job(&id).map(|status| match status {
+            JobStatus::Pending => "pending",
+            JobStatus::Running => "running",
+            JobStatus::Completed => "completed",
+            JobStatus::Failed(_) => "failed",
+            JobStatus::Queued => "queued",
+            JobStatus::Skipped(_) => "skipped",
+            JobStatus::Cancelled => "cancelled",
+            JobStatus::Waiting => "waiting",
+        }.to_string())
+    };
+    let status = status.ok_or_else(|| ApiError::not_found(format!("Job {}", id)))?;
+
+    let logs = state.job_manager.get_job_logs(&id).unwrap_or_default();
+    Ok(Json(JobStatusResponse { id, status, logs }))
+}
+
+async fn cancel_job(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path(id): Path<String>
+) -> Result<StatusCode, ApiError> {
+    let execution_id = job_execution_id(&id)?;
+    authorize_execution(&state, &current, execution_id, Role::Member).await?;
+    if !state.job_manager.cancel_job(&id).await {
+        return Err(ApiError::not_found(format!("Job {}", id)));
+    }
+    if let Some(Some(team_id)) = auth::execution_team(state.storage.as_ref(), execution_id).await? {
+        record_audit(&state, &current, team_id, "execution.cancel", format!("execution/{}", id), None).await;
+    }
+    Ok(StatusCode::OK)
+}
+
+// --- Executions ---
+
+async fn list_executions(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Query(filter): Query<ExecutionFilter>
+) -> ApiResult<Vec<crate::storage::ExecutionRecord>> {
+    // Listings are scoped to something the caller can see
+    match (filter.team_id, filter.workflow_id) {
+        (Some(team_id), _) => authorize(&state, &current, Some(team_id), Role::Viewer).await?,
+        (None, Some(workflow_id)) => { authorize_workflow(&state, &current, workflow_id, Role::Viewer).await?; }
+        (None, None) => return Err(ApiError::bad_request("Filter by team_id or workflow_id")),
+    }
+    Ok(Json(state.storage.list_executions(&filter).await?))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CreateExecutionRequest {
+    pub id: Uuid,
+    pub workflow_id: Option<Uuid>,
+    #[serde(default)]
+    pub workflow_version: Option<i32>,
+    /// Team the run used; a stored workflow's runs belong to its team.
+    #[serde(default)]
+    pub team_id: Option<Uuid>,
+    pub status: String,
+}
+
+/// Records a run executed elsewhere, e.g. by `cli run`.
+async fn create_execution(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Json(payload): Json<CreateExecutionRequest>
+) -> ApiResult<Uuid> {
+    let team_id = match payload.workflow_id {
+        Some(workflow_id) => Some(authorize_workflow(&state, &current, workflow_id, Role::Member).await?.account_id),
+        None => {
+            authorize(&state, &current, payload.team_id, Role::Member).await?;
+            payload.team_id
+        }
+    };
+    if state.storage.get_execution(payload.id).await?.is_some() {
+        return Err(ApiError::new(StatusCode::CONFLICT, format!("Execution {} already exists", payload.id)));
+    }
+    Ok(Json(state.storage.create_execution(payload.id, payload.workflow_id, payload.workflow_version, team_id, &payload.status).await?))
+}
+
+async fn get_execution(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path(id): Path<Uuid>
+) -> ApiResult<crate::storage::ExecutionRecord> {
+    authorize_execution(&state, &current, id, Role::Viewer).await?;
+    let execution = state.storage.get_execution(id).await?
+        .ok_or_else(|| ApiError::not_found(format!("Execution {}", id)))?;
+    Ok(Json(execution))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UpdateExecutionRequest {
+    pub status: String,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub error: Option<String>,
+}
+
+async fn update_execution(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<UpdateExecutionRequest>
+) -> Result<StatusCode, ApiError> {
+    authorize_execution(&state, &current, id, Role::Member).await?;
+    state.storage.update_execution(id, &payload.status, payload.finished_at, payload.error).await?;
+    Ok(StatusCode::OK)
+}
+
+async fn get_execution_logs(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path(id): Path<Uuid>
+) -> ApiResult<Vec<ExecutionEvent>> {
+    authorize_execution(&state, &current, id, Role::Viewer).await?;
+    Ok(Json(state.storage.get_execution_logs(id).await?))
+}
+
+async fn log_execution_event(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path(id): Path<Uuid>,
+    Json(event): Json<ExecutionEvent>
+) -> Result<StatusCode, ApiError> {
+    authorize_execution(&state, &current, id, Role::Member).await?;
+    state.storage.log_execution_event(id, &event).await?;
+    Ok(StatusCode::OK)
+}
+
+/// `null` for executions that ran in-process.
+async fn get_execution_definition(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path(id): Path<Uuid>
+) -> ApiResult<Option<Value>> {
+    authorize_execution(&state, &current, id, Role::Viewer).await?;
+    Ok(Json(state.storage.get_execution_definition(id).await?))
+}
+
+#[derive(Deserialize)]
+struct SignalRequest {
+    decision: String, // "approve" or "reject"
+    comment: Option<String>,
+}
+
+async fn signal_execution(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path((id, token)): Path<(Uuid, String)>,
+    Json(payload): Json<SignalRequest>
+) -> Result<StatusCode, ApiError> {
+    authorize_execution(&state, &current, id, Role::Member).await?;
+    let status = match payload.decision.as_str() {
+        "approve" | "approved" => "approved",
+        "reject" | "rejected" => "rejected",
+        other => return Err(ApiError::bad_request(format!("Unknown decision '{}', use approve or reject", other))),
+    };
+
+    let approval = match state.storage.get_approval(&token).await? {
+        Some(approval) if approval.execution_id == id => approval,
+        _ => return Err(ApiError::not_found(format!("Approval {}", token))),
+    };
+
+    let decision = serde_json::json!({
+        "decision": status,
+        "comment": payload.comment,
+        "by": current.user.username,
+    });
+    if !state.storage.resolve_approval(&token, status, Some(&decision)).await? {
+        return Err(ApiError::new(StatusCode::CONFLICT, format!("Approval {} is already settled", token)));
+    }
+
+    // A suspended execution continues from the approval node with the decided record
+    if let Some(node_id) = approval.node_id {
+        // The expiry timer shares the token
+        if let Ok(timer_id) = Uuid::parse_str(&token) {
+            state.storage.update_timer_status(&[timer_id], "cancelled").await?;
+        }
+        let record = serde_json::json!({ "token": token });
+        if !state.job_manager.resume_execution(id, &node_id, vec![record.clone()]).await? {
+            // Still running: the timers resume it once it is waiting
+            let now = Utc::now();
+            state.storage.create_timer(&TimerRecord {
+                id: Uuid::new_v4(),
+                execution_id: id,
+                node_id,
+                record,
+                wake_at: now,
+                status: "waiting".to_string(),
+                created_at: now,
+            }).await?;
+        }
+    }
+    Ok(StatusCode::OK)
+}
+
+// --- Approvals and node state, for runs outside the server ---
+
+async fn create_approval(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Json(approval): Json<ApprovalRecord>
+) -> Result<StatusCode, ApiError> {
+    authorize_execution(&state, &current, approval.execution_id, Role::Member).await?;
+    state.storage.create_approval(&approval).await?;
+    Ok(StatusCode::OK)
+}
+
+async fn get_approval(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path(token): Path<String>
+) -> ApiResult<ApprovalRecord> {
+    let approval = state.storage.get_approval(&token).await?
+        .ok_or_else(|| ApiError::not_found(format!("Approval {}", token)))?;
+    authorize_execution(&state, &current, approval.execution_id, Role::Viewer).await?;
+    Ok(Json(approval))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ResolveApprovalRequest {
+    pub status: String,
+    pub decision: Option<Value>,
+}
+
+/// Settles an approval as its run does, e.g. `expired`. Returns false if already settled.
+async fn resolve_approval(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path(token): Path<String>,
+    Json(payload): Json<ResolveApprovalRequest>
+) -> ApiResult<bool> {
+    let approval = state.storage.get_approval(&token).await?
+        .ok_or_else(|| ApiError::not_found(format!("Approval {}", token)))?;
+    authorize_execution(&state, &current, approval.execution_id, Role::Member).await?;
+    Ok(Json(state.storage.resolve_approval(&token, &payload.status, payload.decision.as_ref()).await?))
+}
+
+// Node state is keyed by the nodes themselves (e.g. poll watermarks), under the team of the run
+
+async fn get_kv(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path(key): Path<String>
+) -> ApiResult<Value> {
+    authorize(&state, &current, storage::kv_key_team(&key), Role::Viewer).await?;
+    let value = state.storage.get_kv(&key).await?
+        .ok_or_else(|| ApiError::not_found(format!("Key {}", key)))?;
+    Ok(Json(value))
+}
+
+async fn set_kv(
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+    Path(key): Path<String>,
+    Json(value): Json<Value>
+) -> Result<StatusCode, ApiError> {
+    authorize(&state, &current, storage::kv_key_team(&key), Role::Member).await?;
+    state.storage.set_kv(&key, &value).await?;
+    Ok(StatusCode::OK)
+}
+
+async fn get_node_types() -> impl IntoResponse {
+    let registry = crate::node_registry::get_node_registry();
+    Json(registry)
+}
+
+async fn get_credential_types() -> impl IntoResponse {
+    Json(credentials::get_credential_types())
+}
+
+async fn ws_handler(
+    ws: WebSocketUpgrade,
+    Path(id): Path<String>,
+    State(state): State<Arc<AppState>>,
+    Extension(current): Extension<CurrentUser>,
+) -> Result<Response, ApiError> {
+    authorize_execution(&state, &current, job_execution_id(&id)?, Role::Viewer).await?;
+    Ok(ws.on_upgrade(move |socket| handle_socket(socket, id, state)))
+}
+
+async fn handle_socket(mut socket: WebSocket, job_id: String, state: Arc<AppState>) {
+    let mut rx = match state.job_manager.subscribe_to_events(&job_id) {
+        Some(rx) => rx,
+        None => {
+            let _ = socket.send(Message::Text("Job not found".into())).await;
+            return;
+        }
+    };
+
+    while let Ok(event) = rx.recv().await {
+        if let Ok(msg) = serde_json::to_string(&event) {
+            if socket.send(Message::Text(msg.into())).await.is_err() {
+                break;
+            }
+        }
+    }
+}
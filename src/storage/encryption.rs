@@ -1,55 +1,190 @@
 use aes_gcm::{
     aead::{Aead, KeyInit, OsRng, rand_core::RngCore},
-    Aes256Gcm, Nonce 
+    Aes256Gcm, Nonce
 };
-use anyhow::{Result, anyhow};
+use anyhow::{Result, Context, anyhow, bail};
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use sha2::{Digest, Sha256};
+use std::sync::{Arc, RwLock};
 
-// We need a master key. For CLI, we can use an env var or a fixed key for now (POC).
-// In production, this should be securely managed.
-// For this implementation, we will expect RUSTFLOW_MASTER_KEY env var (32 bytes hex), 
-// or generate one if missing (but that means we can't decrypt later if we restart).
-// Better: Store a key file locally if not present?
-// Or just ask user to provide it.
-// Let's use a default fixed key for "dev" mode if env is missing, but warn.
+// Credentials are encrypted with AES-256-GCM under the master key, which comes from exactly
+// one of RUSTFLOW_MASTER_KEY (64 hex characters), RUSTFLOW_MASTER_KEY_FILE (a file holding
+// the hex key or 32 raw bytes) or RUSTFLOW_MASTER_PASSPHRASE (stretched with argon2 and the
+// deployment's RUSTFLOW_MASTER_KEY_SALT).
+// Ciphertexts are prefixed with the id of their key, so keys listed in
+// RUSTFLOW_PREVIOUS_MASTER_KEYS keep decrypting while credentials are rotated to a new one.
+// Without a key, a fixed development key is used, which RUSTFLOW_ENV=production refuses.
 
 const DEFAULT_DEV_KEY: &[u8; 32] = b"01234567890123456789012345678901"; // 32 bytes
 
-fn get_master_key() -> [u8; 32] {
-    if let Ok(key_hex) = std::env::var("RUSTFLOW_MASTER_KEY") {
-        if let Ok(bytes) = hex::decode(key_hex) {
-            if bytes.len() == 32 {
-                let mut key = [0u8; 32];
-                key.copy_from_slice(&bytes);
-                return key;
+/// The keyring `encrypt` and `decrypt` use, read from the environment on first use.
+static KEYRING: RwLock<Option<Arc<Keyring>>> = RwLock::new(None);
+
+/// Where the current master key came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeySource {
+    Env,
+    File,
+    Passphrase,
+    /// The built-in development key
+    Development,
+}
+
+#[derive(Clone)]
+pub struct MasterKey {
+    /// First 8 hex characters of the key's SHA-256, prefixed to its ciphertexts
+    pub id: String,
+    pub source: KeySource,
+    key: [u8; 32],
+}
+
+impl MasterKey {
+    fn new(key: [u8; 32], source: KeySource) -> Self {
+        let id = hex::encode(&Sha256::digest(key)[..4]);
+        Self { id, source, key }
+    }
+}
+
+/// The current master key, used to encrypt, and the previous ones still accepted to decrypt.
+pub struct Keyring {
+    pub current: MasterKey,
+    pub previous: Vec<MasterKey>,
+}
+
+impl Keyring {
+    /// Reads the keys from the environment. Malformed or conflicting settings are errors,
+    /// as is a missing key with RUSTFLOW_ENV=production.
+    pub fn from_env() -> Result<Self> {
+        let current = match current_key()? {
+            Some(key) => key,
+            None if is_production() => bail!(
+                "RUSTFLOW_ENV=production requires a master key: set RUSTFLOW_MASTER_KEY, RUSTFLOW_MASTER_KEY_FILE or RUSTFLOW_MASTER_PASSPHRASE"
+            ),
+            None => MasterKey::new(*DEFAULT_DEV_KEY, KeySource::Development),
+        };
+        let mut previous = Vec::new();
+        if let Ok(specs) = std::env::var("RUSTFLOW_PREVIOUS_MASTER_KEYS") {
+            for spec in specs.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+                previous.push(parse_previous(spec).context("Invalid RUSTFLOW_PREVIOUS_MASTER_KEYS")?);
+            }
+        }
+        // Credentials written before a key was configured can still be read, and rotated
+        if current.source != KeySource::Development && !is_production() {
+            previous.push(MasterKey::new(*DEFAULT_DEV_KEY, KeySource::Development));
+        }
+        Ok(Self { current, previous })
+    }
+
+    fn find(&self, id: &str) -> Option<&MasterKey> {
+        std::iter::once(&self.current).chain(&self.previous).find(|k| k.id == id)
+    }
+
+    pub fn encrypt(&self, data: &str) -> Result<String> {
+        let cipher = Aes256Gcm::new(&self.current.key.into());
+
+        let mut nonce_bytes = [0u8; 12];
+        OsRng.fill_bytes(&mut nonce_bytes);
+        let nonce = Nonce::from_slice(&nonce_bytes); // 96-bits; unique per message
+
+        let ciphertext = cipher.encrypt(nonce, data.as_bytes())
+            .map_err(|e| anyhow!("Encryption failure: {:?}", e))?;
+
+        // Prepend nonce to ciphertext
+        let mut combined = nonce_bytes.to_vec();
+        combined.extend(ciphertext);
+
+        Ok(format!("{}:{}", self.current.id, BASE64.encode(combined)))
+    }
+
+    pub fn decrypt(&self, encrypted_data: &str) -> Result<String> {
+        match encrypted_data.split_once(':') {
+            Some((id, data)) => {
+                let key = self.find(id).ok_or_else(|| anyhow!(
+                    "Encrypted with unknown master key {}; add it to RUSTFLOW_PREVIOUS_MASTER_KEYS", id
+                ))?;
+                decrypt_with(key, data)
             }
+            // Written before key ids: try every key
+            None => std::iter::once(&self.current).chain(&self.previous)
+                .find_map(|key| decrypt_with(key, encrypted_data).ok())
+                .ok_or_else(|| anyhow!("Decryption failure: no master key matches")),
         }
     }
-    // Fallback for dev
-    *DEFAULT_DEV_KEY
+
+    /// Whether `encrypted_data` is encrypted under the current key.
+    pub fn is_current(&self, encrypted_data: &str) -> bool {
+        key_id(encrypted_data) == Some(self.current.id.as_str())
+    }
 }
 
-pub fn encrypt(data: &str) -> Result<String> {
-    let key = get_master_key();
-    let cipher = Aes256Gcm::new(&key.into());
-    
-    let mut nonce_bytes = [0u8; 12];
-    OsRng.fill_bytes(&mut nonce_bytes);
-    let nonce = Nonce::from_slice(&nonce_bytes); // 96-bits; unique per message
+/// The id of the key `encrypted_data` is encrypted under; `None` for data written before key ids.
+pub fn key_id(encrypted_data: &str) -> Option<&str> {
+    encrypted_data.split_once(':').map(|(id, _)| id)
+}
 
-    let ciphertext = cipher.encrypt(nonce, data.as_bytes())
-        .map_err(|e| anyhow!("Encryption failure: {:?}", e))?;
+pub fn is_production() -> bool {
+    std::env::var("RUSTFLOW_ENV").is_ok_and(|env| env.eq_ignore_ascii_case("production"))
+}
 
-    // Prepend nonce to ciphertext
-    let mut combined = nonce_bytes.to_vec();
-    combined.extend(ciphertext);
+fn current_key() -> Result<Option<MasterKey>> {
+    let env = std::env::var("RUSTFLOW_MASTER_KEY").ok();
+    let file = std::env::var("RUSTFLOW_MASTER_KEY_FILE").ok();
+    let passphrase = std::env::var("RUSTFLOW_MASTER_PASSPHRASE").ok();
+    match (env, file, passphrase) {
+        (None, None, None) => Ok(None),
+        (Some(hex_key), None, None) => Ok(Some(MasterKey::new(
+            parse_hex(&hex_key).context("Invalid RUSTFLOW_MASTER_KEY")?,
+            KeySource::Env,
+        ))),
+        (None, Some(path), None) => Ok(Some(MasterKey::new(read_key_file(&path)?, KeySource::File))),
+        (None, None, Some(passphrase)) => Ok(Some(MasterKey::new(derive(&passphrase)?, KeySource::Passphrase))),
+        _ => bail!("Set only one of RUSTFLOW_MASTER_KEY, RUSTFLOW_MASTER_KEY_FILE and RUSTFLOW_MASTER_PASSPHRASE"),
+    }
+}
 
-    Ok(BASE64.encode(combined))
+/// A previous key: 64 hex characters, `file:<path>` or `passphrase:<passphrase>`.
+fn parse_previous(spec: &str) -> Result<MasterKey> {
+    if let Some(path) = spec.strip_prefix("file:") {
+        Ok(MasterKey::new(read_key_file(path)?, KeySource::File))
+    } else if let Some(passphrase) = spec.strip_prefix("passphrase:") {
+        Ok(MasterKey::new(derive(passphrase)?, KeySource::Passphrase))
+    } else {
+        Ok(MasterKey::new(parse_hex(spec)?, KeySource::Env))
+    }
 }
 
-pub fn decrypt(encrypted_data: &str) -> Result<String> {
-    let key = get_master_key();
-    let cipher = Aes256Gcm::new(&key.into());
+fn parse_hex(key_hex: &str) -> Result<[u8; 32]> {
+    let bytes = hex::decode(key_hex.trim()).context("expected 64 hex characters")?;
+    bytes.try_into().map_err(|bytes: Vec<u8>| anyhow!("expected 32 bytes, got {}", bytes.len()))
+}
+
+fn read_key_file(path: &str) -> Result<[u8; 32]> {
+    let bytes = std::fs::read(path).with_context(|| format!("Failed to read master key file {}", path))?;
+    if let Ok(key) = <[u8; 32]>::try_from(bytes.as_slice()) {
+        return Ok(key);
+    }
+    let text = std::str::from_utf8(&bytes).map_err(|_| anyhow!("Master key file {} is neither 32 bytes nor hex", path))?;
+    parse_hex(text).with_context(|| format!("Invalid master key file {}", path))
+}
+
+/// Stretches a passphrase with RUSTFLOW_MASTER_KEY_SALT. The salt is required, so
+/// deployments sharing a passphrase do not share a key.
+fn derive(passphrase: &str) -> Result<[u8; 32]> {
+    if passphrase.is_empty() {
+        bail!("The master passphrase is empty");
+    }
+    let salt = std::env::var("RUSTFLOW_MASTER_KEY_SALT").ok().filter(|s| !s.is_empty()).ok_or_else(|| anyhow!(
+        "A master passphrase requires RUSTFLOW_MASTER_KEY_SALT, a random value unique to this deployment (e.g. `openssl rand -hex 16`)"
+    ))?;
+    let mut key = [0u8; 32];
+    argon2::Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt.as_bytes(), &mut key)
+        .map_err(|e| anyhow!("Failed to derive the master key: {}", e))?;
+    Ok(key)
+}
+
+fn decrypt_with(key: &MasterKey, encrypted_data: &str) -> Result<String> {
+    let cipher = Aes256Gcm::new(&key.key.into());
 
     let decoded = BASE64.decode(encrypted_data)?;
     if decoded.len() < 12 {
@@ -64,3 +199,32 @@ pub fn decrypt(encrypted_data: &str) -> Result<String> {
 
     Ok(String::from_utf8(plaintext)?)
 }
+
+/// The process's keyring, read from the environment once (deriving a passphrase is slow on
+/// purpose). Settings that are errors are read again on the next call.
+pub fn keyring() -> Result<Arc<Keyring>> {
+    if let Some(keyring) = KEYRING.read().unwrap().as_ref() {
+        return Ok(keyring.clone());
+    }
+    let mut cached = KEYRING.write().unwrap();
+    if let Some(keyring) = cached.as_ref() {
+        return Ok(keyring.clone());
+    }
+    let keyring = Arc::new(Keyring::from_env()?);
+    *cached = Some(keyring.clone());
+    Ok(keyring)
+}
+
+/// Reads the keyring from the environment again, after its key settings changed.
+pub fn reload_keyring() -> Result<Arc<Keyring>> {
+    KEYRING.write().unwrap().take();
+    keyring()
+}
+
+pub fn encrypt(data: &str) -> Result<String> {
+    keyring()?.encrypt(data)
+}
+
+pub fn decrypt(encrypted_data: &str) -> Result<String> {
+    keyring()?.decrypt(encrypted_data)
+}
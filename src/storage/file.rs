@@ -0,0 +1,439 @@
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+use serde_json::Value;
+use anyhow::{Result, anyhow, bail};
+use chrono::{Utc, DateTime};
+use super::{Storage, Team, User, TeamMember, Role, WorkflowEntity, WorkflowVersion, Credential, ExecutionRecord, ExecutionFilter, RetentionPolicy, PruneStats, ApiToken, AuditEntry, EventRecord, QueuedExecution, ApprovalRecord, TimerRecord, SchemaStatus, SqliteStorage};
+use crate::schema::ExecutionEvent;
+
+/// Name of the SQLite file in the root directory holding everything but the workflow files.
+pub const SIDECAR_DB: &str = ".rustflow.db";
+
+/// Storage keeping each workflow's definition as a YAML file, `<root>/<team name>/<workflow name>.yaml`,
+/// so workflows can live in a git repository. Everything else (users, teams, credentials,
+/// executions, and the ids, flags and versions of workflows) is kept in a SQLite file next to
+/// them, `<root>/.rustflow.db`, which should be left out of the repository.
+///
+/// The files are the source of truth: when workflows are read, edited files are saved as a new
+/// version and files added by hand become workflows. Workflows whose file is missing, e.g. on
+/// another git branch, read as disabled until a file with their name is back. Renaming a file
+/// therefore adds a new workflow; the old one stays disabled until it is deleted.
+pub struct FileStorage {
+    root: PathBuf,
+    db: SqliteStorage,
+    /// Held while syncing and writing files, so a file added by hand is registered once.
+    files: Mutex<()>,
+}
+
+impl FileStorage {
+    /// Opens `file://<directory>`, creating the directory and its sidecar database if missing.
+    pub async fn new(database_url: &str) -> Result<Self> {
+        let root = PathBuf::from(database_url.trim_start_matches("file://"));
+        fs::create_dir_all(&root).await?;
+        let db = SqliteStorage::new(&format!("sqlite:{}?mode=rwc", root.join(SIDECAR_DB).display())).await?;
+        Ok(Self { root, db, files: Mutex::new(()) })
+    }
+
+    async fn team_dir(&self, team_id: Uuid) -> Result<PathBuf> {
+        let team = self.db.get_team(team_id).await?
+            .ok_or_else(|| anyhow!("Team {} not found", team_id))?;
+        Ok(self.root.join(path_segment(&team.name, "Team")?))
+    }
+
+    /// Picks up changes to the workflow's file: an edit is saved as a new version, a missing
+    /// file disables the workflow while it is missing. Files that cannot be read leave the
+    /// workflow as it is.
+    async fn sync(&self, workflow: WorkflowEntity) -> Result<WorkflowEntity> {
+        if workflow.deleted_at.is_some() {
+            return Ok(workflow);
+        }
+        let path = workflow_path(&self.team_dir(workflow.account_id).await?, &workflow.name)?;
+        match read_definition(&path).await {
+            // Not stored: the workflow runs again, with its history, once the file is back
+            Ok(None) => return Ok(WorkflowEntity { active: false, ..workflow }),
+            Ok(Some(definition)) if definition != workflow.definition => {
+                let changed = WorkflowEntity { definition, ..workflow.clone() };
+                self.db.save_workflow(&changed, None, Some("Changed on disk")).await?;
+            }
+            Ok(Some(_)) => return Ok(workflow),
+            Err(e) => {
+                eprintln!("Warning: keeping workflow {} as it was, {}", workflow.id, e);
+                return Ok(workflow);
+            }
+        }
+        Ok(self.db.get_workflow(workflow.id).await?.unwrap_or(workflow))
+    }
+
+    /// Syncs the team's workflows and registers the files added by hand; returns them all.
+    async fn sync_team(&self, team_id: Uuid) -> Result<Vec<WorkflowEntity>> {
+        let known = self.db.list_workflows(team_id).await?;
+        let mut synced = Vec::with_capacity(known.len());
+        for workflow in &known {
+            synced.push(self.sync(workflow.clone()).await?);
+        }
+
+        let dir = self.team_dir(team_id).await?;
+        let mut entries = match fs::read_dir(&dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(synced),
+            Err(e) => return Err(e.into()),
+        };
+        let mut added = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let name = match (path.extension().and_then(|e| e.to_str()), path.file_stem().and_then(|s| s.to_str())) {
+                (Some("yaml"), Some(name)) if !name.starts_with('.') => name.to_string(),
+                _ => continue,
+            };
+            if !known.iter().any(|w| w.name == name) {
+                added.push((name, path));
+            }
+        }
+        added.sort();
+
+        for (name, path) in added {
+            let definition = match read_definition(&path).await {
+                Ok(Some(definition)) => definition,
+                Ok(None) => continue,
+                Err(e) => {
+                    eprintln!("Warning: skipping {}", e);
+                    continue;
+                }
+            };
+            let workflow = WorkflowEntity::new(team_id, name, definition);
+            self.db.save_workflow(&workflow, None, Some("Added on disk")).await?;
+            synced.extend(self.db.get_workflow(workflow.id).await?);
+        }
+        Ok(synced)
+    }
+}
+
+/// `name` as a single file or directory name.
+fn path_segment<'a>(name: &'a str, what: &str) -> Result<&'a str> {
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\', '\0']) {
+        bail!("{} name '{}' cannot be used as a file name", what, name);
+    }
+    Ok(name)
+}
+
+fn workflow_path(team_dir: &Path, name: &str) -> Result<PathBuf> {
+    Ok(team_dir.join(format!("{}.yaml", path_segment(name, "Workflow")?)))
+}
+
+/// The definition in a workflow file; `None` if there is no such file.
+async fn read_definition(path: &Path) -> Result<Option<Value>> {
+    let content = match fs::read_to_string(path).await {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(anyhow!("{}: {}", path.display(), e)),
+    };
+    let definition = serde_yaml::from_str(&content).map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+    Ok(Some(definition))
+}
+
+async fn remove_file(path: &Path) -> Result<()> {
+    match fs::remove_file(path).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+#[async_trait]
+impl Storage for FileStorage {
+    async fn init(&self) -> Result<()> {
+        self.db.init().await
+    }
+
+    async fn schema_status(&self) -> Result<SchemaStatus> {
+        self.db.schema_status().await
+    }
+
+    // User
+    async fn create_user(&self, username: &str, password_hash: &str) -> Result<User> {
+        self.db.create_user(username, password_hash).await
+    }
+
+    async fn get_user(&self, id: Uuid) -> Result<Option<User>> {
+        self.db.get_user(id).await
+    }
+
+    async fn get_user_by_username(&self, username: &str) -> Result<Option<User>> {
+        self.db.get_user_by_username(username).await
+    }
+
+    async fn count_users(&self) -> Result<i64> {
+        self.db.count_users().await
+    }
+
+    // API tokens
+    async fn create_api_token(&self, token: &ApiToken) -> Result<()> {
+        self.db.create_api_token(token).await
+    }
+
+    async fn get_api_token_by_hash(&self, token_hash: &str) -> Result<Option<ApiToken>> {
+        self.db.get_api_token_by_hash(token_hash).await
+    }
+
+    async fn list_api_tokens(&self, user_id: Uuid) -> Result<Vec<ApiToken>> {
+        self.db.list_api_tokens(user_id).await
+    }
+
+    async fn delete_api_token(&self, user_id: Uuid, id: Uuid) -> Result<bool> {
+        self.db.delete_api_token(user_id, id).await
+    }
+
+    // Audit log
+    async fn log_audit(&self, entry: &AuditEntry) -> Result<()> {
+        self.db.log_audit(entry).await
+    }
+
+    async fn list_audit(&self, team_id: Uuid, limit: i64, offset: i64) -> Result<Vec<AuditEntry>> {
+        self.db.list_audit(team_id, limit, offset).await
+    }
+
+    // Team
+    async fn create_team(&self, name: &str) -> Result<Team> {
+        self.db.create_team(name).await
+    }
+
+    async fn get_team(&self, id: Uuid) -> Result<Option<Team>> {
+        self.db.get_team(id).await
+    }
+
+    async fn get_team_by_name(&self, name: &str) -> Result<Option<Team>> {
+        self.db.get_team_by_name(name).await
+    }
+
+    async fn list_teams_for_user(&self, user_id: Uuid) -> Result<Vec<(Team, Role)>> {
+        self.db.list_teams_for_user(user_id).await
+    }
+
+    // Team Members
+    async fn add_team_member(&self, team_id: Uuid, user_id: Uuid, role: Role) -> Result<()> {
+        self.db.add_team_member(team_id, user_id, role).await
+    }
+
+    async fn get_team_members(&self, team_id: Uuid) -> Result<Vec<TeamMember>> {
+        self.db.get_team_members(team_id).await
+    }
+
+    // Workflow
+    /// Also writes the workflow's file. Names are file names here, so they must be unique
+    /// within a team.
+    async fn save_workflow(&self, workflow: &WorkflowEntity, author_id: Option<Uuid>, message: Option<&str>) -> Result<i32> {
+        let _files = self.files.lock().await;
+        let previous = self.db.get_workflow(workflow.id).await?;
+        // Saves keep the team, as in the other backends
+        let team_id = previous.as_ref().map(|p| p.account_id).unwrap_or(workflow.account_id);
+        let dir = self.team_dir(team_id).await?;
+        let path = workflow_path(&dir, &workflow.name)?;
+
+        if self.sync_team(team_id).await?.iter().any(|w| w.name == workflow.name && w.id != workflow.id) {
+            bail!("Team {} already has a workflow named {}", team_id, workflow.name);
+        }
+        let version = self.db.save_workflow(workflow, author_id, message).await?;
+
+        fs::create_dir_all(&dir).await?;
+        fs::write(&path, serde_yaml::to_string(&workflow.definition)?).await?;
+        if let Some(previous) = previous.filter(|p| p.name != workflow.name) {
+            remove_file(&workflow_path(&dir, &previous.name)?).await?;
+        }
+        Ok(version)
+    }
+
+    async fn get_workflow(&self, id: Uuid) -> Result<Option<WorkflowEntity>> {
+        let _files = self.files.lock().await;
+        match self.db.get_workflow(id).await? {
+            Some(workflow) => Ok(Some(self.sync(workflow).await?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn list_workflows(&self, team_id: Uuid) -> Result<Vec<WorkflowEntity>> {
+        let _files = self.files.lock().await;
+        self.sync_team(team_id).await
+    }
+
+    async fn set_workflow_active(&self, id: Uuid, active: bool) -> Result<bool> {
+        self.db.set_workflow_active(id, active).await
+    }
+
+    /// Also removes the workflow's file.
+    async fn delete_workflow(&self, id: Uuid) -> Result<bool> {
+        let _files = self.files.lock().await;
+        let Some(workflow) = self.db.get_workflow(id).await? else {
+            return Ok(false);
+        };
+        if !self.db.delete_workflow(id).await? {
+            return Ok(false);
+        }
+        remove_file(&workflow_path(&self.team_dir(workflow.account_id).await?, &workflow.name)?).await?;
+        Ok(true)
+    }
+
+    async fn list_workflow_versions(&self, workflow_id: Uuid) -> Result<Vec<WorkflowVersion>> {
+        self.db.list_workflow_versions(workflow_id).await
+    }
+
+    async fn get_workflow_version(&self, workflow_id: Uuid, version: i32) -> Result<Option<WorkflowVersion>> {
+        self.db.get_workflow_version(workflow_id, version).await
+    }
+
+    // Key-Value
+    async fn set_kv(&self, key: &str, value: &Value) -> Result<()> {
+        self.db.set_kv(key, value).await
+    }
+
+    async fn get_kv(&self, key: &str) -> Result<Option<Value>> {
+        self.db.get_kv(key).await
+    }
+
+    // Credentials
+    async fn create_credential(&self, name: &str, credential_type: &str, data: &Value, team_id: Uuid) -> Result<Credential> {
+        self.db.create_credential(name, credential_type, data, team_id).await
+    }
+
+    async fn get_credential(&self, id: Uuid) -> Result<Option<Credential>> {
+        self.db.get_credential(id).await
+    }
+
+    async fn list_credentials(&self, team_id: Uuid) -> Result<Vec<Credential>> {
+        self.db.list_credentials(team_id).await
+    }
+
+    async fn update_credential(&self, id: Uuid, name: Option<&str>, data: Option<&Value>) -> Result<bool> {
+        self.db.update_credential(id, name, data).await
+    }
+
+    async fn delete_credential(&self, id: Uuid, force: bool) -> Result<bool> {
+        self.db.delete_credential(id, force).await
+    }
+
+    async fn list_all_credentials(&self) -> Result<Vec<Credential>> {
+        self.db.list_all_credentials().await
+    }
+
+    async fn replace_credential_data(&self, id: Uuid, old_data: &str, new_data: &str) -> Result<bool> {
+        self.db.replace_credential_data(id, old_data, new_data).await
+    }
+
+    // Executions
+    async fn create_execution(&self, id: Uuid, workflow_id: Option<Uuid>, workflow_version: Option<i32>, team_id: Option<Uuid>, status: &str) -> Result<Uuid> {
+        self.db.create_execution(id, workflow_id, workflow_version, team_id, status).await
+    }
+
+    async fn update_execution(&self, id: Uuid, status: &str, finished_at: Option<DateTime<Utc>>, error: Option<String>) -> Result<()> {
+        self.db.update_execution(id, status, finished_at, error).await
+    }
+
+    async fn log_execution_event(&self, execution_id: Uuid, event: &ExecutionEvent) -> Result<()> {
+        self.db.log_execution_event(execution_id, event).await
+    }
+
+    async fn get_execution(&self, id: Uuid) -> Result<Option<ExecutionRecord>> {
+        self.db.get_execution(id).await
+    }
+
+    async fn get_execution_logs(&self, id: Uuid) -> Result<Vec<ExecutionEvent>> {
+        self.db.get_execution_logs(id).await
+    }
+
+    async fn get_execution_definition(&self, id: Uuid) -> Result<Option<Value>> {
+        self.db.get_execution_definition(id).await
+    }
+
+    async fn prune_executions(&self, policy: &RetentionPolicy, now: DateTime<Utc>) -> Result<PruneStats> {
+        self.db.prune_executions(policy, now).await
+    }
+
+    async fn list_executions(&self, filter: &ExecutionFilter) -> Result<Vec<ExecutionRecord>> {
+        self.db.list_executions(filter).await
+    }
+
+    // Execution queue
+    async fn enqueue_execution(&self, execution: &QueuedExecution) -> Result<()> {
+        self.db.enqueue_execution(execution).await
+    }
+
+    async fn claim_execution(&self, worker_id: &str) -> Result<Option<QueuedExecution>> {
+        self.db.claim_execution(worker_id).await
+    }
+
+    async fn heartbeat_executions(&self, worker_id: &str, ids: &[Uuid]) -> Result<()> {
+        self.db.heartbeat_executions(worker_id, ids).await
+    }
+
+    async fn recover_expired_executions(&self, cutoff: DateTime<Utc>) -> Result<u64> {
+        self.db.recover_expired_executions(cutoff).await
+    }
+
+    async fn resume_execution(&self, id: Uuid, node_id: &str, inputs: &Value) -> Result<bool> {
+        self.db.resume_execution(id, node_id, inputs).await
+    }
+
+    // Durable timers
+    async fn create_timer(&self, timer: &TimerRecord) -> Result<()> {
+        self.db.create_timer(timer).await
+    }
+
+    async fn list_due_timers(&self, now: DateTime<Utc>) -> Result<Vec<TimerRecord>> {
+        self.db.list_due_timers(now).await
+    }
+
+    async fn update_timer_status(&self, ids: &[Uuid], status: &str) -> Result<()> {
+        self.db.update_timer_status(ids, status).await
+    }
+
+    async fn count_waiting_timers(&self, execution_id: Uuid) -> Result<i64> {
+        self.db.count_waiting_timers(execution_id).await
+    }
+
+    // Approvals
+    async fn create_approval(&self, approval: &ApprovalRecord) -> Result<()> {
+        self.db.create_approval(approval).await
+    }
+
+    async fn get_approval(&self, token: &str) -> Result<Option<ApprovalRecord>> {
+        self.db.get_approval(token).await
+    }
+
+    async fn resolve_approval(&self, token: &str, status: &str, decision: Option<&Value>) -> Result<bool> {
+        self.db.resolve_approval(token, status, decision).await
+    }
+
+    async fn mark_approval_delivered(&self, token: &str) -> Result<bool> {
+        self.db.mark_approval_delivered(token).await
+    }
+
+    async fn count_open_approvals(&self, execution_id: Uuid) -> Result<i64> {
+        self.db.count_open_approvals(execution_id).await
+    }
+
+    async fn try_acquire_leadership(&self, name: &str) -> Result<bool> {
+        self.db.try_acquire_leadership(name).await
+    }
+
+    // Events
+    async fn publish_event(&self, team_id: Uuid, name: &str, payload: &Value) -> Result<EventRecord> {
+        self.db.publish_event(team_id, name, payload).await
+    }
+
+    async fn list_pending_events(&self, limit: i64, offset: i64) -> Result<Vec<EventRecord>> {
+        self.db.list_pending_events(limit, offset).await
+    }
+
+    async fn mark_event_delivered(&self, id: Uuid) -> Result<()> {
+        self.db.mark_event_delivered(id).await
+    }
+
+    async fn record_event_delivery(&self, event_id: Uuid, workflow_id: Uuid) -> Result<()> {
+        self.db.record_event_delivery(event_id, workflow_id).await
+    }
+
+    async fn list_event_deliveries(&self, event_id: Uuid) -> Result<Vec<Uuid>> {
+        self.db.list_event_deliveries(event_id).await
+    }
+}
@@ -0,0 +1,726 @@
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+use uuid::Uuid;
+use serde_json::Value;
+use anyhow::{Result, anyhow, bail};
+use chrono::{Utc, DateTime};
+use super::{Storage, Team, User, TeamMember, Role, WorkflowEntity, WorkflowVersion, Credential, ExecutionRecord, ExecutionFilter, RetentionPolicy, PruneStats, ApiToken, AuditEntry, EventRecord, QueuedExecution, ApprovalRecord, TimerRecord, SchemaStatus};
+use crate::schema::ExecutionEvent;
+use argon2::{
+    password_hash::{
+        rand_core::OsRng,
+        PasswordHasher, SaltString
+    },
+    Argon2
+};
+
+/// Storage in process memory, for tests and for embedding the engine without a database.
+/// Nothing survives the process. Behaves like `SqliteStorage`: usernames and team names are
+/// unique, passwords are hashed with argon2 and listings come back in the same order.
+#[derive(Default)]
+pub struct MemoryStorage {
+    state: Mutex<State>,
+}
+
+/// The tables, in insertion order where the SQL backends order by rowid.
+#[derive(Default)]
+struct State {
+    users: Vec<User>,
+    api_tokens: Vec<ApiToken>,
+    teams: Vec<Team>,
+    team_members: Vec<TeamMember>,
+    workflows: Vec<WorkflowEntity>,
+    workflow_versions: Vec<WorkflowVersion>,
+    key_value: HashMap<String, Value>,
+    credentials: Vec<Credential>,
+    executions: Vec<ExecutionRow>,
+    execution_events: Vec<EventRow>,
+    approvals: Vec<ApprovalRecord>,
+    timers: Vec<TimerRecord>,
+    events: Vec<EventRecord>,
+    event_deliveries: Vec<(Uuid, Uuid)>,
+    audit_log: Vec<AuditEntry>,
+}
+
+/// An `executions` row: the record plus the queue columns.
+struct ExecutionRow {
+    record: ExecutionRecord,
+    priority: i32,
+    definition: Option<Value>,
+    inputs: Value,
+    worker_id: Option<String>,
+    heartbeat_at: Option<DateTime<Utc>>,
+    resume_from: Option<String>,
+    started_by: Option<Uuid>,
+}
+
+struct EventRow {
+    execution_id: Uuid,
+    event: ExecutionEvent,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+}
+
+fn page<T>(items: impl Iterator<Item = T>, limit: i64, offset: i64) -> Vec<T> {
+    // A negative limit means no limit, as in SQLite
+    let limit = usize::try_from(limit).unwrap_or(usize::MAX);
+    items.skip(offset.max(0) as usize).take(limit).collect()
+}
+
+/// Whether a queued run of a `concurrency: queue` workflow waits for a running execution of it
+/// to finish, as in `SqliteStorage::claim_execution`. Resumed runs never wait.
+fn waits_for_slot(row: &ExecutionRow, running: &[Uuid]) -> bool {
+    let queue_mode = row.definition.as_ref().is_some_and(|d| d["concurrency"]["mode"] == "queue");
+    row.resume_from.is_none() && queue_mode && row.record.workflow_id.is_some_and(|w| running.contains(&w))
+}
+
+/// Ids of the executions `policy` deletes, as in `SqliteStorage::prune_executions`.
+fn doomed_executions(executions: &[ExecutionRow], policy: &RetentionPolicy, now: DateTime<Utc>) -> Vec<Uuid> {
+    let cutoff = |days: Option<i64>| days.map(|d| now - chrono::Duration::days(d));
+    let max_age = cutoff(policy.max_age_days);
+    let failed_max_age = cutoff(policy.failed_max_age_days);
+    let older = |record: &ExecutionRecord, cutoff: Option<DateTime<Utc>>| matches!((record.finished_at, cutoff), (Some(f), Some(c)) if f < c);
+
+    let mut candidates: Vec<&ExecutionRecord> = executions.iter()
+        .map(|row| &row.record)
+        .filter(|r| r.finished_at.is_some() && !(failed_max_age.is_some() && r.status == "failed"))
+        .collect();
+    candidates.sort_by_key(|r| std::cmp::Reverse(r.started_at));
+
+    let mut seen: HashMap<Uuid, i64> = HashMap::new();
+    let mut doomed = Vec::new();
+    for record in candidates {
+        let beyond_limit = match (record.workflow_id, policy.max_per_workflow) {
+            (Some(workflow_id), Some(max)) => {
+                let rank = seen.entry(workflow_id).or_insert(0);
+                *rank += 1;
+                *rank > max
+            }
+            _ => false,
+        };
+        if older(record, max_age) || beyond_limit {
+            doomed.push(record.id);
+        }
+    }
+    for row in executions {
+        if row.record.status == "failed" && older(&row.record, failed_max_age) && !doomed.contains(&row.record.id) {
+            doomed.push(row.record.id);
+        }
+    }
+    doomed
+}
+
+#[async_trait]
+impl Storage for MemoryStorage {
+    async fn init(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn schema_status(&self) -> Result<SchemaStatus> {
+        // No schema to migrate
+        Ok(SchemaStatus::default())
+    }
+
+    // User
+    async fn create_user(&self, username: &str, password: &str) -> Result<User> {
+        let salt = SaltString::generate(&mut OsRng);
+        let argon2 = Argon2::default();
+        let password_hash = argon2.hash_password(password.as_bytes(), &salt)
+            .map_err(|e| anyhow!("Failed to hash password: {}", e))?
+            .to_string();
+
+        let mut state = self.state();
+        if state.users.iter().any(|u| u.username == username) {
+            bail!("Username '{}' is already taken", username);
+        }
+        let user = User {
+            id: Uuid::new_v4(),
+            username: username.to_string(),
+            password_hash,
+            created_at: Utc::now(),
+        };
+        state.users.push(user.clone());
+        Ok(user)
+    }
+
+    async fn get_user(&self, id: Uuid) -> Result<Option<User>> {
+        Ok(self.state().users.iter().find(|u| u.id == id).cloned())
+    }
+
+    async fn get_user_by_username(&self, username: &str) -> Result<Option<User>> {
+        Ok(self.state().users.iter().find(|u| u.username == username).cloned())
+    }
+
+    async fn count_users(&self) -> Result<i64> {
+        Ok(self.state().users.len() as i64)
+    }
+
+    // API tokens
+    async fn create_api_token(&self, token: &ApiToken) -> Result<()> {
+        let mut state = self.state();
+        if state.api_tokens.iter().any(|t| t.id == token.id || t.token_hash == token.token_hash) {
+            bail!("API token already exists");
+        }
+        state.api_tokens.push(token.clone());
+        Ok(())
+    }
+
+    async fn get_api_token_by_hash(&self, token_hash: &str) -> Result<Option<ApiToken>> {
+        Ok(self.state().api_tokens.iter().find(|t| t.token_hash == token_hash).cloned())
+    }
+
+    async fn list_api_tokens(&self, user_id: Uuid) -> Result<Vec<ApiToken>> {
+        let mut tokens: Vec<ApiToken> = self.state().api_tokens.iter().filter(|t| t.user_id == user_id).cloned().collect();
+        tokens.sort_by_key(|t| t.created_at);
+        Ok(tokens)
+    }
+
+    async fn delete_api_token(&self, user_id: Uuid, id: Uuid) -> Result<bool> {
+        let mut state = self.state();
+        let before = state.api_tokens.len();
+        state.api_tokens.retain(|t| !(t.id == id && t.user_id == user_id));
+        Ok(state.api_tokens.len() < before)
+    }
+
+    // Audit log
+    async fn log_audit(&self, entry: &AuditEntry) -> Result<()> {
+        let mut state = self.state();
+        if state.audit_log.iter().any(|e| e.id == entry.id) {
+            bail!("Audit entry {} already exists", entry.id);
+        }
+        state.audit_log.push(entry.clone());
+        Ok(())
+    }
+
+    async fn list_audit(&self, team_id: Uuid, limit: i64, offset: i64) -> Result<Vec<AuditEntry>> {
+        let state = self.state();
+        let mut entries: Vec<&AuditEntry> = state.audit_log.iter().rev().filter(|e| e.team_id == team_id).collect();
+        // Stable, so entries of the same instant stay newest first
+        entries.sort_by_key(|e| std::cmp::Reverse(e.created_at));
+        Ok(page(entries.into_iter().cloned(), limit, offset))
+    }
+
+    // Team
+    async fn create_team(&self, name: &str) -> Result<Team> {
+        let mut state = self.state();
+        if state.teams.iter().any(|t| t.name == name) {
+            bail!("Team '{}' already exists", name);
+        }
+        let team = Team {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            created_at: Utc::now(),
+        };
+        state.teams.push(team.clone());
+        Ok(team)
+    }
+
+    async fn get_team(&self, id: Uuid) -> Result<Option<Team>> {
+        Ok(self.state().teams.iter().find(|t| t.id == id).cloned())
+    }
+
+    async fn get_team_by_name(&self, name: &str) -> Result<Option<Team>> {
+        Ok(self.state().teams.iter().find(|t| t.name == name).cloned())
+    }
+
+    async fn list_teams_for_user(&self, user_id: Uuid) -> Result<Vec<(Team, Role)>> {
+        let state = self.state();
+        Ok(state.team_members.iter()
+            .filter(|m| m.user_id == user_id)
+            .filter_map(|m| state.teams.iter().find(|t| t.id == m.team_id).map(|t| (t.clone(), m.role.clone())))
+            .collect())
+    }
+
+    // Team Members
+    async fn add_team_member(&self, team_id: Uuid, user_id: Uuid, role: Role) -> Result<()> {
+        let mut state = self.state();
+        if state.team_members.iter().any(|m| m.team_id == team_id && m.user_id == user_id) {
+            bail!("User {} is already a member of team {}", user_id, team_id);
+        }
+        state.team_members.push(TeamMember { user_id, team_id, role, joined_at: Utc::now() });
+        Ok(())
+    }
+
+    async fn get_team_members(&self, team_id: Uuid) -> Result<Vec<TeamMember>> {
+        Ok(self.state().team_members.iter().filter(|m| m.team_id == team_id).cloned().collect())
+    }
+
+    // Workflow
+    async fn save_workflow(&self, workflow: &WorkflowEntity, author_id: Option<Uuid>, message: Option<&str>) -> Result<i32> {
+        let mut state = self.state();
+        let version = match state.workflows.iter_mut().find(|w| w.id == workflow.id) {
+            Some(existing) if existing.deleted_at.is_some() => bail!("Workflow {} was deleted", workflow.id),
+            Some(existing) if existing.name == workflow.name && existing.definition == workflow.definition => {
+                return Ok(existing.version);
+            }
+            // Like the SQL upsert, only the name and definition change
+            Some(existing) => {
+                existing.name = workflow.name.clone();
+                existing.definition = workflow.definition.clone();
+                existing.version += 1;
+                existing.version
+            }
+            None => {
+                state.workflows.push(WorkflowEntity { version: 1, deleted_at: None, ..workflow.clone() });
+                1
+            }
+        };
+        state.workflow_versions.push(WorkflowVersion {
+            workflow_id: workflow.id,
+            version,
+            name: workflow.name.clone(),
+            definition: workflow.definition.clone(),
+            author_id,
+            message: message.map(str::to_string),
+            created_at: Utc::now(),
+        });
+        Ok(version)
+    }
+
+    async fn get_workflow(&self, id: Uuid) -> Result<Option<WorkflowEntity>> {
+        Ok(self.state().workflows.iter().find(|w| w.id == id).cloned())
+    }
+
+    async fn list_workflows(&self, team_id: Uuid) -> Result<Vec<WorkflowEntity>> {
+        Ok(self.state().workflows.iter().filter(|w| w.account_id == team_id && w.deleted_at.is_none()).cloned().collect())
+    }
+
+    async fn set_workflow_active(&self, id: Uuid, active: bool) -> Result<bool> {
+        let mut state = self.state();
+        let Some(workflow) = state.workflows.iter_mut().find(|w| w.id == id && w.deleted_at.is_none()) else {
+            return Ok(false);
+        };
+        workflow.active = active;
+        Ok(true)
+    }
+
+    async fn delete_workflow(&self, id: Uuid) -> Result<bool> {
+        let mut state = self.state();
+        let Some(workflow) = state.workflows.iter_mut().find(|w| w.id == id && w.deleted_at.is_none()) else {
+            return Ok(false);
+        };
+        workflow.deleted_at = Some(Utc::now());
+        Ok(true)
+    }
+
+    async fn list_workflow_versions(&self, workflow_id: Uuid) -> Result<Vec<WorkflowVersion>> {
+        Ok(self.state().workflow_versions.iter().rev().filter(|v| v.workflow_id == workflow_id).cloned().collect())
+    }
+
+    async fn get_workflow_version(&self, workflow_id: Uuid, version: i32) -> Result<Option<WorkflowVersion>> {
+        Ok(self.state().workflow_versions.iter().find(|v| v.workflow_id == workflow_id && v.version == version).cloned())
+    }
+
+    // Key-Value
+    async fn set_kv(&self, key: &str, value: &Value) -> Result<()> {
+        self.state().key_value.insert(key.to_string(), value.clone());
+        Ok(())
+    }
+
+    async fn get_kv(&self, key: &str) -> Result<Option<Value>> {
+        Ok(self.state().key_value.get(key).cloned())
+    }
+
+    // Credentials
+    async fn create_credential(&self, name: &str, credential_type: &str, data: &Value, team_id: Uuid) -> Result<Credential> {
+        let credential = Credential {
+            id: Uuid::new_v4(),
+            account_id: team_id,
+            name: name.to_string(),
+            credential_type: credential_type.to_string(),
+            data: super::encrypt_credential(credential_type, data)?,
+            created_at: Utc::now(),
+        };
+        self.state().credentials.push(credential.clone());
+        Ok(credential)
+    }
+
+    async fn get_credential(&self, id: Uuid) -> Result<Option<Credential>> {
+        Ok(self.state().credentials.iter().find(|c| c.id == id).cloned())
+    }
+
+    async fn list_credentials(&self, team_id: Uuid) -> Result<Vec<Credential>> {
+        Ok(self.state().credentials.iter().filter(|c| c.account_id == team_id).cloned().collect())
+    }
+
+    async fn update_credential(&self, id: Uuid, name: Option<&str>, data: Option<&Value>) -> Result<bool> {
+        let mut state = self.state();
+        let Some(credential) = state.credentials.iter_mut().find(|c| c.id == id) else {
+            return Ok(false);
+        };
+        if let Some(data) = data {
+            credential.data = super::encrypt_credential(&credential.credential_type, data)?;
+        }
+        if let Some(name) = name {
+            credential.name = name.to_string();
+        }
+        Ok(true)
+    }
+
+    async fn delete_credential(&self, id: Uuid, force: bool) -> Result<bool> {
+        if !force {
+            super::ensure_unused(self, id).await?;
+        }
+        let mut state = self.state();
+        let before = state.credentials.len();
+        state.credentials.retain(|c| c.id != id);
+        Ok(state.credentials.len() < before)
+    }
+
+    async fn list_all_credentials(&self) -> Result<Vec<Credential>> {
+        Ok(self.state().credentials.clone())
+    }
+
+    async fn replace_credential_data(&self, id: Uuid, old_data: &str, new_data: &str) -> Result<bool> {
+        let mut state = self.state();
+        match state.credentials.iter_mut().find(|c| c.id == id && c.data == old_data) {
+            Some(cred) => {
+                cred.data = new_data.to_string();
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    // Executions
+    async fn create_execution(&self, id: Uuid, workflow_id: Option<Uuid>, workflow_version: Option<i32>, team_id: Option<Uuid>, status: &str) -> Result<Uuid> {
+        let mut state = self.state();
+        if state.executions.iter().any(|e| e.record.id == id) {
+            bail!("Execution {} already exists", id);
+        }
+        state.executions.push(ExecutionRow {
+            record: ExecutionRecord {
+                id,
+                workflow_id,
+                workflow_version,
+                team_id,
+                status: status.to_string(),
+                started_at: Utc::now(),
+                finished_at: None,
+                error: None,
+            },
+            priority: 0,
+            definition: None,
+            inputs: Value::Null,
+            worker_id: None,
+            heartbeat_at: None,
+            resume_from: None,
+            started_by: None,
+        });
+        Ok(id)
+    }
+
+    async fn update_execution(&self, id: Uuid, status: &str, finished_at: Option<DateTime<Utc>>, error: Option<String>) -> Result<()> {
+        if let Some(row) = self.state().executions.iter_mut().find(|e| e.record.id == id) {
+            row.record.status = status.to_string();
+            row.record.finished_at = finished_at;
+            row.record.error = error;
+        }
+        Ok(())
+    }
+
+    async fn log_execution_event(&self, execution_id: Uuid, event: &ExecutionEvent) -> Result<()> {
+        self.state().execution_events.push(EventRow { execution_id, event: event.clone() });
+        Ok(())
+    }
+
+    async fn get_execution(&self, id: Uuid) -> Result<Option<ExecutionRecord>> {
+        Ok(self.state().executions.iter().find(|e| e.record.id == id).map(|e| e.record.clone()))
+    }
+
+    async fn get_execution_logs(&self, id: Uuid) -> Result<Vec<ExecutionEvent>> {
+        // Events are appended as they are logged, so this is the order they happened in
+        Ok(self.state().execution_events.iter().filter(|e| e.execution_id == id).map(|e| e.event.clone()).collect())
+    }
+
+    async fn get_execution_definition(&self, id: Uuid) -> Result<Option<Value>> {
+        Ok(self.state().executions.iter().find(|e| e.record.id == id).and_then(|e| e.definition.clone()))
+    }
+
+    async fn prune_executions(&self, policy: &RetentionPolicy, now: DateTime<Utc>) -> Result<PruneStats> {
+        let mut state = self.state();
+        let doomed = doomed_executions(&state.executions, policy, now);
+
+        let mut stats = PruneStats::default();
+        let events = state.execution_events.len();
+        state.execution_events.retain(|e| !doomed.contains(&e.execution_id));
+        stats.events += (events - state.execution_events.len()) as u64;
+        state.approvals.retain(|a| !doomed.contains(&a.execution_id));
+        state.timers.retain(|t| !doomed.contains(&t.execution_id));
+        let executions = state.executions.len();
+        state.executions.retain(|e| !doomed.contains(&e.record.id));
+        stats.executions = (executions - state.executions.len()) as u64;
+
+        if let Some(sample) = policy.success_payload_sample {
+            let completed: Vec<Uuid> = state.executions.iter().filter(|e| e.record.status == "completed").map(|e| e.record.id).collect();
+            let mut kept: HashMap<(Uuid, String, String), i64> = HashMap::new();
+            let events = state.execution_events.len();
+            state.execution_events.retain(|e| match &e.event {
+                ExecutionEvent::EdgeData { from, to, .. } if completed.contains(&e.execution_id) => {
+                    let count = kept.entry((e.execution_id, from.clone(), to.clone())).or_insert(0);
+                    *count += 1;
+                    *count <= sample
+                }
+                _ => true,
+            });
+            stats.events += (events - state.execution_events.len()) as u64;
+        }
+        Ok(stats)
+    }
+
+    async fn list_executions(&self, filter: &ExecutionFilter) -> Result<Vec<ExecutionRecord>> {
+        let state = self.state();
+        let team_workflows: Vec<Uuid> = match filter.team_id {
+            Some(team_id) => state.workflows.iter().filter(|w| w.account_id == team_id).map(|w| w.id).collect(),
+            None => Vec::new(),
+        };
+        let mut records: Vec<&ExecutionRecord> = state.executions.iter().rev()
+            .map(|e| &e.record)
+            .filter(|r| filter.workflow_id.is_none() || r.workflow_id == filter.workflow_id)
+            .filter(|r| filter.team_id.is_none() || r.team_id == filter.team_id || r.workflow_id.is_some_and(|w| team_workflows.contains(&w)))
+            .filter(|r| filter.status.as_ref().is_none_or(|s| &r.status == s))
+            .filter(|r| filter.since.is_none_or(|since| r.started_at >= since))
+            .filter(|r| filter.until.is_none_or(|until| r.started_at < until))
+            .collect();
+        records.sort_by_key(|r| std::cmp::Reverse(r.started_at));
+        Ok(page(records.into_iter().cloned(), filter.limit, filter.offset))
+    }
+
+    // Execution queue
+    async fn enqueue_execution(&self, execution: &QueuedExecution) -> Result<()> {
+        let mut state = self.state();
+        // The execution may already exist, e.g. when it waited for its concurrency slot
+        let index = match state.executions.iter().position(|e| e.record.id == execution.id) {
+            Some(index) => index,
+            None => {
+                state.executions.push(ExecutionRow {
+                    record: ExecutionRecord {
+                        id: execution.id,
+                        workflow_id: execution.workflow_id,
+                        workflow_version: None,
+                        team_id: None,
+                        status: "queued".to_string(),
+                        started_at: Utc::now(),
+                        finished_at: None,
+                        error: None,
+                    },
+                    priority: 0,
+                    definition: None,
+                    inputs: Value::Null,
+                    worker_id: None,
+                    heartbeat_at: None,
+                    resume_from: None,
+                    started_by: None,
+                });
+                state.executions.len() - 1
+            }
+        };
+        let row = &mut state.executions[index];
+        row.record.status = "queued".to_string();
+        row.record.team_id = execution.team_id;
+        row.priority = execution.priority;
+        row.definition = Some(execution.definition.clone());
+        row.inputs = execution.inputs.clone();
+        row.resume_from = execution.resume_from.clone();
+        row.started_by = execution.started_by;
+        Ok(())
+    }
+
+    async fn claim_execution(&self, worker_id: &str) -> Result<Option<QueuedExecution>> {
+        let mut state = self.state();
+        let running: Vec<Uuid> = state.executions.iter()
+            .filter(|e| e.record.status == "running")
+            .filter_map(|e| e.record.workflow_id)
+            .collect();
+        // Highest priority first, then the oldest
+        let next = state.executions.iter_mut()
+            .filter(|e| e.record.status == "queued" && e.definition.is_some())
+            .filter(|e| !waits_for_slot(e, &running))
+            .reduce(|best, e| if e.priority > best.priority { e } else { best });
+        let Some(row) = next else { return Ok(None) };
+
+        row.record.status = "running".to_string();
+        row.worker_id = Some(worker_id.to_string());
+        row.heartbeat_at = Some(Utc::now());
+        Ok(Some(QueuedExecution {
+            id: row.record.id,
+            workflow_id: row.record.workflow_id,
+            team_id: row.record.team_id,
+            definition: row.definition.clone().unwrap_or(Value::Null),
+            inputs: row.inputs.clone(),
+            priority: row.priority,
+            resume_from: row.resume_from.clone(),
+            started_by: row.started_by,
+        }))
+    }
+
+    async fn heartbeat_executions(&self, worker_id: &str, ids: &[Uuid]) -> Result<()> {
+        let now = Utc::now();
+        for row in self.state().executions.iter_mut().filter(|e| ids.contains(&e.record.id)) {
+            if row.worker_id.as_deref().is_none_or(|w| w == worker_id) {
+                row.worker_id = Some(worker_id.to_string());
+                row.heartbeat_at = Some(now);
+            }
+        }
+        Ok(())
+    }
+
+    async fn recover_expired_executions(&self, cutoff: DateTime<Utc>) -> Result<u64> {
+        let now = Utc::now();
+        let mut requeued = 0;
+        for row in self.state().executions.iter_mut() {
+            if row.heartbeat_at.unwrap_or(row.record.started_at) >= cutoff {
+                continue;
+            }
+            match (row.record.status.as_str(), &row.definition) {
+                ("pending" | "running" | "queued", None) if row.worker_id.is_some() => {
+                    row.record.status = "failed".to_string();
+                    row.record.finished_at = Some(now);
+                    row.record.error = Some("Interrupted: the server stopped before the execution finished".to_string());
+                }
+                ("running", Some(_)) => {
+                    row.record.status = "queued".to_string();
+                    row.worker_id = None;
+                    requeued += 1;
+                }
+                _ => {}
+            }
+        }
+        Ok(requeued)
+    }
+
+    async fn resume_execution(&self, id: Uuid, node_id: &str, inputs: &Value) -> Result<bool> {
+        let mut state = self.state();
+        let Some(row) = state.executions.iter_mut().find(|e| e.record.id == id && e.record.status == "waiting" && e.definition.is_some()) else {
+            return Ok(false);
+        };
+        row.record.status = "queued".to_string();
+        row.worker_id = None;
+        row.inputs = inputs.clone();
+        row.resume_from = Some(node_id.to_string());
+        Ok(true)
+    }
+
+    // Durable timers
+    async fn create_timer(&self, timer: &TimerRecord) -> Result<()> {
+        let mut state = self.state();
+        if state.timers.iter().any(|t| t.id == timer.id) {
+            bail!("Timer {} already exists", timer.id);
+        }
+        state.timers.push(timer.clone());
+        Ok(())
+    }
+
+    async fn list_due_timers(&self, now: DateTime<Utc>) -> Result<Vec<TimerRecord>> {
+        let mut timers: Vec<TimerRecord> = self.state().timers.iter().filter(|t| t.status == "waiting" && t.wake_at <= now).cloned().collect();
+        timers.sort_by_key(|t| t.wake_at);
+        Ok(timers)
+    }
+
+    async fn update_timer_status(&self, ids: &[Uuid], status: &str) -> Result<()> {
+        for timer in self.state().timers.iter_mut().filter(|t| ids.contains(&t.id)) {
+            timer.status = status.to_string();
+        }
+        Ok(())
+    }
+
+    async fn count_waiting_timers(&self, execution_id: Uuid) -> Result<i64> {
+        Ok(self.state().timers.iter().filter(|t| t.execution_id == execution_id && t.status == "waiting").count() as i64)
+    }
+
+    // Approvals
+    async fn create_approval(&self, approval: &ApprovalRecord) -> Result<()> {
+        let mut state = self.state();
+        if state.approvals.iter().any(|a| a.token == approval.token) {
+            bail!("Approval {} already exists", approval.token);
+        }
+        state.approvals.push(approval.clone());
+        Ok(())
+    }
+
+    async fn get_approval(&self, token: &str) -> Result<Option<ApprovalRecord>> {
+        Ok(self.state().approvals.iter().find(|a| a.token == token).cloned())
+    }
+
+    async fn resolve_approval(&self, token: &str, status: &str, decision: Option<&Value>) -> Result<bool> {
+        let mut state = self.state();
+        let Some(approval) = state.approvals.iter_mut().find(|a| a.token == token && a.status == "pending") else {
+            return Ok(false);
+        };
+        approval.status = status.to_string();
+        approval.decision = decision.cloned();
+        approval.decided_at = Some(Utc::now());
+        Ok(true)
+    }
+
+    async fn mark_approval_delivered(&self, token: &str) -> Result<bool> {
+        let mut state = self.state();
+        let Some(approval) = state.approvals.iter_mut().find(|a| a.token == token && a.status != "pending" && a.delivered_at.is_none()) else {
+            return Ok(false);
+        };
+        approval.delivered_at = Some(Utc::now());
+        Ok(true)
+    }
+
+    async fn count_open_approvals(&self, execution_id: Uuid) -> Result<i64> {
+        Ok(self.state().approvals.iter().filter(|a| a.execution_id == execution_id && a.node_id.is_some() && a.delivered_at.is_none()).count() as i64)
+    }
+
+    async fn try_acquire_leadership(&self, _name: &str) -> Result<bool> {
+        // Nothing outside this process sees the data
+        Ok(true)
+    }
+
+    // Events
+    async fn publish_event(&self, team_id: Uuid, name: &str, payload: &Value) -> Result<EventRecord> {
+        let event = EventRecord {
+            id: Uuid::new_v4(),
+            team_id,
+            name: name.to_string(),
+            payload: payload.clone(),
+            status: "pending".to_string(),
+            created_at: Utc::now(),
+            delivered_at: None,
+        };
+        self.state().events.push(event.clone());
+        Ok(event)
+    }
+
+    async fn list_pending_events(&self, limit: i64, offset: i64) -> Result<Vec<EventRecord>> {
+        let state = self.state();
+        let mut events: Vec<&EventRecord> = state.events.iter().filter(|e| e.status == "pending").collect();
+        events.sort_by_key(|e| (e.created_at, e.id));
+        Ok(page(events.into_iter().cloned(), limit, offset))
+    }
+
+    async fn mark_event_delivered(&self, id: Uuid) -> Result<()> {
+        let mut state = self.state();
+        if let Some(event) = state.events.iter_mut().find(|e| e.id == id) {
+            event.status = "delivered".to_string();
+            event.delivered_at = Some(Utc::now());
+        }
+        state.event_deliveries.retain(|(event_id, _)| *event_id != id);
+        Ok(())
+    }
+
+    async fn record_event_delivery(&self, event_id: Uuid, workflow_id: Uuid) -> Result<()> {
+        let mut state = self.state();
+        if !state.event_deliveries.contains(&(event_id, workflow_id)) {
+            state.event_deliveries.push((event_id, workflow_id));
+        }
+        Ok(())
+    }
+
+    async fn list_event_deliveries(&self, event_id: Uuid) -> Result<Vec<Uuid>> {
+        Ok(self.state().event_deliveries.iter().filter(|(e, _)| *e == event_id).map(|(_, w)| *w).collect())
+    }
+}
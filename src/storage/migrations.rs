@@ -0,0 +1,86 @@
+use anyhow::{Result, bail};
+use serde::Serialize;
+use sqlx::migrate::{Migrate, Migrator};
+
+/// Schema migrations of `SqliteStorage`, from `migrations/sqlite`.
+pub static SQLITE: Migrator = sqlx::migrate!("./migrations/sqlite");
+
+/// Schema migrations of `PostgresStorage`, from `migrations/postgres`.
+pub static POSTGRES: Migrator = sqlx::migrate!("./migrations/postgres");
+
+/// A migration this build knows, and whether the database has it.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub applied: bool,
+}
+
+/// Where a database's schema stands, see `Storage::schema_status`.
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct SchemaStatus {
+    pub migrations: Vec<MigrationStatus>,
+    /// Applied versions this build does not know: a newer RustFlow migrated the database.
+    pub unknown: Vec<i64>,
+    /// Applied versions whose SQL has changed since.
+    pub modified: Vec<i64>,
+}
+
+impl SchemaStatus {
+    /// The latest applied migration; `None` for an empty database.
+    pub fn version(&self) -> Option<i64> {
+        let applied = self.migrations.iter().filter(|m| m.applied).map(|m| m.version);
+        applied.chain(self.unknown.iter().copied()).max()
+    }
+
+    pub fn pending(&self) -> Vec<&MigrationStatus> {
+        self.migrations.iter().filter(|m| !m.applied).collect()
+    }
+
+    /// Fails if this build cannot safely use or migrate the database.
+    pub fn check(&self) -> Result<()> {
+        if !self.unknown.is_empty() {
+            bail!(
+                "The database schema is at version {}, newer than this RustFlow knows ({:?}); upgrade RustFlow",
+                self.version().unwrap_or_default(),
+                self.migrations.last().map(|m| m.version)
+            );
+        }
+        if !self.modified.is_empty() {
+            bail!("Applied migrations {:?} differ from this RustFlow's; the database was migrated by another build", self.modified);
+        }
+        Ok(())
+    }
+}
+
+/// Compares `migrator` with the migrations recorded in the database (creating the table that
+/// records them if needed).
+pub(crate) async fn status<C: Migrate>(migrator: &Migrator, conn: &mut C) -> Result<SchemaStatus> {
+    conn.ensure_migrations_table().await?;
+    let applied = conn.list_applied_migrations().await?;
+
+    let mut status = SchemaStatus::default();
+    for migration in migrator.iter() {
+        let recorded = applied.iter().find(|a| a.version == migration.version);
+        if recorded.is_some_and(|a| a.checksum != migration.checksum) {
+            status.modified.push(migration.version);
+        }
+        status.migrations.push(MigrationStatus {
+            version: migration.version,
+            description: migration.description.to_string(),
+            applied: recorded.is_some(),
+        });
+    }
+    status.unknown = applied.iter()
+        .map(|a| a.version)
+        .filter(|v| migrator.iter().all(|m| m.version != *v))
+        .collect();
+    Ok(status)
+}
+
+/// Applies the pending migrations, after checking that the database is not ahead of this build.
+pub(crate) async fn run<C: Migrate>(migrator: &Migrator, conn: &mut C) -> Result<()> {
+    status(migrator, conn).await?.check()?;
+    migrator.run_direct(conn).await?;
+    Ok(())
+}
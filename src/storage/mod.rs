@@ -2,28 +2,129 @@ use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use uuid::Uuid;
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use chrono::{DateTime, Utc};
-use crate::schema::ExecutionEvent;
+use crate::schema::{ExecutionEvent, WorkflowDefinition};
+use crate::credentials::Secrets;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExecutionRecord {
     pub id: Uuid,
     pub workflow_id: Option<Uuid>,
+    /// Version of the stored workflow the execution ran.
+    #[serde(default)]
+    pub workflow_version: Option<i32>,
+    /// Team the run belongs to, if known (set for queued runs).
+    #[serde(default)]
+    pub team_id: Option<Uuid>,
     pub status: String,
     pub started_at: DateTime<Utc>,
     pub finished_at: Option<DateTime<Utc>>,
     pub error: Option<String>,
 }
 
+/// Which executions `Storage::list_executions` returns, newest first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExecutionFilter {
+    pub workflow_id: Option<Uuid>,
+    /// Executions of the team's runs and stored workflows.
+    pub team_id: Option<Uuid>,
+    pub status: Option<String>,
+    /// Started at or after.
+    pub since: Option<DateTime<Utc>>,
+    /// Started before.
+    pub until: Option<DateTime<Utc>>,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+impl Default for ExecutionFilter {
+    fn default() -> Self {
+        Self { workflow_id: None, team_id: None, status: None, since: None, until: None, limit: 50, offset: 0 }
+    }
+}
+
+/// How long finished executions and their logged events are kept, see `Storage::prune_executions`.
+/// Unset limits keep everything; executions that have not finished are never pruned.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    /// Executions finished longer ago are deleted.
+    pub max_age_days: Option<i64>,
+    /// Only the newest executions of each stored workflow are kept.
+    pub max_per_workflow: Option<i64>,
+    /// Failed executions are kept this long instead, and do not count towards the limits above.
+    pub failed_max_age_days: Option<i64>,
+    /// Completed executions keep only their first records per edge; 0 keeps no payloads, just
+    /// the node events. Failed executions keep all records, e.g. for replay.
+    pub success_payload_sample: Option<i64>,
+}
+
+impl RetentionPolicy {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// What `Storage::prune_executions` deleted.
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct PruneStats {
+    pub executions: u64,
+    /// Logged events, including those of deleted executions.
+    pub events: u64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct User {
     pub id: Uuid,
     pub username: String,
+    /// Never sent over the API; passwords are verified server-side.
+    #[serde(default, skip_serializing)]
     pub password_hash: String,
     pub created_at: DateTime<Utc>,
 }
 
+impl User {
+    pub fn verify_password(&self, password: &str) -> bool {
+        use argon2::PasswordVerifier;
+        argon2::PasswordHash::new(&self.password_hash)
+            .map(|hash| argon2::Argon2::default().verify_password(password.as_bytes(), &hash).is_ok())
+            .unwrap_or(false)
+    }
+}
+
+/// A bearer token for the HTTP API: a login session or a personal token for automation
+/// (see `crate::auth`). Only the SHA-256 of the secret is stored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    /// `session` or `personal`.
+    pub kind: String,
+    #[serde(default, skip_serializing)]
+    pub token_hash: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Who did what to a team's resources, and when (see `crate::audit`).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AuditEntry {
+    pub id: Uuid,
+    pub team_id: Uuid,
+    /// The acting user; `None` for the system, e.g. queued runs reading credentials.
+    pub actor_id: Option<Uuid>,
+    /// E.g. `workflow.save`, `credential.read` or `execution.cancel`.
+    pub action: String,
+    /// `<kind>/<id>` of the affected resource, e.g. `workflow/<uuid>`.
+    pub target: String,
+    /// What changed, or details of actions that change nothing.
+    pub diff: Option<Value>,
+    pub created_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Team {
     pub id: Uuid,
@@ -56,6 +157,17 @@ impl Role {
             _ => None,
         }
     }
+
+    /// Whether this role grants what `required` does. Admins can do anything members can,
+    /// members anything viewers can.
+    pub fn includes(&self, required: &Role) -> bool {
+        let rank = |role: &Role| match role {
+            Role::Admin => 2,
+            Role::Member => 1,
+            Role::Viewer => 0,
+        };
+        rank(self) >= rank(required)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -73,16 +185,167 @@ pub struct WorkflowEntity {
     pub name: String,
     pub definition: Value, // Stored as JSON
     pub created_at: DateTime<Utc>,
+    /// Latest version, see `WorkflowVersion`. Assigned by `Storage::save_workflow`, which
+    /// ignores it.
+    #[serde(default)]
+    pub version: i32,
+    /// Disabled workflows cannot be run. Set when the workflow is created, then changed with
+    /// `Storage::set_workflow_active` rather than saves.
+    #[serde(default = "default_active")]
+    pub active: bool,
+    /// Set by `Storage::delete_workflow`.
+    #[serde(default)]
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+impl WorkflowEntity {
+    /// A new, active workflow of `team_id`, to pass to `Storage::save_workflow`.
+    pub fn new(team_id: Uuid, name: impl Into<String>, definition: Value) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            account_id: team_id,
+            name: name.into(),
+            definition,
+            created_at: Utc::now(),
+            version: 0,
+            active: true,
+            deleted_at: None,
+        }
+    }
+
+    /// Fails if the workflow was deleted or disabled.
+    pub fn ensure_runnable(&self) -> Result<()> {
+        if self.deleted_at.is_some() {
+            return Err(anyhow!("Workflow {} ({}) was deleted", self.name, self.id));
+        }
+        if !self.active {
+            return Err(anyhow!("Workflow {} ({}) is disabled", self.name, self.id));
+        }
+        Ok(())
+    }
+}
+
+/// A saved state of a workflow. Each `Storage::save_workflow` that changes the workflow adds
+/// the next version (starting at 1); versions are never modified.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WorkflowVersion {
+    pub workflow_id: Uuid,
+    pub version: i32,
+    pub name: String,
+    pub definition: Value,
+    /// Who saved it; `None` for saves without a user, e.g. databases from before versioning.
+    pub author_id: Option<Uuid>,
+    pub message: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl WorkflowVersion {
+    /// What changed in the name and definition from this version to `other`, as
+    /// `crate::audit::diff` reports it.
+    pub fn diff(&self, other: &WorkflowVersion) -> Value {
+        let state = |v: &WorkflowVersion| serde_json::json!({ "name": v.name, "definition": v.definition });
+        crate::audit::diff(&state(self), &state(other))
+    }
+}
+
+/// A persisted request to run a workflow: an `executions` row in `queued` status,
+/// claimed by `JobManager` workers in priority order (higher first, then oldest).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedExecution {
+    pub id: Uuid,
+    pub workflow_id: Option<Uuid>,
+    pub team_id: Option<Uuid>,
+    pub definition: Value,
+    /// Records injected into trigger nodes, as `[[node_id, value], ...]`.
+    pub inputs: Value,
+    pub priority: i32,
+    /// Set when a suspended execution resumes: only this node and what follows it run,
+    /// with `inputs` injected into it (see `TimerRecord`).
+    #[serde(default)]
+    pub resume_from: Option<String>,
+    /// User who started the run, if any; the actor of its audited credential reads.
+    #[serde(default)]
+    pub started_by: Option<Uuid>,
+}
+
+/// A record held by an `approval` node until someone approves or rejects it through
+/// `POST /api/executions/{execution_id}/signal/{token}`, or it expires.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalRecord {
+    pub token: String,
+    pub execution_id: Uuid,
+    pub record: Value,
+    /// `pending`, `approved`, `rejected` or `expired`.
+    pub status: String,
+    /// Signal payload (e.g. `{ "decision": "approve", "comment": "..." }`).
+    pub decision: Option<Value>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub decided_at: Option<DateTime<Utc>>,
+    /// Node a suspended execution resumes from with the decision; unset for approvals
+    /// awaited in-process.
+    #[serde(default)]
+    pub node_id: Option<String>,
+    /// When the resumed node handed the decided record on.
+    #[serde(default)]
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+/// A record held by a durable `delay` node until `wake_at`. The execution is suspended
+/// (`waiting`) meanwhile, and resumed from the node once its timers are due.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimerRecord {
+    pub id: Uuid,
+    pub execution_id: Uuid,
+    pub node_id: String,
+    pub record: Value,
+    pub wake_at: DateTime<Utc>,
+    /// `waiting`, `fired` or `cancelled`.
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A named event published by a workflow (`emit_event`) for other workflows of the same team.
+/// Events are persisted as `pending` and marked `delivered` once the subscribers were started.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventRecord {
+    pub id: Uuid,
+    pub team_id: Uuid,
+    pub name: String,
+    pub payload: Value,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
 }
 
 #[async_trait]
 pub trait Storage: Send + Sync {
+    /// Prepares the storage for use; for databases, applies the pending schema migrations.
     async fn init(&self) -> Result<()>;
+    /// Which schema migrations the database has, and which are pending.
+    async fn schema_status(&self) -> Result<SchemaStatus>;
     
     // User
     async fn create_user(&self, username: &str, password_hash: &str) -> Result<User>;
     async fn get_user(&self, id: Uuid) -> Result<Option<User>>;
     async fn get_user_by_username(&self, username: &str) -> Result<Option<User>>;
+    async fn count_users(&self) -> Result<i64>;
+
+    // API tokens
+    async fn create_api_token(&self, token: &ApiToken) -> Result<()>;
+    async fn get_api_token_by_hash(&self, token_hash: &str) -> Result<Option<ApiToken>>;
+    async fn list_api_tokens(&self, user_id: Uuid) -> Result<Vec<ApiToken>>;
+    /// Returns false if `user_id` has no such token.
+    async fn delete_api_token(&self, user_id: Uuid, id: Uuid) -> Result<bool>;
+
+    // Audit log
+    async fn log_audit(&self, entry: &AuditEntry) -> Result<()>;
+    /// A team's audit entries, newest first.
+    async fn list_audit(&self, team_id: Uuid, limit: i64, offset: i64) -> Result<Vec<AuditEntry>>;
 
     // Team
     async fn create_team(&self, name: &str) -> Result<Team>;
@@ -95,26 +358,107 @@ pub trait Storage: Send + Sync {
     async fn get_team_members(&self, team_id: Uuid) -> Result<Vec<TeamMember>>;
 
     // Workflow
-    async fn save_workflow(&self, workflow: &WorkflowEntity) -> Result<()>;
+    /// Saves the workflow's name and definition as its next version, and returns the version.
+    /// Saving what the latest version already has adds none and returns that version.
+    /// Deleted workflows cannot be saved.
+    async fn save_workflow(&self, workflow: &WorkflowEntity, author_id: Option<Uuid>, message: Option<&str>) -> Result<i32>;
+    /// Also returns deleted workflows, so executions can still be traced to their team.
     async fn get_workflow(&self, id: Uuid) -> Result<Option<WorkflowEntity>>;
+    /// The team's workflows that are not deleted.
     async fn list_workflows(&self, team_id: Uuid) -> Result<Vec<WorkflowEntity>>;
+    /// Enables or disables a workflow. Returns false if there is no such workflow, or it was deleted.
+    async fn set_workflow_active(&self, id: Uuid, active: bool) -> Result<bool>;
+    /// Soft-deletes a workflow: its versions and executions are kept. Returns false if there is
+    /// no such workflow, or it was deleted already.
+    async fn delete_workflow(&self, id: Uuid) -> Result<bool>;
+    /// A workflow's versions, newest first.
+    async fn list_workflow_versions(&self, workflow_id: Uuid) -> Result<Vec<WorkflowVersion>>;
+    async fn get_workflow_version(&self, workflow_id: Uuid, version: i32) -> Result<Option<WorkflowVersion>>;
 
-    // Key-Value (Cross-workflow state)
+    // Key-Value (Cross-workflow state, keyed per team with `team_kv_key`)
     async fn set_kv(&self, key: &str, value: &Value) -> Result<()>;
     async fn get_kv(&self, key: &str) -> Result<Option<Value>>;
 
     // Credentials
-    async fn create_credential(&self, name: &str, credential_type: &str, data: &str, team_id: Uuid) -> Result<Credential>;
+    /// Validates `data`, the credential's fields, against its type (see `encrypt_credential`).
+    async fn create_credential(&self, name: &str, credential_type: &str, data: &Value, team_id: Uuid) -> Result<Credential>;
     async fn get_credential(&self, id: Uuid) -> Result<Option<Credential>>;
     async fn list_credentials(&self, team_id: Uuid) -> Result<Vec<Credential>>;
+    /// Renames a credential and/or replaces its fields, validated against its type and encrypted
+    /// again. Returns false if there is no such credential.
+    async fn update_credential(&self, id: Uuid, name: Option<&str>, data: Option<&Value>) -> Result<bool>;
+    /// Returns false if there is no such credential. Unless `force`, fails if stored workflows
+    /// use it (see `credential_usage`).
+    async fn delete_credential(&self, id: Uuid, force: bool) -> Result<bool>;
+    /// Every team's credentials, e.g. to re-encrypt them under a new master key.
+    async fn list_all_credentials(&self) -> Result<Vec<Credential>>;
+    /// Replaces a credential's encrypted data if it still is `old_data`. Returns false otherwise,
+    /// e.g. when it was changed concurrently.
+    async fn replace_credential_data(&self, id: Uuid, old_data: &str, new_data: &str) -> Result<bool>;
 
 
     // Executions
-    async fn create_execution(&self, id: Uuid, workflow_id: Option<Uuid>, status: &str) -> Result<Uuid>;
+    /// Records a run of `workflow_id` (if stored) for `team_id` (if scoped to a team).
+    async fn create_execution(&self, id: Uuid, workflow_id: Option<Uuid>, workflow_version: Option<i32>, team_id: Option<Uuid>, status: &str) -> Result<Uuid>;
     async fn update_execution(&self, id: Uuid, status: &str, finished_at: Option<DateTime<Utc>>, error: Option<String>) -> Result<()>;
     async fn log_execution_event(&self, execution_id: Uuid, event: &ExecutionEvent) -> Result<()>;
     async fn get_execution(&self, id: Uuid) -> Result<Option<ExecutionRecord>>;
     async fn get_execution_logs(&self, id: Uuid) -> Result<Vec<ExecutionEvent>>;
+    /// Definition an execution was queued with; `None` for executions that ran in-process.
+    async fn get_execution_definition(&self, id: Uuid) -> Result<Option<Value>>;
+    /// Deletes finished executions (with their events, approvals and timers) and payloads as
+    /// `policy` allows, with ages counted back from `now`.
+    async fn prune_executions(&self, policy: &RetentionPolicy, now: DateTime<Utc>) -> Result<PruneStats>;
+    async fn list_executions(&self, filter: &ExecutionFilter) -> Result<Vec<ExecutionRecord>>;
+
+    // Execution queue
+    async fn enqueue_execution(&self, execution: &QueuedExecution) -> Result<()>;
+    /// Atomically moves the next queued execution to `running`, owned by `worker_id`, and returns it.
+    /// Runs of `concurrency: queue` workflows are passed over while the workflow has a running execution.
+    async fn claim_execution(&self, worker_id: &str) -> Result<Option<QueuedExecution>>;
+    /// Marks executions as alive; those owned by another worker are left alone.
+    async fn heartbeat_executions(&self, worker_id: &str, ids: &[Uuid]) -> Result<()>;
+    /// Recovers unfinished executions whose last heartbeat is older than `cutoff` (their process
+    /// died): those with a stored definition go back to the queue, the others are marked failed if
+    /// a worker owned them. Runs that never heartbeat, such as `cli run`, are left alone.
+    /// Returns the number requeued.
+    async fn recover_expired_executions(&self, cutoff: DateTime<Utc>) -> Result<u64>;
+    /// Moves a `waiting` execution back to the queue, to run from `node_id` with `inputs`.
+    /// Returns false if the execution is not waiting or has no stored definition.
+    async fn resume_execution(&self, id: Uuid, node_id: &str, inputs: &Value) -> Result<bool>;
+
+    // Durable timers
+    async fn create_timer(&self, timer: &TimerRecord) -> Result<()>;
+    /// Waiting timers with `wake_at <= now`, earliest first.
+    async fn list_due_timers(&self, now: DateTime<Utc>) -> Result<Vec<TimerRecord>>;
+    async fn update_timer_status(&self, ids: &[Uuid], status: &str) -> Result<()>;
+    async fn count_waiting_timers(&self, execution_id: Uuid) -> Result<i64>;
+
+    // Approvals (Human-in-the-loop)
+    async fn create_approval(&self, approval: &ApprovalRecord) -> Result<()>;
+    async fn get_approval(&self, token: &str) -> Result<Option<ApprovalRecord>>;
+    /// Settles a pending approval. Returns false if it was already settled or does not exist.
+    async fn resolve_approval(&self, token: &str, status: &str, decision: Option<&Value>) -> Result<bool>;
+    /// Marks a settled approval as handed on. Returns false if it already was (or is pending).
+    async fn mark_approval_delivered(&self, token: &str) -> Result<bool>;
+    /// Approvals of a suspended execution (those with a `node_id`) not handed on yet.
+    async fn count_open_approvals(&self, execution_id: Uuid) -> Result<i64>;
+
+    // Coordination between server processes
+    /// Tries to become, or checks that this process still is, the single holder of the named
+    /// leadership. Held until the process exits.
+    async fn try_acquire_leadership(&self, name: &str) -> Result<bool>;
+
+    // Events (Cross-workflow pub/sub)
+    async fn publish_event(&self, team_id: Uuid, name: &str, payload: &Value) -> Result<EventRecord>;
+    /// Pending events, oldest first.
+    async fn list_pending_events(&self, limit: i64, offset: i64) -> Result<Vec<EventRecord>>;
+    /// Marks the event delivered to every subscriber, forgetting its per-workflow deliveries.
+    async fn mark_event_delivered(&self, id: Uuid) -> Result<()>;
+    /// Records that a pending event started `workflow_id`, so retrying the event skips it.
+    async fn record_event_delivery(&self, event_id: Uuid, workflow_id: Uuid) -> Result<()>;
+    /// The workflows a pending event has started so far.
+    async fn list_event_deliveries(&self, event_id: Uuid) -> Result<Vec<Uuid>>;
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -123,14 +467,203 @@ pub struct Credential {
     pub account_id: Uuid,
     pub name: String,
     pub credential_type: String,
-    pub data: String, // Encrypted data (Base64 encoded)
+    pub data: String, // Encrypted JSON object of the fields (Base64 encoded)
     pub created_at: DateTime<Utc>,
 }
 
+/// Validates a credential's fields against its type and encrypts them, as stored in `Credential.data`.
+pub fn encrypt_credential(credential_type: &str, data: &Value) -> Result<String> {
+    let fields = crate::credentials::get_credential_type(credential_type)?.validate(data)?;
+    encryption::encrypt(&fields.to_string())
+}
+
+/// The credential's fields, decrypted, with secrets masked (see `credentials::mask_fields`).
+pub fn reveal_credential(credential: &Credential) -> Result<Value> {
+    let fields = crate::credentials::fields_from_plaintext(&credential.credential_type, &encryption::decrypt(&credential.data)?);
+    Ok(crate::credentials::mask_fields(&credential.credential_type, &fields))
+}
+
+/// A node of a stored workflow that references a credential.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CredentialUsage {
+    pub workflow_id: Uuid,
+    pub workflow_name: String,
+    pub node_id: String,
+}
+
+/// The nodes of the team's stored workflows (not deleted ones) whose config references the
+/// credential as `credential_id`.
+pub async fn credential_usage(storage: &dyn Storage, credential: &Credential) -> Result<Vec<CredentialUsage>> {
+    let id = credential.id.to_string();
+    let mut usage = Vec::new();
+    for workflow in storage.list_workflows(credential.account_id).await? {
+        let nodes = workflow.definition.get("nodes").and_then(|n| n.as_array()).cloned().unwrap_or_default();
+        for node in nodes {
+            if references_credential(node.get("config").unwrap_or(&Value::Null), &id) {
+                usage.push(CredentialUsage {
+                    workflow_id: workflow.id,
+                    workflow_name: workflow.name.clone(),
+                    node_id: node.get("id").and_then(|v| v.as_str()).unwrap_or_default().to_string(),
+                });
+            }
+        }
+    }
+    Ok(usage)
+}
+
+/// Fails if stored workflows use the credential, see `Storage::delete_credential`.
+pub(crate) async fn ensure_unused(storage: &dyn Storage, id: Uuid) -> Result<()> {
+    let Some(credential) = storage.get_credential(id).await? else {
+        return Ok(());
+    };
+    let usage = credential_usage(storage, &credential).await?;
+    if !usage.is_empty() {
+        let names = usage.iter().map(|u| format!("{} ({})", u.workflow_name, u.node_id)).collect::<Vec<_>>();
+        return Err(anyhow!("Credential {} is used by {}; delete it with force anyway", id, names.join(", ")));
+    }
+    Ok(())
+}
+
+fn references_credential(config: &Value, id: &str) -> bool {
+    match config {
+        Value::Object(fields) => fields.iter().any(|(k, v)| {
+            (k == "credential_id" && v.as_str() == Some(id)) || references_credential(v, id)
+        }),
+        Value::Array(items) => items.iter().any(|v| references_credential(v, id)),
+        _ => false,
+    }
+}
+
+/// Key-value key of node state kept by a run of `team_id`, e.g. `team:<uuid>:orders_poll`.
+/// Runs without a team use `key` as is.
+pub fn team_kv_key(team_id: Option<Uuid>, key: &str) -> String {
+    match team_id {
+        Some(team_id) => format!("team:{}:{}", team_id, key),
+        None => key.to_string(),
+    }
+}
+
+/// The team a key-value key belongs to (see `team_kv_key`).
+pub fn kv_key_team(key: &str) -> Option<Uuid> {
+    let (team_id, _) = key.strip_prefix("team:")?.split_once(':')?;
+    Uuid::parse_str(team_id).ok()
+}
+
+/// Decrypted credentials of a team, keyed by credential id, as handed to nodes as secrets.
+/// Credentials that cannot be decrypted (e.g. another master key) are skipped with a warning.
+pub async fn team_secrets(storage: &dyn Storage, team_id: Uuid) -> Result<Secrets> {
+    Ok(decrypt_credentials(storage.list_credentials(team_id).await?))
+}
+
+/// Like `team_secrets`, limited to the credentials the nodes of `definition` reference by id.
+pub async fn referenced_secrets(storage: &dyn Storage, team_id: Uuid, definition: &WorkflowDefinition) -> Result<Secrets> {
+    let referenced = storage.list_credentials(team_id).await?.into_iter()
+        .filter(|cred| {
+            let id = cred.id.to_string();
+            definition.nodes.iter().any(|node| references_credential(&node.config, &id))
+        })
+        .collect();
+    Ok(decrypt_credentials(referenced))
+}
+
+fn decrypt_credentials(credentials: Vec<Credential>) -> Secrets {
+    let mut secrets = Secrets::new();
+    for cred in credentials {
+        match encryption::decrypt(&cred.data) {
+            Ok(decrypted) => {
+                secrets.insert(cred.id.to_string(), crate::credentials::fields_from_plaintext(&cred.credential_type, &decrypted));
+            }
+            Err(e) => {
+                eprintln!("Warning: Failed to decrypt credential {}: {}", cred.id, e);
+            }
+        }
+    }
+    secrets
+}
+
+/// What `rotate_credentials` did.
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct KeyRotation {
+    /// Credentials re-encrypted under the current master key
+    pub rotated: usize,
+    /// Credentials that were under the current key already
+    pub current: usize,
+    /// Credentials that could not be decrypted with any known key, with the error
+    pub failed: Vec<(Uuid, String)>,
+}
+
+/// Re-encrypts every credential under the current master key. Credentials under a previous key
+/// keep working meanwhile, and one changed concurrently is read again, so it can run while
+/// servers are up.
+pub async fn rotate_credentials(storage: &dyn Storage) -> Result<KeyRotation> {
+    let keyring = encryption::keyring()?;
+    let mut rotation = KeyRotation::default();
+    for mut cred in storage.list_all_credentials().await? {
+        loop {
+            if keyring.is_current(&cred.data) {
+                rotation.current += 1;
+                break;
+            }
+            let plaintext = match keyring.decrypt(&cred.data) {
+                Ok(plaintext) => plaintext,
+                Err(e) => {
+                    rotation.failed.push((cred.id, e.to_string()));
+                    break;
+                }
+            };
+            if storage.replace_credential_data(cred.id, &cred.data, &keyring.encrypt(&plaintext)?).await? {
+                rotation.rotated += 1;
+                break;
+            }
+            match storage.get_credential(cred.id).await? {
+                Some(changed) => cred = changed,
+                None => break,
+            }
+        }
+    }
+    Ok(rotation)
+}
+
+/// Saves a stored workflow with a new name and/or definition (the current ones where `None`)
+/// as its next version, by `author_id`, and returns the version.
+pub async fn update_workflow(
+    storage: &dyn Storage,
+    workflow_id: Uuid,
+    name: Option<String>,
+    definition: Option<Value>,
+    author_id: Option<Uuid>,
+    message: Option<&str>,
+) -> Result<i32> {
+    let workflow = storage.get_workflow(workflow_id).await?
+        .filter(|w| w.deleted_at.is_none())
+        .ok_or_else(|| anyhow!("Workflow {} not found", workflow_id))?;
+    let updated = WorkflowEntity {
+        name: name.unwrap_or(workflow.name),
+        definition: definition.unwrap_or(workflow.definition),
+        ..workflow
+    };
+    storage.save_workflow(&updated, author_id, message).await
+}
+
+/// Saves an earlier version of a workflow as its next version, by `author_id`, and returns the
+/// new version. History is kept: the versions in between stay as they are.
+pub async fn rollback_workflow(storage: &dyn Storage, workflow_id: Uuid, version: i32, author_id: Option<Uuid>) -> Result<i32> {
+    let target = storage.get_workflow_version(workflow_id, version).await?
+        .ok_or_else(|| anyhow!("Workflow {} has no version {}", workflow_id, version))?;
+    let message = format!("Rollback to version {}", version);
+    update_workflow(storage, workflow_id, Some(target.name), Some(target.definition), author_id, Some(&message)).await
+}
+
 pub mod sqlite;
 pub mod encryption;
 pub mod remote;
 pub mod postgres;
+pub mod memory;
+pub mod migrations;
+pub mod file;
 pub use sqlite::SqliteStorage;
 pub use remote::RemoteStorage;
 pub use postgres::PostgresStorage;
+pub use memory::MemoryStorage;
+pub use file::FileStorage;
+pub use migrations::{MigrationStatus, SchemaStatus};
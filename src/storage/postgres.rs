@@ -1,10 +1,11 @@
 use async_trait::async_trait;
-use sqlx::{postgres::PgPool, Row};
+use std::collections::HashMap;
+use sqlx::{pool::PoolConnection, postgres::PgPool, Postgres, Row};
 use uuid::Uuid;
 use serde_json::Value;
-use anyhow::{Result, anyhow};
-use chrono::Utc;
-use super::{Storage, Team, User, TeamMember, Role, WorkflowEntity, Credential, ExecutionRecord};
+use anyhow::{Result, anyhow, bail};
+use chrono::{DateTime, Utc};
+use super::{Storage, Team, User, TeamMember, Role, WorkflowEntity, WorkflowVersion, Credential, ExecutionRecord, ExecutionFilter, RetentionPolicy, PruneStats, ApiToken, AuditEntry, EventRecord, QueuedExecution, ApprovalRecord, TimerRecord, SchemaStatus, migrations};
 use crate::schema::ExecutionEvent;
 use argon2::{
     password_hash::{
@@ -16,83 +17,100 @@ use argon2::{
 
 pub struct PostgresStorage {
     pool: PgPool,
+    /// Connections holding a leadership advisory lock, by name.
+    leader_connections: tokio::sync::Mutex<HashMap<String, PoolConnection<Postgres>>>,
 }
 
 impl PostgresStorage {
     pub async fn new(database_url: &str) -> Result<Self> {
         let pool = PgPool::connect(database_url).await?;
-        Ok(Self { pool })
+        Ok(Self { pool, leader_connections: tokio::sync::Mutex::new(HashMap::new()) })
+    }
+}
+
+fn execution_from_row(row: &sqlx::postgres::PgRow) -> ExecutionRecord {
+    ExecutionRecord {
+        id: row.get("id"),
+        workflow_id: row.get("workflow_id"),
+        workflow_version: row.get("workflow_version"),
+        team_id: row.get("team_id"),
+        status: row.get("status"),
+        started_at: row.get("started_at"),
+        finished_at: row.get("finished_at"),
+        error: row.get("error"),
+    }
+}
+
+fn credential_from_row(row: &sqlx::postgres::PgRow) -> Credential {
+    Credential {
+        id: row.get("id"),
+        account_id: row.get("account_id"),
+        name: row.get("name"),
+        credential_type: row.get("type"),
+        data: row.get("data"),
+        created_at: row.get("created_at"),
+    }
+}
+
+fn workflow_from_row(row: &sqlx::postgres::PgRow) -> WorkflowEntity {
+    WorkflowEntity {
+        id: row.get("id"),
+        account_id: row.get("account_id"),
+        name: row.get("name"),
+        definition: row.get("definition"), // JSONB
+        created_at: row.get("created_at"),
+        version: row.get("version"),
+        active: row.get("active"),
+        deleted_at: row.get("deleted_at"),
+    }
+}
+
+fn workflow_version_from_row(row: &sqlx::postgres::PgRow) -> WorkflowVersion {
+    WorkflowVersion {
+        workflow_id: row.get("workflow_id"),
+        version: row.get("version"),
+        name: row.get("name"),
+        definition: row.get("definition"),
+        author_id: row.get("author_id"),
+        message: row.get("message"),
+        created_at: row.get("created_at"),
+    }
+}
+
+fn api_token_from_row(row: &sqlx::postgres::PgRow) -> ApiToken {
+    ApiToken {
+        id: row.get("id"),
+        user_id: row.get("user_id"),
+        name: row.get("name"),
+        kind: row.get("kind"),
+        token_hash: row.get("token_hash"),
+        created_at: row.get("created_at"),
+        expires_at: row.get("expires_at"),
+    }
+}
+
+fn audit_entry_from_row(row: &sqlx::postgres::PgRow) -> AuditEntry {
+    AuditEntry {
+        id: row.get("id"),
+        team_id: row.get("team_id"),
+        actor_id: row.get("actor_id"),
+        action: row.get("action"),
+        target: row.get("target"),
+        diff: row.get("diff"),
+        created_at: row.get("created_at"),
     }
 }
 
 #[async_trait]
 impl Storage for PostgresStorage {
     async fn init(&self) -> Result<()> {
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS users (
-                id UUID PRIMARY KEY,
-                username TEXT NOT NULL UNIQUE,
-                password_hash TEXT NOT NULL,
-                created_at TIMESTAMPTZ NOT NULL
-            );
-            CREATE TABLE IF NOT EXISTS teams (
-                id UUID PRIMARY KEY,
-                name TEXT NOT NULL UNIQUE,
-                created_at TIMESTAMPTZ NOT NULL
-            );
-            CREATE TABLE IF NOT EXISTS team_members (
-                user_id UUID NOT NULL,
-                team_id UUID NOT NULL,
-                role TEXT NOT NULL,
-                joined_at TIMESTAMPTZ NOT NULL,
-                PRIMARY KEY (user_id, team_id),
-                FOREIGN KEY(user_id) REFERENCES users(id),
-                FOREIGN KEY(team_id) REFERENCES teams(id)
-            );
-            CREATE TABLE IF NOT EXISTS workflows (
-                id UUID PRIMARY KEY,
-                account_id UUID NOT NULL,
-                name TEXT NOT NULL,
-                definition JSONB NOT NULL,
-                created_at TIMESTAMPTZ NOT NULL,
-                FOREIGN KEY(account_id) REFERENCES teams(id)
-            );
-            CREATE TABLE IF NOT EXISTS key_value (
-                key TEXT PRIMARY KEY,
-                value JSONB NOT NULL
-            );
-            CREATE TABLE IF NOT EXISTS credentials (
-                id UUID PRIMARY KEY,
-                account_id UUID NOT NULL,
-                name TEXT NOT NULL,
-                type TEXT NOT NULL,
-                data TEXT NOT NULL,
-                created_at TIMESTAMPTZ NOT NULL,
-                FOREIGN KEY(account_id) REFERENCES teams(id)
-            );
-            CREATE TABLE IF NOT EXISTS executions (
-                id UUID PRIMARY KEY,
-                workflow_id UUID,
-                status TEXT NOT NULL,
-                started_at TIMESTAMPTZ NOT NULL,
-                finished_at TIMESTAMPTZ,
-                error TEXT,
-                FOREIGN KEY(workflow_id) REFERENCES workflows(id)
-            );
-            CREATE TABLE IF NOT EXISTS execution_events (
-                id UUID PRIMARY KEY,
-                execution_id UUID NOT NULL,
-                event_type TEXT NOT NULL,
-                data JSONB NOT NULL,
-                created_at TIMESTAMPTZ NOT NULL,
-                FOREIGN KEY(execution_id) REFERENCES executions(id)
-            );
-            "#
-        )
-        .execute(&self.pool)
-        .await?;
-        Ok(())
+        let mut conn = self.pool.acquire().await?;
+        migrations::run(&migrations::POSTGRES, &mut *conn).await
+    }
+
+    async fn schema_status(&self) -> Result<SchemaStatus> {
+        let mut conn = self.pool.acquire().await?;
+        migrations::status(&migrations::POSTGRES, &mut *conn).await
     }
 
     // User
@@ -160,6 +178,13 @@ impl Storage for PostgresStorage {
         }
     }
 
+    async fn count_users(&self) -> Result<i64> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count)
+    }
+
     // Team
     async fn create_team(&self, name: &str) -> Result<Team> {
         let id = Uuid::new_v4();
@@ -275,57 +300,119 @@ impl Storage for PostgresStorage {
     }
 
     // Workflow
-    async fn save_workflow(&self, workflow: &WorkflowEntity) -> Result<()> {
+    async fn save_workflow(&self, workflow: &WorkflowEntity, author_id: Option<Uuid>, message: Option<&str>) -> Result<i32> {
+        let mut tx = self.pool.begin().await?;
+        // Locked, so concurrent saves number their versions one after the other
+        let latest = sqlx::query("SELECT name, definition, version, deleted_at FROM workflows WHERE id = $1 FOR UPDATE")
+            .bind(workflow.id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        let mut version = 1;
+        if let Some(row) = latest {
+            if row.get::<Option<DateTime<Utc>>, _>("deleted_at").is_some() {
+                bail!("Workflow {} was deleted", workflow.id);
+            }
+            let current: i32 = row.get("version");
+            if row.get::<String, _>("name") == workflow.name && row.get::<Value, _>("definition") == workflow.definition {
+                return Ok(current);
+            }
+            version = current + 1;
+        }
+
         sqlx::query(
-            "INSERT INTO workflows (id, account_id, name, definition, created_at) VALUES ($1, $2, $3, $4, $5)
-             ON CONFLICT(id) DO UPDATE SET name=excluded.name, definition=excluded.definition"
+            "INSERT INTO workflows (id, account_id, name, definition, created_at, version, active) VALUES ($1, $2, $3, $4, $5, $6, $7)
+             ON CONFLICT(id) DO UPDATE SET name=excluded.name, definition=excluded.definition, version=excluded.version"
         )
         .bind(workflow.id)
         .bind(workflow.account_id)
         .bind(&workflow.name)
         .bind(&workflow.definition) // JSONB
         .bind(workflow.created_at)
-        .execute(&self.pool)
+        .bind(version)
+        .bind(workflow.active)
+        .execute(&mut *tx)
         .await?;
-        Ok(())
+
+        sqlx::query(
+            "INSERT INTO workflow_versions (workflow_id, version, name, definition, author_id, message, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7)"
+        )
+        .bind(workflow.id)
+        .bind(version)
+        .bind(&workflow.name)
+        .bind(&workflow.definition)
+        .bind(author_id)
+        .bind(message)
+        .bind(Utc::now())
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(version)
     }
 
     async fn get_workflow(&self, id: Uuid) -> Result<Option<WorkflowEntity>> {
-        let row = sqlx::query("SELECT id, account_id, name, definition, created_at FROM workflows WHERE id = $1")
+        let row = sqlx::query("SELECT id, account_id, name, definition, created_at, version, active, deleted_at FROM workflows WHERE id = $1")
             .bind(id)
             .fetch_optional(&self.pool)
             .await?;
 
-        if let Some(row) = row {
-            Ok(Some(WorkflowEntity {
-                id: row.get("id"),
-                account_id: row.get("account_id"),
-                name: row.get("name"),
-                definition: row.get("definition"), // JSONB
-                created_at: row.get("created_at"),
-            }))
-        } else {
-            Ok(None)
-        }
+        Ok(row.as_ref().map(workflow_from_row))
     }
 
     async fn list_workflows(&self, team_id: Uuid) -> Result<Vec<WorkflowEntity>> {
-        let rows = sqlx::query("SELECT id, account_id, name, definition, created_at FROM workflows WHERE account_id = $1")
-            .bind(team_id)
-            .fetch_all(&self.pool)
+        let rows = sqlx::query(
+            "SELECT id, account_id, name, definition, created_at, version, active, deleted_at FROM workflows
+             WHERE account_id = $1 AND deleted_at IS NULL"
+        )
+        .bind(team_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(workflow_from_row).collect())
+    }
+
+    async fn set_workflow_active(&self, id: Uuid, active: bool) -> Result<bool> {
+        let result = sqlx::query("UPDATE workflows SET active = $1 WHERE id = $2 AND deleted_at IS NULL")
+            .bind(active)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_workflow(&self, id: Uuid) -> Result<bool> {
+        let result = sqlx::query("UPDATE workflows SET deleted_at = $1 WHERE id = $2 AND deleted_at IS NULL")
+            .bind(Utc::now())
+            .bind(id)
+            .execute(&self.pool)
             .await?;
+        Ok(result.rows_affected() > 0)
+    }
 
-        let mut workflows = Vec::new();
-        for row in rows {
-            workflows.push(WorkflowEntity {
-                id: row.get("id"),
-                account_id: row.get("account_id"),
-                name: row.get("name"),
-                definition: row.get("definition"),
-                created_at: row.get("created_at"),
-            });
-        }
-        Ok(workflows)
+    async fn list_workflow_versions(&self, workflow_id: Uuid) -> Result<Vec<WorkflowVersion>> {
+        let rows = sqlx::query(
+            "SELECT workflow_id, version, name, definition, author_id, message, created_at FROM workflow_versions
+             WHERE workflow_id = $1 ORDER BY version DESC"
+        )
+        .bind(workflow_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(workflow_version_from_row).collect())
+    }
+
+    async fn get_workflow_version(&self, workflow_id: Uuid, version: i32) -> Result<Option<WorkflowVersion>> {
+        let row = sqlx::query(
+            "SELECT workflow_id, version, name, definition, author_id, message, created_at FROM workflow_versions
+             WHERE workflow_id = $1 AND version = $2"
+        )
+        .bind(workflow_id)
+        .bind(version)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|row| workflow_version_from_row(&row)))
     }
 
     // Key-Value
@@ -355,10 +442,10 @@ impl Storage for PostgresStorage {
     }
 
     // Credentials
-    async fn create_credential(&self, name: &str, credential_type: &str, data: &str, team_id: Uuid) -> Result<Credential> {
+    async fn create_credential(&self, name: &str, credential_type: &str, data: &Value, team_id: Uuid) -> Result<Credential> {
         let id = Uuid::new_v4();
         let created_at = Utc::now();
-        let encrypted_data = super::encryption::encrypt(data)?;
+        let encrypted_data = super::encrypt_credential(credential_type, data)?;
 
         sqlx::query(
             "INSERT INTO credentials (id, account_id, name, type, data, created_at) VALUES ($1, $2, $3, $4, $5, $6)"
@@ -388,18 +475,7 @@ impl Storage for PostgresStorage {
             .fetch_optional(&self.pool)
             .await?;
 
-        if let Some(row) = row {
-            Ok(Some(Credential {
-                id: row.get("id"),
-                account_id: row.get("account_id"),
-                name: row.get("name"),
-                credential_type: row.get("type"),
-                data: row.get("data"),
-                created_at: row.get("created_at"),
-            }))
-        } else {
-            Ok(None)
-        }
+        Ok(row.as_ref().map(credential_from_row))
     }
 
     async fn list_credentials(&self, team_id: Uuid) -> Result<Vec<Credential>> {
@@ -408,28 +484,61 @@ impl Storage for PostgresStorage {
             .fetch_all(&self.pool)
             .await?;
 
-        let mut creds = Vec::new();
-        for row in rows {
-            creds.push(Credential {
-                id: row.get("id"),
-                account_id: row.get("account_id"),
-                name: row.get("name"),
-                credential_type: row.get("type"),
-                data: row.get("data"),
-                created_at: row.get("created_at"),
-            });
+        Ok(rows.iter().map(credential_from_row).collect())
+    }
+
+    async fn update_credential(&self, id: Uuid, name: Option<&str>, data: Option<&Value>) -> Result<bool> {
+        let Some(credential) = self.get_credential(id).await? else {
+            return Ok(false);
+        };
+        let data = data.map(|d| super::encrypt_credential(&credential.credential_type, d)).transpose()?;
+        let result = sqlx::query("UPDATE credentials SET name = COALESCE($1, name), data = COALESCE($2, data) WHERE id = $3")
+            .bind(name)
+            .bind(data)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_credential(&self, id: Uuid, force: bool) -> Result<bool> {
+        if !force {
+            super::ensure_unused(self, id).await?;
         }
-        Ok(creds)
+        let result = sqlx::query("DELETE FROM credentials WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn list_all_credentials(&self) -> Result<Vec<Credential>> {
+        let rows = sqlx::query("SELECT id, account_id, name, type, data, created_at FROM credentials ORDER BY created_at")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.iter().map(credential_from_row).collect())
+    }
+
+    async fn replace_credential_data(&self, id: Uuid, old_data: &str, new_data: &str) -> Result<bool> {
+        let result = sqlx::query("UPDATE credentials SET data = $1 WHERE id = $2 AND data = $3")
+            .bind(new_data)
+            .bind(id)
+            .bind(old_data)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
     }
 
     // Executions
-    async fn create_execution(&self, id: Uuid, workflow_id: Option<Uuid>, status: &str) -> Result<Uuid> {
+    async fn create_execution(&self, id: Uuid, workflow_id: Option<Uuid>, workflow_version: Option<i32>, team_id: Option<Uuid>, status: &str) -> Result<Uuid> {
         let started_at = Utc::now();
         sqlx::query(
-            "INSERT INTO executions (id, workflow_id, status, started_at) VALUES ($1, $2, $3, $4)"
+            "INSERT INTO executions (id, workflow_id, workflow_version, team_id, status, started_at) VALUES ($1, $2, $3, $4, $5, $6)"
         )
         .bind(id)
         .bind(workflow_id)
+        .bind(workflow_version)
+        .bind(team_id)
         .bind(status)
         .bind(started_at)
         .execute(&self.pool)
@@ -477,27 +586,40 @@ impl Storage for PostgresStorage {
     }
 
     async fn get_execution(&self, id: Uuid) -> Result<Option<ExecutionRecord>> {
-        let row = sqlx::query("SELECT id, workflow_id, status, started_at, finished_at, error FROM executions WHERE id = $1")
+        let row = sqlx::query("SELECT id, workflow_id, workflow_version, team_id, status, started_at, finished_at, error FROM executions WHERE id = $1")
             .bind(id)
             .fetch_optional(&self.pool)
             .await?;
 
-        if let Some(row) = row {
-            Ok(Some(ExecutionRecord {
-                id: row.get("id"),
-                workflow_id: row.get("workflow_id"),
-                status: row.get("status"),
-                started_at: row.get("started_at"),
-                finished_at: row.get("finished_at"),
-                error: row.get("error"),
-            }))
-        } else {
-            Ok(None)
-        }
+        Ok(row.map(|row| execution_from_row(&row)))
+    }
+
+    async fn list_executions(&self, filter: &ExecutionFilter) -> Result<Vec<ExecutionRecord>> {
+        // Unset filters are NULL and match everything
+        let rows = sqlx::query(
+            "SELECT id, workflow_id, workflow_version, team_id, status, started_at, finished_at, error FROM executions
+             WHERE ($1::UUID IS NULL OR workflow_id = $1)
+               AND ($2::TEXT IS NULL OR status = $2)
+               AND ($3::TIMESTAMPTZ IS NULL OR started_at >= $3)
+               AND ($4::TIMESTAMPTZ IS NULL OR started_at < $4)
+               AND ($7::UUID IS NULL OR team_id = $7 OR workflow_id IN (SELECT id FROM workflows WHERE account_id = $7))
+             ORDER BY started_at DESC LIMIT $5 OFFSET $6"
+        )
+        .bind(filter.workflow_id)
+        .bind(&filter.status)
+        .bind(filter.since)
+        .bind(filter.until)
+        .bind(filter.limit)
+        .bind(filter.offset)
+        .bind(filter.team_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(execution_from_row).collect())
     }
 
     async fn get_execution_logs(&self, id: Uuid) -> Result<Vec<ExecutionEvent>> {
-        let rows = sqlx::query("SELECT data FROM execution_events WHERE execution_id = $1 ORDER BY created_at ASC")
+        let rows = sqlx::query("SELECT data FROM execution_events WHERE execution_id = $1 ORDER BY created_at ASC, seq ASC")
             .bind(id)
             .fetch_all(&self.pool)
             .await?;
@@ -510,4 +632,488 @@ impl Storage for PostgresStorage {
         }
         Ok(events)
     }
+
+    // Events
+    async fn publish_event(&self, team_id: Uuid, name: &str, payload: &Value) -> Result<EventRecord> {
+        let id = Uuid::new_v4();
+        let created_at = Utc::now();
+
+        sqlx::query(
+            "INSERT INTO events (id, team_id, name, payload, status, created_at) VALUES ($1, $2, $3, $4, 'pending', $5)"
+        )
+        .bind(id)
+        .bind(team_id)
+        .bind(name)
+        .bind(payload)
+        .bind(created_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(EventRecord {
+            id,
+            team_id,
+            name: name.to_string(),
+            payload: payload.clone(),
+            status: "pending".to_string(),
+            created_at,
+            delivered_at: None,
+        })
+    }
+
+    async fn list_pending_events(&self, limit: i64, offset: i64) -> Result<Vec<EventRecord>> {
+        let rows = sqlx::query(
+            "SELECT id, team_id, name, payload, status, created_at, delivered_at FROM events
+             WHERE status = 'pending' ORDER BY created_at ASC, id ASC LIMIT $1 OFFSET $2"
+        )
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut events = Vec::new();
+        for row in rows {
+            events.push(EventRecord {
+                id: row.get("id"),
+                team_id: row.get("team_id"),
+                name: row.get("name"),
+                payload: row.get("payload"),
+                status: row.get("status"),
+                created_at: row.get("created_at"),
+                delivered_at: row.get("delivered_at"),
+            });
+        }
+        Ok(events)
+    }
+
+    async fn mark_event_delivered(&self, id: Uuid) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("UPDATE events SET status = 'delivered', delivered_at = $1 WHERE id = $2")
+            .bind(Utc::now())
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM event_deliveries WHERE event_id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn record_event_delivery(&self, event_id: Uuid, workflow_id: Uuid) -> Result<()> {
+        sqlx::query("INSERT INTO event_deliveries (event_id, workflow_id, delivered_at) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING")
+            .bind(event_id)
+            .bind(workflow_id)
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn list_event_deliveries(&self, event_id: Uuid) -> Result<Vec<Uuid>> {
+        let rows = sqlx::query("SELECT workflow_id FROM event_deliveries WHERE event_id = $1")
+            .bind(event_id)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.iter().map(|row| row.get("workflow_id")).collect())
+    }
+
+    async fn enqueue_execution(&self, execution: &QueuedExecution) -> Result<()> {
+        // The execution may already exist, e.g. when it waited for its concurrency slot
+        sqlx::query(
+            "INSERT INTO executions (id, workflow_id, status, started_at, team_id, priority, definition, inputs, resume_from, started_by)
+             VALUES ($1, $2, 'queued', $3, $4, $5, $6, $7, $8, $9)
+             ON CONFLICT(id) DO UPDATE SET status = 'queued', team_id = EXCLUDED.team_id, priority = EXCLUDED.priority,
+                definition = EXCLUDED.definition, inputs = EXCLUDED.inputs, resume_from = EXCLUDED.resume_from,
+                started_by = EXCLUDED.started_by"
+        )
+        .bind(execution.id)
+        .bind(execution.workflow_id)
+        .bind(Utc::now())
+        .bind(execution.team_id)
+        .bind(execution.priority)
+        .bind(&execution.definition)
+        .bind(&execution.inputs)
+        .bind(&execution.resume_from)
+        .bind(execution.started_by)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn claim_execution(&self, worker_id: &str) -> Result<Option<QueuedExecution>> {
+        // SKIP LOCKED lets concurrent workers claim different rows without waiting on each other
+        let row = sqlx::query(
+            "UPDATE executions SET status = 'running', worker_id = $1, heartbeat_at = $2
+             WHERE id = (
+                SELECT id FROM executions e WHERE status = 'queued' AND definition IS NOT NULL
+                AND NOT (resume_from IS NULL AND definition->'concurrency'->>'mode' = 'queue' AND EXISTS (
+                    SELECT 1 FROM executions r WHERE r.workflow_id = e.workflow_id AND r.status = 'running'
+                ))
+                ORDER BY priority DESC, started_at ASC LIMIT 1
+                FOR UPDATE SKIP LOCKED
+             )
+             RETURNING id, workflow_id, team_id, priority, definition, inputs, resume_from, started_by"
+        )
+        .bind(worker_id)
+        .bind(Utc::now())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|row| QueuedExecution {
+            id: row.get("id"),
+            workflow_id: row.get("workflow_id"),
+            team_id: row.get("team_id"),
+            definition: row.get("definition"),
+            inputs: row.get::<Option<Value>, _>("inputs").unwrap_or(Value::Null),
+            priority: row.get("priority"),
+            resume_from: row.get("resume_from"),
+            started_by: row.get("started_by"),
+        }))
+    }
+
+    async fn heartbeat_executions(&self, worker_id: &str, ids: &[Uuid]) -> Result<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        sqlx::query(
+            "UPDATE executions SET worker_id = $1, heartbeat_at = $2
+             WHERE id = ANY($3) AND (worker_id IS NULL OR worker_id = $1)"
+        )
+        .bind(worker_id)
+        .bind(Utc::now())
+        .bind(ids)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn recover_expired_executions(&self, cutoff: DateTime<Utc>) -> Result<u64> {
+        sqlx::query(
+            "UPDATE executions SET status = 'failed', finished_at = $1, error = 'Interrupted: the server stopped before the execution finished'
+             WHERE status IN ('pending', 'running', 'queued') AND definition IS NULL AND worker_id IS NOT NULL
+             AND COALESCE(heartbeat_at, started_at) < $2"
+        )
+        .bind(Utc::now())
+        .bind(cutoff)
+        .execute(&self.pool)
+        .await?;
+
+        let result = sqlx::query(
+            "UPDATE executions SET status = 'queued', worker_id = NULL
+             WHERE status = 'running' AND definition IS NOT NULL AND COALESCE(heartbeat_at, started_at) < $1"
+        )
+        .bind(cutoff)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn resume_execution(&self, id: Uuid, node_id: &str, inputs: &Value) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE executions SET status = 'queued', worker_id = NULL, inputs = $1, resume_from = $2
+             WHERE id = $3 AND status = 'waiting' AND definition IS NOT NULL"
+        )
+        .bind(inputs)
+        .bind(node_id)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn try_acquire_leadership(&self, name: &str) -> Result<bool> {
+        // Session-level advisory locks belong to a connection: keep it out of the pool while
+        // leading, so the lock is released exactly when this process (or its connection) dies.
+        let mut leaders = self.leader_connections.lock().await;
+
+        if let Some(conn) = leaders.get_mut(name) {
+            if sqlx::query("SELECT 1").execute(&mut **conn).await.is_ok() {
+                return Ok(true);
+            }
+            leaders.remove(name); // Connection lost, and the lock with it
+        }
+
+        let mut conn = self.pool.acquire().await?;
+        let acquired: bool = sqlx::query_scalar("SELECT pg_try_advisory_lock(hashtext($1))")
+            .bind(name)
+            .fetch_one(&mut *conn)
+            .await?;
+        if acquired {
+            leaders.insert(name.to_string(), conn);
+        }
+        Ok(acquired)
+    }
+
+    async fn create_approval(&self, approval: &ApprovalRecord) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO approvals (token, execution_id, record, status, decision, expires_at, created_at, decided_at, node_id, delivered_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)"
+        )
+        .bind(&approval.token)
+        .bind(approval.execution_id)
+        .bind(&approval.record)
+        .bind(&approval.status)
+        .bind(&approval.decision)
+        .bind(approval.expires_at)
+        .bind(approval.created_at)
+        .bind(approval.decided_at)
+        .bind(&approval.node_id)
+        .bind(approval.delivered_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_approval(&self, token: &str) -> Result<Option<ApprovalRecord>> {
+        let row = sqlx::query(
+            "SELECT token, execution_id, record, status, decision, expires_at, created_at, decided_at, node_id, delivered_at FROM approvals WHERE token = $1"
+        )
+        .bind(token)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|row| ApprovalRecord {
+            token: row.get("token"),
+            execution_id: row.get("execution_id"),
+            record: row.get("record"),
+            status: row.get("status"),
+            decision: row.get("decision"),
+            expires_at: row.get("expires_at"),
+            created_at: row.get("created_at"),
+            decided_at: row.get("decided_at"),
+            node_id: row.get("node_id"),
+            delivered_at: row.get("delivered_at"),
+        }))
+    }
+
+    async fn resolve_approval(&self, token: &str, status: &str, decision: Option<&Value>) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE approvals SET status = $1, decision = $2, decided_at = $3 WHERE token = $4 AND status = 'pending'"
+        )
+        .bind(status)
+        .bind(decision)
+        .bind(Utc::now())
+        .bind(token)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn mark_approval_delivered(&self, token: &str) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE approvals SET delivered_at = $1 WHERE token = $2 AND status <> 'pending' AND delivered_at IS NULL"
+        )
+        .bind(Utc::now())
+        .bind(token)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn count_open_approvals(&self, execution_id: Uuid) -> Result<i64> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM approvals WHERE execution_id = $1 AND node_id IS NOT NULL AND delivered_at IS NULL"
+        )
+        .bind(execution_id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(count)
+    }
+
+    async fn create_timer(&self, timer: &TimerRecord) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO timers (id, execution_id, node_id, record, wake_at, status, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7)"
+        )
+        .bind(timer.id)
+        .bind(timer.execution_id)
+        .bind(&timer.node_id)
+        .bind(&timer.record)
+        .bind(timer.wake_at)
+        .bind(&timer.status)
+        .bind(timer.created_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn list_due_timers(&self, now: DateTime<Utc>) -> Result<Vec<TimerRecord>> {
+        let rows = sqlx::query(
+            "SELECT id, execution_id, node_id, record, wake_at, status, created_at FROM timers
+             WHERE status = 'waiting' AND wake_at <= $1 ORDER BY wake_at ASC"
+        )
+        .bind(now)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| TimerRecord {
+            id: row.get("id"),
+            execution_id: row.get("execution_id"),
+            node_id: row.get("node_id"),
+            record: row.get("record"),
+            wake_at: row.get("wake_at"),
+            status: row.get("status"),
+            created_at: row.get("created_at"),
+        }).collect())
+    }
+
+    async fn update_timer_status(&self, ids: &[Uuid], status: &str) -> Result<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        sqlx::query("UPDATE timers SET status = $1 WHERE id = ANY($2)")
+            .bind(status)
+            .bind(ids)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn count_waiting_timers(&self, execution_id: Uuid) -> Result<i64> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM timers WHERE execution_id = $1 AND status = 'waiting'")
+            .bind(execution_id)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count)
+    }
+
+    async fn get_execution_definition(&self, id: Uuid) -> Result<Option<Value>> {
+        let definition: Option<Option<Value>> = sqlx::query_scalar("SELECT definition FROM executions WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(definition.flatten())
+    }
+
+    async fn prune_executions(&self, policy: &RetentionPolicy, now: DateTime<Utc>) -> Result<PruneStats> {
+        let cutoff = |days: Option<i64>| days.map(|d| now - chrono::Duration::days(d));
+        let max_age = cutoff(policy.max_age_days);
+        let failed_max_age = cutoff(policy.failed_max_age_days);
+
+        // Unset limits are NULL, and comparisons with NULL match nothing
+        let doomed = "SELECT id FROM (
+                SELECT id, workflow_id, finished_at,
+                       ROW_NUMBER() OVER (PARTITION BY workflow_id ORDER BY started_at DESC) AS rn
+                FROM executions
+                WHERE finished_at IS NOT NULL AND NOT ($3::BOOLEAN AND status = 'failed')
+            ) ranked
+            WHERE finished_at < $1::TIMESTAMPTZ OR (workflow_id IS NOT NULL AND rn > $2::BIGINT)
+            UNION
+            SELECT id FROM executions WHERE status = 'failed' AND finished_at < $4::TIMESTAMPTZ";
+
+        let mut stats = PruneStats::default();
+        let mut tx = self.pool.begin().await?;
+        for (table, column) in [("execution_events", "execution_id"), ("approvals", "execution_id"), ("timers", "execution_id"), ("executions", "id")] {
+            let deleted = sqlx::query(&format!("DELETE FROM {} WHERE {} IN ({})", table, column, doomed))
+                .bind(max_age)
+                .bind(policy.max_per_workflow)
+                .bind(policy.failed_max_age_days.is_some())
+                .bind(failed_max_age)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+            match table {
+                "execution_events" => stats.events += deleted,
+                "executions" => stats.executions = deleted,
+                _ => {}
+            }
+        }
+
+        if let Some(sample) = policy.success_payload_sample {
+            stats.events += sqlx::query(
+                "DELETE FROM execution_events WHERE id IN (
+                    SELECT id FROM (
+                        SELECT ev.id, ROW_NUMBER() OVER (
+                            PARTITION BY ev.execution_id, ev.data->'EdgeData'->>'from', ev.data->'EdgeData'->>'to'
+                            ORDER BY ev.created_at, ev.seq
+                        ) AS rn
+                        FROM execution_events ev JOIN executions ex ON ex.id = ev.execution_id
+                        WHERE ev.event_type = 'EdgeData' AND ex.status = 'completed'
+                    ) ranked
+                    WHERE rn > $1
+                )"
+            )
+            .bind(sample)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        }
+
+        tx.commit().await?;
+        Ok(stats)
+    }
+
+    async fn create_api_token(&self, token: &ApiToken) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO api_tokens (id, user_id, name, kind, token_hash, created_at, expires_at) VALUES ($1, $2, $3, $4, $5, $6, $7)"
+        )
+        .bind(token.id)
+        .bind(token.user_id)
+        .bind(&token.name)
+        .bind(&token.kind)
+        .bind(&token.token_hash)
+        .bind(token.created_at)
+        .bind(token.expires_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_api_token_by_hash(&self, token_hash: &str) -> Result<Option<ApiToken>> {
+        let row = sqlx::query("SELECT id, user_id, name, kind, token_hash, created_at, expires_at FROM api_tokens WHERE token_hash = $1")
+            .bind(token_hash)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.as_ref().map(api_token_from_row))
+    }
+
+    async fn list_api_tokens(&self, user_id: Uuid) -> Result<Vec<ApiToken>> {
+        let rows = sqlx::query("SELECT id, user_id, name, kind, token_hash, created_at, expires_at FROM api_tokens WHERE user_id = $1 ORDER BY created_at")
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.iter().map(api_token_from_row).collect())
+    }
+
+    async fn delete_api_token(&self, user_id: Uuid, id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM api_tokens WHERE id = $1 AND user_id = $2")
+            .bind(id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn log_audit(&self, entry: &AuditEntry) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO audit_log (id, team_id, actor_id, action, target, diff, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7)"
+        )
+        .bind(entry.id)
+        .bind(entry.team_id)
+        .bind(entry.actor_id)
+        .bind(&entry.action)
+        .bind(&entry.target)
+        .bind(&entry.diff)
+        .bind(entry.created_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn list_audit(&self, team_id: Uuid, limit: i64, offset: i64) -> Result<Vec<AuditEntry>> {
+        let rows = sqlx::query(
+            "SELECT id, team_id, actor_id, action, target, diff, created_at FROM audit_log
+             WHERE team_id = $1 ORDER BY created_at DESC, seq DESC LIMIT $2 OFFSET $3"
+        )
+        .bind(team_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(audit_entry_from_row).collect())
+    }
 }
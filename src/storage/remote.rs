@@ -1,16 +1,31 @@
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use serde_json::Value;
 use uuid::Uuid;
 use anyhow::{Result, anyhow};
-use crate::storage::{Storage, User, Team, Role, TeamMember, WorkflowEntity, Credential};
-use reqwest::Client;
-use serde::Serialize;
+use crate::schema::ExecutionEvent;
+use crate::server::{CreateExecutionRequest, ErrorBody, ResolveApprovalRequest, RollbackRequest, SaveWorkflowRequest, SaveWorkflowResponse, UpdateCredentialRequest, UpdateExecutionRequest};
+use crate::storage::{Storage, User, Team, Role, TeamMember, WorkflowEntity, WorkflowVersion, Credential, ApiToken, AuditEntry};
+use crate::storage::{ApprovalRecord, EventRecord, ExecutionFilter, ExecutionRecord, PruneStats, QueuedExecution, RetentionPolicy, SchemaStatus, TimerRecord};
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 
+/// `Storage` through the HTTP API of a RustFlow server, as the signed-in user.
+/// Methods that only the server itself needs (the execution queue, timers, leadership,
+/// pruning) are not available remotely.
 pub struct RemoteStorage {
     base_url: String,
     client: Client,
-    #[allow(dead_code)]
-    token: Option<String>, // For future auth
+    token: Option<String>, // Sent as bearer token
+}
+
+/// Response of `POST /api/auth/login` and `POST /api/auth/tokens`.
+#[derive(Debug, Deserialize)]
+pub struct IssuedToken {
+    pub token: String,
+    pub info: ApiToken,
+    pub user: User,
 }
 
 impl RemoteStorage {
@@ -21,15 +36,117 @@ impl RemoteStorage {
             token: None,
         }
     }
-    
-    // Helper to add auth header if token exists
-    // fn auth(&self, req: reqwest::RequestBuilder) -> reqwest::RequestBuilder { ... }
+
+    /// Authenticates every request with `token` (a session or personal API token).
+    pub fn with_token(mut self, token: Option<String>) -> Self {
+        self.token = token;
+        self
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    fn request(&self, method: Method, url: &str) -> RequestBuilder {
+        let req = self.client.request(method, url);
+        match &self.token {
+            Some(token) => req.bearer_auth(token),
+            None => req,
+        }
+    }
+
+    /// Verifies the password on the server and starts a session.
+    pub async fn login(&self, username: &str, password: &str) -> Result<IssuedToken> {
+        let resp = self.client.post(self.url("/api/auth/login"))
+            .json(&serde_json::json!({ "username": username, "password": password }))
+            .send()
+            .await?;
+        json(resp, "log in").await
+    }
+
+    /// Ends the session of the current token.
+    pub async fn logout(&self) -> Result<()> {
+        let resp = self.request(Method::POST, &self.url("/api/auth/logout")).send().await?;
+        ok(resp, "log out").await
+    }
+
+    /// Creates a personal API token for the current user.
+    pub async fn create_token(&self, name: &str, expires_in_days: Option<i64>) -> Result<IssuedToken> {
+        let resp = self.request(Method::POST, &self.url("/api/auth/tokens"))
+            .json(&serde_json::json!({ "name": name, "expires_in_days": expires_in_days }))
+            .send()
+            .await?;
+        json(resp, "create token").await
+    }
+
+    /// Restores an earlier version of a workflow as its next version; returns the new version.
+    /// Unlike `storage::rollback_workflow`, the server records it as a rollback.
+    pub async fn rollback_workflow(&self, workflow_id: Uuid, version: i32) -> Result<i32> {
+        let resp = self.request(Method::POST, &self.url(&format!("/api/workflows/{}/rollback", workflow_id)))
+            .json(&RollbackRequest { version })
+            .send()
+            .await?;
+        Ok(json::<SaveWorkflowResponse>(resp, "roll back workflow").await?.version)
+    }
+
+    /// A credential's fields with secrets masked; the server decrypts them and audits the reveal.
+    pub async fn reveal_credential(&self, id: Uuid) -> Result<Option<Value>> {
+        let resp = self.request(Method::GET, &self.url(&format!("/api/credentials/{}/reveal", id))).send().await?;
+        optional(resp, "reveal credential").await
+    }
+
+    /// `/api/kv/{key}`, with the key escaped.
+    fn kv_url(&self, key: &str) -> Result<String> {
+        let mut url = reqwest::Url::parse(&self.url("/api/kv"))?;
+        url.path_segments_mut().map_err(|_| anyhow!("Invalid server URL {}", self.base_url))?.push(key);
+        Ok(url.to_string())
+    }
+}
+
+/// The error of a failed request, with the server's message.
+async fn error_from(resp: Response, action: &str) -> anyhow::Error {
+    let status = resp.status();
+    match resp.json::<ErrorBody>().await {
+        Ok(body) => anyhow!("Failed to {}: {} ({})", action, body.error, status),
+        Err(_) => anyhow!("Failed to {}: {}", action, status),
+    }
+}
+
+/// The JSON body of a successful response.
+async fn json<T: DeserializeOwned>(resp: Response, action: &str) -> Result<T> {
+    if resp.status().is_success() {
+        Ok(resp.json::<T>().await?)
+    } else {
+        Err(error_from(resp, action).await)
+    }
+}
+
+/// Like `json`, with `None` for 404.
+async fn optional<T: DeserializeOwned>(resp: Response, action: &str) -> Result<Option<T>> {
+    if resp.status() == StatusCode::NOT_FOUND {
+        Ok(None)
+    } else {
+        json(resp, action).await.map(Some)
+    }
+}
+
+/// Checks that the request succeeded.
+async fn ok(resp: Response, action: &str) -> Result<()> {
+    if resp.status().is_success() {
+        Ok(())
+    } else {
+        Err(error_from(resp, action).await)
+    }
+}
+
+fn server_only(operation: &str) -> anyhow::Error {
+    anyhow!("{} is only available on the server itself, not through RemoteStorage", operation)
 }
 
 #[derive(Serialize)]
 struct CreateUserRequest {
     username: String,
-    password_hash: String,
+    password: String,
 }
 
 #[derive(Serialize)]
@@ -41,7 +158,7 @@ struct CreateTeamRequest {
 impl Storage for RemoteStorage {
     async fn init(&self) -> Result<()> {
         // Check if server is reachable
-        let resp = self.client.get(format!("{}/health", self.base_url)).send().await?;
+        let resp = self.client.get(self.url("/health")).send().await?;
         if resp.status().is_success() {
             Ok(())
         } else {
@@ -49,210 +166,384 @@ impl Storage for RemoteStorage {
         }
     }
 
+    async fn schema_status(&self) -> Result<SchemaStatus> {
+        Err(server_only("schema_status"))
+    }
+
     // User
     async fn create_user(&self, username: &str, password_hash: &str) -> Result<User> {
-        let url = format!("{}/api/auth/register", self.base_url);
-        let resp = self.client.post(&url)
-            .json(&CreateUserRequest { 
-                username: username.to_string(), 
-                password_hash: password_hash.to_string() 
+        // Signed in, so team admins can register accounts for new members
+        let resp = self.request(Method::POST, &self.url("/api/auth/register"))
+            .json(&CreateUserRequest {
+                username: username.to_string(),
+                password: password_hash.to_string()
             })
             .send()
             .await?;
-            
-        if resp.status().is_success() {
-            Ok(resp.json::<User>().await?)
-        } else {
-            let err_text = resp.text().await?;
-            Err(anyhow!("Failed to register: {}", err_text))
-        }
+        json(resp, "register").await
     }
 
-    async fn get_user(&self, _id: Uuid) -> Result<Option<User>> {
-        // Not strictly needed for CLI flow yet, but good to have
-        Err(anyhow!("Not implemented for remote storage"))
+    async fn get_user(&self, id: Uuid) -> Result<Option<User>> {
+        let resp = self.request(Method::GET, &self.url(&format!("/api/users/{}", id))).send().await?;
+        optional(resp, "get user").await
     }
 
     async fn get_user_by_username(&self, username: &str) -> Result<Option<User>> {
-        // Used for login verification in CLI, but for remote, we should probably just hit a login endpoint
-        // that returns the user. 
-        // However, the CLI `Login` command currently fetches user then verifies hash locally.
-        // We need to change that flow or support this endpoint.
-        // Let's implement a specific endpoint for fetching user details if needed, 
-        // or better, the CLI should just call `login` on the server.
-        
-        // For now, let's assume we add an endpoint to get user by username (publicly safe? maybe not).
-        // Better approach: The `Login` command in CLI needs to be refactored to delegate authentication to the storage.
-        // But `Storage` trait has `get_user_by_username`.
-        
-        // Let's implement a `POST /api/auth/lookup` or similar on server.
-        let url = format!("{}/api/auth/lookup?username={}", self.base_url, username);
-        let resp = self.client.get(&url).send().await?;
-        
-        if resp.status() == reqwest::StatusCode::NOT_FOUND {
-            Ok(None)
-        } else if resp.status().is_success() {
-            Ok(Some(resp.json::<User>().await?))
-        } else {
-            Err(anyhow!("Failed to lookup user"))
-        }
+        // Without the password hash; logging in goes through `login`
+        let resp = self.request(Method::GET, &self.url("/api/auth/lookup")).query(&[("username", username)]).send().await?;
+        optional(resp, "look up user").await
+    }
+
+    async fn count_users(&self) -> Result<i64> {
+        Err(server_only("count_users"))
     }
 
     // Team
     async fn create_team(&self, name: &str) -> Result<Team> {
-        let url = format!("{}/api/teams", self.base_url);
-        let resp = self.client.post(&url)
+        let resp = self.request(Method::POST, &self.url("/api/teams"))
             .json(&CreateTeamRequest { name: name.to_string() })
             .send()
             .await?;
-            
-        if resp.status().is_success() {
-            Ok(resp.json::<Team>().await?)
-        } else {
-            Err(anyhow!("Failed to create team: {}", resp.text().await?))
-        }
+        json(resp, "create team").await
     }
 
-    async fn get_team(&self, _id: Uuid) -> Result<Option<Team>> {
-        Err(anyhow!("Not implemented"))
+    async fn get_team(&self, id: Uuid) -> Result<Option<Team>> {
+        let resp = self.request(Method::GET, &self.url(&format!("/api/teams/{}", id))).send().await?;
+        optional(resp, "get team").await
     }
 
-    async fn get_team_by_name(&self, _name: &str) -> Result<Option<Team>> {
-        Err(anyhow!("Not implemented"))
+    async fn get_team_by_name(&self, name: &str) -> Result<Option<Team>> {
+        // Only teams the user belongs to
+        let resp = self.request(Method::GET, &self.url("/api/teams")).query(&[("name", name)]).send().await?;
+        optional(resp, "find team").await
     }
 
     async fn list_teams_for_user(&self, user_id: Uuid) -> Result<Vec<(Team, Role)>> {
-        let url = format!("{}/api/users/{}/teams", self.base_url, user_id);
-        let resp = self.client.get(&url).send().await?;
-        if resp.status().is_success() {
-            Ok(resp.json::<Vec<(Team, Role)>>().await?)
-        } else {
-            Err(anyhow!("Failed to list teams"))
-        }
+        let resp = self.request(Method::GET, &self.url(&format!("/api/users/{}/teams", user_id))).send().await?;
+        json(resp, "list teams").await
     }
 
     // Team Members
     async fn add_team_member(&self, team_id: Uuid, user_id: Uuid, role: Role) -> Result<()> {
-        let url = format!("{}/api/teams/{}/members", self.base_url, team_id);
         #[derive(Serialize)]
         struct AddMemberReq { user_id: Uuid, role: String }
-        
-        let resp = self.client.post(&url)
+
+        let resp = self.request(Method::POST, &self.url(&format!("/api/teams/{}/members", team_id)))
             .json(&AddMemberReq { user_id, role: role.as_str().to_string() })
             .send()
             .await?;
-            
-        if resp.status().is_success() {
-            Ok(())
-        } else {
-            Err(anyhow!("Failed to add member"))
-        }
+        ok(resp, "add member").await
     }
 
     async fn get_team_members(&self, team_id: Uuid) -> Result<Vec<TeamMember>> {
-        let url = format!("{}/api/teams/{}/members", self.base_url, team_id);
-        let resp = self.client.get(&url).send().await?;
-        if resp.status().is_success() {
-            Ok(resp.json::<Vec<TeamMember>>().await?)
-        } else {
-            Err(anyhow!("Failed to get members"))
-        }
+        let resp = self.request(Method::GET, &self.url(&format!("/api/teams/{}/members", team_id))).send().await?;
+        json(resp, "get members").await
     }
 
     // Workflow
-    async fn save_workflow(&self, workflow: &WorkflowEntity) -> Result<()> {
-        let url = format!("{}/api/workflows", self.base_url);
-        let resp = self.client.post(&url)
-            .json(workflow)
+    /// The server records the signed-in user as author, whatever `author_id` says.
+    async fn save_workflow(&self, workflow: &WorkflowEntity, _author_id: Option<Uuid>, message: Option<&str>) -> Result<i32> {
+        let resp = self.request(Method::POST, &self.url("/api/workflows"))
+            .json(&SaveWorkflowRequest { workflow: workflow.clone(), message: message.map(str::to_string) })
             .send()
             .await?;
-            
-        if resp.status().is_success() {
-            Ok(())
-        } else {
-            Err(anyhow!("Failed to save workflow: {}", resp.text().await?))
+        Ok(json::<SaveWorkflowResponse>(resp, "save workflow").await?.version)
+    }
+
+    async fn set_workflow_active(&self, id: Uuid, active: bool) -> Result<bool> {
+        let action = if active { "enable" } else { "disable" };
+        let resp = self.request(Method::POST, &self.url(&format!("/api/workflows/{}/{}", id, action))).send().await?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Ok(false);
+        }
+        ok(resp, &format!("{} workflow", action)).await.map(|_| true)
+    }
+
+    async fn delete_workflow(&self, id: Uuid) -> Result<bool> {
+        let resp = self.request(Method::DELETE, &self.url(&format!("/api/workflows/{}", id))).send().await?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Ok(false);
         }
+        ok(resp, "delete workflow").await.map(|_| true)
     }
 
-    async fn get_workflow(&self, _id: Uuid) -> Result<Option<WorkflowEntity>> {
-        Err(anyhow!("Not implemented"))
+    async fn list_workflow_versions(&self, workflow_id: Uuid) -> Result<Vec<WorkflowVersion>> {
+        let resp = self.request(Method::GET, &self.url(&format!("/api/workflows/{}/versions", workflow_id))).send().await?;
+        json(resp, "list workflow versions").await
+    }
+
+    async fn get_workflow_version(&self, workflow_id: Uuid, version: i32) -> Result<Option<WorkflowVersion>> {
+        let resp = self.request(Method::GET, &self.url(&format!("/api/workflows/{}/versions/{}", workflow_id, version))).send().await?;
+        optional(resp, "get workflow version").await
+    }
+
+    async fn get_workflow(&self, id: Uuid) -> Result<Option<WorkflowEntity>> {
+        let resp = self.request(Method::GET, &self.url(&format!("/api/workflows/{}", id))).send().await?;
+        optional(resp, "get workflow").await
     }
 
     async fn list_workflows(&self, team_id: Uuid) -> Result<Vec<WorkflowEntity>> {
-        let url = format!("{}/api/teams/{}/workflows", self.base_url, team_id);
-        let resp = self.client.get(&url).send().await?;
-        if resp.status().is_success() {
-            Ok(resp.json::<Vec<WorkflowEntity>>().await?)
-        } else {
-            Err(anyhow!("Failed to list workflows"))
-        }
+        let resp = self.request(Method::GET, &self.url(&format!("/api/teams/{}/workflows", team_id))).send().await?;
+        json(resp, "list workflows").await
     }
 
     // Key-Value
-    async fn set_kv(&self, _key: &str, _value: &Value) -> Result<()> {
-        Err(anyhow!("Not implemented"))
+    async fn set_kv(&self, key: &str, value: &Value) -> Result<()> {
+        let resp = self.request(Method::PUT, &self.kv_url(key)?).json(value).send().await?;
+        ok(resp, "set key").await
     }
 
-    async fn get_kv(&self, _key: &str) -> Result<Option<Value>> {
-        Err(anyhow!("Not implemented"))
+    async fn get_kv(&self, key: &str) -> Result<Option<Value>> {
+        let resp = self.request(Method::GET, &self.kv_url(key)?).send().await?;
+        optional(resp, "get key").await
     }
 
     // Credentials
-    async fn create_credential(&self, name: &str, credential_type: &str, data: &str, team_id: Uuid) -> Result<Credential> {
-        let url = format!("{}/api/credentials", self.base_url);
+    async fn create_credential(&self, name: &str, credential_type: &str, data: &Value, team_id: Uuid) -> Result<Credential> {
         #[derive(Serialize)]
-        struct CreateCredReq { name: String, credential_type: String, data: String, team_id: Uuid }
-        
-        let resp = self.client.post(&url)
-            .json(&CreateCredReq { 
-                name: name.to_string(), 
-                credential_type: credential_type.to_string(), 
-                data: data.to_string(), 
-                team_id 
+        struct CreateCredReq { name: String, credential_type: String, data: Value, team_id: Uuid }
+
+        let resp = self.request(Method::POST, &self.url("/api/credentials"))
+            .json(&CreateCredReq {
+                name: name.to_string(),
+                credential_type: credential_type.to_string(),
+                data: data.clone(),
+                team_id
             })
             .send()
             .await?;
-            
-        if resp.status().is_success() {
-            Ok(resp.json::<Credential>().await?)
-        } else {
-            Err(anyhow!("Failed to create credential"))
-        }
+        json(resp, "create credential").await
     }
 
-    async fn get_credential(&self, _id: Uuid) -> Result<Option<Credential>> {
-        Err(anyhow!("Not implemented"))
+    async fn get_credential(&self, id: Uuid) -> Result<Option<Credential>> {
+        let resp = self.request(Method::GET, &self.url(&format!("/api/credentials/{}", id))).send().await?;
+        optional(resp, "get credential").await
     }
 
-
     async fn list_credentials(&self, team_id: Uuid) -> Result<Vec<Credential>> {
-        let url = format!("{}/api/teams/{}/credentials", self.base_url, team_id);
-        let resp = self.client.get(&url).send().await?;
-        if resp.status().is_success() {
-            Ok(resp.json::<Vec<Credential>>().await?)
-        } else {
-            Err(anyhow!("Failed to list credentials"))
+        let resp = self.request(Method::GET, &self.url(&format!("/api/teams/{}/credentials", team_id))).send().await?;
+        json(resp, "list credentials").await
+    }
+
+    async fn update_credential(&self, id: Uuid, name: Option<&str>, data: Option<&Value>) -> Result<bool> {
+        let resp = self.request(Method::PUT, &self.url(&format!("/api/credentials/{}", id)))
+            .json(&UpdateCredentialRequest { name: name.map(String::from), data: data.cloned() })
+            .send()
+            .await?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Ok(false);
         }
+        ok(resp, "update credential").await.map(|_| true)
+    }
+
+    async fn delete_credential(&self, id: Uuid, force: bool) -> Result<bool> {
+        let resp = self.request(Method::DELETE, &self.url(&format!("/api/credentials/{}", id)))
+            .query(&[("force", force)])
+            .send()
+            .await?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Ok(false);
+        }
+        ok(resp, "delete credential").await.map(|_| true)
+    }
+
+    async fn list_all_credentials(&self) -> Result<Vec<Credential>> {
+        Err(server_only("list_all_credentials"))
+    }
+
+    async fn replace_credential_data(&self, _id: Uuid, _old_data: &str, _new_data: &str) -> Result<bool> {
+        Err(server_only("replace_credential_data"))
     }
 
     // Executions
-    async fn create_execution(&self, _id: Uuid, _workflow_id: Option<Uuid>, _status: &str) -> Result<Uuid> {
-        Err(anyhow!("Not implemented for RemoteStorage"))
+    async fn create_execution(&self, id: Uuid, workflow_id: Option<Uuid>, workflow_version: Option<i32>, team_id: Option<Uuid>, status: &str) -> Result<Uuid> {
+        let resp = self.request(Method::POST, &self.url("/api/executions"))
+            .json(&CreateExecutionRequest { id, workflow_id, workflow_version, team_id, status: status.to_string() })
+            .send()
+            .await?;
+        json(resp, "create execution").await
+    }
+
+    async fn update_execution(&self, id: Uuid, status: &str, finished_at: Option<DateTime<Utc>>, error: Option<String>) -> Result<()> {
+        let resp = self.request(Method::PATCH, &self.url(&format!("/api/executions/{}", id)))
+            .json(&UpdateExecutionRequest { status: status.to_string(), finished_at, error })
+            .send()
+            .await?;
+        ok(resp, "update execution").await
+    }
+
+    async fn log_execution_event(&self, execution_id: Uuid, event: &ExecutionEvent) -> Result<()> {
+        let resp = self.request(Method::POST, &self.url(&format!("/api/executions/{}/logs", execution_id)))
+            .json(event)
+            .send()
+            .await?;
+        ok(resp, "log execution event").await
+    }
+
+    async fn get_execution(&self, id: Uuid) -> Result<Option<ExecutionRecord>> {
+        let resp = self.request(Method::GET, &self.url(&format!("/api/executions/{}", id))).send().await?;
+        optional(resp, "get execution").await
+    }
+
+    async fn get_execution_logs(&self, id: Uuid) -> Result<Vec<ExecutionEvent>> {
+        let resp = self.request(Method::GET, &self.url(&format!("/api/executions/{}/logs", id))).send().await?;
+        json(resp, "get execution logs").await
+    }
+
+    async fn get_execution_definition(&self, id: Uuid) -> Result<Option<Value>> {
+        let resp = self.request(Method::GET, &self.url(&format!("/api/executions/{}/definition", id))).send().await?;
+        json(resp, "get execution definition").await
+    }
+
+    async fn prune_executions(&self, _policy: &RetentionPolicy, _now: DateTime<Utc>) -> Result<PruneStats> {
+        // The server prunes per its RUSTFLOW_RETENTION_* settings
+        Err(server_only("prune_executions"))
+    }
+
+    async fn list_executions(&self, filter: &ExecutionFilter) -> Result<Vec<ExecutionRecord>> {
+        let resp = self.request(Method::GET, &self.url("/api/executions")).query(filter).send().await?;
+        json(resp, "list executions").await
+    }
+
+    // Events
+    async fn publish_event(&self, team_id: Uuid, name: &str, payload: &Value) -> Result<EventRecord> {
+        let resp = self.request(Method::POST, &self.url(&format!("/api/teams/{}/events", team_id)))
+            .json(&serde_json::json!({ "name": name, "payload": payload }))
+            .send()
+            .await?;
+        json(resp, "publish event").await
+    }
+
+    async fn list_pending_events(&self, _limit: i64, _offset: i64) -> Result<Vec<EventRecord>> {
+        Err(server_only("list_pending_events"))
+    }
+
+    async fn mark_event_delivered(&self, _id: Uuid) -> Result<()> {
+        Err(server_only("mark_event_delivered"))
+    }
+
+    async fn record_event_delivery(&self, _event_id: Uuid, _workflow_id: Uuid) -> Result<()> {
+        Err(server_only("record_event_delivery"))
+    }
+
+    async fn list_event_deliveries(&self, _event_id: Uuid) -> Result<Vec<Uuid>> {
+        Err(server_only("list_event_deliveries"))
+    }
+
+    // Execution queue
+    async fn enqueue_execution(&self, _execution: &QueuedExecution) -> Result<()> {
+        // Runs are queued through `POST /api/run` and `POST /api/workflows/{id}/run`
+        Err(server_only("enqueue_execution"))
+    }
+
+    async fn claim_execution(&self, _worker_id: &str) -> Result<Option<QueuedExecution>> {
+        Err(server_only("claim_execution"))
+    }
+
+    async fn heartbeat_executions(&self, _worker_id: &str, _ids: &[Uuid]) -> Result<()> {
+        Err(server_only("heartbeat_executions"))
+    }
+
+    async fn recover_expired_executions(&self, _cutoff: DateTime<Utc>) -> Result<u64> {
+        Err(server_only("recover_expired_executions"))
+    }
+
+    async fn resume_execution(&self, _id: Uuid, _node_id: &str, _inputs: &Value) -> Result<bool> {
+        Err(server_only("resume_execution"))
     }
 
-    async fn update_execution(&self, _id: Uuid, _status: &str, _finished_at: Option<chrono::DateTime<chrono::Utc>>, _error: Option<String>) -> Result<()> {
-        Err(anyhow!("Not implemented for RemoteStorage"))
+    async fn try_acquire_leadership(&self, _name: &str) -> Result<bool> {
+        Err(server_only("try_acquire_leadership"))
     }
 
-    async fn log_execution_event(&self, _execution_id: Uuid, _event: &crate::schema::ExecutionEvent) -> Result<()> {
-        Err(anyhow!("Not implemented for RemoteStorage"))
+    // Approvals
+    async fn create_approval(&self, approval: &ApprovalRecord) -> Result<()> {
+        let resp = self.request(Method::POST, &self.url("/api/approvals")).json(approval).send().await?;
+        ok(resp, "create approval").await
     }
 
-    async fn get_execution(&self, _id: Uuid) -> Result<Option<crate::storage::ExecutionRecord>> {
-        Err(anyhow!("Not implemented for RemoteStorage"))
+    async fn get_approval(&self, token: &str) -> Result<Option<ApprovalRecord>> {
+        let resp = self.request(Method::GET, &self.url(&format!("/api/approvals/{}", token))).send().await?;
+        optional(resp, "get approval").await
     }
 
-    async fn get_execution_logs(&self, _id: Uuid) -> Result<Vec<crate::schema::ExecutionEvent>> {
-        Err(anyhow!("Not implemented for RemoteStorage"))
+    async fn resolve_approval(&self, token: &str, status: &str, decision: Option<&Value>) -> Result<bool> {
+        let resp = self.request(Method::POST, &self.url(&format!("/api/approvals/{}/resolve", token)))
+            .json(&ResolveApprovalRequest { status: status.to_string(), decision: decision.cloned() })
+            .send()
+            .await?;
+        json(resp, "resolve approval").await
+    }
+
+    async fn mark_approval_delivered(&self, _token: &str) -> Result<bool> {
+        // Approvals are only suspended on in the server's execution queue
+        Err(server_only("mark_approval_delivered"))
+    }
+
+    async fn count_open_approvals(&self, _execution_id: Uuid) -> Result<i64> {
+        Err(server_only("count_open_approvals"))
+    }
+
+    // Durable timers
+    async fn create_timer(&self, _timer: &TimerRecord) -> Result<()> {
+        // Only runs executed by the server suspend on durable delays
+        Err(server_only("create_timer"))
+    }
+
+    async fn list_due_timers(&self, _now: DateTime<Utc>) -> Result<Vec<TimerRecord>> {
+        Err(server_only("list_due_timers"))
+    }
+
+    async fn update_timer_status(&self, _ids: &[Uuid], _status: &str) -> Result<()> {
+        Err(server_only("update_timer_status"))
+    }
+
+    async fn count_waiting_timers(&self, _execution_id: Uuid) -> Result<i64> {
+        Err(server_only("count_waiting_timers"))
+    }
+
+    // API tokens
+    async fn create_api_token(&self, _token: &ApiToken) -> Result<()> {
+        // Tokens are issued by the server, see `create_token`
+        Err(server_only("create_api_token"))
+    }
+
+    async fn get_api_token_by_hash(&self, _token_hash: &str) -> Result<Option<ApiToken>> {
+        Err(server_only("get_api_token_by_hash"))
+    }
+
+    async fn list_api_tokens(&self, _user_id: Uuid) -> Result<Vec<ApiToken>> {
+        // Always the tokens of the authenticated user
+        let resp = self.request(Method::GET, &self.url("/api/auth/tokens")).send().await?;
+        json(resp, "list tokens").await
+    }
+
+    async fn delete_api_token(&self, _user_id: Uuid, id: Uuid) -> Result<bool> {
+        let resp = self.request(Method::DELETE, &self.url(&format!("/api/auth/tokens/{}", id))).send().await?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Ok(false);
+        }
+        ok(resp, "delete token").await.map(|_| true)
+    }
+
+    // Audit log
+    async fn log_audit(&self, entry: &AuditEntry) -> Result<()> {
+        // The server recorded the operations it performed itself; the actor is the token's user
+        if !crate::audit::CLIENT_ACTIONS.contains(&entry.action.as_str()) {
+            return Ok(());
+        }
+        let resp = self.request(Method::POST, &self.url(&format!("/api/teams/{}/audit", entry.team_id)))
+            .json(&serde_json::json!({ "action": entry.action, "target": entry.target, "diff": entry.diff }))
+            .send()
+            .await?;
+        ok(resp, "record audit entry").await
+    }
+
+    async fn list_audit(&self, team_id: Uuid, limit: i64, offset: i64) -> Result<Vec<AuditEntry>> {
+        let resp = self.request(Method::GET, &self.url(&format!("/api/teams/{}/audit", team_id)))
+            .query(&[("limit", limit), ("offset", offset)])
+            .send()
+            .await?;
+        json(resp, "list audit entries").await
     }
 }
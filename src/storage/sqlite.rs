@@ -2,9 +2,9 @@ use async_trait::async_trait;
 use sqlx::{sqlite::SqlitePool, Row};
 use uuid::Uuid;
 use serde_json::Value;
-use anyhow::{Result, anyhow};
+use anyhow::{Result, anyhow, bail};
 use chrono::{Utc, DateTime};
-use super::{Storage, Team, User, TeamMember, Role, WorkflowEntity, Credential, ExecutionRecord};
+use super::{Storage, Team, User, TeamMember, Role, WorkflowEntity, WorkflowVersion, Credential, ExecutionRecord, ExecutionFilter, RetentionPolicy, PruneStats, ApiToken, AuditEntry, EventRecord, QueuedExecution, ApprovalRecord, TimerRecord, SchemaStatus, migrations};
 use crate::schema::ExecutionEvent;
 use argon2::{
     password_hash::{
@@ -25,79 +25,102 @@ impl SqliteStorage {
     }
 }
 
+fn execution_from_row(row: &sqlx::sqlite::SqliteRow) -> Result<ExecutionRecord> {
+    let wf_id_db: Option<String> = row.get("workflow_id");
+    let wf_id = if let Some(s) = wf_id_db { Some(Uuid::parse_str(&s)?) } else { None };
+
+    let team_id: Option<String> = row.get("team_id");
+
+    let fin_at_db: Option<String> = row.get("finished_at");
+    let fin_at = if let Some(s) = fin_at_db { Some(DateTime::parse_from_rfc3339(&s)?.with_timezone(&Utc)) } else { None };
+
+    Ok(ExecutionRecord {
+        id: Uuid::parse_str(row.get("id"))?,
+        workflow_id: wf_id,
+        workflow_version: row.get("workflow_version"),
+        team_id: team_id.map(|t| Uuid::parse_str(&t)).transpose()?,
+        status: row.get("status"),
+        started_at: DateTime::parse_from_rfc3339(row.get("started_at"))?.with_timezone(&Utc),
+        finished_at: fin_at,
+        error: row.get("error"),
+    })
+}
+
+fn api_token_from_row(row: &sqlx::sqlite::SqliteRow) -> Result<ApiToken> {
+    let expires_at: Option<String> = row.get("expires_at");
+    Ok(ApiToken {
+        id: Uuid::parse_str(row.get("id"))?,
+        user_id: Uuid::parse_str(row.get("user_id"))?,
+        name: row.get("name"),
+        kind: row.get("kind"),
+        token_hash: row.get("token_hash"),
+        created_at: DateTime::parse_from_rfc3339(row.get("created_at"))?.with_timezone(&Utc),
+        expires_at: expires_at.map(|t| DateTime::parse_from_rfc3339(&t).map(|t| t.with_timezone(&Utc))).transpose()?,
+    })
+}
+
+fn audit_entry_from_row(row: &sqlx::sqlite::SqliteRow) -> Result<AuditEntry> {
+    let actor_id: Option<String> = row.get("actor_id");
+    let diff: Option<String> = row.get("diff");
+    Ok(AuditEntry {
+        id: Uuid::parse_str(row.get("id"))?,
+        team_id: Uuid::parse_str(row.get("team_id"))?,
+        actor_id: actor_id.map(|a| Uuid::parse_str(&a)).transpose()?,
+        action: row.get("action"),
+        target: row.get("target"),
+        diff: diff.map(|d| serde_json::from_str(&d)).transpose()?,
+        created_at: DateTime::parse_from_rfc3339(row.get("created_at"))?.with_timezone(&Utc),
+    })
+}
+
+fn credential_from_row(row: &sqlx::sqlite::SqliteRow) -> Result<Credential> {
+    Ok(Credential {
+        id: Uuid::parse_str(row.get("id"))?,
+        account_id: Uuid::parse_str(row.get("account_id"))?,
+        name: row.get("name"),
+        credential_type: row.get("type"),
+        data: row.get("data"),
+        created_at: DateTime::parse_from_rfc3339(row.get("created_at"))?.with_timezone(&Utc),
+    })
+}
+
+fn workflow_from_row(row: &sqlx::sqlite::SqliteRow) -> Result<WorkflowEntity> {
+    let deleted_at: Option<String> = row.get("deleted_at");
+    Ok(WorkflowEntity {
+        id: Uuid::parse_str(row.get("id"))?,
+        account_id: Uuid::parse_str(row.get("account_id"))?,
+        name: row.get("name"),
+        definition: serde_json::from_str(row.get("definition"))?,
+        created_at: DateTime::parse_from_rfc3339(row.get("created_at"))?.with_timezone(&Utc),
+        version: row.get("version"),
+        active: row.get("active"),
+        deleted_at: deleted_at.map(|t| DateTime::parse_from_rfc3339(&t).map(|t| t.with_timezone(&Utc))).transpose()?,
+    })
+}
+
+fn workflow_version_from_row(row: &sqlx::sqlite::SqliteRow) -> Result<WorkflowVersion> {
+    let author_id: Option<String> = row.get("author_id");
+    Ok(WorkflowVersion {
+        workflow_id: Uuid::parse_str(row.get("workflow_id"))?,
+        version: row.get("version"),
+        name: row.get("name"),
+        definition: serde_json::from_str(row.get("definition"))?,
+        author_id: author_id.map(|a| Uuid::parse_str(&a)).transpose()?,
+        message: row.get("message"),
+        created_at: DateTime::parse_from_rfc3339(row.get("created_at"))?.with_timezone(&Utc),
+    })
+}
+
 #[async_trait]
 impl Storage for SqliteStorage {
     async fn init(&self) -> Result<()> {
-        // Create tables if not exist
-        // Note: 'accounts' is renamed to 'teams' conceptually, but we'll use 'teams' table name.
-        // If 'accounts' exists, we might want to migrate, but for now we'll just create new tables.
-        // In a real scenario, we'd have a migration script.
-        
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS users (
-                id TEXT PRIMARY KEY,
-                username TEXT NOT NULL UNIQUE,
-                password_hash TEXT NOT NULL,
-                created_at TEXT NOT NULL
-            );
-            CREATE TABLE IF NOT EXISTS teams (
-                id TEXT PRIMARY KEY,
-                name TEXT NOT NULL UNIQUE,
-                created_at TEXT NOT NULL
-            );
-            CREATE TABLE IF NOT EXISTS team_members (
-                user_id TEXT NOT NULL,
-                team_id TEXT NOT NULL,
-                role TEXT NOT NULL,
-                joined_at TEXT NOT NULL,
-                PRIMARY KEY (user_id, team_id),
-                FOREIGN KEY(user_id) REFERENCES users(id),
-                FOREIGN KEY(team_id) REFERENCES teams(id)
-            );
-            CREATE TABLE IF NOT EXISTS workflows (
-                id TEXT PRIMARY KEY,
-                account_id TEXT NOT NULL, -- This is team_id
-                name TEXT NOT NULL,
-                definition TEXT NOT NULL,
-                created_at TEXT NOT NULL,
-                FOREIGN KEY(account_id) REFERENCES teams(id)
-            );
-            CREATE TABLE IF NOT EXISTS key_value (
-                key TEXT PRIMARY KEY,
-                value TEXT NOT NULL
-            );
-            CREATE TABLE IF NOT EXISTS credentials (
-                id TEXT PRIMARY KEY,
-                account_id TEXT NOT NULL, -- This is team_id
-                name TEXT NOT NULL,
-                type TEXT NOT NULL,
-                data TEXT NOT NULL,
-                created_at TEXT NOT NULL,
-                FOREIGN KEY(account_id) REFERENCES teams(id)
-            );
-            CREATE TABLE IF NOT EXISTS executions (
-                id TEXT PRIMARY KEY,
-                workflow_id TEXT, -- Nullable
-                status TEXT NOT NULL,
-                started_at TEXT NOT NULL,
-                finished_at TEXT,
-                error TEXT,
-                FOREIGN KEY(workflow_id) REFERENCES workflows(id)
-            );
-            CREATE TABLE IF NOT EXISTS execution_events (
-                id TEXT PRIMARY KEY,
-                execution_id TEXT NOT NULL,
-                event_type TEXT NOT NULL,
-                data TEXT NOT NULL,
-                created_at TEXT NOT NULL,
-                FOREIGN KEY(execution_id) REFERENCES executions(id)
-            );
-            "#
-        )
-        .execute(&self.pool)
-        .await?;
-        Ok(())
+        let mut conn = self.pool.acquire().await?;
+        migrations::run(&migrations::SQLITE, &mut *conn).await
+    }
+
+    async fn schema_status(&self) -> Result<SchemaStatus> {
+        let mut conn = self.pool.acquire().await?;
+        migrations::status(&migrations::SQLITE, &mut *conn).await
     }
 
     // User
@@ -165,6 +188,13 @@ impl Storage for SqliteStorage {
         }
     }
 
+    async fn count_users(&self) -> Result<i64> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count)
+    }
+
     // Team
     async fn create_team(&self, name: &str) -> Result<Team> {
         let id = Uuid::new_v4();
@@ -280,59 +310,121 @@ impl Storage for SqliteStorage {
     }
 
     // Workflow
-    async fn save_workflow(&self, workflow: &WorkflowEntity) -> Result<()> {
+    async fn save_workflow(&self, workflow: &WorkflowEntity, author_id: Option<Uuid>, message: Option<&str>) -> Result<i32> {
+        // IMMEDIATE: concurrent saves wait for each other instead of numbering the same version
+        let mut tx = self.pool.begin_with("BEGIN IMMEDIATE").await?;
+        let latest = sqlx::query("SELECT name, definition, version, deleted_at FROM workflows WHERE id = ?")
+            .bind(workflow.id.to_string())
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        let mut version = 1;
+        if let Some(row) = latest {
+            if row.get::<Option<String>, _>("deleted_at").is_some() {
+                bail!("Workflow {} was deleted", workflow.id);
+            }
+            let definition: Value = serde_json::from_str(row.get("definition"))?;
+            let current: i32 = row.get("version");
+            if row.get::<String, _>("name") == workflow.name && definition == workflow.definition {
+                return Ok(current);
+            }
+            version = current + 1;
+        }
+
+        let definition = serde_json::to_string(&workflow.definition)?;
         sqlx::query(
-            "INSERT INTO workflows (id, account_id, name, definition, created_at) VALUES (?, ?, ?, ?, ?)
-             ON CONFLICT(id) DO UPDATE SET name=excluded.name, definition=excluded.definition"
+            "INSERT INTO workflows (id, account_id, name, definition, created_at, version, active) VALUES (?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT(id) DO UPDATE SET name=excluded.name, definition=excluded.definition, version=excluded.version"
         )
         .bind(workflow.id.to_string())
         .bind(workflow.account_id.to_string())
         .bind(&workflow.name)
-        .bind(serde_json::to_string(&workflow.definition)?)
+        .bind(&definition)
         .bind(workflow.created_at.to_rfc3339())
-        .execute(&self.pool)
+        .bind(version)
+        .bind(workflow.active)
+        .execute(&mut *tx)
         .await?;
-        Ok(())
+
+        sqlx::query(
+            "INSERT INTO workflow_versions (workflow_id, version, name, definition, author_id, message, created_at) VALUES (?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(workflow.id.to_string())
+        .bind(version)
+        .bind(&workflow.name)
+        .bind(&definition)
+        .bind(author_id.map(|a| a.to_string()))
+        .bind(message)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(version)
     }
 
     async fn get_workflow(&self, id: Uuid) -> Result<Option<WorkflowEntity>> {
-        let row = sqlx::query("SELECT id, account_id, name, definition, created_at FROM workflows WHERE id = ?")
+        let row = sqlx::query("SELECT id, account_id, name, definition, created_at, version, active, deleted_at FROM workflows WHERE id = ?")
             .bind(id.to_string())
             .fetch_optional(&self.pool)
             .await?;
 
-        if let Some(row) = row {
-            let def_str: String = row.get("definition");
-            Ok(Some(WorkflowEntity {
-                id: Uuid::parse_str(row.get("id"))?,
-                account_id: Uuid::parse_str(row.get("account_id"))?,
-                name: row.get("name"),
-                definition: serde_json::from_str(&def_str)?,
-                created_at: DateTime::parse_from_rfc3339(row.get("created_at"))?.with_timezone(&Utc),
-            }))
-        } else {
-            Ok(None)
-        }
+        row.map(|row| workflow_from_row(&row)).transpose()
     }
 
     async fn list_workflows(&self, team_id: Uuid) -> Result<Vec<WorkflowEntity>> {
-        let rows = sqlx::query("SELECT id, account_id, name, definition, created_at FROM workflows WHERE account_id = ?")
-            .bind(team_id.to_string())
-            .fetch_all(&self.pool)
+        let rows = sqlx::query(
+            "SELECT id, account_id, name, definition, created_at, version, active, deleted_at FROM workflows
+             WHERE account_id = ? AND deleted_at IS NULL"
+        )
+        .bind(team_id.to_string())
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(workflow_from_row).collect()
+    }
+
+    async fn set_workflow_active(&self, id: Uuid, active: bool) -> Result<bool> {
+        let result = sqlx::query("UPDATE workflows SET active = ? WHERE id = ? AND deleted_at IS NULL")
+            .bind(active)
+            .bind(id.to_string())
+            .execute(&self.pool)
             .await?;
+        Ok(result.rows_affected() > 0)
+    }
 
-        let mut workflows = Vec::new();
-        for row in rows {
-            let def_str: String = row.get("definition");
-            workflows.push(WorkflowEntity {
-                id: Uuid::parse_str(row.get("id"))?,
-                account_id: Uuid::parse_str(row.get("account_id"))?,
-                name: row.get("name"),
-                definition: serde_json::from_str(&def_str)?,
-                created_at: DateTime::parse_from_rfc3339(row.get("created_at"))?.with_timezone(&Utc),
-            });
-        }
-        Ok(workflows)
+    async fn delete_workflow(&self, id: Uuid) -> Result<bool> {
+        let result = sqlx::query("UPDATE workflows SET deleted_at = ? WHERE id = ? AND deleted_at IS NULL")
+            .bind(Utc::now().to_rfc3339())
+            .bind(id.to_string())
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn list_workflow_versions(&self, workflow_id: Uuid) -> Result<Vec<WorkflowVersion>> {
+        let rows = sqlx::query(
+            "SELECT workflow_id, version, name, definition, author_id, message, created_at FROM workflow_versions
+             WHERE workflow_id = ? ORDER BY version DESC"
+        )
+        .bind(workflow_id.to_string())
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(workflow_version_from_row).collect()
+    }
+
+    async fn get_workflow_version(&self, workflow_id: Uuid, version: i32) -> Result<Option<WorkflowVersion>> {
+        let row = sqlx::query(
+            "SELECT workflow_id, version, name, definition, author_id, message, created_at FROM workflow_versions
+             WHERE workflow_id = ? AND version = ?"
+        )
+        .bind(workflow_id.to_string())
+        .bind(version)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(|row| workflow_version_from_row(&row)).transpose()
     }
 
     async fn set_kv(&self, key: &str, value: &Value) -> Result<()> {
@@ -361,10 +453,10 @@ impl Storage for SqliteStorage {
         }
     }
 
-    async fn create_credential(&self, name: &str, credential_type: &str, data: &str, team_id: Uuid) -> Result<Credential> {
+    async fn create_credential(&self, name: &str, credential_type: &str, data: &Value, team_id: Uuid) -> Result<Credential> {
         let id = Uuid::new_v4();
         let created_at = Utc::now();
-        let encrypted_data = super::encryption::encrypt(data)?;
+        let encrypted_data = super::encrypt_credential(credential_type, data)?;
 
         sqlx::query(
             "INSERT INTO credentials (id, account_id, name, type, data, created_at) VALUES (?, ?, ?, ?, ?, ?)"
@@ -394,18 +486,7 @@ impl Storage for SqliteStorage {
             .fetch_optional(&self.pool)
             .await?;
 
-        if let Some(row) = row {
-            Ok(Some(Credential {
-                id: Uuid::parse_str(row.get("id"))?,
-                account_id: Uuid::parse_str(row.get("account_id"))?,
-                name: row.get("name"),
-                credential_type: row.get("type"),
-                data: row.get("data"),
-                created_at: DateTime::parse_from_rfc3339(row.get("created_at"))?.with_timezone(&Utc),
-            }))
-        } else {
-            Ok(None)
-        }
+        row.as_ref().map(credential_from_row).transpose()
     }
 
     async fn list_credentials(&self, team_id: Uuid) -> Result<Vec<Credential>> {
@@ -414,30 +495,63 @@ impl Storage for SqliteStorage {
             .fetch_all(&self.pool)
             .await?;
 
-        let mut creds = Vec::new();
-        for row in rows {
-            creds.push(Credential {
-                id: Uuid::parse_str(row.get("id"))?,
-                account_id: Uuid::parse_str(row.get("account_id"))?,
-                name: row.get("name"),
-                credential_type: row.get("type"),
-                data: row.get("data"),
-                created_at: DateTime::parse_from_rfc3339(row.get("created_at"))?.with_timezone(&Utc),
-            });
+        rows.iter().map(credential_from_row).collect()
+    }
+
+    async fn update_credential(&self, id: Uuid, name: Option<&str>, data: Option<&Value>) -> Result<bool> {
+        let Some(credential) = self.get_credential(id).await? else {
+            return Ok(false);
+        };
+        let data = data.map(|d| super::encrypt_credential(&credential.credential_type, d)).transpose()?;
+        let result = sqlx::query("UPDATE credentials SET name = COALESCE(?, name), data = COALESCE(?, data) WHERE id = ?")
+            .bind(name)
+            .bind(data)
+            .bind(id.to_string())
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_credential(&self, id: Uuid, force: bool) -> Result<bool> {
+        if !force {
+            super::ensure_unused(self, id).await?;
         }
-        Ok(creds)
+        let result = sqlx::query("DELETE FROM credentials WHERE id = ?")
+            .bind(id.to_string())
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn list_all_credentials(&self) -> Result<Vec<Credential>> {
+        let rows = sqlx::query("SELECT id, account_id, name, type, data, created_at FROM credentials ORDER BY created_at")
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter().map(credential_from_row).collect()
+    }
+
+    async fn replace_credential_data(&self, id: Uuid, old_data: &str, new_data: &str) -> Result<bool> {
+        let result = sqlx::query("UPDATE credentials SET data = ? WHERE id = ? AND data = ?")
+            .bind(new_data)
+            .bind(id.to_string())
+            .bind(old_data)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
     }
 
     // Executions
-    async fn create_execution(&self, id: Uuid, workflow_id: Option<Uuid>, status: &str) -> Result<Uuid> {
+    async fn create_execution(&self, id: Uuid, workflow_id: Option<Uuid>, workflow_version: Option<i32>, team_id: Option<Uuid>, status: &str) -> Result<Uuid> {
         let started_at = Utc::now();
         let wf_id_str = workflow_id.map(|u| u.to_string());
         
         sqlx::query(
-            "INSERT INTO executions (id, workflow_id, status, started_at) VALUES (?, ?, ?, ?)"
+            "INSERT INTO executions (id, workflow_id, workflow_version, team_id, status, started_at) VALUES (?, ?, ?, ?, ?, ?)"
         )
         .bind(id.to_string())
         .bind(wf_id_str)
+        .bind(workflow_version)
+        .bind(team_id.map(|u| u.to_string()))
         .bind(status)
         .bind(started_at.to_rfc3339())
         .execute(&self.pool)
@@ -486,33 +600,56 @@ impl Storage for SqliteStorage {
     }
 
     async fn get_execution(&self, id: Uuid) -> Result<Option<ExecutionRecord>> {
-        let row = sqlx::query("SELECT id, workflow_id, status, started_at, finished_at, error FROM executions WHERE id = ?")
+        let row = sqlx::query("SELECT id, workflow_id, workflow_version, team_id, status, started_at, finished_at, error FROM executions WHERE id = ?")
             .bind(id.to_string())
             .fetch_optional(&self.pool)
             .await?;
 
-        if let Some(row) = row {
-            let wf_id_db: Option<String> = row.get("workflow_id");
-            let wf_id = if let Some(s) = wf_id_db { Some(Uuid::parse_str(&s)?) } else { None };
-            
-            let fin_at_db: Option<String> = row.get("finished_at");
-            let fin_at = if let Some(s) = fin_at_db { Some(DateTime::parse_from_rfc3339(&s)?.with_timezone(&Utc)) } else { None };
+        row.map(|row| execution_from_row(&row)).transpose()
+    }
 
-            Ok(Some(ExecutionRecord {
-                id: Uuid::parse_str(row.get("id"))?,
-                workflow_id: wf_id,
-                status: row.get("status"),
-                started_at: DateTime::parse_from_rfc3339(row.get("started_at"))?.with_timezone(&Utc),
-                finished_at: fin_at,
-                error: row.get("error"),
-            }))
-        } else {
-            Ok(None)
+    async fn list_executions(&self, filter: &ExecutionFilter) -> Result<Vec<ExecutionRecord>> {
+        let mut sql = "SELECT id, workflow_id, workflow_version, team_id, status, started_at, finished_at, error FROM executions WHERE 1 = 1".to_string();
+        if filter.workflow_id.is_some() {
+            sql.push_str(" AND workflow_id = ?");
+        }
+        if filter.team_id.is_some() {
+            sql.push_str(" AND (team_id = ? OR workflow_id IN (SELECT id FROM workflows WHERE account_id = ?))");
+        }
+        if filter.status.is_some() {
+            sql.push_str(" AND status = ?");
+        }
+        if filter.since.is_some() {
+            sql.push_str(" AND started_at >= ?");
+        }
+        if filter.until.is_some() {
+            sql.push_str(" AND started_at < ?");
+        }
+        sql.push_str(" ORDER BY started_at DESC LIMIT ? OFFSET ?");
+
+        let mut query = sqlx::query(&sql);
+        if let Some(wf_id) = filter.workflow_id {
+            query = query.bind(wf_id.to_string());
         }
+        if let Some(team_id) = filter.team_id {
+            query = query.bind(team_id.to_string()).bind(team_id.to_string());
+        }
+        if let Some(status) = &filter.status {
+            query = query.bind(status);
+        }
+        if let Some(since) = filter.since {
+            query = query.bind(since.to_rfc3339());
+        }
+        if let Some(until) = filter.until {
+            query = query.bind(until.to_rfc3339());
+        }
+        let rows = query.bind(filter.limit).bind(filter.offset).fetch_all(&self.pool).await?;
+
+        rows.iter().map(execution_from_row).collect()
     }
 
     async fn get_execution_logs(&self, id: Uuid) -> Result<Vec<ExecutionEvent>> {
-        let rows = sqlx::query("SELECT data FROM execution_events WHERE execution_id = ? ORDER BY created_at ASC")
+        let rows = sqlx::query("SELECT data FROM execution_events WHERE execution_id = ? ORDER BY created_at ASC, rowid ASC")
             .bind(id.to_string())
             .fetch_all(&self.pool)
             .await?;
@@ -525,5 +662,501 @@ impl Storage for SqliteStorage {
         }
         Ok(events)
     }
-}
 
+    // Events
+    async fn publish_event(&self, team_id: Uuid, name: &str, payload: &Value) -> Result<EventRecord> {
+        let id = Uuid::new_v4();
+        let created_at = Utc::now();
+
+        sqlx::query(
+            "INSERT INTO events (id, team_id, name, payload, status, created_at) VALUES (?, ?, ?, ?, 'pending', ?)"
+        )
+        .bind(id.to_string())
+        .bind(team_id.to_string())
+        .bind(name)
+        .bind(serde_json::to_string(payload)?)
+        .bind(created_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(EventRecord {
+            id,
+            team_id,
+            name: name.to_string(),
+            payload: payload.clone(),
+            status: "pending".to_string(),
+            created_at,
+            delivered_at: None,
+        })
+    }
+
+    async fn list_pending_events(&self, limit: i64, offset: i64) -> Result<Vec<EventRecord>> {
+        let rows = sqlx::query(
+            "SELECT id, team_id, name, payload, status, created_at, delivered_at FROM events
+             WHERE status = 'pending' ORDER BY created_at ASC, id ASC LIMIT ? OFFSET ?"
+        )
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut events = Vec::new();
+        for row in rows {
+            let payload_str: String = row.get("payload");
+            let delivered_db: Option<String> = row.get("delivered_at");
+            let delivered_at = if let Some(s) = delivered_db { Some(DateTime::parse_from_rfc3339(&s)?.with_timezone(&Utc)) } else { None };
+
+            events.push(EventRecord {
+                id: Uuid::parse_str(row.get("id"))?,
+                team_id: Uuid::parse_str(row.get("team_id"))?,
+                name: row.get("name"),
+                payload: serde_json::from_str(&payload_str)?,
+                status: row.get("status"),
+                created_at: DateTime::parse_from_rfc3339(row.get("created_at"))?.with_timezone(&Utc),
+                delivered_at,
+            });
+        }
+        Ok(events)
+    }
+
+    async fn mark_event_delivered(&self, id: Uuid) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("UPDATE events SET status = 'delivered', delivered_at = ? WHERE id = ?")
+            .bind(Utc::now().to_rfc3339())
+            .bind(id.to_string())
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM event_deliveries WHERE event_id = ?")
+            .bind(id.to_string())
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn record_event_delivery(&self, event_id: Uuid, workflow_id: Uuid) -> Result<()> {
+        sqlx::query("INSERT INTO event_deliveries (event_id, workflow_id, delivered_at) VALUES (?, ?, ?) ON CONFLICT DO NOTHING")
+            .bind(event_id.to_string())
+            .bind(workflow_id.to_string())
+            .bind(Utc::now().to_rfc3339())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn list_event_deliveries(&self, event_id: Uuid) -> Result<Vec<Uuid>> {
+        let rows = sqlx::query("SELECT workflow_id FROM event_deliveries WHERE event_id = ?")
+            .bind(event_id.to_string())
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter().map(|row| Ok(Uuid::parse_str(row.get("workflow_id"))?)).collect()
+    }
+
+    async fn enqueue_execution(&self, execution: &QueuedExecution) -> Result<()> {
+        // The execution may already exist, e.g. when it waited for its concurrency slot
+        sqlx::query(
+            "INSERT INTO executions (id, workflow_id, status, started_at, team_id, priority, definition, inputs, resume_from, started_by)
+             VALUES (?, ?, 'queued', ?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT(id) DO UPDATE SET status = 'queued', team_id = excluded.team_id, priority = excluded.priority,
+                definition = excluded.definition, inputs = excluded.inputs, resume_from = excluded.resume_from,
+                started_by = excluded.started_by"
+        )
+        .bind(execution.id.to_string())
+        .bind(execution.workflow_id.map(|u| u.to_string()))
+        .bind(Utc::now().to_rfc3339())
+        .bind(execution.team_id.map(|u| u.to_string()))
+        .bind(execution.priority)
+        .bind(serde_json::to_string(&execution.definition)?)
+        .bind(serde_json::to_string(&execution.inputs)?)
+        .bind(&execution.resume_from)
+        .bind(execution.started_by.map(|u| u.to_string()))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn claim_execution(&self, worker_id: &str) -> Result<Option<QueuedExecution>> {
+        // A single statement, so concurrent workers never claim the same row
+        let row = sqlx::query(
+            "UPDATE executions SET status = 'running', worker_id = ?, heartbeat_at = ?
+             WHERE id = (
+                SELECT id FROM executions e WHERE status = 'queued' AND definition IS NOT NULL
+                AND NOT (resume_from IS NULL AND json_extract(definition, '$.concurrency.mode') = 'queue' AND EXISTS (
+                    SELECT 1 FROM executions r WHERE r.workflow_id = e.workflow_id AND r.status = 'running'
+                ))
+                ORDER BY priority DESC, rowid ASC LIMIT 1
+             )
+             RETURNING id, workflow_id, team_id, priority, definition, inputs, resume_from, started_by"
+        )
+        .bind(worker_id)
+        .bind(Utc::now().to_rfc3339())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(row) = row else { return Ok(None) };
+        let wf_id: Option<String> = row.get("workflow_id");
+        let team_id: Option<String> = row.get("team_id");
+        let definition: String = row.get("definition");
+        let inputs: Option<String> = row.get("inputs");
+        let started_by: Option<String> = row.get("started_by");
+
+        Ok(Some(QueuedExecution {
+            id: Uuid::parse_str(row.get("id"))?,
+            workflow_id: wf_id.map(|s| Uuid::parse_str(&s)).transpose()?,
+            team_id: team_id.map(|s| Uuid::parse_str(&s)).transpose()?,
+            definition: serde_json::from_str(&definition)?,
+            inputs: inputs.map(|s| serde_json::from_str(&s)).transpose()?.unwrap_or(Value::Null),
+            priority: row.get("priority"),
+            resume_from: row.get("resume_from"),
+            started_by: started_by.map(|s| Uuid::parse_str(&s)).transpose()?,
+        }))
+    }
+
+    async fn heartbeat_executions(&self, worker_id: &str, ids: &[Uuid]) -> Result<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        let placeholders = vec!["?"; ids.len()].join(", ");
+        let sql = format!(
+            "UPDATE executions SET worker_id = ?, heartbeat_at = ?
+             WHERE id IN ({}) AND (worker_id IS NULL OR worker_id = ?)",
+            placeholders
+        );
+
+        let mut query = sqlx::query(&sql)
+            .bind(worker_id)
+            .bind(Utc::now().to_rfc3339());
+        for id in ids {
+            query = query.bind(id.to_string());
+        }
+        query.bind(worker_id).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn recover_expired_executions(&self, cutoff: DateTime<Utc>) -> Result<u64> {
+        let cutoff = cutoff.to_rfc3339();
+        sqlx::query(
+            "UPDATE executions SET status = 'failed', finished_at = ?, error = 'Interrupted: the server stopped before the execution finished'
+             WHERE status IN ('pending', 'running', 'queued') AND definition IS NULL AND worker_id IS NOT NULL
+             AND COALESCE(heartbeat_at, started_at) < ?"
+        )
+        .bind(Utc::now().to_rfc3339())
+        .bind(&cutoff)
+        .execute(&self.pool)
+        .await?;
+
+        let result = sqlx::query(
+            "UPDATE executions SET status = 'queued', worker_id = NULL
+             WHERE status = 'running' AND definition IS NOT NULL AND COALESCE(heartbeat_at, started_at) < ?"
+        )
+        .bind(&cutoff)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn resume_execution(&self, id: Uuid, node_id: &str, inputs: &Value) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE executions SET status = 'queued', worker_id = NULL, inputs = ?, resume_from = ?
+             WHERE id = ? AND status = 'waiting' AND definition IS NOT NULL"
+        )
+        .bind(serde_json::to_string(inputs)?)
+        .bind(node_id)
+        .bind(id.to_string())
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn try_acquire_leadership(&self, _name: &str) -> Result<bool> {
+        // A SQLite database is served by a single process
+        Ok(true)
+    }
+
+    async fn create_approval(&self, approval: &ApprovalRecord) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO approvals (token, execution_id, record, status, decision, expires_at, created_at, decided_at, node_id, delivered_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&approval.token)
+        .bind(approval.execution_id.to_string())
+        .bind(serde_json::to_string(&approval.record)?)
+        .bind(&approval.status)
+        .bind(approval.decision.as_ref().map(|d| d.to_string()))
+        .bind(approval.expires_at.map(|t| t.to_rfc3339()))
+        .bind(approval.created_at.to_rfc3339())
+        .bind(approval.decided_at.map(|t| t.to_rfc3339()))
+        .bind(&approval.node_id)
+        .bind(approval.delivered_at.map(|t| t.to_rfc3339()))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_approval(&self, token: &str) -> Result<Option<ApprovalRecord>> {
+        let row = sqlx::query(
+            "SELECT token, execution_id, record, status, decision, expires_at, created_at, decided_at, node_id, delivered_at FROM approvals WHERE token = ?"
+        )
+        .bind(token)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(row) = row else { return Ok(None) };
+        let parse_time = |s: Option<String>| -> Result<Option<DateTime<Utc>>> {
+            Ok(s.map(|s| DateTime::parse_from_rfc3339(&s)).transpose()?.map(|t| t.with_timezone(&Utc)))
+        };
+        let record: String = row.get("record");
+        let decision: Option<String> = row.get("decision");
+
+        Ok(Some(ApprovalRecord {
+            token: row.get("token"),
+            execution_id: Uuid::parse_str(row.get("execution_id"))?,
+            record: serde_json::from_str(&record)?,
+            status: row.get("status"),
+            decision: decision.map(|d| serde_json::from_str(&d)).transpose()?,
+            expires_at: parse_time(row.get("expires_at"))?,
+            created_at: DateTime::parse_from_rfc3339(row.get("created_at"))?.with_timezone(&Utc),
+            decided_at: parse_time(row.get("decided_at"))?,
+            node_id: row.get("node_id"),
+            delivered_at: parse_time(row.get("delivered_at"))?,
+        }))
+    }
+
+    async fn resolve_approval(&self, token: &str, status: &str, decision: Option<&Value>) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE approvals SET status = ?, decision = ?, decided_at = ? WHERE token = ? AND status = 'pending'"
+        )
+        .bind(status)
+        .bind(decision.map(|d| d.to_string()))
+        .bind(Utc::now().to_rfc3339())
+        .bind(token)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn mark_approval_delivered(&self, token: &str) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE approvals SET delivered_at = ? WHERE token = ? AND status <> 'pending' AND delivered_at IS NULL"
+        )
+        .bind(Utc::now().to_rfc3339())
+        .bind(token)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn count_open_approvals(&self, execution_id: Uuid) -> Result<i64> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM approvals WHERE execution_id = ? AND node_id IS NOT NULL AND delivered_at IS NULL"
+        )
+        .bind(execution_id.to_string())
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(count)
+    }
+
+    async fn create_timer(&self, timer: &TimerRecord) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO timers (id, execution_id, node_id, record, wake_at, status, created_at) VALUES (?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(timer.id.to_string())
+        .bind(timer.execution_id.to_string())
+        .bind(&timer.node_id)
+        .bind(serde_json::to_string(&timer.record)?)
+        .bind(timer.wake_at.to_rfc3339())
+        .bind(&timer.status)
+        .bind(timer.created_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn list_due_timers(&self, now: DateTime<Utc>) -> Result<Vec<TimerRecord>> {
+        let rows = sqlx::query(
+            "SELECT id, execution_id, node_id, record, wake_at, status, created_at FROM timers
+             WHERE status = 'waiting' AND wake_at <= ? ORDER BY wake_at ASC"
+        )
+        .bind(now.to_rfc3339())
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut timers = Vec::new();
+        for row in rows {
+            let record: String = row.get("record");
+            timers.push(TimerRecord {
+                id: Uuid::parse_str(row.get("id"))?,
+                execution_id: Uuid::parse_str(row.get("execution_id"))?,
+                node_id: row.get("node_id"),
+                record: serde_json::from_str(&record)?,
+                wake_at: DateTime::parse_from_rfc3339(row.get("wake_at"))?.with_timezone(&Utc),
+                status: row.get("status"),
+                created_at: DateTime::parse_from_rfc3339(row.get("created_at"))?.with_timezone(&Utc),
+            });
+        }
+        Ok(timers)
+    }
+
+    async fn update_timer_status(&self, ids: &[Uuid], status: &str) -> Result<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        let placeholders = vec!["?"; ids.len()].join(", ");
+        let sql = format!("UPDATE timers SET status = ? WHERE id IN ({})", placeholders);
+
+        let mut query = sqlx::query(&sql).bind(status);
+        for id in ids {
+            query = query.bind(id.to_string());
+        }
+        query.execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn count_waiting_timers(&self, execution_id: Uuid) -> Result<i64> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM timers WHERE execution_id = ? AND status = 'waiting'")
+            .bind(execution_id.to_string())
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count)
+    }
+
+    async fn get_execution_definition(&self, id: Uuid) -> Result<Option<Value>> {
+        let definition: Option<Option<String>> = sqlx::query_scalar("SELECT definition FROM executions WHERE id = ?")
+            .bind(id.to_string())
+            .fetch_optional(&self.pool)
+            .await?;
+
+        definition.flatten().map(|d| Ok(serde_json::from_str(&d)?)).transpose()
+    }
+
+    async fn prune_executions(&self, policy: &RetentionPolicy, now: DateTime<Utc>) -> Result<PruneStats> {
+        let cutoff = |days: Option<i64>| days.map(|d| (now - chrono::Duration::days(d)).to_rfc3339());
+        let max_age = cutoff(policy.max_age_days);
+        let failed_max_age = cutoff(policy.failed_max_age_days);
+
+        // Unset limits are NULL, and comparisons with NULL match nothing
+        let doomed = "SELECT id FROM (
+                SELECT id, workflow_id, finished_at,
+                       ROW_NUMBER() OVER (PARTITION BY workflow_id ORDER BY started_at DESC) AS rn
+                FROM executions
+                WHERE finished_at IS NOT NULL AND NOT (?3 AND status = 'failed')
+            )
+            WHERE finished_at < ?1 OR (workflow_id IS NOT NULL AND rn > ?2)
+            UNION
+            SELECT id FROM executions WHERE status = 'failed' AND finished_at < ?4";
+
+        let mut stats = PruneStats::default();
+        let mut tx = self.pool.begin().await?;
+        for (table, column) in [("execution_events", "execution_id"), ("approvals", "execution_id"), ("timers", "execution_id"), ("executions", "id")] {
+            let deleted = sqlx::query(&format!("DELETE FROM {} WHERE {} IN ({})", table, column, doomed))
+                .bind(&max_age)
+                .bind(policy.max_per_workflow)
+                .bind(policy.failed_max_age_days.is_some())
+                .bind(&failed_max_age)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+            match table {
+                "execution_events" => stats.events += deleted,
+                "executions" => stats.executions = deleted,
+                _ => {}
+            }
+        }
+
+        if let Some(sample) = policy.success_payload_sample {
+            stats.events += sqlx::query(
+                "DELETE FROM execution_events WHERE id IN (
+                    SELECT id FROM (
+                        SELECT ev.id, ROW_NUMBER() OVER (
+                            PARTITION BY ev.execution_id, json_extract(ev.data, '$.EdgeData.from'), json_extract(ev.data, '$.EdgeData.to')
+                            ORDER BY ev.created_at, ev.rowid
+                        ) AS rn
+                        FROM execution_events ev JOIN executions ex ON ex.id = ev.execution_id
+                        WHERE ev.event_type = 'EdgeData' AND ex.status = 'completed'
+                    )
+                    WHERE rn > ?
+                )"
+            )
+            .bind(sample)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        }
+
+        tx.commit().await?;
+        Ok(stats)
+    }
+
+    async fn create_api_token(&self, token: &ApiToken) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO api_tokens (id, user_id, name, kind, token_hash, created_at, expires_at) VALUES (?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(token.id.to_string())
+        .bind(token.user_id.to_string())
+        .bind(&token.name)
+        .bind(&token.kind)
+        .bind(&token.token_hash)
+        .bind(token.created_at.to_rfc3339())
+        .bind(token.expires_at.map(|t| t.to_rfc3339()))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_api_token_by_hash(&self, token_hash: &str) -> Result<Option<ApiToken>> {
+        let row = sqlx::query("SELECT id, user_id, name, kind, token_hash, created_at, expires_at FROM api_tokens WHERE token_hash = ?")
+            .bind(token_hash)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        row.map(|row| api_token_from_row(&row)).transpose()
+    }
+
+    async fn list_api_tokens(&self, user_id: Uuid) -> Result<Vec<ApiToken>> {
+        let rows = sqlx::query("SELECT id, user_id, name, kind, token_hash, created_at, expires_at FROM api_tokens WHERE user_id = ? ORDER BY created_at")
+            .bind(user_id.to_string())
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.iter().map(api_token_from_row).collect()
+    }
+
+    async fn delete_api_token(&self, user_id: Uuid, id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM api_tokens WHERE id = ? AND user_id = ?")
+            .bind(id.to_string())
+            .bind(user_id.to_string())
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn log_audit(&self, entry: &AuditEntry) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO audit_log (id, team_id, actor_id, action, target, diff, created_at) VALUES (?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(entry.id.to_string())
+        .bind(entry.team_id.to_string())
+        .bind(entry.actor_id.map(|a| a.to_string()))
+        .bind(&entry.action)
+        .bind(&entry.target)
+        .bind(entry.diff.as_ref().map(|d| d.to_string()))
+        .bind(entry.created_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn list_audit(&self, team_id: Uuid, limit: i64, offset: i64) -> Result<Vec<AuditEntry>> {
+        let rows = sqlx::query(
+            "SELECT id, team_id, actor_id, action, target, diff, created_at FROM audit_log
+             WHERE team_id = ? ORDER BY created_at DESC, rowid DESC LIMIT ? OFFSET ?"
+        )
+        .bind(team_id.to_string())
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(audit_entry_from_row).collect()
+    }
+}
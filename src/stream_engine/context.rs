@@ -0,0 +1,25 @@
+use std::sync::Arc;
+use tokio::sync::Notify;
+use uuid::Uuid;
+use crate::storage::Storage;
+
+/// Runtime services handed to nodes by the `NodeFactory`.
+/// Nodes that only need their config ignore it; stateful nodes (e.g. polling triggers)
+/// use `storage` to persist progress across runs. Everything is optional so workflows
+/// can still run standalone without a database.
+#[derive(Clone, Default)]
+pub struct NodeContext {
+    pub storage: Option<Arc<dyn Storage>>,
+    /// Team the running workflow belongs to (scopes emitted events).
+    pub team_id: Option<Uuid>,
+    /// Wakes the server's event bus after an event was persisted, instead of waiting for its next poll.
+    pub event_signal: Option<Arc<Notify>>,
+    /// Execution the nodes run in (e.g. to address approval signals to it).
+    pub execution_id: Option<Uuid>,
+    /// Stored workflow being executed, if any.
+    pub workflow_id: Option<Uuid>,
+    /// The execution runs through the durable queue, so nodes may suspend it and have it resumed.
+    pub resumable: bool,
+    /// Id of the node being built, set per node by `WorkflowDefinition::to_executor_with_context`.
+    pub node_id: Option<String>,
+}
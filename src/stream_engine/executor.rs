@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use tokio::sync::{mpsc, broadcast};
 use tokio::task::JoinSet;
 use crate::stream_engine::{StreamNode, DebugConfig};
@@ -8,6 +9,29 @@ use serde_json::Value;
 
 type InputsMap = HashMap<String, HashMap<usize, Vec<mpsc::Receiver<Value>>>>;
 type OutputsMap = HashMap<String, HashMap<usize, Vec<mpsc::Sender<Value>>>>;
+type LastInputs = Arc<Mutex<HashMap<String, Value>>>;
+
+/// Error returned by `StreamExecutor::run` when a node fails and its policy stops the workflow.
+/// Displays as the underlying node error.
+#[derive(Debug)]
+pub struct NodeFailure {
+    pub node_id: String,
+    pub error: anyhow::Error,
+    /// Last record delivered to the failing node, if any.
+    pub last_input: Option<Value>,
+}
+
+impl std::fmt::Display for NodeFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl std::error::Error for NodeFailure {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.source()
+    }
+}
 
 pub struct StreamExecutor {
     nodes: HashMap<String, Box<dyn StreamNode>>,
@@ -17,6 +41,7 @@ pub struct StreamExecutor {
     event_sender: Option<broadcast::Sender<ExecutionEvent>>,
     debug_config: DebugConfig,
     initial_inputs: HashMap<String, Vec<Value>>,
+    last_inputs: LastInputs,
 }
 
 impl Default for StreamExecutor {
@@ -34,6 +59,7 @@ impl StreamExecutor {
             event_sender: None,
             debug_config,
             initial_inputs: HashMap::new(),
+            last_inputs: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
@@ -69,6 +95,7 @@ impl StreamExecutor {
             let event_sender = self.event_sender.clone();
             let node_id = id.clone();
             let policy = self.node_policies.get(&node_id).cloned().unwrap_or(ErrorPolicy::Stop);
+            let last_inputs = self.last_inputs.clone();
 
             set.spawn(async move {
                 // Emit NodeStart
@@ -97,17 +124,21 @@ impl StreamExecutor {
                 match result {
                     Ok(_) => Ok(()),
                     Err(e) => {
+                        let failure = |error: anyhow::Error| {
+                            let last_input = last_inputs.lock().unwrap().get(&node_id).cloned();
+                            anyhow::Error::new(NodeFailure { node_id: node_id.clone(), error, last_input })
+                        };
                         match policy {
                             ErrorPolicy::Continue => {
                                 eprintln!("Node {} failed but policy is Continue. Error: {}", node_id, e);
                                 Ok(()) // Suppress error
                             }
-                            ErrorPolicy::Stop => Err(e),
+                            ErrorPolicy::Stop => Err(failure(e)),
                             ErrorPolicy::Retry { .. } => {
                                 // Retry handled internally by node or currently unsupported at executor level
                                 // Fallback to Stop for now or simple error propagation
                                 eprintln!("Node {} failed. Retry policy set but not supported at executor level yet. Error: {}", node_id, e);
-                                Err(e)
+                                Err(failure(e))
                             }
                         }
                     }
@@ -142,10 +173,14 @@ impl StreamExecutor {
             let from_id = from.clone();
             let to_id = to.clone();
             let limit = self.debug_config.limit_records;
+            let last_inputs = self.last_inputs.clone();
             
             tokio::spawn(async move {
                 let mut count = 0;
                 while let Some(val) = rx.recv().await {
+                    // Remembered for failure reports
+                    last_inputs.lock().unwrap().insert(to_id.clone(), val.clone());
+
                     // Broadcast event
                     if let Some(sender) = &event_sender {
                         let _ = sender.send(ExecutionEvent::EdgeData {
@@ -199,8 +234,11 @@ impl StreamExecutor {
                 // We can create a channel, spawn a task to send values, and add the receiver.
                 let (tx, rx) = mpsc::channel(100);
                 let values = values.clone();
+                let last_inputs = self.last_inputs.clone();
+                let to_id = node_id.clone();
                 tokio::spawn(async move {
                     for val in values {
+                        last_inputs.lock().unwrap().insert(to_id.clone(), val.clone());
                         let _ = tx.send(val).await;
                     }
                 });
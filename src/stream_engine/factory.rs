@@ -1,14 +1,16 @@
 use std::collections::HashMap;
 use serde_json::Value;
 use anyhow::{Result, anyhow};
-use crate::stream_engine::StreamNode;
+use crate::stream_engine::{StreamNode, NodeContext};
 use crate::stream_engine::nodes;
 use crate::integrations;
+use crate::credentials::{self, Secrets};
 
-type NodeCreator = Box<dyn Fn(Value, &HashMap<String, String>) -> Result<Box<dyn StreamNode>> + Send + Sync>;
+type NodeCreator = Box<dyn Fn(Value, &Secrets) -> Result<Box<dyn StreamNode>> + Send + Sync>;
 
 pub struct NodeFactory {
     creators: HashMap<String, NodeCreator>,
+    context: NodeContext,
 }
 
 impl Default for NodeFactory {
@@ -19,8 +21,14 @@ impl Default for NodeFactory {
 
 impl NodeFactory {
     pub fn new() -> Self {
+        Self::with_context(NodeContext::default())
+    }
+
+    /// Creates a factory whose nodes can reach the given runtime services (storage, ...).
+    pub fn with_context(context: NodeContext) -> Self {
         let mut factory = Self {
             creators: HashMap::new(),
+            context,
         };
         factory.register_defaults();
         factory
@@ -28,18 +36,25 @@ impl NodeFactory {
 
     pub fn register<F>(&mut self, type_name: &str, creator: F)
     where
-        F: Fn(Value, &HashMap<String, String>) -> Result<Box<dyn StreamNode>> + Send + Sync + 'static,
+        F: Fn(Value, &Secrets) -> Result<Box<dyn StreamNode>> + Send + Sync + 'static,
     {
         self.creators.insert(type_name.to_string(), Box::new(creator));
     }
 
-    pub fn create(&self, type_name: &str, config: Value, secrets: &HashMap<String, String>) -> Result<Box<dyn StreamNode>> {
+    /// Whether nodes of `type_name` can be created, built-in or integration.
+    pub fn supports(&self, type_name: &str) -> bool {
+        self.creators.contains_key(type_name) || integrations::create_node_by_id(type_name, Value::Null).is_some()
+    }
+
+    pub fn create(&self, type_name: &str, config: Value, secrets: &Secrets) -> Result<Box<dyn StreamNode>> {
+        let credential = credentials::node_credential(&config, secrets);
+        let config = credentials::resolve_config(config, credential.as_ref())?;
         if let Some(creator) = self.creators.get(type_name) {
             return creator(config, secrets);
         }
 
         // Fallback for integrations
-        if let Some(node) = integrations::create_node_by_id(type_name) {
+        if let Some(node) = integrations::create_node_by_id(type_name, credential.unwrap_or(Value::Null)) {
             return Ok(node);
         }
 
@@ -49,6 +64,8 @@ impl NodeFactory {
     fn register_defaults(&mut self) {
         self.register("manual_trigger", |_, _| Ok(Box::new(nodes::ManualTrigger)));
         self.register("child_workflow_trigger", |_, _| Ok(Box::new(nodes::ChildWorkflowTrigger)));
+        self.register("event_trigger", |_, _| Ok(Box::new(nodes::EventTrigger)));
+        self.register("error_trigger", |_, _| Ok(Box::new(nodes::ErrorTrigger)));
         self.register("console_output", |_, _| Ok(Box::new(nodes::ConsoleOutputNode)));
         self.register("set_data", |config, _| Ok(Box::new(nodes::SetDataNode::new(config))));
         
@@ -63,6 +80,11 @@ impl NodeFactory {
             Ok(Box::new(nodes::RouterNode::new(key.to_string(), value, operator)))
         });
 
+        let context = self.context.clone();
+        self.register("approval", move |config, _| {
+            Ok(Box::new(nodes::ApprovalNode::new(config, context.clone())?))
+        });
+
         self.register("join", |config, _| {
             let join_type_str = config.get("type").and_then(|v| v.as_str()).unwrap_or("index");
             let mode_str = config.get("mode").and_then(|v| v.as_str()).unwrap_or("inner");
@@ -128,10 +150,22 @@ impl NodeFactory {
             Ok(Box::new(nodes::HttpRequestNode::new(method, url, headers, body, retry_count, retry_delay_ms, auto_split)))
         });
 
-        self.register("time_trigger", |config, _| {
+        let context = self.context.clone();
+        self.register("sql_poll_trigger", move |config, _| {
+            Ok(Box::new(nodes::SqlPollTrigger::new(config, context.storage.clone(), context.team_id)?))
+        });
+
+        self.register("pg_notify_trigger", |config, _| {
+            Ok(Box::new(nodes::PgNotifyTrigger::new(config)?))
+        });
+
+        let context = self.context.clone();
+        self.register("time_trigger", move |config, _| {
             let cron = config.get("cron").and_then(|v| v.as_str()).unwrap_or("0 * * * * *").to_string();
             let interval = config.get("interval").and_then(|v| v.as_u64()).unwrap_or(1);
-            Ok(Box::new(nodes::TimeTrigger::new(cron, interval)))
+            // Stored workflows may be running on several servers sharing the storage
+            let leadership = context.workflow_id.and(context.storage.clone());
+            Ok(Box::new(nodes::TimeTrigger::new(cron, interval).with_leadership(leadership)))
         });
 
         self.register("webhook_trigger", |config, _| {
@@ -151,6 +185,15 @@ impl NodeFactory {
             Ok(Box::new(nodes::SqlNode::new(config)))
         });
 
+        self.register("pg_notify", |config, _| {
+            Ok(Box::new(nodes::PgNotifyNode::new(config)?))
+        });
+
+        let context = self.context.clone();
+        self.register("emit_event", move |config, _| {
+            Ok(Box::new(nodes::EmitEventNode::new(config, context.clone())?))
+        });
+
         // File System
         self.register("file_read", |config, _| {
              Ok(Box::new(nodes::FileReadNode::new(config)))
@@ -170,8 +213,9 @@ impl NodeFactory {
              Ok(Box::new(nodes::SshNode::new(config)))
         });
 
-        self.register("delay", |config, _| {
-            Ok(Box::new(nodes::DelayNode::new(config)))
+        let context = self.context.clone();
+        self.register("delay", move |config, _| {
+            Ok(Box::new(nodes::DelayNode::new(config, context.clone())))
         });
 
         self.register("select", |config, _| {
@@ -288,10 +332,8 @@ impl NodeFactory {
             
             // Resolve API Key from secrets if credential_id is present
             let mut final_api_key = api_key;
-            if let Some(ref cred_id) = credential_id {
-                if let Some(secret) = secrets.get(cred_id) {
-                    final_api_key = Some(secret.clone());
-                }
+            if let Some(key) = credentials::node_credential(&config, secrets).and_then(|c| c["api_key"].as_str().map(String::from)) {
+                final_api_key = Some(key);
             }
 
             Ok(Box::new(nodes::AgentNode {
@@ -21,4 +21,6 @@ pub mod nodes;
 pub mod factory;
 pub mod expressions;
 pub mod utils;
-pub use executor::StreamExecutor;
+pub mod context;
+pub use executor::{StreamExecutor, NodeFailure};
+pub use context::NodeContext;
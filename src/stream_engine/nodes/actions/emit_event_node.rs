@@ -0,0 +1,77 @@
+use async_trait::async_trait;
+use crate::stream_engine::{StreamNode, NodeContext};
+use tokio::sync::mpsc::{Receiver, Sender};
+use serde_json::Value;
+use anyhow::{Result, anyhow};
+use uuid::Uuid;
+
+/// Publishes a named event for every incoming record and passes the record through.
+/// Events are written to storage first (durable), then the event bus starts every stored
+/// workflow of the same team whose `event_trigger` subscribes to that name. Events only go to
+/// the running workflow's team; a `team_id` in the config must name that team.
+pub struct EmitEventNode {
+    event: String,
+    payload_template: Option<String>,
+    team_id: Option<Uuid>,
+    context: NodeContext,
+}
+
+impl EmitEventNode {
+    pub fn new(config: Value, context: NodeContext) -> Result<Self> {
+        let event = config.get("event")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing 'event' for emit_event"))?
+            .to_string();
+
+        let payload_template = config.get("payload")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+
+        // Publishing into another team would start its workflows with its credentials
+        if let Some(s) = config.get("team_id").and_then(|v| v.as_str()) {
+            let configured = Uuid::parse_str(s).map_err(|e| anyhow!("Invalid 'team_id' for emit_event: {}", e))?;
+            if context.team_id != Some(configured) {
+                return Err(anyhow!("emit_event can only publish to the team of the running workflow, not {}", configured));
+            }
+        }
+
+        Ok(Self { event, payload_template, team_id: context.team_id, context })
+    }
+}
+
+#[async_trait]
+impl StreamNode for EmitEventNode {
+    async fn run(&self, mut inputs: Vec<Receiver<Value>>, outputs: Vec<Sender<Value>>) -> Result<()> {
+        let storage = self.context.storage.as_ref()
+            .ok_or_else(|| anyhow!("emit_event requires storage (run the workflow through the server or with a database)"))?;
+        let team_id = self.team_id
+            .ok_or_else(|| anyhow!("emit_event requires a team (run the workflow for a team)"))?;
+
+        let env = crate::stream_engine::expressions::create_environment();
+
+        if let Some(rx) = inputs.get_mut(0) {
+            while let Some(data) = rx.recv().await {
+                let payload = match &self.payload_template {
+                    Some(tmpl) => {
+                        let rendered = env.render_str(tmpl, &data)
+                            .map_err(|e| anyhow!("Failed to render event payload: {}", e))?;
+                        serde_json::from_str(&rendered).unwrap_or(Value::String(rendered))
+                    }
+                    None => data.clone(),
+                };
+
+                storage.publish_event(team_id, &self.event, &payload).await?;
+                if let Some(signal) = &self.context.event_signal {
+                    signal.notify_one();
+                }
+
+                if let Some(tx) = outputs.first() {
+                    if tx.send(data).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
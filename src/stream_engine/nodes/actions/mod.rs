@@ -9,5 +9,7 @@ pub mod code_node;
 pub mod function_node;
 pub mod select_node;
 pub mod sql_node;
+pub mod pg_notify_node;
+pub mod emit_event_node;
 pub mod file_ops;
 pub mod connectivity;
@@ -0,0 +1,90 @@
+use crate::stream_engine::StreamNode;
+use async_trait::async_trait;
+use serde_json::Value;
+use tokio::sync::mpsc;
+use sqlx::postgres::PgPool;
+use anyhow::anyhow;
+
+/// Sends `NOTIFY <channel>, <payload>` for every incoming record and passes the record through.
+/// The payload is rendered from a template, or the whole record serialized as JSON by default.
+#[derive(Clone, Debug)]
+pub struct PgNotifyNode {
+    connection_string: String,
+    channel: String,
+    payload_template: Option<String>,
+}
+
+impl PgNotifyNode {
+    pub fn new(config: Value) -> anyhow::Result<Self> {
+        let connection_string = config.get("connection_string")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing 'connection_string' for pg_notify"))?
+            .to_string();
+
+        let channel = config.get("channel")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing 'channel' for pg_notify"))?
+            .to_string();
+
+        let payload_template = config.get("payload")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+
+        Ok(Self {
+            connection_string,
+            channel,
+            payload_template,
+        })
+    }
+}
+
+#[async_trait]
+impl StreamNode for PgNotifyNode {
+    async fn run(
+        &self,
+        mut inputs: Vec<mpsc::Receiver<Value>>,
+        outputs: Vec<mpsc::Sender<Value>>,
+    ) -> anyhow::Result<()> {
+        if inputs.is_empty() {
+            return Ok(());
+        }
+
+        let mut rx = inputs.remove(0);
+        let tx = outputs.first().cloned();
+
+        let pool = PgPool::connect(&self.connection_string).await?;
+        let env = crate::stream_engine::expressions::create_environment();
+
+        while let Some(data) = rx.recv().await {
+            let payload = match &self.payload_template {
+                Some(tmpl) => match env.render_str(tmpl, &data) {
+                    Ok(rendered) => rendered,
+                    Err(e) => {
+                        eprintln!("PgNotifyNode Payload Render Error: {}", e);
+                        continue;
+                    }
+                },
+                None => data.to_string(),
+            };
+
+            if let Err(e) = sqlx::query("SELECT pg_notify($1, $2)")
+                .bind(&self.channel)
+                .bind(&payload)
+                .execute(&pool)
+                .await
+            {
+                pool.close().await;
+                return Err(anyhow!("Failed to NOTIFY channel '{}': {}", self.channel, e));
+            }
+
+            if let Some(tx) = &tx {
+                if tx.send(data).await.is_err() {
+                    break;
+                }
+            }
+        }
+
+        pool.close().await;
+        Ok(())
+    }
+}
@@ -35,49 +35,51 @@ impl SqlNode {
             parameters_template,
         }
     }
+}
 
-    async fn execute_query(&self, pool: &AnyPool, query_str: &str, params: Vec<Value>) -> anyhow::Result<Vec<Value>> {
-        let mut query_builder = sqlx::query(query_str);
-        
-        for param in params {
-            match param {
-                Value::Null => query_builder = query_builder.bind(Option::<String>::None),
-                Value::Bool(b) => query_builder = query_builder.bind(b),
-                Value::Number(n) => {
-                    if let Some(i) = n.as_i64() {
-                        query_builder = query_builder.bind(i);
-                    } else if let Some(f) = n.as_f64() {
-                         query_builder = query_builder.bind(f);
-                    }
-                },
-                Value::String(s) => query_builder = query_builder.bind(s),
-                Value::Array(_) | Value::Object(_) => {
-                    // For complex types, bind as string? Or fail?
-                    // Let's bind as string for now
-                    query_builder = query_builder.bind(param.to_string());
+/// Runs `query_str` with positional `params` and maps every returned row to a JSON object.
+/// Shared with `SqlPollTrigger`, which polls through the same `AnyPool` logic.
+pub(crate) async fn execute_query(pool: &AnyPool, query_str: &str, params: Vec<Value>) -> anyhow::Result<Vec<Value>> {
+    let mut query_builder = sqlx::query(query_str);
+
+    for param in params {
+        match param {
+            Value::Null => query_builder = query_builder.bind(Option::<String>::None),
+            Value::Bool(b) => query_builder = query_builder.bind(b),
+            Value::Number(n) => {
+                if let Some(i) = n.as_i64() {
+                    query_builder = query_builder.bind(i);
+                } else if let Some(f) = n.as_f64() {
+                     query_builder = query_builder.bind(f);
                 }
+            },
+            Value::String(s) => query_builder = query_builder.bind(s),
+            Value::Array(_) | Value::Object(_) => {
+                // For complex types, bind as string? Or fail?
+                // Let's bind as string for now
+                query_builder = query_builder.bind(param.to_string());
             }
         }
+    }
 
-        let rows = query_builder.fetch_all(pool).await?;
-        let mut results = Vec::new();
-
-        for row in rows {
-            let mut row_json = serde_json::Map::new();
-            for col in row.columns() {
-                let col_name = col.name();
-                
-                // Try to decode based on type info if possible, or try common types
-                // AnyRow handling is a bit specific.
-                // We'll use a helper to map to Value
-                let val = map_row_value(&row, col.ordinal());
-                row_json.insert(col_name.to_string(), val);
-            }
-            results.push(Value::Object(row_json));
-        }
+    let rows = query_builder.fetch_all(pool).await?;
+    let mut results = Vec::new();
+
+    for row in rows {
+        let mut row_json = serde_json::Map::new();
+        for col in row.columns() {
+            let col_name = col.name();
 
-        Ok(results)
+            // Try to decode based on type info if possible, or try common types
+            // AnyRow handling is a bit specific.
+            // We'll use a helper to map to Value
+            let val = map_row_value(&row, col.ordinal());
+            row_json.insert(col_name.to_string(), val);
+        }
+        results.push(Value::Object(row_json));
     }
+
+    Ok(results)
 }
 
 fn map_row_value(row: &AnyRow, ordinal: usize) -> Value {
@@ -153,7 +155,7 @@ impl StreamNode for SqlNode {
                  }
             }
 
-            match self.execute_query(&pool, &self.query, params).await {
+            match execute_query(&pool, &self.query, params).await {
                 Ok(rows) => {
                     let output = serde_json::json!({
                         "rows": rows,
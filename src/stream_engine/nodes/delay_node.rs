@@ -2,21 +2,39 @@ use async_trait::async_trait;
 use tokio::sync::mpsc::{Receiver, Sender};
 use serde_json::Value;
 use anyhow::Result;
+use chrono::Utc;
 use std::time::Duration;
-use crate::stream_engine::StreamNode;
+use uuid::Uuid;
+use crate::stream_engine::{StreamNode, NodeContext};
+use crate::storage::TimerRecord;
 
-#[derive(Debug)]
+/// Delays at or above this are persisted in durable mode; shorter ones just sleep.
+const DEFAULT_DURABLE_THRESHOLD_MS: u64 = 60_000;
+
+/// Holds every record for `duration_ms`.
+/// With `durable: true`, long delays do not sleep in-process: the record and its wake-up time
+/// are stored as a timer and the execution is suspended (`waiting`) once everything else
+/// finished. The server resumes it from this node when the timer is due, also after restarts.
+/// Durable mode needs an execution the server's workers can resume; elsewhere (e.g. `cli run`)
+/// it sleeps.
 pub struct DelayNode {
     duration_ms: u64,
+    durable: bool,
+    durable_threshold_ms: u64,
+    context: NodeContext,
 }
 
 impl DelayNode {
-    pub fn new(config: Value) -> Self {
+    pub fn new(config: Value, context: NodeContext) -> Self {
         let duration_ms = config.get("duration_ms")
             .and_then(|v| v.as_u64())
             .unwrap_or(1000); // Default to 1 second
-            
-        Self { duration_ms }
+        let durable = config.get("durable").and_then(|v| v.as_bool()).unwrap_or(false);
+        let durable_threshold_ms = config.get("durable_threshold_ms")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(DEFAULT_DURABLE_THRESHOLD_MS);
+
+        Self { duration_ms, durable, durable_threshold_ms, context }
     }
 }
 
@@ -26,7 +44,35 @@ impl StreamNode for DelayNode {
         let mut input = inputs.into_iter().next().unwrap();
         let output = outputs.into_iter().next().unwrap();
 
+        let timers = match (&self.context.storage, self.context.execution_id, &self.context.node_id) {
+            (Some(storage), Some(execution_id), Some(node_id))
+                if self.durable && self.context.resumable && self.duration_ms >= self.durable_threshold_ms => {
+                Some((storage.clone(), execution_id, node_id.clone()))
+            }
+            _ => {
+                if self.durable && self.duration_ms >= self.durable_threshold_ms {
+                    eprintln!("Durable delay outside a resumable execution; sleeping in-process instead");
+                }
+                None
+            }
+        };
+
         while let Some(value) = input.recv().await {
+            if let Some((storage, execution_id, node_id)) = &timers {
+                let now = Utc::now();
+                let timer = TimerRecord {
+                    id: Uuid::new_v4(),
+                    execution_id: *execution_id,
+                    node_id: node_id.clone(),
+                    record: value,
+                    wake_at: now + chrono::Duration::milliseconds(self.duration_ms as i64),
+                    status: "waiting".to_string(),
+                    created_at: now,
+                };
+                storage.create_timer(&timer).await?;
+                continue;
+            }
+
             tokio::time::sleep(Duration::from_millis(self.duration_ms)).await;
             if output.send(value).await.is_err() {
                 break;
@@ -0,0 +1,202 @@
+use async_trait::async_trait;
+use crate::stream_engine::{StreamNode, NodeContext};
+use crate::storage::{ApprovalRecord, Storage, TimerRecord};
+use tokio::sync::mpsc::{Receiver, Sender};
+use tokio::task::JoinSet;
+use tokio::time::{sleep, Duration};
+use serde_json::{json, Value};
+use anyhow::{Result, anyhow};
+use chrono::Utc;
+use std::sync::Arc;
+use uuid::Uuid;
+
+const APPROVED: usize = 0;
+const REJECTED: usize = 1;
+const EXPIRED: usize = 2;
+const REQUESTED: usize = 3;
+
+/// Holds every record until it is approved or rejected through
+/// `POST /api/executions/{execution_id}/signal/{token}`, or until `timeout_seconds` pass.
+/// Records wait independently (persisted in storage) and leave on the `approved`, `rejected`
+/// or `expired` port with an `approval` field added. For each record, a request
+/// `{ token, execution_id, signal_path, message, record, expires_at }` is sent on the
+/// `requested` port first, e.g. to notify approvers.
+/// In executions run by the server's workers, the node does not wait: the execution is
+/// suspended (`waiting`) once everything else finished and resumed from this node when the
+/// approval is signalled or expires (through a durable timer).
+pub struct ApprovalNode {
+    message_template: Option<String>,
+    timeout: Option<Duration>,
+    poll_interval: Duration,
+    /// Set on resumed executions: inputs are `{ token }` of settled approvals to hand on.
+    resume: bool,
+    context: NodeContext,
+}
+
+impl ApprovalNode {
+    pub fn new(config: Value, context: NodeContext) -> Result<Self> {
+        let message_template = config.get("message")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+        let timeout = config.get("timeout_seconds")
+            .and_then(|v| v.as_u64())
+            .map(Duration::from_secs);
+        let poll_interval = Duration::from_millis(config.get("poll_interval_ms").and_then(|v| v.as_u64()).unwrap_or(1000));
+        let resume = config.get("resume").and_then(|v| v.as_bool()).unwrap_or(false);
+
+        Ok(Self { message_template, timeout, poll_interval, resume, context })
+    }
+}
+
+#[async_trait]
+impl StreamNode for ApprovalNode {
+    async fn run(&self, mut inputs: Vec<Receiver<Value>>, outputs: Vec<Sender<Value>>) -> Result<()> {
+        let storage = self.context.storage.clone()
+            .ok_or_else(|| anyhow!("approval requires storage (run the workflow through the server or with a database)"))?;
+        if self.resume {
+            if let Some(rx) = inputs.get_mut(0) {
+                while let Some(data) = rx.recv().await {
+                    let token = data.get("token").and_then(|v| v.as_str())
+                        .ok_or_else(|| anyhow!("Resumed approval input has no token"))?;
+                    hand_on(storage.as_ref(), token, &outputs).await?;
+                }
+            }
+            return Ok(());
+        }
+
+        // Standalone runs have no execution record; signals then use this generated id
+        let execution_id = self.context.execution_id.unwrap_or_else(Uuid::new_v4);
+        // Suspend instead of waiting when the execution can be resumed from this node
+        let suspend_at = self.context.node_id.clone()
+            .filter(|_| self.context.resumable && self.context.execution_id.is_some());
+
+        let env = crate::stream_engine::expressions::create_environment();
+        let mut waiting = JoinSet::new();
+
+        if let Some(rx) = inputs.get_mut(0) {
+            while let Some(data) = rx.recv().await {
+                let message = match &self.message_template {
+                    Some(tmpl) => Some(env.render_str(tmpl, &data)
+                        .map_err(|e| anyhow!("Failed to render approval message: {}", e))?),
+                    None => None,
+                };
+
+                let now = Utc::now();
+                let approval = ApprovalRecord {
+                    token: Uuid::new_v4().to_string(),
+                    execution_id,
+                    record: data.clone(),
+                    status: "pending".to_string(),
+                    decision: None,
+                    expires_at: self.timeout.map(|t| now + chrono::Duration::from_std(t).unwrap_or(chrono::Duration::MAX)),
+                    created_at: now,
+                    decided_at: None,
+                    node_id: suspend_at.clone(),
+                    delivered_at: None,
+                };
+                storage.create_approval(&approval).await?;
+
+                if let Some(tx) = outputs.get(REQUESTED) {
+                    let request = json!({
+                        "token": approval.token,
+                        "execution_id": execution_id,
+                        "signal_path": format!("/api/executions/{}/signal/{}", execution_id, approval.token),
+                        "message": message,
+                        "record": data,
+                        "expires_at": approval.expires_at,
+                    });
+                    let _ = tx.send(request).await;
+                }
+
+                match (&suspend_at, approval.expires_at) {
+                    // Resumes the execution to hand the record on as expired, unless signalled first
+                    (Some(node_id), Some(expires_at)) => {
+                        storage.create_timer(&TimerRecord {
+                            id: Uuid::parse_str(&approval.token)?,
+                            execution_id,
+                            node_id: node_id.clone(),
+                            record: json!({ "token": approval.token }),
+                            wake_at: expires_at,
+                            status: "waiting".to_string(),
+                            created_at: now,
+                        }).await?;
+                    }
+                    (Some(_), None) => {}
+                    (None, _) => {
+                        waiting.spawn(wait_for_decision(storage.clone(), approval, self.poll_interval, outputs.clone()));
+                    }
+                }
+            }
+        }
+
+        while let Some(res) = waiting.join_next().await {
+            res??;
+        }
+        Ok(())
+    }
+}
+
+async fn wait_for_decision(storage: Arc<dyn Storage>, approval: ApprovalRecord, poll_interval: Duration, outputs: Vec<Sender<Value>>) -> Result<()> {
+    let settled = loop {
+        let current = storage.get_approval(&approval.token).await?
+            .ok_or_else(|| anyhow!("Approval {} disappeared", approval.token))?;
+        if current.status != "pending" {
+            break current;
+        }
+
+        if approval.expires_at.is_some_and(|at| Utc::now() >= at) {
+            // Lost races against a late signal are settled by the next read
+            if storage.resolve_approval(&approval.token, "expired", None).await? {
+                break ApprovalRecord { status: "expired".to_string(), decided_at: Some(Utc::now()), ..current };
+            }
+            continue;
+        }
+        sleep(poll_interval).await;
+    };
+    emit(settled, &outputs).await;
+    Ok(())
+}
+
+/// Sends the record of a settled approval on, once, from a resumed execution.
+/// Approvals still pending are left for their signal, unless they expired.
+async fn hand_on(storage: &dyn Storage, token: &str, outputs: &[Sender<Value>]) -> Result<()> {
+    let mut approval = storage.get_approval(token).await?
+        .ok_or_else(|| anyhow!("Approval {} disappeared", token))?;
+    if approval.status == "pending" {
+        if approval.expires_at.is_none_or(|at| Utc::now() < at) {
+            return Ok(());
+        }
+        // Lost races against a late signal are settled by the read below
+        storage.resolve_approval(token, "expired", None).await?;
+        approval = storage.get_approval(token).await?
+            .ok_or_else(|| anyhow!("Approval {} disappeared", token))?;
+    }
+
+    // Both the signal and the expiry timer may resume the execution
+    if storage.mark_approval_delivered(token).await? {
+        emit(approval, outputs).await;
+    }
+    Ok(())
+}
+
+async fn emit(settled: ApprovalRecord, outputs: &[Sender<Value>]) {
+    let port = match settled.status.as_str() {
+        "approved" => APPROVED,
+        "rejected" => REJECTED,
+        _ => EXPIRED,
+    };
+
+    let mut record = settled.record;
+    if let Value::Object(map) = &mut record {
+        map.insert("approval".to_string(), json!({
+            "token": settled.token,
+            "status": settled.status,
+            "decision": settled.decision,
+            "decided_at": settled.decided_at,
+        }));
+    }
+
+    if let Some(tx) = outputs.get(port) {
+        let _ = tx.send(record).await;
+    }
+}
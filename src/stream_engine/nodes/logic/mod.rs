@@ -1,2 +1,3 @@
 pub mod router_node;
 pub mod switch_node;
+pub mod approval_node;
@@ -11,6 +11,10 @@ pub use triggers::manual_trigger::ManualTrigger;
 pub use triggers::time_trigger::TimeTrigger;
 pub use triggers::webhook_trigger::WebhookTrigger;
 pub use triggers::child_workflow_trigger::ChildWorkflowTrigger;
+pub use triggers::sql_poll_trigger::SqlPollTrigger;
+pub use triggers::pg_notify_trigger::PgNotifyTrigger;
+pub use triggers::event_trigger::EventTrigger;
+pub use triggers::error_trigger::ErrorTrigger;
 
 pub use actions::http_request_node::HttpRequestNode;
 pub use actions::console_output::ConsoleOutputNode;
@@ -23,12 +27,15 @@ pub use actions::code_node::CodeNode;
 pub use actions::function_node::FunctionNode;
 pub use actions::select_node::SelectNode;
 pub use actions::sql_node::SqlNode;
+pub use actions::pg_notify_node::PgNotifyNode;
+pub use actions::emit_event_node::EmitEventNode;
 pub use actions::file_ops::{FileReadNode, FileWriteNode, ListDirNode};
 pub use actions::connectivity::{FtpNode, SshNode};
 
 
 pub use logic::router_node::RouterNode;
 pub use logic::switch_node::SwitchNode;
+pub use logic::approval_node::ApprovalNode;
 
 pub use data::join_node::{JoinNode, JoinType, JoinMode};
 pub use data::union_node::{UnionNode, UnionMode};
@@ -0,0 +1,22 @@
+use async_trait::async_trait;
+use crate::stream_engine::StreamNode;
+use tokio::sync::mpsc::{Receiver, Sender};
+use serde_json::Value;
+use anyhow::Result;
+
+/// Entry point for failure-handler workflows (`on_failure`).
+/// The job manager injects one record describing the failed execution:
+/// `{ execution_id, workflow_id, failed_node, error, last_input, failed_at }`.
+pub struct ErrorTrigger;
+
+#[async_trait]
+impl StreamNode for ErrorTrigger {
+    async fn run(&self, mut inputs: Vec<Receiver<Value>>, outputs: Vec<Sender<Value>>) -> Result<()> {
+        if let (Some(tx), Some(rx)) = (outputs.first(), inputs.get_mut(0)) {
+            while let Some(data) = rx.recv().await {
+                tx.send(data).await?;
+            }
+        }
+        Ok(())
+    }
+}
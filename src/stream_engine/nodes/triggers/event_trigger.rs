@@ -0,0 +1,22 @@
+use async_trait::async_trait;
+use crate::stream_engine::StreamNode;
+use tokio::sync::mpsc::{Receiver, Sender};
+use serde_json::Value;
+use anyhow::Result;
+
+/// Entry point for workflows started by the event bus.
+/// The bus injects one record per delivered event; the `event` config is only read by the bus
+/// to match subscriptions. Run on its own (no injected input) the trigger emits nothing.
+pub struct EventTrigger;
+
+#[async_trait]
+impl StreamNode for EventTrigger {
+    async fn run(&self, mut inputs: Vec<Receiver<Value>>, outputs: Vec<Sender<Value>>) -> Result<()> {
+        if let (Some(tx), Some(rx)) = (outputs.first(), inputs.get_mut(0)) {
+            while let Some(data) = rx.recv().await {
+                tx.send(data).await?;
+            }
+        }
+        Ok(())
+    }
+}
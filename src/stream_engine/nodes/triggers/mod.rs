@@ -2,4 +2,8 @@ pub mod manual_trigger;
 pub mod time_trigger;
 pub mod webhook_trigger;
 pub mod child_workflow_trigger;
+pub mod sql_poll_trigger;
+pub mod pg_notify_trigger;
+pub mod event_trigger;
+pub mod error_trigger;
 
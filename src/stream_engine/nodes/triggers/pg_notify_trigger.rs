@@ -0,0 +1,108 @@
+use async_trait::async_trait;
+use crate::stream_engine::StreamNode;
+use tokio::sync::mpsc::{Receiver, Sender};
+use tokio::time::{sleep, Duration};
+use serde_json::{json, Value};
+use anyhow::{Result, anyhow};
+use sqlx::postgres::PgListener;
+
+/// LISTENs on one or more Postgres channels and emits every NOTIFY as
+/// `{ "channel", "payload", "process_id" }`, with the payload parsed as JSON when possible.
+/// Lost connections are re-established (and channels re-subscribed) until downstream closes.
+pub struct PgNotifyTrigger {
+    connection_string: String,
+    channels: Vec<String>,
+    reconnect_delay_ms: u64,
+}
+
+impl PgNotifyTrigger {
+    pub fn new(config: Value) -> Result<Self> {
+        let connection_string = config.get("connection_string")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing 'connection_string' for pg_notify_trigger"))?
+            .to_string();
+
+        // Accept either a list or a comma separated string
+        let channels: Vec<String> = match config.get("channels").or_else(|| config.get("channel")) {
+            Some(Value::Array(arr)) => arr.iter().filter_map(|v| v.as_str()).map(|s| s.trim().to_string()).collect(),
+            Some(Value::String(s)) => s.split(',').map(|c| c.trim().to_string()).collect(),
+            _ => Vec::new(),
+        };
+        let channels: Vec<String> = channels.into_iter().filter(|c| !c.is_empty()).collect();
+        if channels.is_empty() {
+            return Err(anyhow!("Missing 'channels' for pg_notify_trigger"));
+        }
+
+        let reconnect_delay_ms = config.get("reconnect_delay_ms").and_then(|v| v.as_u64()).unwrap_or(1000);
+
+        Ok(Self { connection_string, channels, reconnect_delay_ms })
+    }
+
+    async fn connect(&self) -> Result<PgListener> {
+        let mut listener = PgListener::connect(&self.connection_string).await?;
+        listener.listen_all(self.channels.iter().map(|c| c.as_str())).await?;
+        Ok(listener)
+    }
+}
+
+#[async_trait]
+impl StreamNode for PgNotifyTrigger {
+    async fn run(&self, _inputs: Vec<Receiver<Value>>, outputs: Vec<Sender<Value>>) -> Result<()> {
+        let tx = match outputs.first() {
+            Some(tx) => tx,
+            None => return Ok(()),
+        };
+
+        let retry_delay = Duration::from_millis(self.reconnect_delay_ms);
+
+        'connect: loop {
+            let mut listener = tokio::select! {
+                _ = tx.closed() => break 'connect,
+                res = self.connect() => match res {
+                    Ok(l) => l,
+                    Err(e) => {
+                        eprintln!("PgNotifyTrigger: Failed to listen on {:?}: {}. Retrying...", self.channels, e);
+                        sleep(retry_delay).await;
+                        continue 'connect;
+                    }
+                },
+            };
+
+            loop {
+                let received = tokio::select! {
+                    _ = tx.closed() => break 'connect,
+                    res = listener.try_recv() => res,
+                };
+
+                match received {
+                    Ok(Some(notification)) => {
+                        let raw = notification.payload();
+                        let payload = serde_json::from_str::<Value>(raw)
+                            .unwrap_or_else(|_| Value::String(raw.to_string()));
+
+                        let data = json!({
+                            "channel": notification.channel(),
+                            "payload": payload,
+                            "process_id": notification.process_id(),
+                        });
+
+                        if tx.send(data).await.is_err() {
+                            break 'connect; // Downstream closed
+                        }
+                    }
+                    Ok(None) => {
+                        // Connection lost; the next try_recv reconnects and re-subscribes.
+                        eprintln!("PgNotifyTrigger: Connection lost, reconnecting...");
+                    }
+                    Err(e) => {
+                        eprintln!("PgNotifyTrigger: Listener error: {}. Reconnecting...", e);
+                        sleep(retry_delay).await;
+                        continue 'connect;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
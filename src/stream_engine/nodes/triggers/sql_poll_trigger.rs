@@ -0,0 +1,145 @@
+use async_trait::async_trait;
+use crate::stream_engine::StreamNode;
+use crate::stream_engine::nodes::actions::sql_node::execute_query;
+use crate::storage::{team_kv_key, Storage};
+use tokio::sync::mpsc::{Receiver, Sender};
+use tokio::time::{interval, Duration};
+use serde_json::{json, Value};
+use anyhow::{Result, anyhow};
+use sqlx::AnyPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Polls a table (SQLite or Postgres, via `AnyPool`) for rows whose watermark column is
+/// greater than the last value seen, emitting each new row.
+/// The watermark is persisted in the key-value store when storage is available,
+/// so a restarted workflow resumes where it left off instead of replaying the table.
+/// Runs of a team keep their watermarks under the team (see `team_kv_key`).
+pub struct SqlPollTrigger {
+    connection_string: String,
+    query: String,
+    watermark_column: String,
+    initial_watermark: Value,
+    interval_seconds: u64,
+    state_key: String,
+    storage: Option<Arc<dyn Storage>>,
+}
+
+impl SqlPollTrigger {
+    pub fn new(config: Value, storage: Option<Arc<dyn Storage>>, team_id: Option<Uuid>) -> Result<Self> {
+        let connection_string = config.get("connection_string")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing 'connection_string' for sql_poll_trigger"))?
+            .to_string();
+
+        let watermark_column = config.get("watermark_column")
+            .and_then(|v| v.as_str())
+            .unwrap_or("id")
+            .to_string();
+
+        let table = config.get("table").and_then(|v| v.as_str());
+        let batch_size = config.get("batch_size").and_then(|v| v.as_u64()).unwrap_or(100);
+
+        // A custom query must compare against the watermark with `$1`, e.g.
+        // `SELECT id, total FROM orders WHERE id > $1 ORDER BY id`.
+        let query = match (config.get("query").and_then(|v| v.as_str()), table) {
+            (Some(q), _) if !q.trim().is_empty() => q.to_string(),
+            (_, Some(t)) => format!(
+                "SELECT * FROM {} WHERE {} > $1 ORDER BY {} ASC LIMIT {}",
+                t, watermark_column, watermark_column, batch_size
+            ),
+            _ => return Err(anyhow!("sql_poll_trigger requires either 'table' or 'query'")),
+        };
+
+        let initial_watermark = config.get("initial_watermark").cloned().unwrap_or(json!(0));
+        let interval_seconds = config.get("interval_seconds").and_then(|v| v.as_u64()).unwrap_or(60);
+
+        let key = config.get("state_key")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| format!("sql_poll_trigger:{}:{}", table.unwrap_or(&query), watermark_column));
+        let state_key = team_kv_key(team_id, &key);
+
+        Ok(Self {
+            connection_string,
+            query,
+            watermark_column,
+            initial_watermark,
+            interval_seconds,
+            state_key,
+            storage,
+        })
+    }
+
+    async fn load_watermark(&self) -> Value {
+        if let Some(storage) = &self.storage {
+            match storage.get_kv(&self.state_key).await {
+                Ok(Some(v)) => return v,
+                Ok(None) => {}
+                Err(e) => eprintln!("SqlPollTrigger: Failed to load watermark '{}': {}", self.state_key, e),
+            }
+        }
+        self.initial_watermark.clone()
+    }
+
+    async fn save_watermark(&self, watermark: &Value) {
+        if let Some(storage) = &self.storage {
+            if let Err(e) = storage.set_kv(&self.state_key, watermark).await {
+                eprintln!("SqlPollTrigger: Failed to persist watermark '{}': {}", self.state_key, e);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl StreamNode for SqlPollTrigger {
+    async fn run(&self, _inputs: Vec<Receiver<Value>>, outputs: Vec<Sender<Value>>) -> Result<()> {
+        let tx = match outputs.first() {
+            Some(tx) => tx,
+            None => return Ok(()),
+        };
+
+        sqlx::any::install_default_drivers();
+        let pool = AnyPool::connect(&self.connection_string).await?;
+
+        let mut watermark = self.load_watermark().await;
+        let mut ticker = interval(Duration::from_secs(self.interval_seconds.max(1)));
+
+        'poll: loop {
+            ticker.tick().await; // First tick completes immediately
+
+            if tx.is_closed() {
+                break; // Downstream closed
+            }
+
+            let rows = match execute_query(&pool, &self.query, vec![watermark.clone()]).await {
+                Ok(rows) => rows,
+                Err(e) => {
+                    eprintln!("SqlPollTrigger Query Error: {}", e);
+                    continue;
+                }
+            };
+
+            if rows.is_empty() {
+                continue;
+            }
+
+            for row in rows {
+                // Rows are ordered by the watermark column, so the last one delivered is the new high mark.
+                let next_mark = row.get(&self.watermark_column).filter(|v| !v.is_null()).cloned();
+                if tx.send(row).await.is_err() {
+                    self.save_watermark(&watermark).await;
+                    break 'poll; // Downstream closed
+                }
+                if let Some(v) = next_mark {
+                    watermark = v;
+                }
+            }
+
+            self.save_watermark(&watermark).await;
+        }
+
+        pool.close().await;
+        Ok(())
+    }
+}
@@ -5,15 +5,39 @@ use serde_json::{json, Value};
 use anyhow::Result;
 use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::time::{interval, Duration};
+use std::sync::Arc;
+use crate::storage::Storage;
+
+/// Leadership name shared by the schedules of every server, see `Storage::try_acquire_leadership`.
+const LEADERSHIP: &str = "rustflow:schedules";
 
 pub struct TimeTrigger {
     cron_expression: String, // Kept for metadata, though we just loop for now
     interval_seconds: u64,
+    leadership: Option<Arc<dyn Storage>>,
 }
 
 impl TimeTrigger {
     pub fn new(cron_expression: String, interval_seconds: u64) -> Self {
-        Self { cron_expression, interval_seconds }
+        Self { cron_expression, interval_seconds, leadership: None }
+    }
+
+    /// Only fires while this process is the elected leader of `storage`, so a schedule that
+    /// runs on several servers fires once.
+    pub fn with_leadership(mut self, storage: Option<Arc<dyn Storage>>) -> Self {
+        self.leadership = storage;
+        self
+    }
+
+    async fn is_leader(&self) -> bool {
+        let Some(storage) = &self.leadership else { return true };
+        match storage.try_acquire_leadership(LEADERSHIP).await {
+            Ok(leader) => leader,
+            Err(e) => {
+                tracing::error!("Schedule leader election failed: {}", e);
+                false
+            }
+        }
     }
 }
 
@@ -26,6 +50,9 @@ impl StreamNode for TimeTrigger {
             // Loop forever (or until channel closed)
             loop {
                 ticker.tick().await; // First tick completes immediately
+                if !self.is_leader().await {
+                    continue;
+                }
 
                 let start = SystemTime::now();
                 let since_the_epoch = start
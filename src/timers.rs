@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::Result;
+use chrono::Utc;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+use crate::job_manager::JobManager;
+use crate::storage::{Storage, TimerRecord};
+
+const LEADERSHIP: &str = "rustflow:timers";
+
+/// Resumes executions suspended by durable `delay` nodes, and by `approval` nodes whose
+/// decision arrived while the execution was still running or whose approval expired.
+///
+/// A durable delay stores each record as a timer and the execution ends up `waiting`.
+/// Once timers are due, the execution is put back into the execution queue to run from the
+/// delay node onwards with the due records. Timers of one node are resumed together; an
+/// execution that is still running is resumed once it is waiting again. Timers of executions
+/// that finished otherwise (e.g. cancelled) are dropped.
+pub struct Timers {
+    storage: Arc<dyn Storage>,
+    job_manager: Arc<JobManager>,
+}
+
+impl Timers {
+    pub fn new(storage: Arc<dyn Storage>, job_manager: Arc<JobManager>) -> Self {
+        Self { storage, job_manager }
+    }
+
+    /// Resumes the executions with due timers. Returns the ids of the resumed executions.
+    pub async fn fire_due(&self) -> Result<Vec<Uuid>> {
+        let mut due: HashMap<Uuid, Vec<TimerRecord>> = HashMap::new();
+        for timer in self.storage.list_due_timers(Utc::now()).await? {
+            due.entry(timer.execution_id).or_default().push(timer);
+        }
+
+        let mut resumed = Vec::new();
+        for (execution_id, timers) in due {
+            let status = self.storage.get_execution(execution_id).await?.map(|e| e.status);
+            match status.as_deref() {
+                Some("waiting") => {}
+                Some("pending" | "running" | "queued") => continue,
+                _ => {
+                    let ids: Vec<Uuid> = timers.iter().map(|t| t.id).collect();
+                    self.storage.update_timer_status(&ids, "cancelled").await?;
+                    continue;
+                }
+            }
+
+            // Earliest first, so the node of the first timer is resumed first
+            let node_id = timers[0].node_id.clone();
+            let (ids, records): (Vec<Uuid>, Vec<_>) = timers.into_iter()
+                .filter(|t| t.node_id == node_id)
+                .map(|t| (t.id, t.record))
+                .unzip();
+
+            // Fired before resuming, so the resumed run does not count them as still waiting
+            self.storage.update_timer_status(&ids, "fired").await?;
+            if self.job_manager.resume_execution(execution_id, &node_id, records).await? {
+                tracing::info!("Resumed execution {} at node {} with {} records", execution_id, node_id, ids.len());
+                resumed.push(execution_id);
+            } else {
+                self.storage.update_timer_status(&ids, "waiting").await?;
+            }
+        }
+        Ok(resumed)
+    }
+
+    /// Checks for due timers every `poll_interval` in the background.
+    /// When several servers share the storage, only the elected leader resumes executions.
+    pub fn start(self, poll_interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                match self.storage.try_acquire_leadership(LEADERSHIP).await {
+                    Ok(true) => {
+                        if let Err(e) = self.fire_due().await {
+                            tracing::error!("Failed to fire timers: {}", e);
+                        }
+                    }
+                    Ok(false) => {}
+                    Err(e) => tracing::error!("Timer leader election failed: {}", e),
+                }
+                tokio::time::sleep(poll_interval).await;
+            }
+        })
+    }
+}
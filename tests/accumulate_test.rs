@@ -41,7 +41,7 @@ async fn test_accumulate_all() {
         EdgeDefinition { from: "accumulate".to_string(), to: "sink".to_string(), from_port: None, to_port: None },
     ];
 
-    let workflow = WorkflowDefinition { nodes, edges };
+    let workflow = WorkflowDefinition { nodes, edges, ..Default::default() };
     let mut executor = workflow.to_executor(&HashMap::new(), DebugConfig::default()).expect("Failed to create executor");
     executor.inject_input("source", json!(null));
     
@@ -107,7 +107,7 @@ async fn test_accumulate_batch() {
         EdgeDefinition { from: "accumulate".to_string(), to: "sink".to_string(), from_port: None, to_port: None },
     ];
 
-    let workflow = WorkflowDefinition { nodes, edges };
+    let workflow = WorkflowDefinition { nodes, edges, ..Default::default() };
     let mut executor = workflow.to_executor(&HashMap::new(), DebugConfig::default()).expect("Failed to create executor");
     executor.inject_input("source", json!(null));
     
@@ -0,0 +1,220 @@
+use rust_flow::job_manager::{JobManager, JobSpec, WorkerConfig};
+use rust_flow::schema::{ExecutionEvent, WorkflowLoader};
+use rust_flow::server::{self, AppState};
+use rust_flow::stream_engine::{StreamNode, NodeContext};
+use rust_flow::stream_engine::nodes::ApprovalNode;
+use rust_flow::storage::{Role, Storage, MemoryStorage};
+use rust_flow::timers::Timers;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio::time::{timeout, Duration};
+use uuid::Uuid;
+
+struct Ports {
+    approved: mpsc::Receiver<Value>,
+    rejected: mpsc::Receiver<Value>,
+    expired: mpsc::Receiver<Value>,
+    requested: mpsc::Receiver<Value>,
+}
+
+fn spawn_node(node: ApprovalNode, records: Vec<Value>) -> (Ports, tokio::task::JoinHandle<anyhow::Result<()>>) {
+    let (in_tx, in_rx) = mpsc::channel(10);
+    let (approved_tx, approved) = mpsc::channel(10);
+    let (rejected_tx, rejected) = mpsc::channel(10);
+    let (expired_tx, expired) = mpsc::channel(10);
+    let (requested_tx, requested) = mpsc::channel(10);
+
+    let handle = tokio::spawn(async move {
+        for record in records {
+            in_tx.send(record).await?;
+        }
+        drop(in_tx);
+        node.run(vec![in_rx], vec![approved_tx, rejected_tx, expired_tx, requested_tx]).await
+    });
+    (Ports { approved, rejected, expired, requested }, handle)
+}
+
+async fn recv(rx: &mut mpsc::Receiver<Value>) -> Value {
+    timeout(Duration::from_secs(5), rx.recv()).await.expect("timed out").expect("channel closed")
+}
+
+#[tokio::test]
+async fn test_approval_routes_decisions() -> anyhow::Result<()> {
+    let storage = Arc::new(MemoryStorage::new());
+    let execution_id = Uuid::new_v4();
+    let context = NodeContext { storage: Some(storage.clone()), execution_id: Some(execution_id), ..Default::default() };
+    let node = ApprovalNode::new(json!({ "message": "Pay {{ amount }}?", "poll_interval_ms": 20 }), context)?;
+
+    let (mut ports, handle) = spawn_node(node, vec![json!({ "amount": 100 }), json!({ "amount": 5000 })]);
+
+    let first = recv(&mut ports.requested).await;
+    let second = recv(&mut ports.requested).await;
+    assert_eq!(first["message"], "Pay 100?");
+    assert_eq!(first["execution_id"], execution_id.to_string());
+    let token = first["token"].as_str().unwrap();
+    assert_eq!(first["signal_path"], format!("/api/executions/{}/signal/{}", execution_id, token));
+
+    let pending = storage.get_approval(token).await?.unwrap();
+    assert_eq!(pending.status, "pending");
+    assert_eq!(pending.record, json!({ "amount": 100 }));
+
+    // Decide the second record first: records wait independently
+    let decision = json!({ "decision": "rejected", "comment": "too much" });
+    assert!(storage.resolve_approval(second["token"].as_str().unwrap(), "rejected", Some(&decision)).await?);
+    let rejected = recv(&mut ports.rejected).await;
+    assert_eq!(rejected["amount"], 5000);
+    assert_eq!(rejected["approval"]["status"], "rejected");
+    assert_eq!(rejected["approval"]["decision"]["comment"], "too much");
+
+    assert!(storage.resolve_approval(token, "approved", None).await?);
+    // A decision is final
+    assert!(!storage.resolve_approval(token, "rejected", None).await?);
+    let approved = recv(&mut ports.approved).await;
+    assert_eq!(approved["amount"], 100);
+    assert_eq!(approved["approval"]["status"], "approved");
+
+    handle.await??;
+    assert!(ports.expired.try_recv().is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_approval_expires() -> anyhow::Result<()> {
+    let storage = Arc::new(MemoryStorage::new());
+    let context = NodeContext { storage: Some(storage.clone()), ..Default::default() };
+    let node = ApprovalNode::new(json!({ "timeout_seconds": 1, "poll_interval_ms": 20 }), context)?;
+
+    let (mut ports, handle) = spawn_node(node, vec![json!({ "id": 1 })]);
+
+    let request = recv(&mut ports.requested).await;
+    assert!(request["expires_at"].is_string());
+    let expired = recv(&mut ports.expired).await;
+    assert_eq!(expired["approval"]["status"], "expired");
+
+    // Signals arriving after expiry are refused
+    assert!(!storage.resolve_approval(request["token"].as_str().unwrap(), "approved", None).await?);
+    handle.await??;
+    assert!(ports.approved.try_recv().is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_approval_requires_storage() {
+    let node = ApprovalNode::new(json!({}), NodeContext::default()).unwrap();
+    let (_ports, handle) = spawn_node(node, vec![json!({ "id": 1 })]);
+    assert!(handle.await.unwrap().is_err());
+}
+
+const REVIEW: &str = r#"
+nodes:
+  - id: start
+    type: manual_trigger
+    config: {}
+  - id: review
+    type: approval
+    config: { message: "Refund {{ amount }}?" }
+  - id: refund
+    type: console_output
+    config: {}
+  - id: notify
+    type: console_output
+    config: {}
+edges:
+  - from: start
+    to: review
+  - from: review
+    from_port: approved
+    to: refund
+  - from: review
+    from_port: requested
+    to: notify
+"#;
+
+async fn wait_for_status(storage: &MemoryStorage, id: Uuid, status: &str) {
+    for _ in 0..50 {
+        if storage.get_execution(id).await.unwrap().unwrap().status == status {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("Execution {} never became {}", id, status);
+}
+
+fn edge_records(logs: &[ExecutionEvent], from: &str) -> Vec<Value> {
+    logs.iter()
+        .filter_map(|e| match e {
+            ExecutionEvent::EdgeData { from: f, value, .. } if f == from => Some(value.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_approval_suspends_execution_until_signalled() -> anyhow::Result<()> {
+    let storage = Arc::new(MemoryStorage::new());
+    let manager = Arc::new(JobManager::new(storage.clone()));
+    manager.start_workers(WorkerConfig { workers: 1, ..Default::default() }).await?;
+
+    let team = storage.create_team("ops").await?;
+    let ada = storage.create_user("ada", "pw").await?;
+    storage.add_team_member(team.id, ada.id, Role::Member).await?;
+    let (_, session, _) = rust_flow::auth::login(storage.as_ref(), "ada", "pw").await?.unwrap();
+
+    let state = Arc::new(AppState { job_manager: manager.clone(), storage: storage.clone(), settings: Default::default() });
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}", listener.local_addr()?);
+    tokio::spawn(async move {
+        axum::serve(listener, server::router(state)).await.unwrap();
+    });
+
+    let definition = WorkflowLoader::new().load(REVIEW)?;
+    let spec = JobSpec { team_id: Some(team.id), inputs: vec![("start".to_string(), json!({ "amount": 40 }))], ..Default::default() };
+    let id = Uuid::parse_str(&manager.start_workflow(&definition, spec).await?)?;
+
+    // The run ends with the approval pending instead of holding a worker
+    wait_for_status(&storage, id, "waiting").await;
+    assert_eq!(storage.count_open_approvals(id).await?, 1);
+    let requests = edge_records(&storage.get_execution_logs(id).await?, "review");
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0]["message"], "Refund 40?");
+
+    let client = reqwest::Client::new();
+    client.post(format!("{}{}", url, requests[0]["signal_path"].as_str().unwrap()))
+        .bearer_auth(&session)
+        .json(&json!({ "decision": "approve", "comment": "ok" }))
+        .send().await?
+        .error_for_status()?;
+    wait_for_status(&storage, id, "completed").await;
+
+    let approved = edge_records(&storage.get_execution_logs(id).await?, "review");
+    assert_eq!(approved.len(), 2);
+    assert_eq!(approved[1]["amount"], 40);
+    assert_eq!(approved[1]["approval"]["status"], "approved");
+    assert_eq!(storage.count_open_approvals(id).await?, 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_suspended_approval_expires_through_timers() -> anyhow::Result<()> {
+    let storage = Arc::new(MemoryStorage::new());
+    let manager = Arc::new(JobManager::new(storage.clone()));
+    manager.start_workers(WorkerConfig { workers: 1, ..Default::default() }).await?;
+    let timers = Timers::new(storage.clone(), manager.clone());
+
+    let definition = WorkflowLoader::new().load(&REVIEW.replace("config: { message", "config: { timeout_seconds: 1, message"))?;
+    let spec = JobSpec { inputs: vec![("start".to_string(), json!({ "amount": 40 }))], ..Default::default() };
+    let id = Uuid::parse_str(&manager.start_workflow(&definition, spec).await?)?;
+    wait_for_status(&storage, id, "waiting").await;
+    assert!(timers.fire_due().await?.is_empty());
+
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    assert_eq!(timers.fire_due().await?, vec![id]);
+    wait_for_status(&storage, id, "completed").await;
+
+    let requests = edge_records(&storage.get_execution_logs(id).await?, "review");
+    let approval = storage.get_approval(requests[0]["token"].as_str().unwrap()).await?.unwrap();
+    assert_eq!(approval.status, "expired");
+    assert!(approval.delivered_at.is_some());
+    Ok(())
+}
@@ -0,0 +1,160 @@
+use rust_flow::audit;
+use rust_flow::job_manager::{JobManager, JobSpec, WorkerConfig};
+use rust_flow::schema::WorkflowLoader;
+use rust_flow::storage::{Storage, MemoryStorage};
+use rust_flow::timers::Timers;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio::time::{sleep, Duration};
+use uuid::Uuid;
+
+mod common;
+
+const CHAT: &str = r#"
+nodes:
+  - id: start
+    type: manual_trigger
+    config: {}
+  - id: chat
+    type: console_output
+    config: { credential_id: "CREDENTIAL" }
+edges:
+  - from: start
+    to: chat
+"#;
+
+const DRIP: &str = r#"
+nodes:
+  - id: start
+    type: manual_trigger
+    config: {}
+  - id: wait
+    type: delay
+    config: { duration_ms: 100, durable: true, durable_threshold_ms: 10 }
+  - id: chat
+    type: console_output
+    config: { credential_id: "CREDENTIAL" }
+edges:
+  - from: start
+    to: wait
+  - from: wait
+    to: chat
+"#;
+
+async fn wait_for_status(storage: &MemoryStorage, id: Uuid, status: &str) {
+    for _ in 0..50 {
+        if storage.get_execution(id).await.unwrap().unwrap().status == status {
+            return;
+        }
+        sleep(Duration::from_millis(50)).await;
+    }
+    panic!("Execution {} never became {}", id, status);
+}
+
+async fn check_audit_log(storage: &dyn Storage) -> anyhow::Result<()> {
+    let team = storage.create_team(&format!("audit-{}", Uuid::new_v4())).await?;
+    let other = storage.create_team(&format!("audit-{}", Uuid::new_v4())).await?;
+    let actor = storage.create_user(&format!("auditor-{}", Uuid::new_v4()), "pw").await?;
+
+    let diff = audit::diff(&Value::Null, &json!({ "name": "sync" }));
+    audit::record(storage, Some(actor.id), team.id, "workflow.save", "workflow/1".to_string(), Some(diff.clone())).await?;
+    audit::record(storage, Some(actor.id), team.id, "execution.start", "execution/2".to_string(), None).await?;
+    audit::record(storage, None, other.id, "credential.read", "credential/3".to_string(), None).await?;
+
+    // Newest first, scoped to the team
+    let entries = storage.list_audit(team.id, 10, 0).await?;
+    assert_eq!(entries.iter().map(|e| e.action.as_str()).collect::<Vec<_>>(), vec!["execution.start", "workflow.save"]);
+    assert_eq!(entries[1].actor_id, Some(actor.id));
+    assert_eq!(entries[1].target, "workflow/1");
+    assert_eq!(entries[1].diff, Some(diff));
+    assert_eq!(entries[0].diff, None);
+
+    let page = storage.list_audit(team.id, 1, 1).await?;
+    assert_eq!(page.len(), 1);
+    assert_eq!(page[0].action, "workflow.save");
+
+    let entries = storage.list_audit(other.id, 10, 0).await?;
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].actor_id, None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sqlite_audit_log() -> anyhow::Result<()> {
+    let (storage, _db) = common::sqlite("audit").await?;
+    check_audit_log(storage.as_ref()).await
+}
+
+#[tokio::test]
+async fn test_memory_audit_log() -> anyhow::Result<()> {
+    check_audit_log(&MemoryStorage::new()).await
+}
+
+#[tokio::test]
+async fn test_postgres_audit_log() -> anyhow::Result<()> {
+    let Some(storage) = common::postgres().await? else {
+        return Ok(());
+    };
+    check_audit_log(&storage).await
+}
+
+#[tokio::test]
+async fn test_secret_reads_are_audited() -> anyhow::Result<()> {
+    let storage = MemoryStorage::new();
+    let team = storage.create_team("ops").await?;
+    let ada = storage.create_user("ada", "pw").await?;
+    let cred = storage.create_credential("openai", "openai_api", &json!("sk-secret"), team.id).await?;
+    storage.create_credential("unused", "openai_api", &json!("sk-other"), team.id).await?;
+
+    // Only credentials the run references are decrypted
+    let definition = WorkflowLoader::new().load(&CHAT.replace("CREDENTIAL", &cred.id.to_string()))?;
+    let execution_id = Uuid::new_v4();
+    let secrets = audit::read_secrets(&storage, Some(ada.id), team.id, &definition, Some(execution_id)).await?;
+    assert_eq!(secrets.keys().collect::<Vec<_>>(), vec![&cred.id.to_string()]);
+
+    let entries = storage.list_audit(team.id, 10, 0).await?;
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].action, "credential.read");
+    assert_eq!(entries[0].actor_id, Some(ada.id));
+    assert_eq!(entries[0].target, format!("credential/{}", cred.id));
+    assert_eq!(entries[0].diff, Some(json!({ "execution_id": execution_id })));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_resumed_runs_are_audited_once() -> anyhow::Result<()> {
+    let storage = Arc::new(MemoryStorage::new());
+    let team = storage.create_team("ops").await?;
+    let ada = storage.create_user("ada", "pw").await?;
+    let cred = storage.create_credential("openai", "openai_api", &json!("sk-secret"), team.id).await?;
+    let manager = Arc::new(JobManager::new(storage.clone()));
+    manager.start_workers(WorkerConfig { workers: 1, ..Default::default() }).await?;
+    let timers = Timers::new(storage.clone(), manager.clone());
+
+    // Suspends at the delay, then resumes to the node using the credential
+    let definition = WorkflowLoader::new().load(&DRIP.replace("CREDENTIAL", &cred.id.to_string()))?;
+    let spec = JobSpec { team_id: Some(team.id), actor_id: Some(ada.id), ..Default::default() };
+    let id = Uuid::parse_str(&manager.start_workflow(&definition, spec).await?)?;
+    wait_for_status(&storage, id, "waiting").await;
+    sleep(Duration::from_millis(200)).await;
+    assert_eq!(timers.fire_due().await?, vec![id]);
+    wait_for_status(&storage, id, "completed").await;
+
+    let reads: Vec<_> = storage.list_audit(team.id, 10, 0).await?.into_iter().filter(|e| e.action == "credential.read").collect();
+    assert_eq!(reads.len(), 1);
+    assert_eq!(reads[0].actor_id, Some(ada.id));
+    Ok(())
+}
+
+#[test]
+fn test_diff() {
+    let before = json!({ "name": "sync", "definition": { "nodes": [{ "id": "a" }, { "id": "b" }], "a/b": 1 } });
+    let after = json!({ "name": "sync v2", "definition": { "nodes": [{ "id": "a" }], "a/b": 1 }, "team": "ops" });
+    assert_eq!(audit::diff(&before, &after), json!({
+        "/name": { "before": "sync", "after": "sync v2" },
+        "/definition/nodes/1": { "before": { "id": "b" }, "after": null },
+        "/team": { "before": null, "after": "ops" },
+    }));
+    assert_eq!(audit::diff(&before, &before), json!({}));
+    assert_eq!(audit::diff(&Value::Null, &json!({ "role": "admin" })), json!({ "/role": { "before": null, "after": "admin" } }));
+}
@@ -0,0 +1,157 @@
+use rust_flow::auth;
+use rust_flow::job_manager::JobManager;
+use rust_flow::server::{self, AppState, ServerSettings};
+use rust_flow::storage::{Role, Storage, MemoryStorage};
+use chrono::{Duration, Utc};
+use reqwest::StatusCode;
+use serde_json::json;
+use std::sync::Arc;
+use uuid::Uuid;
+
+mod common;
+
+async fn check_tokens(storage: &dyn Storage) -> anyhow::Result<()> {
+    let username = format!("ada-{}", Uuid::new_v4());
+    let user = storage.create_user(&username, "correct horse").await?;
+
+    // Passwords are verified server-side
+    assert!(auth::login(storage, &username, "wrong").await?.is_none());
+    assert!(auth::login(storage, "nobody", "correct horse").await?.is_none());
+    let (logged_in, session, info) = auth::login(storage, &username, "correct horse").await?.unwrap();
+    assert_eq!(logged_in.id, user.id);
+    assert_eq!(info.kind, "session");
+    assert!(info.expires_at.unwrap() > Utc::now() + Duration::days(auth::SESSION_TTL_DAYS - 1));
+
+    let (authenticated, token) = auth::authenticate(storage, &session).await?.unwrap();
+    assert_eq!(authenticated.id, user.id);
+    assert_eq!(token.id, info.id);
+    assert!(auth::authenticate(storage, "rf_forged").await?.is_none());
+
+    // Only the hash is stored
+    let stored = storage.get_api_token_by_hash(&auth::hash_token(&session)).await?.unwrap();
+    assert_ne!(stored.token_hash, session);
+
+    // Personal tokens, expired tokens and revocation
+    let (personal, _) = auth::issue_token(storage, user.id, "ci", "personal", None).await?;
+    let (expired, _) = auth::issue_token(storage, user.id, "old", "personal", Some(Utc::now() - Duration::minutes(1))).await?;
+    assert!(auth::authenticate(storage, &personal).await?.is_some());
+    assert!(auth::authenticate(storage, &expired).await?.is_none());
+
+    let tokens = storage.list_api_tokens(user.id).await?;
+    assert_eq!(tokens.iter().map(|t| t.name.as_str()).collect::<Vec<_>>(), vec!["login", "ci", "old"]);
+
+    let other = storage.create_user(&format!("eve-{}", Uuid::new_v4()), "pw").await?;
+    assert!(!storage.delete_api_token(other.id, info.id).await?, "only the owner revokes");
+    assert!(storage.delete_api_token(user.id, info.id).await?);
+    assert!(auth::authenticate(storage, &session).await?.is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sqlite_tokens() -> anyhow::Result<()> {
+    let (storage, _db) = common::sqlite("auth").await?;
+    check_tokens(storage.as_ref()).await
+}
+
+#[tokio::test]
+async fn test_memory_tokens() -> anyhow::Result<()> {
+    check_tokens(&MemoryStorage::new()).await
+}
+
+#[tokio::test]
+async fn test_postgres_tokens() -> anyhow::Result<()> {
+    let Some(storage) = common::postgres().await? else {
+        return Ok(());
+    };
+    check_tokens(&storage).await
+}
+
+#[tokio::test]
+async fn test_secrets_are_not_serialized() -> anyhow::Result<()> {
+    let storage = MemoryStorage::new();
+
+    let user = storage.create_user("grace", "pw").await?;
+    assert!(user.verify_password("pw"));
+    let json = serde_json::to_value(&user)?;
+    assert!(json.get("password_hash").is_none());
+
+    let (_, token) = auth::issue_token(&storage, user.id, "ci", "personal", None).await?;
+    assert!(serde_json::to_value(&token)?.get("token_hash").is_none());
+    Ok(())
+}
+
+/// Serves the API over `storage` on a free local port; returns its URL.
+async fn serve(storage: Arc<MemoryStorage>, settings: ServerSettings) -> anyhow::Result<String> {
+    let state = Arc::new(AppState { job_manager: Arc::new(JobManager::new(storage.clone())), storage, settings });
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}", listener.local_addr()?);
+    tokio::spawn(async move {
+        axum::serve(listener, server::router(state)).await.unwrap();
+    });
+    Ok(url)
+}
+
+#[tokio::test]
+async fn test_registration_is_closed_after_the_first_account() -> anyhow::Result<()> {
+    let storage = Arc::new(MemoryStorage::new());
+    let url = serve(storage.clone(), ServerSettings::default()).await?;
+    let client = reqwest::Client::new();
+    let register = |name: &str, token: Option<&str>| {
+        let req = client.post(format!("{}/api/auth/register", url)).json(&json!({ "username": name, "password": "pw" }));
+        match token {
+            Some(token) => req.bearer_auth(token),
+            None => req,
+        }
+    };
+
+    assert_eq!(register("ada", None).send().await?.status(), StatusCode::OK, "the first account registers itself");
+    assert_eq!(register("eve", None).send().await?.status(), StatusCode::UNAUTHORIZED);
+
+    let (ada, session, _) = auth::login(storage.as_ref(), "ada", "pw").await?.unwrap();
+    assert_eq!(register("eve", Some(&session)).send().await?.status(), StatusCode::FORBIDDEN, "ada administers no team");
+    let team = storage.create_team("ops").await?;
+    storage.add_team_member(team.id, ada.id, Role::Admin).await?;
+    assert_eq!(register("bob", Some(&session)).send().await?.status(), StatusCode::OK);
+
+    let url = serve(storage.clone(), ServerSettings { open_registration: true, ..Default::default() }).await?;
+    let response = client.post(format!("{}/api/auth/register", url)).json(&json!({ "username": "eve", "password": "pw" })).send().await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_tokens_only_open_websockets() -> anyhow::Result<()> {
+    let storage = Arc::new(MemoryStorage::new());
+    storage.create_user("ada", "pw").await?;
+    let (_, session, _) = auth::login(storage.as_ref(), "ada", "pw").await?.unwrap();
+    let url = serve(storage, ServerSettings::default()).await?;
+
+    let client = reqwest::Client::new();
+    let response = client.get(format!("{}/api/auth/me?access_token={}", url, session)).send().await?;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = client.get(format!("{}/api/auth/me", url)).bearer_auth(&session).send().await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    // Not a WebSocket handshake, but authenticated
+    let response = client.get(format!("{}/api/ws/{}?access_token={}", url, Uuid::new_v4(), session)).send().await?;
+    assert_ne!(response.status(), StatusCode::UNAUTHORIZED);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_token_lifetimes_are_bounded() -> anyhow::Result<()> {
+    let storage = Arc::new(MemoryStorage::new());
+    storage.create_user("ada", "pw").await?;
+    let (_, session, _) = auth::login(storage.as_ref(), "ada", "pw").await?.unwrap();
+    let url = serve(storage, ServerSettings::default()).await?;
+
+    let client = reqwest::Client::new();
+    let create = |days: i64| {
+        client.post(format!("{}/api/auth/tokens", url)).bearer_auth(&session).json(&json!({ "name": "ci", "expires_in_days": days })).send()
+    };
+    assert_eq!(create(90).await?.status(), StatusCode::OK);
+    assert_eq!(create(auth::MAX_TOKEN_DAYS).await?.status(), StatusCode::OK);
+    for days in [0, -1, auth::MAX_TOKEN_DAYS + 1, i64::MAX, i64::MIN] {
+        assert_eq!(create(days).await?.status(), StatusCode::BAD_REQUEST, "{} days", days);
+    }
+    Ok(())
+}
@@ -0,0 +1,154 @@
+use rust_flow::job_manager::{JobManager, JobSpec, JobStatus, WorkerConfig};
+use rust_flow::schema::{ConcurrencyPolicy, WorkflowDefinition, WorkflowLoader};
+use rust_flow::storage::{Storage, MemoryStorage, WorkflowEntity, QueuedExecution};
+use std::sync::Arc;
+use tokio::time::{sleep, Duration};
+use uuid::Uuid;
+
+const SLOW: &str = r#"
+nodes:
+  - id: start
+    type: manual_trigger
+    config: {}
+  - id: wait
+    type: delay
+    config: { duration_ms: 300 }
+edges:
+  - from: start
+    to: wait
+"#;
+
+async fn setup(policy: ConcurrencyPolicy) -> anyhow::Result<(Arc<MemoryStorage>, Arc<JobManager>, WorkflowDefinition, JobSpec)> {
+    let storage = Arc::new(MemoryStorage::new());
+
+    let team = storage.create_team("sync").await?;
+    let mut definition = WorkflowLoader::new().load(SLOW)?;
+    definition.concurrency = policy;
+
+    let workflow = WorkflowEntity::new(team.id, "slow sync", serde_json::to_value(&definition)?);
+    storage.save_workflow(&workflow, None, None).await?;
+
+    let spec = JobSpec { workflow_id: Some(workflow.id), team_id: Some(team.id), ..Default::default() };
+    Ok((storage.clone(), Arc::new(JobManager::new(storage)), definition, spec))
+}
+
+async fn stored_status(storage: &MemoryStorage, job_id: &str) -> String {
+    storage.get_execution(Uuid::parse_str(job_id).unwrap()).await.unwrap().unwrap().status
+}
+
+#[tokio::test]
+async fn test_skip_policy() -> anyhow::Result<()> {
+    let (storage, manager, definition, spec) = setup(ConcurrencyPolicy::Skip).await?;
+
+    let first = manager.start_workflow(&definition, spec.clone()).await?;
+    let second = manager.start_workflow(&definition, spec.clone()).await?;
+
+    assert!(matches!(manager.get_job(&second), Some(JobStatus::Skipped(_))));
+    assert_eq!(stored_status(&storage, &second).await, "skipped");
+
+    sleep(Duration::from_millis(600)).await;
+    assert_eq!(stored_status(&storage, &first).await, "completed");
+
+    // Once finished, the workflow can start again
+    let third = manager.start_workflow(&definition, spec).await?;
+    assert_ne!(stored_status(&storage, &third).await, "skipped");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_queue_policy() -> anyhow::Result<()> {
+    let (storage, manager, definition, spec) = setup(ConcurrencyPolicy::Queue { max_depth: 1 }).await?;
+
+    let first = manager.start_workflow(&definition, spec.clone()).await?;
+    let second = manager.start_workflow(&definition, spec.clone()).await?;
+    let third = manager.start_workflow(&definition, spec).await?;
+
+    assert_eq!(stored_status(&storage, &second).await, "queued");
+    assert_eq!(stored_status(&storage, &third).await, "skipped");
+
+    // The queued run starts after the first one finished
+    sleep(Duration::from_millis(450)).await;
+    assert_eq!(stored_status(&storage, &first).await, "completed");
+    assert_eq!(manager.get_job(&second), Some(JobStatus::Running));
+
+    sleep(Duration::from_millis(450)).await;
+    assert_eq!(stored_status(&storage, &second).await, "completed");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_replace_policy() -> anyhow::Result<()> {
+    let (storage, manager, definition, spec) = setup(ConcurrencyPolicy::Replace).await?;
+
+    let first = manager.start_workflow(&definition, spec.clone()).await?;
+    sleep(Duration::from_millis(50)).await;
+    let second = manager.start_workflow(&definition, spec).await?;
+
+    sleep(Duration::from_millis(100)).await;
+    assert_eq!(manager.get_job(&first), Some(JobStatus::Cancelled));
+    assert_eq!(stored_status(&storage, &first).await, "cancelled");
+
+    sleep(Duration::from_millis(500)).await;
+    assert_eq!(stored_status(&storage, &second).await, "completed");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_queue_policy_with_workers() -> anyhow::Result<()> {
+    let (storage, manager, definition, spec) = setup(ConcurrencyPolicy::Queue { max_depth: 1 }).await?;
+    manager.start_workers(WorkerConfig { workers: 2, ..Default::default() }).await?;
+
+    let first = manager.start_workflow(&definition, spec.clone()).await?;
+    let second = manager.start_workflow(&definition, spec.clone()).await?;
+    let third = manager.start_workflow(&definition, spec).await?;
+    assert_eq!(stored_status(&storage, &third).await, "skipped");
+
+    // A worker is free, but the queued run waits for the running one
+    sleep(Duration::from_millis(150)).await;
+    assert_eq!(stored_status(&storage, &first).await, "running");
+    assert_eq!(stored_status(&storage, &second).await, "queued");
+
+    sleep(Duration::from_millis(700)).await;
+    assert_eq!(stored_status(&storage, &first).await, "completed");
+    assert_eq!(stored_status(&storage, &second).await, "completed");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_skip_policy_applies_when_claimed() -> anyhow::Result<()> {
+    let (storage, manager, definition, spec) = setup(ConcurrencyPolicy::Skip).await?;
+
+    // Enqueued by other processes before either started
+    let mut ids = Vec::new();
+    for _ in 0..2 {
+        let run = QueuedExecution {
+            id: Uuid::new_v4(),
+            workflow_id: spec.workflow_id,
+            team_id: spec.team_id,
+            definition: serde_json::to_value(&definition)?,
+            inputs: serde_json::json!([]),
+            priority: 0,
+            resume_from: None,
+            started_by: None,
+        };
+        storage.enqueue_execution(&run).await?;
+        ids.push(run.id.to_string());
+        sleep(Duration::from_millis(10)).await;
+    }
+
+    manager.start_workers(WorkerConfig { workers: 2, ..Default::default() }).await?;
+    sleep(Duration::from_millis(600)).await;
+    assert_eq!(stored_status(&storage, &ids[0]).await, "completed");
+    assert_eq!(stored_status(&storage, &ids[1]).await, "skipped");
+    Ok(())
+}
+
+#[test]
+fn test_concurrency_policy_yaml() {
+    let definition = WorkflowLoader::new().load(&format!("concurrency:\n  mode: queue\n{}", SLOW)).unwrap();
+    assert_eq!(definition.concurrency, ConcurrencyPolicy::Queue { max_depth: 10 });
+
+    // The default is not written back out
+    let parallel = WorkflowLoader::new().load(SLOW).unwrap();
+    assert!(serde_json::to_value(&parallel).unwrap().get("concurrency").is_none());
+}
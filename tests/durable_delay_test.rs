@@ -0,0 +1,140 @@
+use rust_flow::job_manager::{JobManager, JobSpec, WorkerConfig};
+use rust_flow::schema::{ExecutionEvent, WorkflowDefinition, WorkflowLoader};
+use rust_flow::storage::{Storage, MemoryStorage};
+use rust_flow::timers::Timers;
+use serde_json::json;
+use std::sync::Arc;
+use tokio::time::{sleep, Duration};
+use uuid::Uuid;
+
+const DRIP: &str = r#"
+nodes:
+  - id: start
+    type: manual_trigger
+    config: {}
+  - id: wait
+    type: delay
+    config: { duration_ms: 300, durable: true, durable_threshold_ms: 100 }
+  - id: follow_up
+    type: console_output
+    config: {}
+edges:
+  - from: start
+    to: wait
+  - from: wait
+    to: follow_up
+"#;
+
+async fn setup() -> anyhow::Result<(Arc<MemoryStorage>, Arc<JobManager>, Timers)> {
+    let storage = Arc::new(MemoryStorage::new());
+
+    let manager = Arc::new(JobManager::new(storage.clone()));
+    manager.start_workers(WorkerConfig { workers: 2, ..Default::default() }).await?;
+    let timers = Timers::new(storage.clone(), manager.clone());
+    Ok((storage, manager, timers))
+}
+
+async fn wait_for_status(storage: &MemoryStorage, id: Uuid, status: &str) {
+    for _ in 0..50 {
+        if storage.get_execution(id).await.unwrap().unwrap().status == status {
+            return;
+        }
+        sleep(Duration::from_millis(50)).await;
+    }
+    panic!("Execution {} never became {}", id, status);
+}
+
+fn edge_records(logs: &[ExecutionEvent], from: &str) -> Vec<serde_json::Value> {
+    logs.iter()
+        .filter_map(|e| match e {
+            ExecutionEvent::EdgeData { from: f, value, .. } if f == from => Some(value.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_durable_delay_suspends_and_resumes() -> anyhow::Result<()> {
+    let (storage, manager, timers) = setup().await?;
+    let definition = WorkflowLoader::new().load(DRIP)?;
+
+    let spec = JobSpec { inputs: vec![("start".to_string(), json!({ "email": "a@example.com" }))], ..Default::default() };
+    let job = manager.start_workflow(&definition, spec).await?;
+    let id = Uuid::parse_str(&job)?;
+
+    // The run ends with the record parked in storage
+    wait_for_status(&storage, id, "waiting").await;
+    assert_eq!(manager.get_job(&job), None, "suspended jobs are not kept in memory");
+    assert_eq!(storage.count_waiting_timers(id).await?, 1);
+    assert!(storage.get_execution(id).await?.unwrap().finished_at.is_none());
+
+    // Not due yet
+    assert!(timers.fire_due().await?.is_empty());
+
+    sleep(Duration::from_millis(300)).await;
+    assert_eq!(timers.fire_due().await?, vec![id]);
+    wait_for_status(&storage, id, "completed").await;
+
+    let logs = storage.get_execution_logs(id).await?;
+    assert_eq!(edge_records(&logs, "wait"), vec![json!({ "email": "a@example.com" })]);
+    // Only the part after the delay ran again
+    assert_eq!(edge_records(&logs, "start").len(), 1);
+    assert_eq!(storage.count_waiting_timers(id).await?, 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cancelled_execution_drops_timers() -> anyhow::Result<()> {
+    let (storage, manager, timers) = setup().await?;
+    let definition = WorkflowLoader::new().load(DRIP)?;
+
+    let spec = JobSpec { inputs: vec![("start".to_string(), json!({ "id": 1 }))], ..Default::default() };
+    let job = manager.start_workflow(&definition, spec).await?;
+    let id = Uuid::parse_str(&job)?;
+    wait_for_status(&storage, id, "waiting").await;
+
+    assert!(manager.cancel_job(&job).await);
+    sleep(Duration::from_millis(300)).await;
+    assert!(timers.fire_due().await?.is_empty());
+    assert_eq!(storage.count_waiting_timers(id).await?, 0);
+    assert_eq!(storage.get_execution(id).await?.unwrap().status, "cancelled");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_short_delays_stay_in_process() -> anyhow::Result<()> {
+    let (storage, manager, _timers) = setup().await?;
+    let definition = WorkflowLoader::new().load(&DRIP.replace("duration_ms: 300", "duration_ms: 50"))?;
+
+    let spec = JobSpec { inputs: vec![("start".to_string(), json!({ "id": 1 }))], ..Default::default() };
+    let id = Uuid::parse_str(&manager.start_workflow(&definition, spec).await?)?;
+    wait_for_status(&storage, id, "completed").await;
+    assert_eq!(storage.count_waiting_timers(id).await?, 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_durable_delays_sleep_outside_workers() -> anyhow::Result<()> {
+    // No workers to resume the execution, as with `cli run`
+    let storage = Arc::new(MemoryStorage::new());
+    let manager = Arc::new(JobManager::new(storage.clone()));
+    let definition = WorkflowLoader::new().load(DRIP)?;
+
+    let spec = JobSpec { inputs: vec![("start".to_string(), json!({ "id": 1 }))], ..Default::default() };
+    let id = Uuid::parse_str(&manager.start_workflow(&definition, spec).await?)?;
+    wait_for_status(&storage, id, "completed").await;
+    assert_eq!(storage.count_waiting_timers(id).await?, 0);
+    assert_eq!(edge_records(&storage.get_execution_logs(id).await?, "wait"), vec![json!({ "id": 1 })]);
+    Ok(())
+}
+
+#[test]
+fn test_downstream_of() {
+    let definition: WorkflowDefinition = WorkflowLoader::new().load(DRIP).unwrap();
+    let rest = definition.downstream_of("wait").unwrap();
+
+    let ids: Vec<&str> = rest.nodes.iter().map(|n| n.id.as_str()).collect();
+    assert_eq!(ids, vec!["wait", "follow_up"]);
+    assert_eq!(rest.edges.len(), 1);
+    assert!(definition.downstream_of("missing").is_err());
+}
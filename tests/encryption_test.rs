@@ -0,0 +1,144 @@
+// The CLI is a binary; its handlers are compiled into this test the same way
+#[allow(dead_code)]
+#[path = "../src/cli/mod.rs"]
+mod cli;
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use cli::commands::{Args, Commands, CredentialCommands};
+use cli::handlers::handle_command;
+use rust_flow::storage::encryption::{self, KeySource, Keyring};
+use rust_flow::storage::{self, SqliteStorage, Storage};
+use serde_json::json;
+use std::sync::Arc;
+
+mod common;
+
+const KEY_VARS: [&str; 6] = [
+    "RUSTFLOW_ENV",
+    "RUSTFLOW_MASTER_KEY",
+    "RUSTFLOW_MASTER_KEY_FILE",
+    "RUSTFLOW_MASTER_PASSPHRASE",
+    "RUSTFLOW_MASTER_KEY_SALT",
+    "RUSTFLOW_PREVIOUS_MASTER_KEYS",
+];
+
+// Keys are read from the environment, so every step runs in this one test
+fn set_keys(vars: &[(&str, &str)]) {
+    for var in KEY_VARS {
+        std::env::remove_var(var);
+    }
+    for (var, value) in vars {
+        std::env::set_var(var, value);
+    }
+    // Malformed settings fail again in the steps that check them
+    let _ = encryption::reload_keyring();
+}
+
+/// Ciphertext as written before key ids, under the development key.
+fn legacy_ciphertext(data: &str) -> String {
+    let cipher = Aes256Gcm::new(b"01234567890123456789012345678901".into());
+    let nonce = [7u8; 12];
+    let mut combined = nonce.to_vec();
+    combined.extend(cipher.encrypt(Nonce::from_slice(&nonce), data.as_bytes()).unwrap());
+    BASE64.encode(combined)
+}
+
+#[tokio::test]
+async fn test_master_keys_and_rotation() -> anyhow::Result<()> {
+    let key_a = "aa".repeat(32);
+    let key_b = "bb".repeat(32);
+    let dir = common::TempPath::new("keys");
+    std::fs::create_dir_all(&dir)?;
+
+    // Development key: works, tags its ciphertexts and reads ones written before key ids
+    set_keys(&[]);
+    let dev = Keyring::from_env()?;
+    assert_eq!(dev.current.source, KeySource::Development);
+    let encrypted = encryption::encrypt("secret")?;
+    assert_eq!(encryption::key_id(&encrypted), Some(dev.current.id.as_str()));
+    assert_eq!(encryption::decrypt(&encrypted)?, "secret");
+    assert_eq!(encryption::decrypt(&legacy_ciphertext("old"))?, "old");
+
+    // Production refuses the development key, and malformed or ambiguous keys are errors
+    set_keys(&[("RUSTFLOW_ENV", "production")]);
+    assert!(Keyring::from_env().is_err());
+    assert!(encryption::encrypt("secret").is_err());
+    set_keys(&[("RUSTFLOW_ENV", "production"), ("RUSTFLOW_MASTER_KEY", &key_a)]);
+    assert_eq!(Keyring::from_env()?.current.source, KeySource::Env);
+    assert!(encryption::decrypt(&legacy_ciphertext("old")).is_err(), "no development key in production");
+    set_keys(&[("RUSTFLOW_MASTER_KEY", "not hex")]);
+    assert!(Keyring::from_env().is_err());
+    set_keys(&[("RUSTFLOW_MASTER_KEY", "abcd")]);
+    assert!(Keyring::from_env().is_err());
+    set_keys(&[("RUSTFLOW_MASTER_KEY", &key_a), ("RUSTFLOW_MASTER_PASSPHRASE", "hunter22")]);
+    assert!(Keyring::from_env().is_err());
+    set_keys(&[("RUSTFLOW_MASTER_KEY", &key_a), ("RUSTFLOW_PREVIOUS_MASTER_KEYS", "nope")]);
+    assert!(Keyring::from_env().is_err());
+
+    // Key files hold the hex key or its raw bytes; the id only depends on the key
+    set_keys(&[("RUSTFLOW_MASTER_KEY", &key_a)]);
+    let id_a = Keyring::from_env()?.current.id;
+    let hex_file = dir.join("hex.key");
+    std::fs::write(&hex_file, format!("{}\n", key_a))?;
+    set_keys(&[("RUSTFLOW_MASTER_KEY_FILE", hex_file.to_str().unwrap())]);
+    assert_eq!(Keyring::from_env()?.current.id, id_a);
+    let raw_file = dir.join("raw.key");
+    std::fs::write(&raw_file, [0xaau8; 32])?;
+    set_keys(&[("RUSTFLOW_MASTER_KEY_FILE", raw_file.to_str().unwrap())]);
+    assert_eq!(Keyring::from_env()?.current.id, id_a);
+    set_keys(&[("RUSTFLOW_MASTER_KEY_FILE", dir.join("missing.key").to_str().unwrap())]);
+    assert!(Keyring::from_env().is_err());
+
+    // Passphrases are stretched with the deployment's salt, which is required
+    set_keys(&[("RUSTFLOW_MASTER_PASSPHRASE", "correct horse battery staple")]);
+    assert!(Keyring::from_env().is_err());
+    assert!(encryption::encrypt("secret").is_err());
+    set_keys(&[("RUSTFLOW_MASTER_PASSPHRASE", "correct horse battery staple"), ("RUSTFLOW_MASTER_KEY_SALT", "deployment salt")]);
+    let from_passphrase = encryption::keyring()?.current.clone();
+    assert_eq!(from_passphrase.source, KeySource::Passphrase);
+    assert!(Arc::ptr_eq(&encryption::keyring()?, &encryption::keyring()?), "read once");
+    assert_eq!(Keyring::from_env()?.current.id, from_passphrase.id);
+    set_keys(&[("RUSTFLOW_MASTER_PASSPHRASE", "correct horse battery staple"), ("RUSTFLOW_MASTER_KEY_SALT", "another deployment salt")]);
+    assert_ne!(Keyring::from_env()?.current.id, from_passphrase.id);
+
+    // Rotation: credentials under key A, the development key and an unknown key
+    let db_url = format!("sqlite:{}?mode=rwc", dir.join("rustflow.db").display());
+    let db = SqliteStorage::new(&db_url).await?;
+    db.init().await?;
+    let team = db.create_team("ops").await?;
+    set_keys(&[("RUSTFLOW_MASTER_KEY", &key_a)]);
+    let under_a = db.create_credential("a", "api_key", &json!("secret a"), team.id).await?;
+    let legacy = db.create_credential("legacy", "api_key", &json!("placeholder"), team.id).await?;
+    assert!(db.replace_credential_data(legacy.id, &legacy.data, &legacy_ciphertext("secret legacy")).await?);
+    set_keys(&[("RUSTFLOW_MASTER_KEY", &"cc".repeat(32))]);
+    let unknown = db.create_credential("unknown", "api_key", &json!("secret c"), team.id).await?;
+
+    // With B current and A previous, everything but the unknown key keeps working
+    set_keys(&[("RUSTFLOW_MASTER_KEY", &key_b), ("RUSTFLOW_PREVIOUS_MASTER_KEYS", &format!(" {} ,", key_a))]);
+    let id_b = Keyring::from_env()?.current.id;
+    let secrets = storage::team_secrets(&db, team.id).await?;
+    assert_eq!(secrets[&under_a.id.to_string()], json!({ "api_key": "secret a" }));
+    assert_eq!(secrets[&legacy.id.to_string()], json!({ "api_key": "secret legacy" }), "bare secrets become the type's field");
+    assert!(!secrets.contains_key(&unknown.id.to_string()));
+
+    let rotation = storage::rotate_credentials(&db).await?;
+    assert_eq!((rotation.rotated, rotation.current), (2, 0));
+    assert_eq!(rotation.failed.iter().map(|(id, _)| *id).collect::<Vec<_>>(), vec![unknown.id]);
+    let rotate = || Args { command: Some(Commands::Credentials { cmd: CredentialCommands::RotateKey, db_url: db_url.clone() }), server: None };
+    assert!(handle_command(rotate()).await.is_err(), "reports what it could not decrypt");
+
+    // Once the unknown key is listed too, the CLI finishes the job and previous keys can go
+    set_keys(&[("RUSTFLOW_MASTER_KEY", &key_b), ("RUSTFLOW_PREVIOUS_MASTER_KEYS", &format!("{},{}", key_a, "cc".repeat(32)))]);
+    handle_command(rotate()).await?;
+    set_keys(&[("RUSTFLOW_MASTER_KEY", &key_b)]);
+    for cred in db.list_all_credentials().await? {
+        assert_eq!(encryption::key_id(&cred.data), Some(id_b.as_str()));
+    }
+    assert_eq!(storage::team_secrets(&db, team.id).await?.len(), 3);
+    assert_eq!(storage::rotate_credentials(&db).await?.current, 3);
+
+    set_keys(&[]);
+    Ok(())
+}
@@ -0,0 +1,185 @@
+use rust_flow::event_bus::EventBus;
+use rust_flow::job_manager::{JobManager, JobSpec};
+use rust_flow::schema::WorkflowLoader;
+use rust_flow::storage::{MemoryStorage, Storage, WorkflowEntity};
+use rust_flow::stream_engine::NodeContext;
+use rust_flow::stream_engine::nodes::EmitEventNode;
+use serde_json::json;
+use std::sync::Arc;
+use tokio::time::{sleep, Duration};
+use uuid::Uuid;
+
+const EMITTER: &str = r#"
+nodes:
+  - id: start
+    type: manual_trigger
+    config: {}
+  - id: order
+    type: set_data
+    config: { "id": 7, "total": 12.5 }
+  - id: emit
+    type: emit_event
+    config:
+      event: order.created
+edges:
+  - from: start
+    to: order
+  - from: order
+    to: emit
+"#;
+
+// Re-publishes the received event so the test can observe what the subscriber got
+const SUBSCRIBER: &str = r#"
+nodes:
+  - id: on_order
+    type: event_trigger
+    config:
+      event: order.created
+  - id: forward
+    type: emit_event
+    config:
+      event: order.seen
+      payload: '{"id": {{ payload.id }}, "source": "{{ event }}"}'
+edges:
+  - from: on_order
+    to: forward
+"#;
+
+async fn wait_for_status(storage: &Arc<MemoryStorage>, job_id: &str, status: &str) {
+    let id = Uuid::parse_str(job_id).unwrap();
+    for _ in 0..50 {
+        if let Some(exec) = storage.get_execution(id).await.unwrap() {
+            if exec.status == status {
+                return;
+            }
+        }
+        sleep(Duration::from_millis(100)).await;
+    }
+    panic!("Job {} did not reach status {}", job_id, status);
+}
+
+#[tokio::test]
+async fn test_event_starts_subscribed_workflows() -> anyhow::Result<()> {
+    let storage = Arc::new(MemoryStorage::new());
+
+    let team = storage.create_team("events").await?;
+    let other_team = storage.create_team("other").await?;
+    let loader = WorkflowLoader::new();
+
+    // Subscribed in the emitting team, and the same subscription in another team
+    let subscriber = WorkflowEntity::new(team.id, "subscriber", serde_json::to_value(loader.load(SUBSCRIBER)?)?);
+    storage.save_workflow(&subscriber, None, None).await?;
+    storage.save_workflow(&WorkflowEntity {
+        id: Uuid::new_v4(),
+        account_id: other_team.id,
+        ..subscriber.clone()
+    }, None, None).await?;
+
+    let job_manager = Arc::new(JobManager::new(storage.clone()));
+    let bus = EventBus::new(storage.clone(), job_manager.clone());
+
+    // 1. Run the emitter; the event is persisted as pending
+    let spec = JobSpec { team_id: Some(team.id), ..Default::default() };
+    let emitter_job = job_manager.start_workflow(&loader.load(EMITTER)?, spec).await?;
+    wait_for_status(&storage, &emitter_job, "completed").await;
+
+    let pending = storage.list_pending_events(10, 0).await?;
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].name, "order.created");
+    assert_eq!(pending[0].team_id, team.id);
+    assert_eq!(pending[0].payload, json!({ "id": 7, "total": 12.5 }));
+
+    // 2. Dispatch: only the same-team subscriber starts
+    let started = bus.dispatch_pending().await?;
+    assert_eq!(started.len(), 1);
+    wait_for_status(&storage, &started[0], "completed").await;
+
+    let exec = storage.get_execution(Uuid::parse_str(&started[0])?).await?.unwrap();
+    assert_eq!(exec.workflow_id, Some(subscriber.id));
+
+    // 3. The subscriber received the event and published its own
+    let pending = storage.list_pending_events(10, 0).await?;
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].name, "order.seen");
+    assert_eq!(pending[0].payload, json!({ "id": 7, "source": "order.created" }));
+
+    // 4. Nothing subscribes to order.seen; delivered events are not redelivered
+    assert!(bus.dispatch_pending().await?.is_empty());
+    assert!(storage.list_pending_events(10, 0).await?.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_failed_deliveries_are_retried() -> anyhow::Result<()> {
+    let storage = Arc::new(MemoryStorage::new());
+    let team = storage.create_team("events").await?;
+    let loader = WorkflowLoader::new();
+    let workflow = |name: &str, definition| WorkflowEntity::new(team.id, name, definition);
+
+    // The second subscriber cannot start until its definition is fixed
+    let subscriber = workflow("subscriber", serde_json::to_value(loader.load(SUBSCRIBER)?)?);
+    let mut broken = serde_json::to_value(loader.load(SUBSCRIBER)?)?;
+    broken["nodes"][1]["type"] = json!("no_such_node");
+    let mut flaky = workflow("flaky", broken);
+    storage.save_workflow(&subscriber, None, None).await?;
+    storage.save_workflow(&flaky, None, None).await?;
+
+    let job_manager = Arc::new(JobManager::new(storage.clone()));
+    let bus = EventBus::new(storage.clone(), job_manager);
+    let event = storage.publish_event(team.id, "order.created", &json!({ "id": 1 })).await?;
+
+    assert_eq!(bus.dispatch_pending().await?.len(), 1);
+    assert!(storage.list_pending_events(10, 0).await?.iter().any(|e| e.id == event.id), "stays pending");
+    assert_eq!(storage.list_event_deliveries(event.id).await?, vec![subscriber.id]);
+    assert_eq!(bus.dispatch_pending().await?.len(), 0, "the subscriber that started is not started again");
+
+    flaky.definition = serde_json::to_value(loader.load(SUBSCRIBER)?)?;
+    storage.save_workflow(&flaky, None, None).await?;
+    let started = bus.dispatch_pending().await?;
+    assert_eq!(started.len(), 1);
+    let execution = storage.get_execution(Uuid::parse_str(&started[0])?).await?.unwrap();
+    assert_eq!(execution.workflow_id, Some(flaky.id));
+    assert!(!storage.list_pending_events(10, 0).await?.iter().any(|e| e.id == event.id));
+    assert!(storage.list_event_deliveries(event.id).await?.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_undeliverable_events_do_not_hold_up_later_ones() -> anyhow::Result<()> {
+    let storage = Arc::new(MemoryStorage::new());
+    let stuck = storage.create_team("stuck").await?;
+    let team = storage.create_team("events").await?;
+    let loader = WorkflowLoader::new();
+
+    let mut broken = serde_json::to_value(loader.load(SUBSCRIBER)?)?;
+    broken["nodes"][1]["type"] = json!("no_such_node");
+    storage.save_workflow(&WorkflowEntity::new(stuck.id, "broken", broken), None, None).await?;
+    let subscriber = WorkflowEntity::new(team.id, "subscriber", serde_json::to_value(loader.load(SUBSCRIBER)?)?);
+    storage.save_workflow(&subscriber, None, None).await?;
+
+    // More undeliverable events than one dispatch page, published first
+    for id in 0..150 {
+        storage.publish_event(stuck.id, "order.created", &json!({ "id": id })).await?;
+    }
+    storage.publish_event(team.id, "order.created", &json!({ "id": 1 })).await?;
+
+    let bus = EventBus::new(storage.clone(), Arc::new(JobManager::new(storage.clone())));
+    let started = bus.dispatch_pending().await?;
+    assert_eq!(started.len(), 1);
+    let execution = storage.get_execution(Uuid::parse_str(&started[0])?).await?.unwrap();
+    assert_eq!(execution.workflow_id, Some(subscriber.id));
+    assert_eq!(storage.list_pending_events(1_000, 0).await?.len(), 150);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_events_stay_in_the_running_team() -> anyhow::Result<()> {
+    let team = Uuid::new_v4();
+    let other = Uuid::new_v4();
+    let context = NodeContext { team_id: Some(team), ..Default::default() };
+    assert!(EmitEventNode::new(json!({ "event": "order.created", "team_id": team.to_string() }), context.clone()).is_ok());
+    assert!(EmitEventNode::new(json!({ "event": "order.created", "team_id": other.to_string() }), context).is_err());
+    assert!(EmitEventNode::new(json!({ "event": "order.created", "team_id": other.to_string() }), NodeContext::default()).is_err());
+    Ok(())
+}
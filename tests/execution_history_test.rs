@@ -0,0 +1,87 @@
+use rust_flow::storage::{Storage, MemoryStorage, ExecutionFilter, WorkflowEntity};
+use chrono::Utc;
+use uuid::Uuid;
+
+mod common;
+
+/// Creates a workflow and five executions (two failed), returning the workflow id and the
+/// execution ids in creation order.
+async fn seed(storage: &dyn Storage) -> anyhow::Result<(Uuid, Vec<Uuid>)> {
+    let team = storage.create_team(&format!("history-{}", Uuid::new_v4())).await?;
+    let workflow = WorkflowEntity::new(team.id, "nightly", serde_json::json!({ "nodes": [], "edges": [] }));
+    storage.save_workflow(&workflow, None, None).await?;
+
+    let mut ids = Vec::new();
+    for i in 0..5 {
+        let id = Uuid::new_v4();
+        storage.create_execution(id, Some(workflow.id), None, None, "running").await?;
+        let (status, error) = if i % 2 == 1 { ("failed", Some("boom".to_string())) } else { ("completed", None) };
+        storage.update_execution(id, status, Some(Utc::now()), error).await?;
+        ids.push(id);
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    }
+    // Not part of the workflow
+    storage.create_execution(Uuid::new_v4(), None, None, None, "running").await?;
+    Ok((workflow.id, ids))
+}
+
+async fn check_listing(storage: &dyn Storage) -> anyhow::Result<()> {
+    let started = Utc::now();
+    let (workflow_id, ids) = seed(storage).await?;
+
+    let all = storage.list_executions(&ExecutionFilter { workflow_id: Some(workflow_id), ..Default::default() }).await?;
+    assert_eq!(all.iter().map(|e| e.id).collect::<Vec<_>>(), ids.iter().rev().copied().collect::<Vec<_>>(), "newest first");
+
+    let failed = storage.list_executions(&ExecutionFilter {
+        workflow_id: Some(workflow_id),
+        status: Some("failed".to_string()),
+        ..Default::default()
+    }).await?;
+    assert_eq!(failed.iter().map(|e| e.id).collect::<Vec<_>>(), vec![ids[3], ids[1]]);
+    assert_eq!(failed[0].error.as_deref(), Some("boom"));
+
+    let page = storage.list_executions(&ExecutionFilter { workflow_id: Some(workflow_id), limit: 2, offset: 2, ..Default::default() }).await?;
+    assert_eq!(page.iter().map(|e| e.id).collect::<Vec<_>>(), vec![ids[2], ids[1]]);
+
+    let third_started = storage.get_execution(ids[2]).await?.unwrap().started_at;
+    let range = storage.list_executions(&ExecutionFilter {
+        workflow_id: Some(workflow_id),
+        since: Some(started),
+        until: Some(third_started),
+        ..Default::default()
+    }).await?;
+    assert_eq!(range.iter().map(|e| e.id).collect::<Vec<_>>(), vec![ids[1], ids[0]]);
+
+    let recent = storage.list_executions(&ExecutionFilter { since: Some(third_started), ..Default::default() }).await?;
+    assert!(recent.len() >= 4, "includes executions without workflow");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sqlite_execution_listing() -> anyhow::Result<()> {
+    let (storage, _db) = common::sqlite("history").await?;
+    check_listing(storage.as_ref()).await
+}
+
+#[tokio::test]
+async fn test_memory_execution_listing() -> anyhow::Result<()> {
+    check_listing(&MemoryStorage::new()).await
+}
+
+#[tokio::test]
+async fn test_postgres_execution_listing() -> anyhow::Result<()> {
+    let Some(storage) = common::postgres().await? else {
+        return Ok(());
+    };
+    check_listing(&storage).await
+}
+
+#[test]
+fn test_filter_defaults() {
+    let filter: ExecutionFilter = serde_json::from_value(serde_json::json!({ "status": "failed", "limit": 5 })).unwrap();
+    assert_eq!(filter.status.as_deref(), Some("failed"));
+    assert_eq!(filter.limit, 5);
+    assert_eq!(filter.offset, 0);
+
+    assert_eq!(serde_json::from_value::<ExecutionFilter>(serde_json::json!({})).unwrap().limit, 50);
+}
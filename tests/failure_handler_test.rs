@@ -0,0 +1,113 @@
+use rust_flow::job_manager::{JobManager, JobSpec};
+use rust_flow::schema::WorkflowLoader;
+use rust_flow::storage::{ExecutionFilter, MemoryStorage, Storage, WorkflowEntity};
+use serde_json::json;
+use std::sync::Arc;
+use tokio::time::{sleep, Duration};
+use uuid::Uuid;
+
+// Publishes the error record as an event so the test can inspect it
+const HANDLER: &str = r#"
+nodes:
+  - id: on_error
+    type: error_trigger
+    config: {}
+  - id: alert
+    type: emit_event
+    config:
+      event: workflow.failed
+edges:
+  - from: on_error
+    to: alert
+"#;
+
+// Fails reading a file that does not exist, then starts `handler`
+fn failing_workflow(handler: Uuid) -> String {
+    format!(r#"
+on_failure: "{}"
+nodes:
+  - id: start
+    type: manual_trigger
+    config: {{}}
+  - id: input
+    type: set_data
+    config: {{ "path": "/nonexistent/orders.json" }}
+  - id: read
+    type: file_source
+    config: {{ "path": "unused.json" }}
+edges:
+  - from: start
+    to: input
+  - from: input
+    to: read
+"#, handler)
+}
+
+#[tokio::test]
+async fn test_failed_execution_starts_handler() -> anyhow::Result<()> {
+    let storage = Arc::new(MemoryStorage::new());
+
+    let team = storage.create_team("ops").await?;
+    let loader = WorkflowLoader::new();
+
+    let handler = WorkflowEntity::new(team.id, "alerts", serde_json::to_value(loader.load(HANDLER)?)?);
+    storage.save_workflow(&handler, None, None).await?;
+
+    let failing = loader.load(&failing_workflow(handler.id))?;
+
+    let job_manager = Arc::new(JobManager::new(storage.clone()));
+    let spec = JobSpec { team_id: Some(team.id), ..Default::default() };
+    let job_id = job_manager.start_workflow(&failing, spec).await?;
+
+    // The handler publishes one event once it has run
+    let mut events = Vec::new();
+    for _ in 0..50 {
+        events = storage.list_pending_events(10, 0).await?;
+        if !events.is_empty() {
+            break;
+        }
+        sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(events.len(), 1, "failure handler did not run");
+
+    let failed = storage.get_execution(Uuid::parse_str(&job_id)?).await?.unwrap();
+    assert_eq!(failed.status, "failed");
+
+    let record = &events[0].payload;
+    assert_eq!(events[0].team_id, team.id);
+    assert_eq!(record["execution_id"], json!(job_id));
+    assert_eq!(record["failed_node"], "read");
+    assert_eq!(record["last_input"], json!({ "path": "/nonexistent/orders.json" }));
+    assert_eq!(record["error"], json!(failed.error.unwrap()));
+    assert!(record["error"].as_str().unwrap().contains("/nonexistent/orders.json"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_handlers_of_other_teams_are_not_started() -> anyhow::Result<()> {
+    let storage = Arc::new(MemoryStorage::new());
+    let team = storage.create_team("ops").await?;
+    let other = storage.create_team("other").await?;
+    let loader = WorkflowLoader::new();
+
+    let handler = WorkflowEntity::new(other.id, "alerts", serde_json::to_value(loader.load(HANDLER)?)?);
+    storage.save_workflow(&handler, None, None).await?;
+
+    let job_manager = Arc::new(JobManager::new(storage.clone()));
+    let spec = JobSpec { team_id: Some(team.id), ..Default::default() };
+    let job_id = Uuid::parse_str(&job_manager.start_workflow(&loader.load(&failing_workflow(handler.id))?, spec).await?)?;
+    for _ in 0..50 {
+        if storage.get_execution(job_id).await?.is_some_and(|e| e.status == "failed") {
+            break;
+        }
+        sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(storage.get_execution(job_id).await?.unwrap().status, "failed");
+
+    sleep(Duration::from_millis(200)).await;
+    let filter = ExecutionFilter { workflow_id: Some(handler.id), ..Default::default() };
+    assert!(storage.list_executions(&filter).await?.is_empty(), "ran with another team's credentials");
+    assert!(storage.list_pending_events(10, 0).await?.is_empty());
+    Ok(())
+}
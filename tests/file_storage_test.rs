@@ -0,0 +1,85 @@
+// The CLI is a binary; its handlers are compiled into this test the same way
+#[allow(dead_code)]
+#[path = "../src/cli/mod.rs"]
+mod cli;
+
+use cli::commands::{Args, Commands};
+use cli::handlers::handle_command;
+use rust_flow::storage::{FileStorage, Storage, WorkflowEntity};
+use serde_json::json;
+use uuid::Uuid;
+
+mod common;
+
+const WORKFLOW: &str = r#"
+nodes:
+  - id: start
+    type: manual_trigger
+    config: {}
+edges: []
+"#;
+
+#[tokio::test]
+async fn test_workflows_are_files() -> anyhow::Result<()> {
+    let dir = common::TempPath::new("files");
+    let url = format!("file://{}", dir.display());
+    let storage = FileStorage::new(&url).await?;
+    storage.init().await?;
+    let team = storage.create_team("ops").await?;
+
+    let mut workflow = WorkflowEntity::new(team.id, "sync", serde_yaml::from_str(WORKFLOW)?);
+    storage.save_workflow(&workflow, None, Some("first")).await?;
+    let file = dir.join("ops").join("sync.yaml");
+    assert_eq!(serde_yaml::from_str::<serde_json::Value>(&std::fs::read_to_string(&file)?)?, workflow.definition);
+    assert!(dir.join(".rustflow.db").exists(), "everything else is in the sidecar");
+
+    // Names are file names: unique in a team, and renames move the file
+    let twin = WorkflowEntity { id: Uuid::new_v4(), ..workflow.clone() };
+    assert!(storage.save_workflow(&twin, None, None).await.is_err());
+    workflow.name = "nightly sync".to_string();
+    storage.save_workflow(&workflow, None, None).await?;
+    assert!(!file.exists());
+    let file = dir.join("ops").join("nightly sync.yaml");
+    assert!(file.exists());
+    let bad = WorkflowEntity { id: Uuid::new_v4(), name: "../escape".to_string(), ..workflow.clone() };
+    assert!(storage.save_workflow(&bad, None, None).await.is_err());
+
+    // Edits on disk, e.g. from a git pull, become versions
+    std::fs::write(&file, WORKFLOW.replace("config: {}", "config: { label: go }"))?;
+    let edited = storage.get_workflow(workflow.id).await?.unwrap();
+    assert_eq!((edited.version, &edited.definition["nodes"][0]["config"]), (3, &json!({ "label": "go" })));
+    let latest = storage.get_workflow_version(workflow.id, 3).await?.unwrap();
+    assert_eq!((latest.author_id, latest.message.as_deref()), (None, Some("Changed on disk")));
+    assert_eq!(storage.get_workflow(workflow.id).await?.unwrap().version, 3, "synced once");
+
+    // Files added and removed by hand
+    std::fs::write(dir.join("ops").join("cleanup.yaml"), WORKFLOW)?;
+    std::fs::write(dir.join("ops").join("notes.txt"), "not a workflow")?;
+    std::fs::write(dir.join("ops").join("broken.yaml"), "nodes: [")?;
+    let names = |workflows: Vec<WorkflowEntity>| workflows.into_iter().map(|w| w.name).collect::<Vec<_>>();
+    assert_eq!(names(storage.list_workflows(team.id).await?), vec!["nightly sync", "cleanup"]);
+    let cleanup = storage.list_workflows(team.id).await?.pop().unwrap();
+    assert_eq!(storage.list_workflow_versions(cleanup.id).await?[0].message.as_deref(), Some("Added on disk"));
+
+    let execution = Uuid::new_v4();
+    storage.create_execution(execution, Some(workflow.id), Some(3), None, "completed").await?;
+    // A missing file, e.g. on another branch, disables the workflow until it is back
+    let content = std::fs::read_to_string(&file)?;
+    std::fs::remove_file(&file)?;
+    let missing = storage.get_workflow(workflow.id).await?.unwrap();
+    assert!(missing.deleted_at.is_none());
+    assert!(missing.ensure_runnable().unwrap_err().to_string().contains("disabled"));
+    assert_eq!(storage.list_workflows(team.id).await?.iter().map(|w| (w.name.as_str(), w.active)).collect::<Vec<_>>(), vec![("nightly sync", false), ("cleanup", true)]);
+    std::fs::write(&file, &content)?;
+    let back = storage.get_workflow(workflow.id).await?.unwrap();
+    assert!(back.active);
+    assert_eq!(back.version, 3, "same history");
+    assert_eq!(storage.get_execution(execution).await?.unwrap().workflow_id, Some(workflow.id));
+
+    assert!(storage.delete_workflow(cleanup.id).await?);
+    assert!(!dir.join("ops").join("cleanup.yaml").exists());
+
+    // The CLI opens the same directory
+    handle_command(Args { command: Some(Commands::ListWorkflows { team_id: Some(team.id), db_url: url }), server: None }).await?;
+    Ok(())
+}
@@ -57,7 +57,7 @@ async fn test_http_auto_split() {
         EdgeDefinition { from: "http".to_string(), to: "sink".to_string(), from_port: None, to_port: None },
     ];
 
-    let workflow = WorkflowDefinition { nodes, edges };
+    let workflow = WorkflowDefinition { nodes, edges, ..Default::default() };
     let mut executor = workflow.to_executor(&HashMap::new(), DebugConfig::default()).expect("Failed to create executor");
     executor.inject_input("source", json!(null));
     
@@ -0,0 +1,173 @@
+// The CLI is a binary; its handlers are compiled into this test the same way
+#[allow(dead_code)]
+#[path = "../src/cli/mod.rs"]
+mod cli;
+
+use cli::commands::{Args, Commands, DbCommands, ExecutionCommands};
+use cli::handlers::handle_command;
+use rust_flow::storage::{migrations, AuditEntry, ExecutionFilter, QueuedExecution, SqliteStorage, Storage};
+use sqlx::sqlite::SqlitePool;
+use uuid::Uuid;
+
+mod common;
+
+#[tokio::test]
+async fn test_sqlite_migrations() -> anyhow::Result<()> {
+    let db = common::TempPath::new("migrations");
+    let url = db.sqlite_url();
+    let storage = SqliteStorage::new(&url).await?;
+
+    let status = storage.schema_status().await?;
+    assert_eq!(status.version(), None);
+    assert_eq!(status.pending().len(), migrations::SQLITE.iter().count());
+
+    storage.init().await?;
+    let status = storage.schema_status().await?;
+    assert!(status.pending().is_empty());
+    assert_eq!(status.version(), migrations::SQLITE.iter().map(|m| m.version).max());
+    storage.init().await?; // Nothing left to do
+    status.check()?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_databases_from_before_migrations_are_adopted() -> anyhow::Result<()> {
+    let db = common::TempPath::new("migrations");
+    let url = db.sqlite_url();
+
+    // What `init` used to leave behind: the initial tables, but no record of migrations
+    let pool = SqlitePool::connect(&url).await?;
+    let initial = migrations::SQLITE.iter().next().unwrap();
+    sqlx::raw_sql(&initial.sql).execute(&pool).await?;
+    let storage = SqliteStorage::new(&url).await?;
+    let user = storage.create_user("ada", "pw").await?;
+    let team = storage.create_team("ops").await?;
+    let workflow_id = Uuid::new_v4();
+    sqlx::query("INSERT INTO workflows (id, account_id, name, definition, created_at) VALUES (?, ?, 'sync', '{}', '2024-01-01T00:00:00Z')")
+        .bind(workflow_id.to_string())
+        .bind(team.id.to_string())
+        .execute(&pool)
+        .await?;
+
+    assert_eq!(storage.schema_status().await?.version(), None);
+    storage.init().await?;
+    assert!(storage.schema_status().await?.pending().is_empty());
+    assert_eq!(storage.get_user_by_username("ada").await?.unwrap().id, user.id);
+
+    // Existing workflows start their history at version 1
+    assert_eq!(storage.get_workflow(workflow_id).await?.unwrap().version, 1);
+    let versions = storage.list_workflow_versions(workflow_id).await?;
+    assert_eq!(versions.iter().map(|v| (v.version, v.name.as_str())).collect::<Vec<_>>(), vec![(1, "sync")]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_baseline_databases_get_every_column() -> anyhow::Result<()> {
+    let db = common::TempPath::new("migrations");
+    let url = db.sqlite_url();
+
+    // A database of the release before the execution queue, events, approvals and the audit log
+    let pool = SqlitePool::connect(&url).await?;
+    let initial = migrations::SQLITE.iter().next().unwrap();
+    sqlx::raw_sql(&initial.sql).execute(&pool).await?;
+    let old = Uuid::new_v4();
+    sqlx::query("INSERT INTO executions (id, status, started_at) VALUES (?, 'completed', '2024-01-01T00:00:00Z')")
+        .bind(old.to_string())
+        .execute(&pool)
+        .await?;
+
+    let db = |cmd| Args { command: Some(Commands::Db { cmd, db_url: url.clone() }), server: None };
+    handle_command(db(DbCommands::Migrate)).await?;
+    let list = ExecutionCommands::List { workflow_id: None, team_id: None, status: None, since: None, until: None, limit: 20, offset: 0 };
+    handle_command(Args { command: Some(Commands::Executions { cmd: list, db_url: url.clone() }), server: None }).await?;
+
+    // Every table and column added since is there
+    let storage = SqliteStorage::new(&url).await?;
+    let team = storage.create_team("ops").await?;
+    assert_eq!(storage.list_executions(&ExecutionFilter::default()).await?.len(), 1);
+    let queued = QueuedExecution {
+        id: Uuid::new_v4(),
+        workflow_id: None,
+        team_id: Some(team.id),
+        definition: serde_json::json!({ "nodes": [], "edges": [] }),
+        inputs: serde_json::Value::Null,
+        priority: 1,
+        resume_from: None,
+        started_by: None,
+    };
+    storage.enqueue_execution(&queued).await?;
+    assert_eq!(storage.claim_execution("worker").await?.map(|r| r.id), Some(queued.id));
+    let filter = ExecutionFilter { team_id: Some(team.id), ..Default::default() };
+    assert_eq!(storage.list_executions(&filter).await?.len(), 1);
+    assert!(storage.resume_execution(old, "n", &serde_json::Value::Null).await.is_ok());
+    storage.publish_event(team.id, "created", &serde_json::json!({})).await?;
+    assert_eq!(storage.list_pending_events(10, 0).await?.len(), 1);
+    assert!(storage.get_approval("none").await?.is_none());
+    assert!(storage.list_due_timers(chrono::Utc::now()).await?.is_empty());
+    let user = storage.create_user("ada", "pw").await?;
+    assert!(storage.list_api_tokens(user.id).await?.is_empty());
+    storage.log_audit(&AuditEntry {
+        id: Uuid::new_v4(),
+        team_id: team.id,
+        actor_id: Some(user.id),
+        action: "team.create".to_string(),
+        target: format!("team/{}", team.id),
+        diff: None,
+        created_at: chrono::Utc::now(),
+    }).await?;
+    assert_eq!(storage.list_audit(team.id, 10, 0).await?.len(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_newer_schemas_are_refused() -> anyhow::Result<()> {
+    let db = common::TempPath::new("migrations");
+    let url = db.sqlite_url();
+    let storage = SqliteStorage::new(&url).await?;
+    storage.init().await?;
+
+    // Migrated by a newer RustFlow
+    let pool = SqlitePool::connect(&url).await?;
+    sqlx::query("INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time) VALUES (99999, 'future', TRUE, X'00', 0)")
+        .execute(&pool)
+        .await?;
+    let status = storage.schema_status().await?;
+    assert_eq!(status.unknown, vec![99999]);
+    assert_eq!(status.version(), Some(99999));
+    let err = storage.init().await.unwrap_err().to_string();
+    assert!(err.contains("newer than this RustFlow"), "{}", err);
+
+    // An applied migration that was edited since
+    sqlx::query("DELETE FROM _sqlx_migrations WHERE version = 99999").execute(&pool).await?;
+    sqlx::query("UPDATE _sqlx_migrations SET checksum = X'00' WHERE version = 1").execute(&pool).await?;
+    assert_eq!(storage.schema_status().await?.modified, vec![1]);
+    assert!(storage.init().await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_postgres_migrations() -> anyhow::Result<()> {
+    let Some(storage) = common::postgres().await? else {
+        return Ok(());
+    };
+    let status = storage.schema_status().await?;
+    status.check()?;
+    assert!(status.pending().is_empty());
+    assert_eq!(status.version(), migrations::POSTGRES.iter().map(|m| m.version).max());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cli_db_commands() -> anyhow::Result<()> {
+    let db = common::TempPath::new("migrations");
+    let db_url = db.sqlite_url();
+    let db = |cmd| Args { command: Some(Commands::Db { cmd, db_url: db_url.clone() }), server: None };
+    handle_command(db(DbCommands::Status)).await?;
+    handle_command(db(DbCommands::Migrate)).await?;
+    handle_command(db(DbCommands::Status)).await?;
+
+    let storage = SqliteStorage::new(&db_url).await?;
+    assert!(storage.schema_status().await?.pending().is_empty());
+    assert!(handle_command(Args { command: Some(Commands::Db { cmd: DbCommands::Status, db_url }), server: Some("http://localhost:3000".to_string()) }).await.is_err());
+    Ok(())
+}
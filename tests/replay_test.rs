@@ -0,0 +1,159 @@
+use rust_flow::job_manager::{JobManager, JobSpec};
+use rust_flow::replay::{replay, ReplayOptions};
+use rust_flow::schema::{ExecutionEvent, WorkflowLoader};
+use rust_flow::storage::{Storage, MemoryStorage, QueuedExecution};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::time::{sleep, Duration};
+use uuid::Uuid;
+
+const ORDERS: &str = r#"
+nodes:
+  - id: start
+    type: manual_trigger
+    config: {}
+  - id: route
+    type: switch
+    config:
+      expression: "{{ kind }}"
+      cases: ["refund", "order"]
+  - id: refunds
+    type: set_data
+    config: { queue: refunds }
+  - id: orders
+    type: console_output
+    config: {}
+edges:
+  - from: start
+    to: route
+  - from: route
+    from_port: "0"
+    to: refunds
+  - from: route
+    from_port: "1"
+    to: orders
+"#;
+
+/// Runs ORDERS once and returns the events it logged.
+async fn record_run(storage: Arc<MemoryStorage>) -> anyhow::Result<Vec<ExecutionEvent>> {
+    let definition = WorkflowLoader::new().load(ORDERS)?;
+    let inputs = vec![
+        ("start".to_string(), json!({ "kind": "order", "id": 1 })),
+        ("start".to_string(), json!({ "kind": "refund", "id": 2 })),
+        ("start".to_string(), json!({ "kind": "order", "id": 3 })),
+    ];
+    let manager = Arc::new(JobManager::new(storage.clone()));
+    let id = Uuid::parse_str(&manager.start_workflow(&definition, JobSpec { inputs, ..Default::default() }).await?)?;
+    for _ in 0..50 {
+        if storage.get_execution(id).await?.unwrap().finished_at.is_some() {
+            break;
+        }
+        sleep(Duration::from_millis(50)).await;
+    }
+    storage.get_execution_logs(id).await
+}
+
+#[tokio::test]
+async fn test_replay_whole_graph_matches() -> anyhow::Result<()> {
+    let storage = Arc::new(MemoryStorage::new());
+    let events = record_run(storage).await?;
+    let definition = WorkflowLoader::new().load(ORDERS)?;
+
+    let replays = replay(&definition, &events, &HashMap::new(), &ReplayOptions::default()).await?;
+    let ids: Vec<&str> = replays.iter().map(|r| r.node_id.as_str()).collect();
+    assert_eq!(ids, vec!["route", "refunds", "orders"], "triggers are not replayed");
+    assert!(replays.iter().all(|r| r.matches()), "{:?}", replays);
+
+    let route = &replays[0];
+    assert_eq!(route.inputs[0].len(), 3);
+    assert_eq!(route.outputs, vec![
+        vec![json!({ "kind": "refund", "id": 2 })],
+        vec![json!({ "kind": "order", "id": 1 }), json!({ "kind": "order", "id": 3 })],
+    ]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_replay_single_node_with_changed_definition() -> anyhow::Result<()> {
+    let storage = Arc::new(MemoryStorage::new());
+    let events = record_run(storage).await?;
+
+    // A local change to the switch sends orders to the refund port
+    let changed = WorkflowLoader::new().load(&ORDERS.replace(r#"["refund", "order"]"#, r#"["order", "refund"]"#))?;
+    let options = ReplayOptions { node: Some("route".to_string()), ..Default::default() };
+    let replays = replay(&changed, &events, &HashMap::new(), &options).await?;
+
+    assert_eq!(replays.len(), 1);
+    assert!(!replays[0].matches());
+    assert_eq!(replays[0].outputs[0].len(), 2);
+    assert_eq!(replays[0].recorded_outputs[0].as_ref().map(Vec::len), Some(1));
+
+    let options = ReplayOptions { node: Some("start".to_string()), ..Default::default() };
+    assert!(replay(&changed, &events, &HashMap::new(), &options).await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_side_effects_are_stubbed() -> anyhow::Result<()> {
+    let definition = WorkflowLoader::new().load(r#"
+nodes:
+  - id: start
+    type: manual_trigger
+    config: {}
+  - id: fetch
+    type: http_request
+    config: { url: "http://127.0.0.1:9/unreachable" }
+  - id: shape
+    type: set_data
+    config: { done: true }
+edges:
+  - from: start
+    to: fetch
+  - from: fetch
+    to: shape
+"#)?;
+    let events = vec![
+        ExecutionEvent::EdgeData { from: "start".to_string(), to: "fetch".to_string(), value: json!({}) },
+        ExecutionEvent::EdgeData { from: "fetch".to_string(), to: "shape".to_string(), value: json!({ "status": 200 }) },
+    ];
+
+    let options = ReplayOptions { stub_side_effects: true, ..Default::default() };
+    let replays = replay(&definition, &events, &HashMap::new(), &options).await?;
+    assert!(replays[0].stubbed);
+    assert_eq!(replays[0].outputs, vec![vec![json!({ "status": 200 })]]);
+    // Ran on the recorded response; its own outputs went nowhere, so there is nothing to compare
+    assert!(!replays[1].stubbed);
+    assert_eq!(replays[1].outputs, vec![vec![json!({ "done": true })]]);
+    assert!(replays[1].matches());
+
+    // Stubbing by id works for any node
+    let options = ReplayOptions { stub_nodes: vec!["shape".to_string()], stub_side_effects: true, ..Default::default() };
+    let replays = replay(&definition, &events, &HashMap::new(), &options).await?;
+    assert!(replays.iter().all(|r| r.stubbed));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_queued_execution_definition_is_kept() -> anyhow::Result<()> {
+    let storage = Arc::new(MemoryStorage::new());
+    let definition = serde_json::to_value(WorkflowLoader::new().load(ORDERS)?)?;
+    let run = QueuedExecution {
+        id: Uuid::new_v4(),
+        workflow_id: None,
+        team_id: None,
+        definition: definition.clone(),
+        inputs: json!([]),
+        priority: 0,
+        resume_from: None,
+        started_by: None,
+    };
+    storage.enqueue_execution(&run).await?;
+
+    assert_eq!(storage.get_execution_definition(run.id).await?, Some(definition));
+
+    let in_process = Uuid::new_v4();
+    storage.create_execution(in_process, None, None, None, "running").await?;
+    assert_eq!(storage.get_execution_definition(in_process).await?, None);
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use rust_flow::schema::ExecutionEvent;
+use rust_flow::storage::{Storage, MemoryStorage, RetentionPolicy, WorkflowEntity};
+use chrono::{Duration, Utc};
+use serde_json::json;
+use uuid::Uuid;
+
+mod common;
+
+async fn execution(storage: &dyn Storage, workflow_id: Option<Uuid>, status: &str, days_ago: Option<i64>) -> anyhow::Result<Uuid> {
+    let id = Uuid::new_v4();
+    storage.create_execution(id, workflow_id, None, None, "running").await?;
+    if let Some(days) = days_ago {
+        storage.update_execution(id, status, Some(Utc::now() - Duration::days(days)), None).await?;
+    }
+    // Distinct start times, newest last
+    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    Ok(id)
+}
+
+async fn edge(storage: &dyn Storage, execution_id: Uuid, from: &str, to: &str, n: i64) -> anyhow::Result<()> {
+    for i in 0..n {
+        let event = ExecutionEvent::EdgeData { from: from.to_string(), to: to.to_string(), value: json!({ "i": i }) };
+        storage.log_execution_event(execution_id, &event).await?;
+    }
+    Ok(())
+}
+
+async fn exists(storage: &dyn Storage, id: Uuid) -> bool {
+    storage.get_execution(id).await.unwrap().is_some()
+}
+
+async fn check_pruning(storage: &dyn Storage) -> anyhow::Result<()> {
+    let team = storage.create_team(&format!("retention-{}", Uuid::new_v4())).await?;
+    let workflow = WorkflowEntity::new(team.id, "sync", json!({ "nodes": [], "edges": [] }));
+    storage.save_workflow(&workflow, None, None).await?;
+    let wf = Some(workflow.id);
+
+    let old = execution(storage, wf, "completed", Some(40)).await?;
+    let old_failed = execution(storage, wf, "failed", Some(40)).await?;
+    let ancient_failed = execution(storage, wf, "failed", Some(100)).await?;
+    let older = execution(storage, wf, "completed", Some(1)).await?;
+    let newest = execution(storage, wf, "completed", Some(1)).await?;
+    let running = execution(storage, wf, "running", None).await?;
+    let adhoc = execution(storage, None, "completed", Some(40)).await?;
+    edge(storage, old, "a", "b", 2).await?;
+
+    // Age, with failures kept longer
+    let policy = RetentionPolicy { max_age_days: Some(30), failed_max_age_days: Some(90), ..Default::default() };
+    let stats = storage.prune_executions(&policy, Utc::now()).await?;
+    assert!(stats.executions >= 3);
+    assert!(stats.events >= 2);
+    for id in [old, ancient_failed, adhoc] {
+        assert!(!exists(storage, id).await);
+    }
+    for id in [old_failed, older, newest, running] {
+        assert!(exists(storage, id).await);
+    }
+    assert!(storage.get_execution_logs(old).await?.is_empty());
+
+    // Per workflow; failed and unfinished executions do not count
+    let policy = RetentionPolicy { max_per_workflow: Some(1), failed_max_age_days: Some(90), ..Default::default() };
+    storage.prune_executions(&policy, Utc::now()).await?;
+    assert!(!exists(storage, older).await);
+    for id in [old_failed, newest, running] {
+        assert!(exists(storage, id).await);
+    }
+
+    // Sampled payloads of completed executions
+    storage.log_execution_event(newest, &ExecutionEvent::NodeStart { node_id: "a".to_string() }).await?;
+    edge(storage, newest, "a", "b", 3).await?;
+    edge(storage, newest, "b", "c", 1).await?;
+    edge(storage, old_failed, "a", "b", 3).await?;
+
+    let policy = RetentionPolicy { success_payload_sample: Some(1), ..Default::default() };
+    storage.prune_executions(&policy, Utc::now()).await?;
+    let logs = storage.get_execution_logs(newest).await?;
+    assert_eq!(logs.len(), 3);
+    assert!(logs.contains(&ExecutionEvent::EdgeData { from: "a".to_string(), to: "b".to_string(), value: json!({ "i": 0 }) }));
+    assert_eq!(storage.get_execution_logs(old_failed).await?.len(), 3);
+
+    let policy = RetentionPolicy { success_payload_sample: Some(0), ..Default::default() };
+    storage.prune_executions(&policy, Utc::now()).await?;
+    assert_eq!(storage.get_execution_logs(newest).await?, vec![ExecutionEvent::NodeStart { node_id: "a".to_string() }]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sqlite_pruning() -> anyhow::Result<()> {
+    let (storage, _db) = common::sqlite("retention").await?;
+    check_pruning(storage.as_ref()).await
+}
+
+#[tokio::test]
+async fn test_memory_pruning() -> anyhow::Result<()> {
+    check_pruning(&MemoryStorage::new()).await
+}
+
+#[tokio::test]
+async fn test_postgres_pruning() -> anyhow::Result<()> {
+    let Some(storage) = common::postgres().await? else {
+        return Ok(());
+    };
+    check_pruning(&storage).await
+}
+
+#[test]
+fn test_empty_policy() {
+    assert!(RetentionPolicy::default().is_empty());
+    assert!(!RetentionPolicy { success_payload_sample: Some(0), ..Default::default() }.is_empty());
+}
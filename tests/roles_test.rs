@@ -0,0 +1,215 @@
+use rust_flow::auth;
+use rust_flow::job_manager::JobManager;
+use rust_flow::server::{self, AppState, ServerSettings};
+use rust_flow::storage::{team_kv_key, ApprovalRecord, Storage, MemoryStorage, Role, WorkflowEntity, ExecutionFilter, QueuedExecution};
+use chrono::Utc;
+use reqwest::{Method, StatusCode};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use uuid::Uuid;
+
+mod common;
+
+async fn check_roles(storage: &dyn Storage) -> anyhow::Result<()> {
+    let team = storage.create_team(&format!("roles-{}", Uuid::new_v4())).await?;
+    let admin = storage.create_user(&format!("admin-{}", Uuid::new_v4()), "pw").await?;
+    let member = storage.create_user(&format!("member-{}", Uuid::new_v4()), "pw").await?;
+    let viewer = storage.create_user(&format!("viewer-{}", Uuid::new_v4()), "pw").await?;
+    let outsider = storage.create_user(&format!("outsider-{}", Uuid::new_v4()), "pw").await?;
+    storage.add_team_member(team.id, admin.id, Role::Admin).await?;
+    storage.add_team_member(team.id, member.id, Role::Member).await?;
+    storage.add_team_member(team.id, viewer.id, Role::Viewer).await?;
+
+    assert_eq!(auth::team_role(storage, member.id, team.id).await?, Some(Role::Member));
+    assert_eq!(auth::team_role(storage, outsider.id, team.id).await?, None);
+
+    let expected = [
+        (admin.id, [true, true, true]),
+        (member.id, [false, true, true]),
+        (viewer.id, [false, false, true]),
+        (outsider.id, [false, false, false]),
+    ];
+    for (user_id, allowed) in expected {
+        for (role, allowed) in [Role::Admin, Role::Member, Role::Viewer].into_iter().zip(allowed) {
+            assert_eq!(auth::has_role(storage, user_id, team.id, role.clone()).await?, allowed, "{:?}", role);
+        }
+    }
+    Ok(())
+}
+
+async fn check_execution_teams(storage: &dyn Storage) -> anyhow::Result<()> {
+    let team = storage.create_team(&format!("roles-{}", Uuid::new_v4())).await?;
+    let other = storage.create_team(&format!("roles-{}", Uuid::new_v4())).await?;
+    let workflow = WorkflowEntity::new(team.id, "sync", json!({ "nodes": [], "edges": [] }));
+    storage.save_workflow(&workflow, None, None).await?;
+
+    // Queued for a team, run in-process from a stored workflow, and ad hoc
+    let queued = QueuedExecution {
+        id: Uuid::new_v4(),
+        workflow_id: None,
+        team_id: Some(team.id),
+        definition: json!({ "nodes": [], "edges": [] }),
+        inputs: json!([]),
+        priority: 0,
+        resume_from: None,
+        started_by: None,
+    };
+    storage.enqueue_execution(&queued).await?;
+    let stored = Uuid::new_v4();
+    storage.create_execution(stored, Some(workflow.id), None, None, "running").await?;
+    let adhoc = Uuid::new_v4();
+    storage.create_execution(adhoc, None, None, None, "running").await?;
+
+    assert_eq!(storage.get_execution(queued.id).await?.unwrap().team_id, Some(team.id));
+    assert_eq!(auth::execution_team(storage, queued.id).await?, Some(Some(team.id)));
+    assert_eq!(auth::execution_team(storage, stored).await?, Some(Some(team.id)));
+    assert_eq!(auth::execution_team(storage, adhoc).await?, Some(None));
+    assert_eq!(auth::execution_team(storage, Uuid::new_v4()).await?, None);
+
+    let filter = ExecutionFilter { team_id: Some(team.id), ..Default::default() };
+    let mut ids: Vec<Uuid> = storage.list_executions(&filter).await?.iter().map(|e| e.id).collect();
+    ids.sort();
+    let mut expected = vec![queued.id, stored];
+    expected.sort();
+    assert_eq!(ids, expected);
+
+    let filter = ExecutionFilter { team_id: Some(other.id), ..Default::default() };
+    assert!(storage.list_executions(&filter).await?.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sqlite_roles() -> anyhow::Result<()> {
+    let (storage, _db) = common::sqlite("roles").await?;
+    check_roles(storage.as_ref()).await?;
+    check_execution_teams(storage.as_ref()).await
+}
+
+#[tokio::test]
+async fn test_memory_roles() -> anyhow::Result<()> {
+    let storage = MemoryStorage::new();
+    check_roles(&storage).await?;
+    check_execution_teams(&storage).await
+}
+
+#[tokio::test]
+async fn test_postgres_roles() -> anyhow::Result<()> {
+    let Some(storage) = common::postgres().await? else {
+        return Ok(());
+    };
+    check_roles(&storage).await?;
+    check_execution_teams(&storage).await
+}
+
+#[test]
+fn test_role_ordering() {
+    assert!(Role::Admin.includes(&Role::Viewer));
+    assert!(Role::Member.includes(&Role::Member));
+    assert!(!Role::Viewer.includes(&Role::Member));
+    assert!(!Role::Member.includes(&Role::Admin));
+}
+
+const RUN: &str = "nodes:\n  - id: start\n    type: manual_trigger\n    config: {}\nedges: []\n";
+
+/// Serves the API on a free local port; returns its URL.
+async fn serve(storage: Arc<MemoryStorage>, settings: ServerSettings) -> anyhow::Result<String> {
+    let state = Arc::new(AppState { job_manager: Arc::new(JobManager::new(storage.clone())), storage, settings });
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}", listener.local_addr()?);
+    tokio::spawn(async move {
+        axum::serve(listener, server::router(state)).await.unwrap();
+    });
+    Ok(url)
+}
+
+async fn send(url: &str, token: &str, method: Method, path: &str, body: Option<Value>) -> anyhow::Result<StatusCode> {
+    let mut req = reqwest::Client::new().request(method, format!("{}{}", url, path)).bearer_auth(token);
+    if let Some(body) = body {
+        req = req.json(&body);
+    }
+    Ok(req.send().await?.status())
+}
+
+#[tokio::test]
+async fn test_api_refuses_insufficient_roles() -> anyhow::Result<()> {
+    let storage = Arc::new(MemoryStorage::new());
+    let team = storage.create_team("ops").await?;
+    let mut sessions = Vec::new();
+    // Admins last: their requests (e.g. deletes) may leave nothing to refuse
+    for (name, role) in [("viewer", Some(Role::Viewer)), ("member", Some(Role::Member)), ("outsider", None), ("admin", Some(Role::Admin))] {
+        let user = storage.create_user(name, "pw").await?;
+        if let Some(role) = role {
+            storage.add_team_member(team.id, user.id, role).await?;
+        }
+        sessions.push((name, auth::login(storage.as_ref(), name, "pw").await?.unwrap().1));
+    }
+
+    let workflow = WorkflowEntity::new(team.id, "sync", json!({ "nodes": [], "edges": [] }));
+    storage.save_workflow(&workflow, None, None).await?;
+    let execution = Uuid::new_v4();
+    storage.create_execution(execution, Some(workflow.id), None, None, "running").await?;
+    let adhoc = Uuid::new_v4();
+    storage.create_execution(adhoc, None, None, None, "running").await?;
+    let approval = |execution_id| ApprovalRecord {
+        token: Uuid::new_v4().to_string(),
+        execution_id,
+        record: json!({ "id": 1 }),
+        status: "pending".to_string(),
+        decision: None,
+        expires_at: None,
+        created_at: Utc::now(),
+        decided_at: None,
+        node_id: None,
+        delivered_at: None,
+    };
+    let (pending, expiring) = (approval(execution), approval(execution));
+    storage.create_approval(&pending).await?;
+    storage.create_approval(&expiring).await?;
+    let kv = team_kv_key(Some(team.id), "orders_poll");
+
+    // The lowest role each request needs; `None` for requests nobody may make by default
+    let requests: Vec<(Method, String, Option<Value>, Option<Role>)> = vec![
+        (Method::GET, format!("/api/executions/{}", execution), None, Some(Role::Viewer)),
+        (Method::GET, format!("/api/kv/{}", kv), None, Some(Role::Viewer)),
+        (Method::PUT, format!("/api/kv/{}", kv), Some(json!(3)), Some(Role::Member)),
+        (Method::POST, "/api/run".to_string(), Some(json!({ "workflow": RUN, "team_id": team.id })), Some(Role::Member)),
+        (Method::POST, format!("/api/approvals/{}/resolve", expiring.token), Some(json!({ "status": "expired" })), Some(Role::Member)),
+        (Method::POST, format!("/api/executions/{}/signal/{}", execution, pending.token), Some(json!({ "decision": "approve" })), Some(Role::Member)),
+        (Method::POST, "/api/approvals".to_string(), Some(serde_json::to_value(approval(execution))?), Some(Role::Member)),
+        (Method::GET, format!("/api/teams/{}/credentials", team.id), None, Some(Role::Admin)),
+        (Method::DELETE, format!("/api/workflows/{}", workflow.id), None, Some(Role::Admin)),
+        // Runs without a team, and their node state
+        (Method::POST, "/api/run".to_string(), Some(json!({ "workflow": RUN })), None),
+        (Method::GET, format!("/api/executions/{}", adhoc), None, None),
+        (Method::POST, "/api/executions".to_string(), Some(json!({ "id": Uuid::new_v4(), "workflow_id": null, "status": "running" })), None),
+        (Method::PUT, "/api/kv/orders_poll".to_string(), Some(json!(3)), None),
+    ];
+
+    let url = serve(storage.clone(), ServerSettings::default()).await?;
+    for (method, path, body, required) in &requests {
+        for (name, session) in &sessions {
+            let allowed = match (required, *name) {
+                (Some(required), "viewer") => Role::Viewer.includes(required),
+                (Some(required), "member") => Role::Member.includes(required),
+                (Some(_), "admin") => true,
+                _ => false,
+            };
+            let status = send(&url, session, method.clone(), path, body.clone()).await?;
+            assert_eq!(status == StatusCode::FORBIDDEN, !allowed, "{} {} {} answered {}", name, method, path, status);
+        }
+    }
+
+    // Signals are recorded as the signed-in user
+    let decision = storage.get_approval(&pending.token).await?.unwrap().decision.unwrap();
+    assert_eq!(decision["by"], "member");
+
+    // Approvals need an execution the caller can act on
+    let status = send(&url, &sessions[3].1, Method::POST, "/api/approvals", Some(serde_json::to_value(approval(Uuid::new_v4()))?)).await?;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Servers may open runs without a team to everyone
+    let url = serve(storage.clone(), ServerSettings { allow_unscoped_runs: true, ..Default::default() }).await?;
+    let status = send(&url, &sessions[2].1, Method::GET, &format!("/api/executions/{}", adhoc), None).await?;
+    assert_eq!(status, StatusCode::OK);
+    Ok(())
+}
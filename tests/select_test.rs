@@ -44,7 +44,7 @@ async fn test_select_node_functionality() {
         },
     ];
 
-    let workflow = WorkflowDefinition { nodes, edges };
+    let workflow = WorkflowDefinition { nodes, edges, ..Default::default() };
     
     let mut executor = workflow.to_executor(&HashMap::new(), DebugConfig::default()).expect("Failed to create executor");
     executor.inject_input("source", json!(null)); // Trigger the set_data node
@@ -129,7 +129,7 @@ async fn test_select_node_casting() {
         EdgeDefinition { from: "cast_bool".to_string(), to: "sink".to_string(), from_port: None, to_port: None },
     ];
 
-    let workflow = WorkflowDefinition { nodes, edges };
+    let workflow = WorkflowDefinition { nodes, edges, ..Default::default() };
     let mut executor = workflow.to_executor(&HashMap::new(), DebugConfig::default()).expect("Failed to create executor");
     executor.inject_input("source", json!(null));
     
@@ -0,0 +1,87 @@
+use rust_flow::stream_engine::nodes::SqlPollTrigger;
+use rust_flow::stream_engine::StreamNode;
+use rust_flow::storage::{team_kv_key, Storage, SqliteStorage};
+use serde_json::json;
+use tokio::sync::mpsc;
+use sqlx::SqlitePool;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_sql_poll_trigger_emits_new_rows_and_persists_watermark() -> anyhow::Result<()> {
+    let conn_str = "sqlite:file:pollmem1?mode=memory&cache=shared";
+
+    // 1. Setup watched DB
+    let pool = SqlitePool::connect(conn_str).await?;
+    sqlx::query("CREATE TABLE orders (id INTEGER PRIMARY KEY, item TEXT)").execute(&pool).await?;
+    sqlx::query("INSERT INTO orders (id, item) VALUES (1, 'Apple'), (2, 'Pear')").execute(&pool).await?;
+
+    // 2. Setup workflow storage for the watermark
+    let storage = SqliteStorage::new("sqlite::memory:").await?;
+    storage.init().await?;
+    let storage: Arc<dyn Storage> = Arc::new(storage);
+
+    let config = json!({
+        "connection_string": conn_str,
+        "table": "orders",
+        "watermark_column": "id",
+        "interval_seconds": 1,
+        "state_key": "orders_poll"
+    });
+
+    let node = SqlPollTrigger::new(config.clone(), Some(storage.clone()), None)?;
+    let (tx_out, mut rx_out) = mpsc::channel(10);
+    let handle = tokio::spawn(async move {
+        node.run(vec![], vec![tx_out]).await
+    });
+
+    // 3. Existing rows are emitted on the first poll
+    let first = tokio::time::timeout(Duration::from_secs(2), rx_out.recv()).await?.expect("row 1");
+    let second = tokio::time::timeout(Duration::from_secs(2), rx_out.recv()).await?.expect("row 2");
+    assert_eq!(first["item"], "Apple");
+    assert_eq!(second["item"], "Pear");
+
+    // 4. A row inserted later is picked up by the next poll
+    sqlx::query("INSERT INTO orders (id, item) VALUES (3, 'Plum')").execute(&pool).await?;
+    let third = tokio::time::timeout(Duration::from_secs(3), rx_out.recv()).await?.expect("row 3");
+    assert_eq!(third["id"], 3);
+
+    // Closing the output stops the trigger
+    drop(rx_out);
+    tokio::time::timeout(Duration::from_secs(3), handle).await???;
+
+    assert_eq!(storage.get_kv("orders_poll").await?, Some(json!(3)));
+
+    // 5. A fresh trigger resumes from the persisted watermark
+    sqlx::query("INSERT INTO orders (id, item) VALUES (4, 'Fig')").execute(&pool).await?;
+    let node = SqlPollTrigger::new(config.clone(), Some(storage.clone()), None)?;
+    let (tx_out, mut rx_out) = mpsc::channel(10);
+    let handle = tokio::spawn(async move {
+        node.run(vec![], vec![tx_out]).await
+    });
+
+    let resumed = tokio::time::timeout(Duration::from_secs(2), rx_out.recv()).await?.expect("row 4");
+    assert_eq!(resumed["item"], "Fig");
+    drop(rx_out);
+    tokio::time::timeout(Duration::from_secs(3), handle).await???;
+
+    // 6. A team's trigger keeps its own watermark
+    let team_id = uuid::Uuid::new_v4();
+    let node = SqlPollTrigger::new(config, Some(storage.clone()), Some(team_id))?;
+    let (tx_out, mut rx_out) = mpsc::channel(10);
+    let handle = tokio::spawn(async move {
+        node.run(vec![], vec![tx_out]).await
+    });
+
+    let first = tokio::time::timeout(Duration::from_secs(2), rx_out.recv()).await?.expect("row 1");
+    assert_eq!(first["item"], "Apple");
+    for _ in 0..3 {
+        tokio::time::timeout(Duration::from_secs(2), rx_out.recv()).await?.expect("rows 2 to 4");
+    }
+    drop(rx_out);
+    tokio::time::timeout(Duration::from_secs(3), handle).await???;
+    assert_eq!(storage.get_kv(&team_kv_key(Some(team_id), "orders_poll")).await?, Some(json!(4)));
+    assert_eq!(storage.get_kv("orders_poll").await?, Some(json!(4)));
+
+    Ok(())
+}
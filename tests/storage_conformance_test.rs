@@ -0,0 +1,359 @@
+//! The same cases against every `Storage` backend, so they behave alike.
+use rust_flow::schema::ExecutionEvent;
+use rust_flow::storage::{encryption, ApprovalRecord, FileStorage, MemoryStorage, QueuedExecution, Role, Storage, TimerRecord, WorkflowEntity};
+use chrono::{Duration, Utc};
+use serde_json::json;
+use uuid::Uuid;
+
+mod common;
+
+fn unique(prefix: &str) -> String {
+    format!("{}-{}", prefix, Uuid::new_v4())
+}
+
+async fn check_users(storage: &dyn Storage) -> anyhow::Result<()> {
+    let name = unique("user");
+    let user = storage.create_user(&name, "hunter2").await?;
+    assert!(user.password_hash.starts_with("$argon2"), "{}", user.password_hash);
+    assert!(user.verify_password("hunter2"));
+    assert!(!user.verify_password("hunter3"));
+    assert!(storage.create_user(&name, "other").await.is_err(), "usernames are unique");
+
+    let found = storage.get_user_by_username(&name).await?.unwrap();
+    assert_eq!(found.id, user.id);
+    assert!(found.verify_password("hunter2"));
+    assert_eq!(storage.get_user(user.id).await?.unwrap().username, name);
+    assert!(storage.get_user(Uuid::new_v4()).await?.is_none());
+    assert!(storage.get_user_by_username(&unique("user")).await?.is_none());
+    Ok(())
+}
+
+async fn check_teams(storage: &dyn Storage) -> anyhow::Result<()> {
+    let name = unique("team");
+    let team = storage.create_team(&name).await?;
+    assert!(storage.create_team(&name).await.is_err(), "team names are unique");
+    assert_eq!(storage.get_team(team.id).await?.unwrap().name, name);
+    assert_eq!(storage.get_team_by_name(&name).await?.unwrap().id, team.id);
+    assert!(storage.get_team_by_name(&unique("team")).await?.is_none());
+
+    let user = storage.create_user(&unique("member"), "pw").await?;
+    storage.add_team_member(team.id, user.id, Role::Member).await?;
+    assert!(storage.add_team_member(team.id, user.id, Role::Admin).await.is_err(), "one membership per team");
+    let members = storage.get_team_members(team.id).await?;
+    assert_eq!(members.iter().map(|m| (m.user_id, m.role.clone())).collect::<Vec<_>>(), vec![(user.id, Role::Member)]);
+    let teams = storage.list_teams_for_user(user.id).await?;
+    assert_eq!(teams.iter().map(|(t, r)| (t.id, r.clone())).collect::<Vec<_>>(), vec![(team.id, Role::Member)]);
+    Ok(())
+}
+
+async fn check_workflows_and_kv(storage: &dyn Storage) -> anyhow::Result<()> {
+    let team = storage.create_team(&unique("team")).await?;
+    let mut workflow = WorkflowEntity::new(team.id, "sync", json!({ "nodes": [], "edges": [] }));
+    storage.save_workflow(&workflow, None, None).await?;
+    workflow.name = "sync v2".to_string();
+    storage.save_workflow(&workflow, None, None).await?;
+    let workflows = storage.list_workflows(team.id).await?;
+    assert_eq!(workflows.iter().map(|w| w.name.as_str()).collect::<Vec<_>>(), vec!["sync v2"], "saving again updates");
+    assert_eq!(storage.get_workflow(workflow.id).await?.unwrap().definition, workflow.definition);
+    assert!(storage.get_workflow(Uuid::new_v4()).await?.is_none());
+
+    let key = unique("kv");
+    assert_eq!(storage.get_kv(&key).await?, None);
+    storage.set_kv(&key, &json!({ "cursor": 1 })).await?;
+    storage.set_kv(&key, &json!({ "cursor": 2 })).await?;
+    assert_eq!(storage.get_kv(&key).await?, Some(json!({ "cursor": 2 })));
+    Ok(())
+}
+
+async fn check_workflow_versions(storage: &dyn Storage) -> anyhow::Result<()> {
+    let team = storage.create_team(&unique("team")).await?;
+    let author = storage.create_user(&unique("author"), "pw").await?;
+    let mut workflow = WorkflowEntity::new(team.id, "sync", json!({ "nodes": [], "edges": [] }));
+    assert_eq!(storage.save_workflow(&workflow, Some(author.id), Some("first")).await?, 1);
+    assert_eq!(storage.save_workflow(&workflow, None, Some("again")).await?, 1, "nothing changed");
+    workflow.definition = json!({ "nodes": [{ "id": "start", "type": "manual_trigger", "config": {} }], "edges": [] });
+    assert_eq!(storage.save_workflow(&workflow, None, None).await?, 2);
+    assert_eq!(storage.get_workflow(workflow.id).await?.unwrap().version, 2);
+
+    let versions = storage.list_workflow_versions(workflow.id).await?;
+    assert_eq!(versions.iter().map(|v| v.version).collect::<Vec<_>>(), vec![2, 1], "newest first");
+    assert_eq!((versions[1].author_id, versions[1].message.as_deref()), (Some(author.id), Some("first")));
+    assert_eq!(versions[0].definition, workflow.definition);
+    assert_eq!(storage.get_workflow_version(workflow.id, 1).await?.as_ref(), Some(&versions[1]));
+    assert!(storage.get_workflow_version(workflow.id, 3).await?.is_none());
+    assert!(versions[1].diff(&versions[0]).get("/definition/nodes/0").is_some());
+
+    assert_eq!(rust_flow::storage::rollback_workflow(storage, workflow.id, 1, Some(author.id)).await?, 3);
+    let restored = storage.get_workflow_version(workflow.id, 3).await?.unwrap();
+    assert_eq!((restored.definition, restored.message.as_deref()), (versions[1].definition.clone(), Some("Rollback to version 1")));
+    assert!(rust_flow::storage::rollback_workflow(storage, workflow.id, 9, None).await.is_err());
+
+    let id = Uuid::new_v4();
+    storage.create_execution(id, Some(workflow.id), Some(2), None, "running").await?;
+    assert_eq!(storage.get_execution(id).await?.unwrap().workflow_version, Some(2));
+    storage.update_execution(id, "completed", Some(Utc::now()), None).await?;
+    Ok(())
+}
+
+async fn check_workflow_lifecycle(storage: &dyn Storage) -> anyhow::Result<()> {
+    let team = storage.create_team(&unique("team")).await?;
+    let workflow = WorkflowEntity::new(team.id, "sync", json!({ "nodes": [], "edges": [] }));
+    storage.save_workflow(&workflow, None, None).await?;
+    let disabled = WorkflowEntity { id: Uuid::new_v4(), name: "sync disabled".to_string(), active: false, ..workflow.clone() };
+    storage.save_workflow(&disabled, None, None).await?;
+    assert!(!storage.get_workflow(disabled.id).await?.unwrap().active, "created disabled");
+
+    assert!(storage.set_workflow_active(workflow.id, false).await?);
+    let stored = storage.get_workflow(workflow.id).await?.unwrap();
+    assert!(!stored.active);
+    assert!(stored.ensure_runnable().unwrap_err().to_string().contains("disabled"));
+    assert_eq!(rust_flow::storage::update_workflow(storage, workflow.id, Some("sync v2".to_string()), None, None, None).await?, 2);
+    assert!(!storage.get_workflow(workflow.id).await?.unwrap().active, "saves keep the flag");
+    assert!(storage.set_workflow_active(workflow.id, true).await?);
+    assert!(!storage.set_workflow_active(Uuid::new_v4(), true).await?);
+
+    let execution = Uuid::new_v4();
+    storage.create_execution(execution, Some(workflow.id), Some(2), None, "running").await?;
+    storage.update_execution(execution, "completed", Some(Utc::now()), None).await?;
+    assert!(storage.delete_workflow(workflow.id).await?);
+    assert!(!storage.delete_workflow(workflow.id).await?, "deleted once");
+    assert_eq!(storage.list_workflows(team.id).await?.iter().map(|w| w.id).collect::<Vec<_>>(), vec![disabled.id]);
+    let deleted = storage.get_workflow(workflow.id).await?.unwrap();
+    assert!(deleted.deleted_at.is_some());
+    assert!(deleted.ensure_runnable().is_err());
+    assert!(!storage.set_workflow_active(workflow.id, false).await?);
+    assert!(storage.save_workflow(&WorkflowEntity { name: "revived".to_string(), ..deleted }, None, None).await.is_err());
+
+    // The history stays
+    assert_eq!(storage.list_workflow_versions(workflow.id).await?.len(), 2);
+    assert_eq!(storage.get_execution(execution).await?.unwrap().workflow_id, Some(workflow.id));
+    let filter = rust_flow::storage::ExecutionFilter { team_id: Some(team.id), ..Default::default() };
+    assert_eq!(storage.list_executions(&filter).await?.iter().map(|e| e.id).collect::<Vec<_>>(), vec![execution]);
+    Ok(())
+}
+
+async fn check_credentials(storage: &dyn Storage) -> anyhow::Result<()> {
+    let team = storage.create_team(&unique("team")).await?;
+    let cred = storage.create_credential("openai", "openai_api", &json!({ "api_key": "sk-secret" }), team.id).await?;
+    assert!(!cred.data.contains("sk-secret"), "stored encrypted");
+    assert_eq!(serde_json::from_str::<serde_json::Value>(&encryption::decrypt(&cred.data)?)?, json!({ "api_key": "sk-secret" }));
+    // Fields are checked against the type
+    for (credential_type, data) in [("openai_api", json!({})), ("openai_api", json!({ "api_key": "sk", "org": "x" })), ("nope", json!("sk"))] {
+        assert!(storage.create_credential("bad", credential_type, &data, team.id).await.is_err(), "{} {}", credential_type, data);
+    }
+    let stored = storage.get_credential(cred.id).await?.unwrap();
+    assert_eq!((stored.name.as_str(), stored.credential_type.as_str(), stored.data.as_str()), ("openai", "openai_api", cred.data.as_str()));
+    assert_eq!(storage.list_credentials(team.id).await?.len(), 1);
+    assert!(storage.list_all_credentials().await?.iter().any(|c| c.id == cred.id));
+
+    // Replacing the encrypted data only succeeds against what is stored
+    let reencrypted = encryption::encrypt("sk-secret")?;
+    assert!(!storage.replace_credential_data(cred.id, "stale", &reencrypted).await?);
+    assert!(storage.replace_credential_data(cred.id, &cred.data, &reencrypted).await?);
+    assert_eq!(storage.get_credential(cred.id).await?.unwrap().data, reencrypted);
+    assert!(!storage.replace_credential_data(Uuid::new_v4(), &reencrypted, &cred.data).await?);
+
+    // Updates change the name and the fields independently; the data is checked like on create
+    assert!(storage.update_credential(cred.id, Some("openai prod"), None).await?);
+    let renamed = storage.get_credential(cred.id).await?.unwrap();
+    assert_eq!((renamed.name.as_str(), renamed.data.as_str()), ("openai prod", reencrypted.as_str()));
+    assert!(storage.update_credential(cred.id, None, Some(&json!("sk-rotated"))).await?);
+    let updated = storage.get_credential(cred.id).await?.unwrap();
+    assert_eq!(updated.name, "openai prod");
+    assert_eq!(serde_json::from_str::<serde_json::Value>(&encryption::decrypt(&updated.data)?)?, json!({ "api_key": "sk-rotated" }));
+    assert!(storage.update_credential(cred.id, None, Some(&json!({ "org": "x" }))).await.is_err());
+    assert!(!storage.update_credential(Uuid::new_v4(), Some("ghost"), None).await?);
+
+    // Credentials stored workflows use are only deleted when forced
+    let chat = WorkflowEntity::new(team.id, "chat", json!({ "nodes": [{ "id": "ask", "type": "openai", "config": { "credential_id": cred.id } }], "edges": [] }));
+    storage.save_workflow(&chat, None, None).await?;
+    assert!(storage.delete_credential(cred.id, false).await.is_err());
+    assert!(storage.get_credential(cred.id).await?.is_some());
+    assert!(storage.delete_credential(cred.id, true).await?);
+    assert!(storage.get_credential(cred.id).await?.is_none());
+    assert!(storage.list_credentials(team.id).await?.is_empty());
+    assert!(!storage.delete_credential(cred.id, false).await?);
+    Ok(())
+}
+
+async fn check_executions(storage: &dyn Storage) -> anyhow::Result<()> {
+    let id = Uuid::new_v4();
+    storage.create_execution(id, None, None, None, "running").await?;
+    assert!(storage.create_execution(id, None, None, None, "running").await.is_err(), "execution ids are unique");
+
+    // Logged back to back, many share a timestamp
+    let events: Vec<ExecutionEvent> = (0..50).map(|i| ExecutionEvent::NodeStart { node_id: format!("n{}", i) }).collect();
+    for event in &events {
+        storage.log_execution_event(id, event).await?;
+    }
+    assert_eq!(storage.get_execution_logs(id).await?, events, "events come back in the order they were logged");
+
+    storage.update_execution(id, "failed", Some(Utc::now()), Some("boom".to_string())).await?;
+    let record = storage.get_execution(id).await?.unwrap();
+    assert_eq!((record.status.as_str(), record.error.as_deref()), ("failed", Some("boom")));
+    assert!(record.finished_at.is_some());
+    assert_eq!(storage.get_execution_definition(id).await?, None);
+    assert!(storage.get_execution(Uuid::new_v4()).await?.is_none());
+    Ok(())
+}
+
+async fn check_queue(storage: &dyn Storage) -> anyhow::Result<()> {
+    // Far above what other tests queue with, as Postgres databases are shared
+    let queued = |priority| QueuedExecution {
+        id: Uuid::new_v4(),
+        workflow_id: None,
+        team_id: None,
+        definition: json!({ "nodes": [], "edges": [] }),
+        inputs: json!([["start", { "n": priority }]]),
+        priority,
+        resume_from: None,
+        started_by: None,
+    };
+    let (low, high, high_later) = (queued(1_000_000), queued(2_000_000), queued(2_000_000));
+    for execution in [&low, &high, &high_later] {
+        storage.enqueue_execution(execution).await?;
+    }
+    assert_eq!(storage.get_execution(low.id).await?.unwrap().status, "queued");
+
+    let worker = unique("worker");
+    let mut claimed = Vec::new();
+    for _ in 0..3 {
+        let next = storage.claim_execution(&worker).await?.unwrap();
+        assert_eq!(storage.get_execution(next.id).await?.unwrap().status, "running");
+        claimed.push(next);
+    }
+    assert_eq!(claimed.iter().map(|c| c.id).collect::<Vec<_>>(), vec![high.id, high_later.id, low.id], "by priority, then oldest");
+    assert_eq!(claimed[2].inputs, low.inputs);
+    assert_eq!(storage.get_execution_definition(low.id).await?, Some(low.definition.clone()));
+
+    // Suspended and resumed from a node
+    storage.update_execution(low.id, "waiting", None, None).await?;
+    assert!(storage.resume_execution(low.id, "wait", &json!([["wait", 1]])).await?);
+    assert!(!storage.resume_execution(low.id, "wait", &json!([])).await?, "no longer waiting");
+    let resumed = storage.claim_execution(&worker).await?.unwrap();
+    assert_eq!((resumed.id, resumed.resume_from.as_deref()), (low.id, Some("wait")));
+
+    // A stale worker's runs go back to the queue; runs without a worker are not its to recover
+    let unowned = storage.create_execution(Uuid::new_v4(), None, None, None, "running").await?;
+    storage.heartbeat_executions(&worker, &[low.id]).await?;
+    storage.recover_expired_executions(Utc::now() - Duration::hours(1)).await?;
+    assert_eq!(storage.get_execution(low.id).await?.unwrap().status, "running");
+    assert!(storage.recover_expired_executions(Utc::now() + Duration::seconds(1)).await? >= 3);
+    assert_eq!(storage.get_execution(low.id).await?.unwrap().status, "queued");
+    assert_eq!(storage.get_execution(unowned).await?.unwrap().status, "running");
+    storage.update_execution(unowned, "completed", Some(Utc::now()), None).await?;
+    for execution in [&low, &high, &high_later] {
+        storage.update_execution(execution.id, "completed", Some(Utc::now()), None).await?;
+    }
+    Ok(())
+}
+
+async fn check_approvals_and_timers(storage: &dyn Storage) -> anyhow::Result<()> {
+    let execution_id = Uuid::new_v4();
+    let approval = ApprovalRecord {
+        token: Uuid::new_v4().to_string(),
+        execution_id,
+        record: json!({ "id": 1 }),
+        status: "pending".to_string(),
+        decision: None,
+        expires_at: None,
+        created_at: Utc::now(),
+        decided_at: None,
+        node_id: Some("review".to_string()),
+        delivered_at: None,
+    };
+    storage.create_approval(&approval).await?;
+    assert!(storage.create_approval(&approval).await.is_err(), "tokens are unique");
+    assert_eq!(storage.count_open_approvals(execution_id).await?, 1);
+    assert!(!storage.mark_approval_delivered(&approval.token).await?, "pending approvals are not handed on");
+    let decision = json!({ "decision": "approve" });
+    assert!(storage.resolve_approval(&approval.token, "approved", Some(&decision)).await?);
+    assert!(!storage.resolve_approval(&approval.token, "rejected", None).await?, "settled once");
+    let stored = storage.get_approval(&approval.token).await?.unwrap();
+    assert_eq!((stored.status.as_str(), stored.decision), ("approved", Some(decision)));
+    assert!(stored.decided_at.is_some());
+    assert_eq!(stored.node_id.as_deref(), Some("review"));
+    assert!(storage.get_approval("missing").await?.is_none());
+    assert!(storage.mark_approval_delivered(&approval.token).await?);
+    assert!(!storage.mark_approval_delivered(&approval.token).await?, "handed on once");
+    assert!(storage.get_approval(&approval.token).await?.unwrap().delivered_at.is_some());
+    assert_eq!(storage.count_open_approvals(execution_id).await?, 0);
+
+    let now = Utc::now();
+    let timer = |minutes| TimerRecord {
+        id: Uuid::new_v4(),
+        execution_id,
+        node_id: "wait".to_string(),
+        record: json!({}),
+        wake_at: now + Duration::minutes(minutes),
+        status: "waiting".to_string(),
+        created_at: now,
+    };
+    let (late, early, future) = (timer(-1), timer(-2), timer(10));
+    for t in [&late, &early, &future] {
+        storage.create_timer(t).await?;
+    }
+    assert_eq!(storage.count_waiting_timers(execution_id).await?, 3);
+    let due: Vec<Uuid> = storage.list_due_timers(now).await?.into_iter().filter(|t| t.execution_id == execution_id).map(|t| t.id).collect();
+    assert_eq!(due, vec![early.id, late.id], "earliest first");
+    storage.update_timer_status(&due, "fired").await?;
+    assert_eq!(storage.count_waiting_timers(execution_id).await?, 1);
+    assert!(storage.list_due_timers(now).await?.iter().all(|t| t.execution_id != execution_id));
+    Ok(())
+}
+
+async fn check_events(storage: &dyn Storage) -> anyhow::Result<()> {
+    let team = storage.create_team(&unique("team")).await?;
+    let first = storage.publish_event(team.id, "order.created", &json!({ "id": 1 })).await?;
+    let second = storage.publish_event(team.id, "order.created", &json!({ "id": 2 })).await?;
+    assert_eq!(first.status, "pending");
+
+    let ours = |events: Vec<rust_flow::storage::EventRecord>| events.into_iter().filter(|e| e.team_id == team.id).map(|e| e.id).collect::<Vec<_>>();
+    assert_eq!(ours(storage.list_pending_events(10_000, 0).await?), vec![first.id, second.id]);
+    storage.mark_event_delivered(first.id).await?;
+    assert_eq!(ours(storage.list_pending_events(10_000, 0).await?), vec![second.id]);
+    storage.mark_event_delivered(second.id).await?;
+    Ok(())
+}
+
+async fn check_storage(storage: &dyn Storage) -> anyhow::Result<()> {
+    check_users(storage).await?;
+    check_teams(storage).await?;
+    check_workflows_and_kv(storage).await?;
+    check_workflow_versions(storage).await?;
+    check_workflow_lifecycle(storage).await?;
+    check_credentials(storage).await?;
+    check_executions(storage).await?;
+    check_queue(storage).await?;
+    check_approvals_and_timers(storage).await?;
+    check_events(storage).await
+}
+
+#[tokio::test]
+async fn test_memory_storage() -> anyhow::Result<()> {
+    let storage = MemoryStorage::new();
+    storage.init().await?;
+    check_storage(&storage).await
+}
+
+#[tokio::test]
+async fn test_sqlite_storage() -> anyhow::Result<()> {
+    let (storage, _db) = common::sqlite("conformance").await?;
+    check_storage(storage.as_ref()).await
+}
+
+#[tokio::test]
+async fn test_file_storage() -> anyhow::Result<()> {
+    let dir = common::TempPath::new("conformance");
+    let storage = FileStorage::new(&format!("file://{}", dir.display())).await?;
+    storage.init().await?;
+    check_storage(&storage).await
+}
+
+#[tokio::test]
+async fn test_postgres_storage() -> anyhow::Result<()> {
+    let Some(storage) = common::postgres().await? else {
+        return Ok(());
+    };
+    check_storage(&storage).await
+}
@@ -0,0 +1,81 @@
+use rust_flow::job_manager::{JobManager, JobSpec};
+use rust_flow::schema::{ExecutionEvent, WorkflowDefinition, WorkflowLoader};
+use rust_flow::storage::{Storage, MemoryStorage, WorkflowEntity, team_secrets};
+use serde_json::json;
+use std::sync::Arc;
+use tokio::time::{sleep, Duration};
+use uuid::Uuid;
+
+const GREETER: &str = r#"
+nodes:
+  - id: start
+    type: manual_trigger
+    config: {}
+  - id: out
+    type: console_output
+    config: {}
+edges:
+  - from: start
+    to: out
+"#;
+
+#[tokio::test]
+async fn test_stored_workflow_run_records_workflow_and_input() -> anyhow::Result<()> {
+    let storage = Arc::new(MemoryStorage::new());
+    let team = storage.create_team("growth").await?;
+    let workflow = WorkflowEntity::new(team.id, "greeter", serde_json::to_value(WorkflowLoader::new().load(GREETER)?)?);
+    storage.save_workflow(&workflow, None, None).await?;
+
+    // What `POST /api/workflows/{id}/run` does
+    let stored = storage.get_workflow(workflow.id).await?.unwrap();
+    let definition: WorkflowDefinition = serde_json::from_value(stored.definition)?;
+    let spec = JobSpec {
+        workflow_id: Some(stored.id),
+        team_id: Some(stored.account_id),
+        inputs: JobSpec::manual_inputs(&definition, &json!({ "name": "Ada" })),
+        ..Default::default()
+    };
+    let manager = Arc::new(JobManager::new(storage.clone()));
+    let id = Uuid::parse_str(&manager.start_workflow(&definition, spec).await?)?;
+
+    let mut execution = storage.get_execution(id).await?.unwrap();
+    for _ in 0..50 {
+        if execution.finished_at.is_some() {
+            break;
+        }
+        sleep(Duration::from_millis(50)).await;
+        execution = storage.get_execution(id).await?.unwrap();
+    }
+    assert_eq!(execution.status, "completed");
+    assert_eq!(execution.workflow_id, Some(workflow.id));
+
+    let logs = storage.get_execution_logs(id).await?;
+    assert!(logs.contains(&ExecutionEvent::EdgeData {
+        from: "start".to_string(),
+        to: "out".to_string(),
+        value: json!({ "name": "Ada" }),
+    }));
+    Ok(())
+}
+
+#[test]
+fn test_manual_inputs() {
+    let definition = WorkflowLoader::new().load(GREETER).unwrap();
+    assert_eq!(JobSpec::manual_inputs(&definition, &json!({ "a": 1 })), vec![("start".to_string(), json!({ "a": 1 }))]);
+    // Without input the trigger fires on its own
+    assert!(JobSpec::manual_inputs(&definition, &serde_json::Value::Null).is_empty());
+}
+
+#[tokio::test]
+async fn test_team_secrets_are_decrypted() -> anyhow::Result<()> {
+    let storage = Arc::new(MemoryStorage::new());
+    let team = storage.create_team("ops").await?;
+    let other = storage.create_team("other").await?;
+    let cred = storage.create_credential("openai", "openai_api", &json!("sk-secret"), team.id).await?;
+    storage.create_credential("foreign", "openai_api", &json!("sk-other"), other.id).await?;
+
+    let secrets = team_secrets(storage.as_ref(), team.id).await?;
+    assert_eq!(secrets.len(), 1);
+    assert_eq!(secrets.get(&cred.id.to_string()), Some(&json!({ "api_key": "sk-secret" })));
+    Ok(())
+}
@@ -5,10 +5,11 @@ import { Sidebar } from './components/Sidebar';
 import { FlowCanvas } from './components/FlowCanvas';
 import { PropertiesPanel } from './components/PropertiesPanel';
 import { Toolbar } from './components/Toolbar';
-import { fetchNodeTypes, runWorkflow, getJobStatus } from './api';
-import { type NodeType, type ExecutionEvent } from './types';
+import { fetchNodeTypes, fetchRunnableTeams, runWorkflow, getJobStatus, getToken, onUnauthorized, UnauthorizedError } from './api';
+import { type NodeType, type ExecutionEvent, type Team } from './types';
 import { useWebSocket } from './hooks/useWebSocket';
 import { DebugPanel } from './components/DebugPanel';
+import { LoginForm } from './components/LoginForm';
 import './layout.css';
 
 function App() {
@@ -21,6 +22,15 @@ function App() {
   const [events, setEvents] = useState<ExecutionEvent[]>([]);
   const [edgeActivity, setEdgeActivity] = useState<Record<string, number>>({});
   const [nodeStatus, setNodeStatus] = useState<Record<string, string>>({});
+  const [signedIn, setSignedIn] = useState(() => getToken() !== null);
+  const [teams, setTeams] = useState<Team[]>([]);
+  const [teamId, setTeamId] = useState<string | null>(null);
+
+  // Expired or revoked sessions bring the login form back
+  useEffect(() => {
+    onUnauthorized(() => setSignedIn(false));
+    return () => onUnauthorized(null);
+  }, []);
 
   const { connect, lastEvent } = useWebSocket();
 
@@ -43,8 +53,16 @@ function App() {
   }, [lastEvent]);
 
   useEffect(() => {
-    fetchNodeTypes().then(setNodeTypes).catch(console.error);
-  }, []);
+    if (signedIn) {
+      fetchNodeTypes().then(setNodeTypes).catch(console.error);
+      fetchRunnableTeams()
+        .then(teams => {
+          setTeams(teams);
+          setTeamId(current => teams.some(t => t.id === current) ? current : teams[0]?.id ?? null);
+        })
+        .catch(console.error);
+    }
+  }, [signedIn]);
 
   const onNodeSelect = (node: Node | null) => {
     setSelectedNode(node);
@@ -66,6 +84,10 @@ function App() {
   };
 
   const handleRun = async () => {
+    if (!teamId) {
+      alert('Join a team as a member to run workflows.');
+      return;
+    }
     setIsRunning(true);
     try {
       // Convert React Flow graph to Backend Workflow Definition
@@ -84,7 +106,7 @@ function App() {
       };
 
       console.log('Running workflow:', workflow);
-      const result = await runWorkflow(workflow);
+      const result = await runWorkflow(workflow, teamId);
       console.log('Execution initiated:', result);
 
       if (result.job_id) {
@@ -98,7 +120,9 @@ function App() {
       }
     } catch (error) {
       console.error('Execution failed:', error);
-      alert('Workflow execution failed. Check console for details.');
+      if (!(error instanceof UnauthorizedError)) {
+        alert('Workflow execution failed. Check console for details.');
+      }
       setIsRunning(false);
     }
   };
@@ -214,6 +238,9 @@ function App() {
           isRunning={isRunning}
           workflowName={workflowName}
           onNameChange={setWorkflowName}
+          teams={teams}
+          teamId={teamId}
+          onTeamChange={setTeamId}
         />
         <div className="main-content">
           <Sidebar />
@@ -235,6 +262,7 @@ function App() {
           />
           <DebugPanel events={events} />
         </div>
+        {!signedIn && <LoginForm onLogin={() => setSignedIn(true)} />}
       </div>
     </ReactFlowProvider>
   );
@@ -1,20 +1,82 @@
-import { type NodeType } from './types';
+import { type NodeType, type Role, type Team } from './types';
+
+const TOKEN_KEY = 'rustflow_token';
+
+export const getToken = (): string | null => localStorage.getItem(TOKEN_KEY);
+
+export const login = async (username: string, password: string): Promise<void> => {
+    const response = await fetch('/api/auth/login', {
+        method: 'POST',
+        headers: {
+            'Content-Type': 'application/json',
+        },
+        body: JSON.stringify({ username, password }),
+    });
+    if (!response.ok) {
+        throw new Error('Invalid username or password');
+    }
+    const { token } = await response.json();
+    localStorage.setItem(TOKEN_KEY, token);
+};
+
+export class UnauthorizedError extends Error {
+    constructor() {
+        super('Please sign in');
+    }
+}
+
+let unauthorizedListener: (() => void) | null = null;
+
+// Called whenever the server rejects the session, e.g. to show the login form
+export const onUnauthorized = (listener: (() => void) | null): void => {
+    unauthorizedListener = listener;
+};
+
+// Sends the session token; a rejected session is dropped and reported to `onUnauthorized`
+const apiFetch = async (input: string, init: RequestInit = {}): Promise<Response> => {
+    const response = await fetch(input, {
+        ...init,
+        headers: { ...init.headers, Authorization: `Bearer ${getToken() ?? ''}` },
+    });
+    if (response.status === 401) {
+        localStorage.removeItem(TOKEN_KEY);
+        unauthorizedListener?.();
+        throw new UnauthorizedError();
+    }
+    return response;
+};
 
 export const fetchNodeTypes = async (): Promise<NodeType[]> => {
-    const response = await fetch('/api/node-types');
+    const response = await apiFetch('/api/node-types');
     if (!response.ok) {
         throw new Error('Failed to fetch node types');
     }
     return response.json();
 };
 
-export const runWorkflow = async (workflow: any): Promise<any> => {
-    const response = await fetch('/api/run', {
+// Teams the signed-in user may run workflows in (member or admin)
+export const fetchRunnableTeams = async (): Promise<Team[]> => {
+    const me = await apiFetch('/api/auth/me');
+    if (!me.ok) {
+        throw new Error('Failed to fetch the current user');
+    }
+    const { id } = await me.json();
+    const response = await apiFetch(`/api/users/${id}/teams`);
+    if (!response.ok) {
+        throw new Error('Failed to fetch teams');
+    }
+    const memberships: [Team, Role][] = await response.json();
+    return memberships.filter(([, role]) => role !== 'Viewer').map(([team]) => team);
+};
+
+// Runs are scoped to a team; the server refuses unscoped runs by default
+export const runWorkflow = async (workflow: any, teamId: string): Promise<any> => {
+    const response = await apiFetch('/api/run', {
         method: 'POST',
         headers: {
             'Content-Type': 'application/json',
         },
-        body: JSON.stringify({ workflow: JSON.stringify(workflow) }),
+        body: JSON.stringify({ workflow: JSON.stringify(workflow), team_id: teamId }),
     });
 
     if (!response.ok) {
@@ -26,7 +88,7 @@ export const runWorkflow = async (workflow: any): Promise<any> => {
 };
 
 export const getJobStatus = async (id: string): Promise<any> => {
-    const response = await fetch(`/api/jobs/${id}`);
+    const response = await apiFetch(`/api/jobs/${id}`);
     if (!response.ok) {
         throw new Error('Failed to fetch job status');
     }
@@ -0,0 +1,67 @@
+import React, { useState } from 'react';
+import { LogIn } from 'lucide-react';
+import { login } from '../api';
+
+interface LoginFormProps {
+    onLogin: () => void;
+}
+
+export const LoginForm: React.FC<LoginFormProps> = ({ onLogin }) => {
+    const [username, setUsername] = useState('');
+    const [password, setPassword] = useState('');
+    const [error, setError] = useState<string | null>(null);
+    const [isSubmitting, setIsSubmitting] = useState(false);
+
+    const handleSubmit = async (event: React.FormEvent) => {
+        event.preventDefault();
+        setIsSubmitting(true);
+        setError(null);
+        try {
+            await login(username, password);
+            setPassword('');
+            onLogin();
+        } catch (e) {
+            setError(e instanceof Error ? e.message : 'Login failed');
+        } finally {
+            setIsSubmitting(false);
+        }
+    };
+
+    return (
+        <div className="login-overlay">
+            <form className="login-form" onSubmit={handleSubmit}>
+                <div className="logo">RustFlow</div>
+                <label>
+                    Username
+                    <input
+                        type="text"
+                        value={username}
+                        onChange={(e) => setUsername(e.target.value)}
+                        autoComplete="username"
+                        autoFocus
+                        required
+                    />
+                </label>
+                <label>
+                    Password
+                    <input
+                        type="password"
+                        value={password}
+                        onChange={(e) => setPassword(e.target.value)}
+                        autoComplete="current-password"
+                        required
+                    />
+                </label>
+                {error && <div className="login-error">{error}</div>}
+                <button
+                    type="submit"
+                    className={`toolbar-button run ${isSubmitting ? 'disabled' : ''}`}
+                    disabled={isSubmitting}
+                >
+                    <LogIn size={18} />
+                    <span className="ml-2">Sign in</span>
+                </button>
+            </form>
+        </div>
+    );
+};
@@ -1,5 +1,6 @@
 import React, { useRef } from 'react';
 import { Play, Square, Save, Upload, Download } from 'lucide-react';
+import { type Team } from '../types';
 
 interface ToolbarProps {
     onRun: () => void;
@@ -10,6 +11,9 @@ interface ToolbarProps {
     isRunning: boolean;
     workflowName: string;
     onNameChange: (name: string) => void;
+    teams: Team[];
+    teamId: string | null;
+    onTeamChange: (teamId: string) => void;
 }
 
 export const Toolbar: React.FC<ToolbarProps> = ({
@@ -20,7 +24,10 @@ export const Toolbar: React.FC<ToolbarProps> = ({
     onImport,
     isRunning,
     workflowName,
-    onNameChange
+    onNameChange,
+    teams,
+    teamId,
+    onTeamChange
 }) => {
     const fileInputRef = useRef<HTMLInputElement>(null);
 
@@ -88,6 +95,19 @@ export const Toolbar: React.FC<ToolbarProps> = ({
 
                 <div className="divider"></div>
 
+                <select
+                    className="team-select"
+                    value={teamId ?? ''}
+                    onChange={(e) => onTeamChange(e.target.value)}
+                    disabled={teams.length === 0}
+                    title="Team to run the workflow in"
+                >
+                    {teams.length === 0 && <option value="">No team</option>}
+                    {teams.map(team => (
+                        <option key={team.id} value={team.id}>{team.name}</option>
+                    ))}
+                </select>
+
                 <button
                     className={`toolbar-button run ${isRunning ? 'disabled' : ''}`}
                     onClick={onRun}
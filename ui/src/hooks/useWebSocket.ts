@@ -1,5 +1,6 @@
 import { useState, useEffect, useRef, useCallback } from 'react';
 import type { ExecutionEvent } from '../types';
+import { getToken } from '../api';
 
 interface UseWebSocketReturn {
     isConnected: boolean;
@@ -18,7 +19,8 @@ export const useWebSocket = (): UseWebSocketReturn => {
             socketRef.current.close();
         }
 
-        const ws = new WebSocket(`ws://localhost:3000/api/ws/${jobId}`);
+        // Browsers cannot set headers on WebSockets
+        const ws = new WebSocket(`ws://localhost:3000/api/ws/${jobId}?access_token=${getToken() ?? ''}`);
 
         ws.onopen = () => {
             console.log('WebSocket Connected');
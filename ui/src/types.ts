@@ -15,6 +15,13 @@ export interface NodeType {
     properties: NodeProperty[];
 }
 
+export interface Team {
+    id: string;
+    name: string;
+}
+
+export type Role = 'Admin' | 'Member' | 'Viewer';
+
 export interface ExecutionEvent {
     type: 'NodeStart' | 'NodeFinish' | 'EdgeData' | 'NodeError';
     node_id?: string;